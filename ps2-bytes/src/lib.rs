@@ -0,0 +1,94 @@
+#![doc = include_str!("../README.md")]
+#![no_std]
+#![forbid(missing_docs, unsafe_code)]
+#![warn(clippy::all, clippy::pedantic)]
+
+/// Read a little-endian `u16` at `offset`, returning `None` if `bytes` ends before it
+#[must_use]
+pub fn get_u16_le(bytes: &[u8], offset: usize) -> Option<u16> {
+    bytes
+        .get(offset..)?
+        .first_chunk()
+        .copied()
+        .map(u16::from_le_bytes)
+}
+
+/// Read a little-endian `u32` at `offset`, returning `None` if `bytes` ends before it
+#[must_use]
+pub fn get_u32_le(bytes: &[u8], offset: usize) -> Option<u32> {
+    bytes
+        .get(offset..)?
+        .first_chunk()
+        .copied()
+        .map(u32::from_le_bytes)
+}
+
+/// Read a big-endian `u32` at `offset`, returning `None` if `bytes` ends before it
+#[must_use]
+pub fn get_u32_be(bytes: &[u8], offset: usize) -> Option<u32> {
+    bytes
+        .get(offset..)?
+        .first_chunk()
+        .copied()
+        .map(u32::from_be_bytes)
+}
+
+/// Read a little-endian `u16` at `offset`, for data whose length has already been checked
+///
+/// # Panics
+///
+/// - `bytes` ends before the value
+#[must_use]
+pub fn read_u16_le(bytes: &[u8], offset: usize) -> u16 {
+    get_u16_le(bytes, offset).expect("u16 out of bounds")
+}
+
+/// Read a little-endian `u32` at `offset`, for data whose length has already been checked
+///
+/// # Panics
+///
+/// - `bytes` ends before the value
+#[must_use]
+pub fn read_u32_le(bytes: &[u8], offset: usize) -> u32 {
+    get_u32_le(bytes, offset).expect("u32 out of bounds")
+}
+
+/// Read a big-endian `u32` at `offset`, for data whose length has already been checked
+///
+/// # Panics
+///
+/// - `bytes` ends before the value
+#[must_use]
+pub fn read_u32_be(bytes: &[u8], offset: usize) -> u32 {
+    get_u32_be(bytes, offset).expect("u32 out of bounds")
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{get_u16_le, get_u32_be, get_u32_le, read_u16_le, read_u32_be, read_u32_le};
+
+    #[test]
+    fn read() {
+        let bytes = [0x01, 0x02, 0x03, 0x04, 0x05];
+        assert_eq!(read_u16_le(&bytes, 3), 0x0504);
+        assert_eq!(read_u32_le(&bytes, 0), 0x0403_0201);
+        assert_eq!(read_u32_le(&bytes, 1), 0x0504_0302);
+        assert_eq!(read_u32_be(&bytes, 1), 0x0203_0405);
+    }
+
+    #[test]
+    fn out_of_bounds() {
+        let bytes = [0x01, 0x02, 0x03, 0x04, 0x05];
+        assert_eq!(get_u16_le(&bytes, 4), None);
+        assert_eq!(get_u32_le(&bytes, 2), None);
+        assert_eq!(get_u32_be(&bytes, 6), None);
+        assert_eq!(get_u32_le(&bytes, usize::MAX), None);
+        assert_eq!(get_u32_be(&bytes, 1), Some(0x0203_0405));
+    }
+
+    #[test]
+    #[should_panic = "u32 out of bounds"]
+    fn read_out_of_bounds() {
+        let _ = read_u32_le(&[0; 3], 0);
+    }
+}
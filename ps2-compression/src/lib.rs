@@ -0,0 +1,60 @@
+#![doc = include_str!("../README.md")]
+#![forbid(missing_docs, unsafe_code)]
+#![warn(clippy::all, clippy::pedantic)]
+
+use std::{
+    error::Error as StdError,
+    fmt::{self, Display},
+};
+
+pub mod lzari;
+pub mod lzs;
+mod matcher;
+pub mod ps2_zlib;
+#[cfg(test)]
+mod test_data;
+
+/// Errors that might occur when decompressing an LZSS stream
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum LzsError {
+    /// The stream ends in the middle of a back-reference
+    Truncated,
+
+    /// The back-reference at the given offset of the stream points before the start of the output
+    InvalidOffset {
+        /// Offset of the back-reference in the compressed stream
+        offset: usize,
+    },
+}
+
+impl Display for LzsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+impl StdError for LzsError {}
+
+/// Errors that might occur when decompressing a zlib stream
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum ZlibError {
+    /// The data doesn't start with a known header or a zlib stream
+    UnknownHeader,
+
+    /// The data ends inside of the header
+    Truncated,
+
+    /// The zlib stream is corrupted or ends early
+    InvalidStream,
+
+    /// The decompressed data doesn't have the size stored in the header
+    SizeMismatch,
+}
+
+impl Display for ZlibError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+impl StdError for ZlibError {}
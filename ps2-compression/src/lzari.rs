@@ -0,0 +1,475 @@
+//! LZARI, Haruhiko Okumura's combination of LZSS with adaptive arithmetic coding
+//!
+//! This is the layout of Okumura's `LZARI.C`, which is also used by the `.max` saves of the Action Replay Max. The
+//! stream starts with the size of the decompressed data as a little-endian `u32`, followed by arithmetic coded
+//! symbols, read starting at the most significant bit of every byte:
+//!
+//! - `0..=255`: a literal byte
+//! - `256..=313`: a back-reference of 3 to 60 bytes, followed by its distance minus 1
+//!
+//! The literals and lengths share an adaptive model in which every symbol starts out equally likely, the distances
+//! use a fixed model favouring close bytes. Every byte of the output is also written into the 4096 byte ring buffer,
+//! starting at position `0xFC4`. The ring buffer starts out filled with spaces, which back-references may copy.
+//! Decompression stops once the stored size is reached, the remaining bits of the last byte are padding
+
+use crate::{
+    matcher::{MatchFinder, MIN_MATCH},
+    LzsError,
+};
+
+/// Size of the ring buffer
+const WINDOW_SIZE: usize = 1 << 12;
+
+/// Longest back-reference
+const MAX_MATCH: usize = 60;
+
+/// Position in the ring buffer the first byte is written to
+const WINDOW_START: usize = WINDOW_SIZE - MAX_MATCH;
+
+/// Size of the header holding the decompressed size
+const HEADER_SIZE: usize = 4;
+
+/// Number of symbols, the literals followed by the lengths of back-references
+const SYMBOL_COUNT: usize = 256 + MAX_MATCH - MIN_MATCH + 1;
+
+/// Quarter of the range of the arithmetic coder
+const QUARTER: u32 = 1 << 15;
+
+/// Half of the range of the arithmetic coder
+const HALF: u32 = 2 * QUARTER;
+
+/// Three quarters of the range of the arithmetic coder
+const THREE_QUARTERS: u32 = 3 * QUARTER;
+
+/// Total frequency of the symbols from which on their frequencies are halved
+const MAX_TOTAL: u32 = QUARTER - 1;
+
+/// Number of bits the decoder reads ahead of the current range
+///
+/// The encoder only outputs two bits after the last symbol, so reading past the end is fine up to this point
+const READ_AHEAD: usize = 17;
+
+/// Decompress an LZARI stream, including its size header
+///
+/// # Errors
+///
+/// - The stream ends before the header or the stored size is reached
+pub fn decompress(input: &[u8]) -> Result<Vec<u8>, LzsError> {
+    let header = input.get(..HEADER_SIZE).ok_or(LzsError::Truncated)?;
+    let size = u32::from_le_bytes([header[0], header[1], header[2], header[3]]) as usize;
+    let mut output = Vec::new();
+    if size == 0 {
+        return Ok(output);
+    }
+
+    let mut decoder = Decoder::new(&input[HEADER_SIZE..])?;
+    let mut model = Model::new();
+    let mut window = [b' '; WINDOW_SIZE];
+    while output.len() < size {
+        let symbol = decoder.symbol(&mut model)?;
+        let Some(len) = symbol.checked_sub(256) else {
+            // The symbol is a literal
+            let byte = symbol.to_le_bytes()[0];
+            window[(WINDOW_START + output.len()) % WINDOW_SIZE] = byte;
+            output.push(byte);
+            continue;
+        };
+
+        let distance = decoder.distance(&model)? + 1;
+        let start = WINDOW_START + WINDOW_SIZE + output.len() - distance;
+        for i in 0..len + MIN_MATCH {
+            // Read before writing, so a distance of 4096 copies the byte 4096 bytes back
+            let byte = window[(start + i) % WINDOW_SIZE];
+            window[(WINDOW_START + output.len()) % WINDOW_SIZE] = byte;
+            output.push(byte);
+        }
+    }
+
+    // A back-reference of a corrupted stream may run past the stored size
+    output.truncate(size);
+    Ok(output)
+}
+
+/// Compress data into an LZARI stream, always using the longest back-reference available at the current position
+///
+/// # Panics
+///
+/// - The input is 4 GiB or larger, which doesn't fit into the size header
+#[must_use]
+pub fn compress(input: &[u8]) -> Vec<u8> {
+    let size = u32::try_from(input.len()).expect("input too large for the size header");
+    let mut encoder = Encoder::new(size);
+    if input.is_empty() {
+        return encoder.output;
+    }
+
+    // Back-references stay within the bytes `LZARI.C` keeps behind the look-ahead buffer
+    let mut finder = MatchFinder::new(input, WINDOW_SIZE - MAX_MATCH, MAX_MATCH);
+    let mut model = Model::new();
+    let mut position = 0;
+    while position < input.len() {
+        let Some((distance, len)) = finder.longest(position) else {
+            encoder.symbol(&mut model, usize::from(input[position]));
+            finder.insert(position);
+            position += 1;
+            continue;
+        };
+
+        encoder.symbol(&mut model, 256 + len - MIN_MATCH);
+        encoder.distance(&model, distance - 1);
+        for matched in position..position + len {
+            finder.insert(matched);
+        }
+        position += len;
+    }
+
+    encoder.finish()
+}
+
+/// Adaptive model of the symbols and fixed model of the distances
+///
+/// Symbols are ordered by descending frequency, their rank is the 1-based position in that order
+struct Model {
+    /// Rank of every symbol
+    ranks: Vec<usize>,
+
+    /// Symbol of every rank, index 0 is unused
+    symbols: Vec<usize>,
+
+    /// Frequency of every rank, index 0 is a zero to stop the search for equal frequencies
+    frequencies: Vec<u32>,
+
+    /// Total frequency of the ranks after the index
+    cumulative: Vec<u32>,
+
+    /// Total weight of the distances after the index, where index 0 is a distance of 1
+    distances: Vec<u32>,
+}
+
+impl Model {
+    fn new() -> Self {
+        let mut frequencies = vec![1; SYMBOL_COUNT + 1];
+        frequencies[0] = 0;
+        let mut cumulative = vec![0; SYMBOL_COUNT + 1];
+        for rank in (0..SYMBOL_COUNT).rev() {
+            cumulative[rank] = cumulative[rank + 1] + frequencies[rank + 1];
+        }
+
+        let weights = (201..).take(WINDOW_SIZE).map(|i: u32| 10_000 / i);
+        let mut distances = weights.collect::<Vec<_>>();
+        distances.push(0);
+        for distance in (0..WINDOW_SIZE).rev() {
+            distances[distance] += distances[distance + 1];
+        }
+
+        Self {
+            ranks: (1..=SYMBOL_COUNT).collect(),
+            symbols: (0..=SYMBOL_COUNT)
+                .map(|rank| rank.saturating_sub(1))
+                .collect(),
+            frequencies,
+            cumulative,
+            distances,
+        }
+    }
+
+    /// Rank of the symbol whose range contains `target`
+    fn rank(&self, target: u64) -> usize {
+        self.cumulative[1..].partition_point(|&total| u64::from(total) > target) + 1
+    }
+
+    /// Distance minus 1 whose range contains `target`
+    fn distance(&self, target: u64) -> usize {
+        self.distances[1..].partition_point(|&total| u64::from(total) > target)
+    }
+
+    /// Count another occurrence of the symbol at `rank`, moving it before the symbols it is now more frequent than
+    fn update(&mut self, rank: usize) {
+        if self.cumulative[0] >= MAX_TOTAL {
+            let mut total = 0;
+            for rank in (1..=SYMBOL_COUNT).rev() {
+                self.cumulative[rank] = total;
+                self.frequencies[rank] = (self.frequencies[rank] + 1) >> 1;
+                total += self.frequencies[rank];
+            }
+            self.cumulative[0] = total;
+        }
+
+        let mut new_rank = rank;
+        while self.frequencies[new_rank] == self.frequencies[new_rank - 1] {
+            new_rank -= 1;
+        }
+        if new_rank < rank {
+            let (moved, displaced) = (self.symbols[rank], self.symbols[new_rank]);
+            self.symbols.swap(rank, new_rank);
+            self.ranks[moved] = new_rank;
+            self.ranks[displaced] = rank;
+        }
+
+        self.frequencies[new_rank] += 1;
+        for total in &mut self.cumulative[..new_rank] {
+            *total += 1;
+        }
+    }
+}
+
+/// Current range of the arithmetic coder
+#[derive(Clone, Copy)]
+struct Interval {
+    low: u32,
+    high: u32,
+}
+
+impl Interval {
+    /// Narrow the range to the part between the totals `upper` and `lower`, relative to `total`
+    fn narrow(&mut self, upper: u32, lower: u32, total: u32) {
+        let range = u64::from(self.high - self.low);
+        // Both parts are at most the range, which is below 2^17
+        let scale =
+            |value: u32| u32::try_from(range * u64::from(value) / u64::from(total)).unwrap();
+        self.high = self.low + scale(upper);
+        self.low += scale(lower);
+    }
+
+    /// Numerator and range of the offset of `value` within the range, used to look up the next symbol
+    fn offset(self, value: u32, total: u32) -> u64 {
+        let range = u64::from(self.high - self.low);
+        let offset = u64::from(value.wrapping_sub(self.low)) + 1;
+        (offset * u64::from(total) - 1) / range
+    }
+}
+
+/// Arithmetic encoder writing the bits of an LZARI stream
+struct Encoder {
+    output: Vec<u8>,
+    bits: usize,
+    interval: Interval,
+    /// Bits of the opposite value to output after the next bit
+    pending: usize,
+}
+
+impl Encoder {
+    fn new(size: u32) -> Self {
+        Self {
+            output: size.to_le_bytes().to_vec(),
+            bits: 0,
+            interval: Interval {
+                low: 0,
+                high: 4 * QUARTER,
+            },
+            pending: 0,
+        }
+    }
+
+    fn put_bit(&mut self, bit: bool) {
+        if self.bits.is_multiple_of(8) {
+            self.output.push(0);
+        }
+        if bit {
+            *self.output.last_mut().unwrap() |= 0x80 >> (self.bits % 8);
+        }
+        self.bits += 1;
+    }
+
+    fn output(&mut self, bit: bool) {
+        self.put_bit(bit);
+        for _ in 0..self.pending {
+            self.put_bit(!bit);
+        }
+        self.pending = 0;
+    }
+
+    fn encode(&mut self, upper: u32, lower: u32, total: u32) {
+        self.interval.narrow(upper, lower, total);
+        loop {
+            let Interval { low, high } = self.interval;
+            let shift = if high <= HALF {
+                self.output(false);
+                0
+            } else if low >= HALF {
+                self.output(true);
+                HALF
+            } else if low >= QUARTER && high <= THREE_QUARTERS {
+                self.pending += 1;
+                QUARTER
+            } else {
+                break;
+            };
+            self.interval = Interval {
+                low: (low - shift) * 2,
+                high: (high - shift) * 2,
+            };
+        }
+    }
+
+    fn symbol(&mut self, model: &mut Model, symbol: usize) {
+        let rank = model.ranks[symbol];
+        let cumulative = &model.cumulative;
+        self.encode(cumulative[rank - 1], cumulative[rank], cumulative[0]);
+        model.update(rank);
+    }
+
+    fn distance(&mut self, model: &Model, distance: usize) {
+        let distances = &model.distances;
+        self.encode(distances[distance], distances[distance + 1], distances[0]);
+    }
+
+    /// Output enough bits to tell the final range apart, the last byte is padded with zeros
+    fn finish(mut self) -> Vec<u8> {
+        self.pending += 1;
+        self.output(self.interval.low >= QUARTER);
+        self.output
+    }
+}
+
+/// Arithmetic decoder reading the bits of an LZARI stream
+struct Decoder<'a> {
+    input: &'a [u8],
+    bits: usize,
+    interval: Interval,
+    value: u32,
+}
+
+impl<'a> Decoder<'a> {
+    fn new(input: &'a [u8]) -> Result<Self, LzsError> {
+        let mut decoder = Self {
+            input,
+            bits: 0,
+            interval: Interval {
+                low: 0,
+                high: 4 * QUARTER,
+            },
+            value: 0,
+        };
+        for _ in 0..READ_AHEAD {
+            decoder.value = 2 * decoder.value + decoder.bit()?;
+        }
+        Ok(decoder)
+    }
+
+    /// Read the next bit, bits past the end of the stream are zeros as long as they are read ahead
+    fn bit(&mut self) -> Result<u32, LzsError> {
+        let byte = match self.input.get(self.bits / 8) {
+            Some(&byte) => byte,
+            None if self.bits < self.input.len() * 8 + READ_AHEAD => 0,
+            None => return Err(LzsError::Truncated),
+        };
+        let bit = byte >> (7 - self.bits % 8) & 1;
+        self.bits += 1;
+        Ok(u32::from(bit))
+    }
+
+    fn decode(&mut self, upper: u32, lower: u32, total: u32) -> Result<(), LzsError> {
+        self.interval.narrow(upper, lower, total);
+        loop {
+            let Interval { low, high } = self.interval;
+            let shift = if low >= HALF {
+                HALF
+            } else if low >= QUARTER && high <= THREE_QUARTERS {
+                QUARTER
+            } else if high <= HALF {
+                0
+            } else {
+                break;
+            };
+            self.interval = Interval {
+                low: (low - shift) * 2,
+                high: (high - shift) * 2,
+            };
+            // Corrupted streams may move the value out of the range
+            let value = self.value.wrapping_sub(shift).wrapping_mul(2);
+            self.value = value.wrapping_add(self.bit()?);
+        }
+        Ok(())
+    }
+
+    fn symbol(&mut self, model: &mut Model) -> Result<usize, LzsError> {
+        let cumulative = &model.cumulative;
+        let rank = model.rank(self.interval.offset(self.value, cumulative[0]));
+        self.decode(cumulative[rank - 1], cumulative[rank], cumulative[0])?;
+        let symbol = model.symbols[rank];
+        model.update(rank);
+        Ok(symbol)
+    }
+
+    fn distance(&mut self, model: &Model) -> Result<usize, LzsError> {
+        let distances = &model.distances;
+        let distance = model.distance(self.interval.offset(self.value, distances[0]));
+        self.decode(distances[distance], distances[distance + 1], distances[0])?;
+        Ok(distance)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{compress, decompress, Encoder, Model};
+    use crate::{test_data::noise, LzsError};
+
+    #[test]
+    fn round_trip() {
+        let text = b"BOOT2 = cdrom0:\\SLUS_213.48;1\r\nVER = 1.00\r\nVMODE = NTSC\r\n".repeat(200);
+        let mut mixed = noise(10_000);
+        mixed.extend(vec![0; 10_000]);
+        mixed.extend(&text);
+        mixed.extend(noise(5000));
+
+        let inputs = [
+            vec![],
+            vec![0x42],
+            vec![0; 3],
+            vec![0; 100_000],
+            text.clone(),
+            noise(1),
+            noise(17),
+            noise(50_000),
+            mixed,
+            (0..=255).cycle().take(20_000).collect(),
+        ];
+        for input in inputs {
+            let compressed = compress(&input);
+            assert_eq!(decompress(&compressed).unwrap(), input, "{}", input.len());
+
+            // The decoder reads ahead, what follows the stream doesn't change the output
+            let mut padded = compressed.clone();
+            padded.extend([0xFF; 8]);
+            assert_eq!(decompress(&padded).unwrap(), input, "{}", input.len());
+        }
+
+        // Repetitive data compresses well, the adaptive model keeps the overhead of noise low
+        assert!(compress(&vec![0; 100_000]).len() < 100_000 / 40);
+        assert!(compress(&text).len() < text.len() / 20);
+        assert!(compress(&noise(50_000)).len() < 50_000 + 50_000 / 100);
+        assert_eq!(compress(b""), [0; 4]);
+    }
+
+    #[test]
+    fn initial_window() {
+        // The ring buffer starts out filled with spaces, copy 5 of them before the first literal
+        let mut model = Model::new();
+        let mut encoder = Encoder::new(6);
+        encoder.symbol(&mut model, 256 + 5 - 3);
+        encoder.distance(&model, 100);
+        encoder.symbol(&mut model, usize::from(b'a'));
+
+        assert_eq!(decompress(&encoder.finish()).unwrap(), b"     a");
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(decompress(&[]), Err(LzsError::Truncated));
+        assert_eq!(decompress(&[1, 0, 0]), Err(LzsError::Truncated));
+        assert_eq!(decompress(&[0, 0, 0, 0]), Ok(Vec::new()));
+
+        let input = noise(1000);
+        let compressed = compress(&input);
+        assert_eq!(
+            decompress(&compressed[..compressed.len() / 2]),
+            Err(LzsError::Truncated)
+        );
+
+        // More data than the stream holds
+        let mut corrupted = compressed;
+        corrupted[..4].copy_from_slice(&2000_u32.to_le_bytes());
+        assert_eq!(decompress(&corrupted), Err(LzsError::Truncated));
+    }
+}
@@ -0,0 +1,201 @@
+//! LZS, the LZSS variant with a 4 KiB ring buffer used by many PS2 games
+//!
+//! This is the layout of Haruhiko Okumura's `LZSS.C`, with the ring buffer initialised with zeros instead of
+//! spaces. The stream consists of groups of up to 8 items, each preceded by a flag byte. Bit `n` (starting at the
+//! least significant one) describes the `n`-th item of the group:
+//!
+//! - `1`: a literal byte
+//! - `0`: a back-reference `0xPP 0xPL`, with a 12-bit position `0xPPP` into the ring buffer and the length minus 3
+//!   in `L`
+//!
+//! Every byte of the output is also written into the ring buffer, starting at position `0xFEE`. Back-references
+//! therefore reach up to 4096 bytes back and copy 3 to 18 bytes. They may overlap the bytes they produce, which
+//! repeats the bytes between the reference and the current position. The stream ends with the input, unused bits
+//! of the last flag byte are ignored
+
+use crate::{
+    matcher::{MatchFinder, MIN_MATCH},
+    LzsError,
+};
+
+/// Size of the ring buffer
+const WINDOW_SIZE: usize = 1 << 12;
+
+/// Position in the ring buffer the first byte is written to
+const WINDOW_START: usize = 0xFEE;
+
+/// Longest back-reference
+const MAX_MATCH: usize = MIN_MATCH + 0xF;
+
+/// Decompress an LZS stream
+///
+/// # Errors
+///
+/// - The stream ends in the middle of a back-reference
+pub fn decompress(input: &[u8]) -> Result<Vec<u8>, LzsError> {
+    let mut window = [0_u8; WINDOW_SIZE];
+    let mut output = Vec::with_capacity(input.len() * 2);
+    let mut position = 0;
+    while let Some(&flags) = input.get(position) {
+        position += 1;
+        for bit in 0..8 {
+            if position == input.len() {
+                break;
+            }
+
+            if flags & (1 << bit) != 0 {
+                window[(WINDOW_START + output.len()) % WINDOW_SIZE] = input[position];
+                output.push(input[position]);
+                position += 1;
+                continue;
+            }
+
+            let reference = input
+                .get(position..position + 2)
+                .ok_or(LzsError::Truncated)?;
+            let start = usize::from(reference[0]) | usize::from(reference[1] & 0xF0) << 4;
+            let len = usize::from(reference[1] & 0x0F) + MIN_MATCH;
+            for i in 0..len {
+                // Read before writing, so a reference to the current position copies the byte 4096 bytes back
+                let byte = window[(start + i) % WINDOW_SIZE];
+                window[(WINDOW_START + output.len()) % WINDOW_SIZE] = byte;
+                output.push(byte);
+            }
+            position += 2;
+        }
+    }
+
+    Ok(output)
+}
+
+/// Compress data into an LZS stream, always using the longest back-reference available at the current position
+#[must_use]
+pub fn compress(input: &[u8]) -> Vec<u8> {
+    let mut finder = MatchFinder::new(input, WINDOW_SIZE, MAX_MATCH);
+    let mut output = Vec::with_capacity(input.len() + input.len() / 8 + 1);
+    let mut position = 0;
+    while position < input.len() {
+        let flags = output.len();
+        output.push(0);
+
+        for bit in 0..8 {
+            if position == input.len() {
+                break;
+            }
+
+            let Some((distance, len)) = finder.longest(position) else {
+                output[flags] |= 1 << bit;
+                output.push(input[position]);
+                finder.insert(position);
+                position += 1;
+                continue;
+            };
+
+            output.extend(reference(position - distance, len));
+            for matched in position..position + len {
+                finder.insert(matched);
+            }
+            position += len;
+        }
+    }
+
+    output
+}
+
+/// Encode a back-reference to the bytes at `start` of the input found by the [`MatchFinder`]
+fn reference(start: usize, len: usize) -> [u8; 2] {
+    let start = (WINDOW_START + start) % WINDOW_SIZE;
+    // The length fits into its nibble thanks to the maximum length of the finder
+    let len = u8::try_from(len - MIN_MATCH).unwrap();
+    [
+        start.to_le_bytes()[0],
+        (start >> 4).to_le_bytes()[0] & 0xF0 | len,
+    ]
+}
+
+#[cfg(test)]
+mod test {
+    use super::{compress, decompress};
+    use crate::{test_data::noise, LzsError};
+
+    #[test]
+    fn decompress_vector() {
+        // "abc" as literals, then "abcabcab" as a back-reference to position 0xFEE with a length of 8
+        let compressed = [0b0000_0111, b'a', b'b', b'c', 0xEE, 0xF5];
+        assert_eq!(decompress(&compressed).unwrap(), b"abcabcabcab");
+
+        // Second group with a literal followed by a back-reference to the first byte
+        let compressed = [
+            0xFF, b'0', b'1', b'2', b'3', b'4', b'5', b'6', b'7', 0b01, b'8', 0xEE, 0xF0,
+        ];
+        assert_eq!(decompress(&compressed).unwrap(), b"012345678012");
+
+        // The ring buffer starts out filled with zeros
+        assert_eq!(decompress(&[0b10, 0x00, 0x01, b'a']).unwrap(), b"\0\0\0\0a");
+
+        assert_eq!(decompress(&[]).unwrap(), b"");
+    }
+
+    #[test]
+    fn compress_vector() {
+        assert_eq!(
+            compress(b"abcabcabcab"),
+            [0b0000_0111, b'a', b'b', b'c', 0xEE, 0xF5]
+        );
+        assert_eq!(compress(b"ab"), [0b11, b'a', b'b']);
+        assert_eq!(compress(b""), b"");
+
+        // The position wraps around the end of the ring buffer
+        let mut input = noise(20);
+        input.extend_from_within(..4);
+        let compressed = compress(&input);
+        assert_eq!(compressed[compressed.len() - 2..], [0xEE, 0xF1]);
+        let mut input = noise(21);
+        input.extend_from_within(18..);
+        let compressed = compress(&input);
+        assert_eq!(compressed[compressed.len() - 2..], [0x00, 0x00]);
+    }
+
+    #[test]
+    fn round_trip() {
+        let text = b"BOOT2 = cdrom0:\\SLUS_213.48;1\r\nVER = 1.00\r\nVMODE = NTSC\r\n".repeat(200);
+        let mut mixed = noise(10_000);
+        mixed.extend(vec![0; 10_000]);
+        mixed.extend(&text);
+        mixed.extend(noise(5000));
+        let mut far = noise(4096);
+        // Repeats from exactly as far back as the ring buffer reaches
+        far.extend_from_within(..4096);
+
+        let inputs = [
+            vec![],
+            vec![0x42],
+            vec![0; 3],
+            vec![0; 100_000],
+            text.clone(),
+            noise(1),
+            noise(17),
+            noise(50_000),
+            mixed,
+            far.clone(),
+            (0..=255).cycle().take(20_000).collect(),
+        ];
+        for input in inputs {
+            let compressed = compress(&input);
+            assert_eq!(decompress(&compressed).unwrap(), input, "{}", input.len());
+        }
+
+        // Repetitive data compresses well, noise only costs the flag bytes
+        assert!(compress(&vec![0; 100_000]).len() < 100_000 / 8);
+        assert!(compress(&text).len() < text.len() / 8);
+        assert!(compress(&noise(50_000)).len() <= 50_000 + 50_000 / 8 + 1);
+        assert!(compress(&far).len() < 4096 + 4096 / 8 + 4096 / 4);
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(decompress(&[0x00, 0xEE]), Err(LzsError::Truncated));
+        assert_eq!(decompress(&[0b01, b'a', 0xEE]), Err(LzsError::Truncated));
+        assert_eq!(decompress(&[0b01, b'a', 0xEE, 0xF0]), Ok(b"aaaa".to_vec()));
+    }
+}
@@ -0,0 +1,81 @@
+/// Shortest match worth encoding as a back-reference
+pub(crate) const MIN_MATCH: usize = 3;
+
+/// Number of hash chain entries searched for every match
+const MAX_CHAIN: usize = 256;
+
+/// Bits of the hash of the next [`MIN_MATCH`] bytes
+const HASH_BITS: u32 = 12;
+
+const NONE: usize = usize::MAX;
+
+/// Finds the longest earlier occurrence of the upcoming bytes, using hash chains over [`MIN_MATCH`] bytes
+pub(crate) struct MatchFinder<'a> {
+    input: &'a [u8],
+    window: usize,
+    max_len: usize,
+    /// Most recent position of every hash
+    head: Vec<usize>,
+    /// Previous position with the same hash as the one at the index
+    prev: Vec<usize>,
+}
+
+impl<'a> MatchFinder<'a> {
+    /// Finder for matches at most `window` bytes back and `max_len` bytes long
+    pub(crate) fn new(input: &'a [u8], window: usize, max_len: usize) -> Self {
+        Self {
+            input,
+            window,
+            max_len,
+            head: vec![NONE; 1 << HASH_BITS],
+            prev: vec![NONE; input.len()],
+        }
+    }
+
+    fn hash(&self, position: usize) -> Option<usize> {
+        let bytes = self.input.get(position..position + MIN_MATCH)?;
+        let value = bytes
+            .iter()
+            .fold(0_u32, |hash, &byte| (hash << 5) ^ u32::from(byte));
+        Some((value.wrapping_mul(0x9E37_79B1) >> (32 - HASH_BITS)) as usize)
+    }
+
+    /// Make the bytes starting at `position` available to later matches
+    pub(crate) fn insert(&mut self, position: usize) {
+        if let Some(hash) = self.hash(position) {
+            self.prev[position] = self.head[hash];
+            self.head[hash] = position;
+        }
+    }
+
+    /// Distance and length of the longest match for the bytes at `position`, if it is at least [`MIN_MATCH`]
+    /// bytes long
+    ///
+    /// Matches may overlap `position`, which repeats the bytes between the match and `position`
+    pub(crate) fn longest(&self, position: usize) -> Option<(usize, usize)> {
+        let max_len = self.max_len.min(self.input.len() - position);
+        let mut best = None;
+        let mut best_len = MIN_MATCH - 1;
+
+        let mut candidate = self.hash(position).map_or(NONE, |hash| self.head[hash]);
+        for _ in 0..MAX_CHAIN {
+            if candidate == NONE || position - candidate > self.window {
+                break;
+            }
+
+            let len = (0..max_len)
+                .take_while(|&i| self.input[candidate + i] == self.input[position + i])
+                .count();
+            if len > best_len {
+                best = Some((position - candidate, len));
+                best_len = len;
+                if len == max_len {
+                    break;
+                }
+            }
+            candidate = self.prev[candidate];
+        }
+
+        best
+    }
+}
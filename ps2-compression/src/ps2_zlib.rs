@@ -0,0 +1,255 @@
+//! zlib streams, optionally behind one of the headers PS2 games put in front of them
+//!
+//! The headers of the [`ZlibVariant`]s are:
+//!
+//! - [`ZlibVariant::Standard`]: none, the data starts with the zlib header (e.g. `78 9C`)
+//! - [`ZlibVariant::Zlb`]: the `ZLB\0` magic, followed by the decompressed size as a little-endian `u32`
+//! - [`ZlibVariant::Zlib`]: the `ZLIB` magic, followed by the decompressed size as a little-endian `u32`
+//! - [`ZlibVariant::SizePrefixed`]: only the decompressed size as a little-endian `u32`
+
+use crate::ZlibError;
+use flate2::{read::ZlibDecoder, write::ZlibEncoder, Compression};
+use std::io::{Read, Write};
+
+/// Magic of [`ZlibVariant::Zlb`]
+const ZLB_MAGIC: &[u8; 4] = b"ZLB\0";
+
+/// Magic of [`ZlibVariant::Zlib`]
+const ZLIB_MAGIC: &[u8; 4] = b"ZLIB";
+
+/// Header in front of the zlib stream
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum ZlibVariant {
+    /// Plain zlib stream without any header
+    Standard,
+
+    /// `ZLB\0` magic and decompressed size
+    Zlb,
+
+    /// `ZLIB` magic and decompressed size
+    Zlib,
+
+    /// Decompressed size only
+    SizePrefixed,
+}
+
+impl ZlibVariant {
+    /// Detect the header of the data
+    ///
+    /// The magics are checked first, then whether the data starts with a zlib header and finally whether one
+    /// follows a size. Returns `None` if none of them match
+    ///
+    /// Some sizes, such as `0x0178`, start with a valid zlib header themselves and are detected as
+    /// [`ZlibVariant::Standard`]. [`decompress`] falls back to [`ZlibVariant::SizePrefixed`] for them
+    #[must_use]
+    pub fn detect(input: &[u8]) -> Option<Self> {
+        if input.starts_with(ZLB_MAGIC) {
+            Some(Self::Zlb)
+        } else if input.starts_with(ZLIB_MAGIC) {
+            Some(Self::Zlib)
+        } else if is_zlib_header(input) {
+            Some(Self::Standard)
+        } else if input.get(4..).is_some_and(is_zlib_header) {
+            Some(Self::SizePrefixed)
+        } else {
+            None
+        }
+    }
+
+    /// Length of the header, without the zlib header itself
+    fn header_len(self) -> usize {
+        match self {
+            Self::Standard => 0,
+            Self::SizePrefixed => 4,
+            Self::Zlb | Self::Zlib => 8,
+        }
+    }
+}
+
+/// Whether the data starts with the header of a zlib stream using DEFLATE
+fn is_zlib_header(input: &[u8]) -> bool {
+    match input {
+        [cmf, flg, ..] => {
+            cmf & 0x0F == 8 && cmf >> 4 <= 7 && u16::from_be_bytes([*cmf, *flg]) % 31 == 0
+        }
+        _ => false,
+    }
+}
+
+/// Decompress a zlib stream, detecting and stripping its header (see [`ZlibVariant::detect`])
+///
+/// Data that could be both a [`ZlibVariant::Standard`] and a [`ZlibVariant::SizePrefixed`] stream is
+/// decompressed as the latter if it isn't a valid stream of the former. Data after the end of the zlib stream
+/// is ignored
+///
+/// # Errors
+///
+/// - The header isn't recognised or the data ends inside of it
+/// - The zlib stream is corrupted or truncated
+/// - The header stores a size that doesn't match the decompressed data
+pub fn decompress(input: &[u8]) -> Result<Vec<u8>, ZlibError> {
+    let variant = ZlibVariant::detect(input).ok_or(ZlibError::UnknownHeader)?;
+    let output = decompress_variant(input, variant);
+    if variant == ZlibVariant::Standard && input.get(4..).is_some_and(is_zlib_header) {
+        output.or_else(|error| {
+            decompress_variant(input, ZlibVariant::SizePrefixed).map_err(|_| error)
+        })
+    } else {
+        output
+    }
+}
+
+/// Decompress a zlib stream behind the header of the given variant
+fn decompress_variant(input: &[u8], variant: ZlibVariant) -> Result<Vec<u8>, ZlibError> {
+    let header_len = variant.header_len();
+    let stream = input.get(header_len..).ok_or(ZlibError::Truncated)?;
+    let size = (header_len >= 4).then(|| {
+        let size = &input[header_len - 4..header_len];
+        u32::from_le_bytes([size[0], size[1], size[2], size[3]]) as usize
+    });
+
+    // Don't trust the header with the allocation
+    let mut output = Vec::with_capacity(size.unwrap_or(0).min(input.len() * 8));
+    // One byte more than the header says is enough to notice a mismatch, without inflating all of the stream
+    let limit = size.map_or(u64::MAX, |size| size as u64 + 1);
+    ZlibDecoder::new(stream)
+        .take(limit)
+        .read_to_end(&mut output)
+        .map_err(|_| ZlibError::InvalidStream)?;
+    if size.is_some_and(|size| size != output.len()) {
+        return Err(ZlibError::SizeMismatch);
+    }
+
+    Ok(output)
+}
+
+/// Compress data into a zlib stream with the header of the given variant
+///
+/// # Panics
+///
+/// - The input is 4 GiB or larger and the variant stores the size
+#[must_use]
+pub fn compress(input: &[u8], variant: ZlibVariant) -> Vec<u8> {
+    let mut output = Vec::with_capacity(input.len() / 2 + 16);
+    match variant {
+        ZlibVariant::Zlb => output.extend(ZLB_MAGIC),
+        ZlibVariant::Zlib => output.extend(ZLIB_MAGIC),
+        ZlibVariant::Standard | ZlibVariant::SizePrefixed => {}
+    }
+    if variant != ZlibVariant::Standard {
+        let size = u32::try_from(input.len()).expect("input too large for the size header");
+        output.extend(size.to_le_bytes());
+    }
+
+    deflate(input, output)
+}
+
+/// Append the zlib stream of the input to `output`
+fn deflate(input: &[u8], output: Vec<u8>) -> Vec<u8> {
+    let mut encoder = ZlibEncoder::new(output, Compression::default());
+    // Writing into a `Vec` can't fail
+    encoder.write_all(input).unwrap();
+    encoder.finish().unwrap()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{compress, decompress, ZlibVariant};
+    use crate::{test_data::noise, ZlibError};
+
+    /// `hello` compressed with the default settings of zlib
+    const HELLO: [u8; 13] = [
+        0x78, 0x9C, 0xCB, 0x48, 0xCD, 0xC9, 0xC9, 0x07, 0x00, 0x06, 0x2C, 0x02, 0x15,
+    ];
+
+    const VARIANTS: [ZlibVariant; 4] = [
+        ZlibVariant::Standard,
+        ZlibVariant::Zlb,
+        ZlibVariant::Zlib,
+        ZlibVariant::SizePrefixed,
+    ];
+
+    #[test]
+    fn decompress_vector() {
+        assert_eq!(decompress(&HELLO).unwrap(), b"hello");
+
+        for magic in [&b"ZLB\0"[..], b"ZLIB", b""] {
+            let with_header = [magic, &[5, 0, 0, 0], &HELLO].concat();
+            assert_eq!(decompress(&with_header).unwrap(), b"hello");
+        }
+
+        // The size 0x0178 starts with the zlib header `78 01`
+        let input = noise(0x0178);
+        let compressed = compress(&input, ZlibVariant::SizePrefixed);
+        assert_eq!(compressed[..2], [0x78, 0x01]);
+        assert_eq!(
+            ZlibVariant::detect(&compressed),
+            Some(ZlibVariant::Standard)
+        );
+        assert_eq!(decompress(&compressed).unwrap(), input);
+
+        // Padding after the stream
+        let padded = [&HELLO[..], &[0; 11]].concat();
+        assert_eq!(decompress(&padded).unwrap(), b"hello");
+    }
+
+    #[test]
+    fn detect() {
+        assert_eq!(ZlibVariant::detect(&HELLO), Some(ZlibVariant::Standard));
+        assert_eq!(ZlibVariant::detect(b"ZLB\0"), Some(ZlibVariant::Zlb));
+        assert_eq!(ZlibVariant::detect(b"ZLIB"), Some(ZlibVariant::Zlib));
+        assert_eq!(
+            ZlibVariant::detect(&[0x10, 0x27, 0, 0, 0x78, 0xDA]),
+            Some(ZlibVariant::SizePrefixed)
+        );
+        assert_eq!(ZlibVariant::detect(&[0x78, 0x9D]), None);
+        assert_eq!(ZlibVariant::detect(b""), None);
+
+        for variant in VARIANTS {
+            assert_eq!(
+                ZlibVariant::detect(&compress(b"hello", variant)),
+                Some(variant)
+            );
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let text = b"BOOT2 = cdrom0:\\SLUS_213.48;1\r\nVER = 1.00\r\nVMODE = NTSC\r\n".repeat(200);
+        let inputs = [
+            vec![],
+            vec![0x42],
+            vec![0; 100_000],
+            text.clone(),
+            noise(50_000),
+        ];
+        for variant in VARIANTS {
+            for input in &inputs {
+                let compressed = compress(input, variant);
+                assert_eq!(&decompress(&compressed).unwrap(), input, "{variant:?}");
+            }
+        }
+
+        assert!(compress(&text, ZlibVariant::Zlb).len() < text.len() / 8);
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(decompress(b""), Err(ZlibError::UnknownHeader));
+        assert_eq!(decompress(b"PK\x03\x04"), Err(ZlibError::UnknownHeader));
+        assert_eq!(decompress(b"ZLB\0\x05\0"), Err(ZlibError::Truncated));
+
+        assert_eq!(decompress(&HELLO[..8]), Err(ZlibError::InvalidStream));
+        let mut corrupted = HELLO;
+        corrupted[5] ^= 0xFF;
+        assert_eq!(decompress(&corrupted), Err(ZlibError::InvalidStream));
+
+        let wrong_size = [&b"ZLB\0\x06\0\0\0"[..], &HELLO].concat();
+        assert_eq!(decompress(&wrong_size), Err(ZlibError::SizeMismatch));
+
+        // A stream inflating to far more than its header says is only decompressed up to the mismatch
+        let bomb = compress(&vec![0; 1 << 20], ZlibVariant::Standard);
+        let small_size = [&b"ZLB\0\x04\0\0\0"[..], &bomb].concat();
+        assert_eq!(decompress(&small_size), Err(ZlibError::SizeMismatch));
+    }
+}
@@ -0,0 +1,14 @@
+//! Inputs shared by the tests of the different formats
+
+/// Deterministic pseudo-random bytes
+pub fn noise(len: usize) -> Vec<u8> {
+    let mut state = 0x2545_F491_u32;
+    (0..len)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state.to_le_bytes()[0]
+        })
+        .collect()
+}
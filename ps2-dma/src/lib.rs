@@ -0,0 +1,388 @@
+#![doc = include_str!("../README.md")]
+#![forbid(missing_docs, unsafe_code)]
+#![warn(clippy::all, clippy::pedantic)]
+
+use std::{
+    collections::HashSet,
+    error::Error as StdError,
+    fmt::{self, Display},
+};
+
+/// Size of a quadword, the unit of all DMA transfers
+const QWORD_SIZE: usize = 16;
+
+/// Depth of the call stack (`ASR0` and `ASR1`)
+const CALL_STACK_DEPTH: usize = 2;
+
+/// Errors that might occur when following a DMA chain
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum DmaError {
+    /// A tag or its data lies outside of the memory
+    OutOfBounds {
+        /// Address that couldn't be accessed
+        addr: u32,
+    },
+
+    /// An address isn't aligned to a quadword
+    Unaligned {
+        /// Misaligned address
+        addr: u32,
+    },
+
+    /// A tag references the scratchpad memory, which isn't part of the memory
+    Scratchpad {
+        /// Address of the tag
+        addr: u32,
+    },
+
+    /// More than two nested `call` tags
+    CallStackOverflow {
+        /// Address of the tag
+        addr: u32,
+    },
+
+    /// The chain returns to an earlier tag with the same call stack and would never end
+    InfiniteLoop {
+        /// Address of the tag
+        addr: u32,
+    },
+}
+
+impl Display for DmaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+impl StdError for DmaError {}
+
+/// Tag ID, determining where the data and the next tag are
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum DmaTagId {
+    /// Transfer the data at ADDR, then end the chain
+    Refe,
+
+    /// Transfer the data following the tag, the next tag follows the data
+    Cnt,
+
+    /// Transfer the data following the tag, the next tag is at ADDR
+    Next,
+
+    /// Transfer the data at ADDR, the next tag follows this one
+    Ref,
+
+    /// Like [`DmaTagId::Ref`], with stall control
+    Refs,
+
+    /// Transfer the data following the tag, push the address following the data and continue at ADDR
+    Call,
+
+    /// Transfer the data following the tag, continue at the address popped off the call stack or end the chain
+    /// if it's empty
+    Ret,
+
+    /// Transfer the data following the tag, then end the chain
+    End,
+}
+
+impl DmaTagId {
+    /// Decode the 3 bit ID field
+    fn from_bits(bits: u64) -> Self {
+        match bits & 0b111 {
+            0 => Self::Refe,
+            1 => Self::Cnt,
+            2 => Self::Next,
+            3 => Self::Ref,
+            4 => Self::Refs,
+            5 => Self::Call,
+            6 => Self::Ret,
+            _ => Self::End,
+        }
+    }
+}
+
+/// Tag of a DMA chain together with the data it transfers
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct DmaTag<'a> {
+    /// Address the tag was read from
+    pub tag_addr: u32,
+
+    /// Number of quadwords transferred
+    pub qwc: u16,
+
+    /// Priority control enable
+    pub pce: u8,
+
+    /// Tag ID
+    pub id: DmaTagId,
+
+    /// Whether an interrupt is requested once the transfer is done
+    pub irq: bool,
+
+    /// Address of the data or the next tag, depending on the ID
+    pub addr: u32,
+
+    /// Whether `addr` refers to the scratchpad memory
+    pub spr: bool,
+
+    /// Data transferred by the tag
+    pub data: &'a [u8],
+}
+
+/// Follows a DMA source chain through a dump of the memory
+#[derive(Clone, Debug)]
+pub struct DmaChainParser<'a> {
+    memory: &'a [u8],
+    base_addr: u32,
+    next: Option<u32>,
+    call_stack: Vec<u32>,
+    visited: HashSet<(u32, Vec<u32>)>,
+}
+
+impl<'a> DmaChainParser<'a> {
+    /// Create a parser for the chain starting at `base_addr`, which is the address `memory` starts at
+    #[must_use]
+    pub fn new(memory: &'a [u8], base_addr: u32) -> Self {
+        Self {
+            memory,
+            base_addr,
+            next: Some(base_addr),
+            call_stack: Vec::new(),
+            visited: HashSet::new(),
+        }
+    }
+
+    /// Read the next tag of the chain, `None` once the chain has ended
+    ///
+    /// # Errors
+    ///
+    /// - A tag or its data is outside of the memory or misaligned
+    /// - A tag references the scratchpad memory
+    /// - The chain nests more than two `call` tags
+    /// - The chain loops forever
+    pub fn next_tag(&mut self) -> Result<Option<DmaTag<'a>>, DmaError> {
+        let Some(tag_addr) = self.next.take() else {
+            return Ok(None);
+        };
+        if !self.visited.insert((tag_addr, self.call_stack.clone())) {
+            return Err(DmaError::InfiniteLoop { addr: tag_addr });
+        }
+
+        let raw = self.read(tag_addr, QWORD_SIZE)?;
+        let raw = u64::from_le_bytes(std::array::from_fn(|i| raw[i]));
+        let field = |shift: u32, mask: u64| raw >> shift & mask;
+        let qwc = field(0, 0xFFFF).to_le_bytes();
+        let tag = DmaTag {
+            tag_addr,
+            qwc: u16::from_le_bytes([qwc[0], qwc[1]]),
+            pce: field(26, 0b11).to_le_bytes()[0],
+            id: DmaTagId::from_bits(field(28, 0b111)),
+            irq: field(31, 1) == 1,
+            addr: u32::try_from(field(32, 0x7FFF_FFFF)).unwrap_or_default(),
+            spr: field(63, 1) == 1,
+            data: &[],
+        };
+        let len = usize::from(tag.qwc) * QWORD_SIZE;
+        let out_of_bounds = DmaError::OutOfBounds { addr: tag_addr };
+        let following = tag_addr.checked_add(16).ok_or(out_of_bounds)?;
+        let after_data = following
+            .checked_add(u32::from(tag.qwc) * 16)
+            .ok_or(out_of_bounds)?;
+
+        let (data_addr, next) = match tag.id {
+            DmaTagId::Refe => (tag.addr, None),
+            DmaTagId::Cnt => (following, Some(after_data)),
+            DmaTagId::Next => (following, Some(tag.addr)),
+            DmaTagId::Ref | DmaTagId::Refs => (tag.addr, Some(following)),
+            DmaTagId::Call => {
+                if self.call_stack.len() == CALL_STACK_DEPTH {
+                    return Err(DmaError::CallStackOverflow { addr: tag_addr });
+                }
+                self.call_stack.push(after_data);
+                (following, Some(tag.addr))
+            }
+            DmaTagId::Ret => (following, self.call_stack.pop()),
+            DmaTagId::End => (following, None),
+        };
+
+        let references_data = matches!(tag.id, DmaTagId::Refe | DmaTagId::Ref | DmaTagId::Refs);
+        let jumps = matches!(tag.id, DmaTagId::Next | DmaTagId::Call);
+        if tag.spr && (references_data || jumps) {
+            return Err(DmaError::Scratchpad { addr: tag_addr });
+        }
+
+        let data = self.read(data_addr, len)?;
+        self.next = next;
+
+        Ok(Some(DmaTag { data, ..tag }))
+    }
+
+    /// Follow the whole chain and concatenate the data of all tags
+    ///
+    /// # Errors
+    ///
+    /// - See [`DmaChainParser::next_tag`]
+    pub fn collect_all(mut self) -> Result<Vec<u8>, DmaError> {
+        let mut data = Vec::new();
+        while let Some(tag) = self.next_tag()? {
+            data.extend_from_slice(tag.data);
+        }
+
+        Ok(data)
+    }
+
+    /// Read `len` bytes at `addr`
+    fn read(&self, addr: u32, len: usize) -> Result<&'a [u8], DmaError> {
+        if !addr.is_multiple_of(16) {
+            return Err(DmaError::Unaligned { addr });
+        }
+
+        let offset = addr
+            .checked_sub(self.base_addr)
+            .ok_or(DmaError::OutOfBounds { addr })? as usize;
+        self.memory
+            .get(offset..offset + len)
+            .ok_or(DmaError::OutOfBounds { addr })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use self::Qword::{Data, Tag};
+    use crate::{DmaChainParser, DmaError, DmaTagId};
+
+    const BASE: u32 = 0x0010_0000;
+
+    /// Quadword of the test memory
+    enum Qword {
+        /// Tag with the ID, QWC and ADDR
+        Tag(u8, u16, u32),
+
+        /// Data filled with a marker byte
+        Data(u8),
+    }
+
+    /// Lay out the quadwords in memory, starting at `BASE`
+    fn memory(qwords: &[Qword]) -> Vec<u8> {
+        qwords
+            .iter()
+            .flat_map(|qword| match *qword {
+                Tag(id, qwc, addr) => {
+                    let tag = u64::from(qwc) | u64::from(id) << 28 | u64::from(addr) << 32;
+                    let mut bytes = [0; 16];
+                    bytes[..8].copy_from_slice(&tag.to_le_bytes());
+                    bytes
+                }
+                Data(marker) => [marker; 16],
+            })
+            .collect()
+    }
+
+    /// Address of the quadword at `index`
+    fn at(index: u32) -> u32 {
+        BASE + index * 16
+    }
+
+    #[test]
+    fn follow_chain() {
+        let memory = memory(&[
+            // cnt with one quadword
+            Tag(1, 1, 0),
+            Data(1),
+            // next, jumping over the garbage
+            Tag(2, 1, at(5)),
+            Data(2),
+            Data(0xFF),
+            // ref to the data at the end, followed by a call
+            Tag(3, 1, at(11)),
+            Tag(5, 1, at(9)),
+            Data(3),
+            // end after the return
+            Tag(7, 0, 0),
+            // called subroutine
+            Tag(6, 1, 0),
+            Data(4),
+            Data(5),
+        ]);
+        let mut parser = DmaChainParser::new(&memory, BASE);
+
+        let mut tags = Vec::new();
+        while let Some(tag) = parser.next_tag().unwrap() {
+            tags.push((tag.id, tag.tag_addr, tag.data.first().copied()));
+        }
+        assert_eq!(
+            tags,
+            [
+                (DmaTagId::Cnt, at(0), Some(1)),
+                (DmaTagId::Next, at(2), Some(2)),
+                (DmaTagId::Ref, at(5), Some(5)),
+                (DmaTagId::Call, at(6), Some(3)),
+                (DmaTagId::Ret, at(9), Some(4)),
+                (DmaTagId::End, at(8), None),
+            ]
+        );
+        assert_eq!(parser.next_tag(), Ok(None));
+
+        let data = DmaChainParser::new(&memory, BASE).collect_all().unwrap();
+        let markers = data.chunks(16).map(|qword| qword[0]).collect::<Vec<_>>();
+        assert_eq!(markers, [1, 2, 5, 3, 4]);
+    }
+
+    #[test]
+    fn tag_fields() {
+        let mut memory = memory(&[Tag(0, 1, at(1)), Data(7)]);
+        // PCE, IRQ
+        memory[3] |= 0b1000_1100;
+
+        let mut parser = DmaChainParser::new(&memory, BASE);
+        let tag = parser.next_tag().unwrap().unwrap();
+        assert_eq!(tag.id, DmaTagId::Refe);
+        assert_eq!(tag.qwc, 1);
+        assert_eq!(tag.pce, 0b11);
+        assert!(tag.irq);
+        assert_eq!(tag.addr, at(1));
+        assert!(!tag.spr);
+        assert_eq!(tag.data, [7; 16]);
+        assert_eq!(parser.next_tag(), Ok(None));
+    }
+
+    #[test]
+    fn reject_invalid() {
+        fn collect(qwords: &[Qword]) -> Result<Vec<u8>, DmaError> {
+            DmaChainParser::new(&memory(qwords), BASE).collect_all()
+        }
+
+        assert_eq!(
+            collect(&[Tag(1, 2, 0), Data(0)]),
+            Err(DmaError::OutOfBounds { addr: at(1) })
+        );
+        assert_eq!(
+            collect(&[Tag(2, 0, BASE + 8)]),
+            Err(DmaError::Unaligned { addr: BASE + 8 })
+        );
+        assert_eq!(
+            collect(&[Tag(3, 0, BASE - 16)]),
+            Err(DmaError::OutOfBounds { addr: BASE - 16 })
+        );
+        assert_eq!(
+            collect(&[Tag(2, 0, at(1)), Tag(1, 0, 0), Tag(2, 0, at(1))]),
+            Err(DmaError::InfiniteLoop { addr: at(1) })
+        );
+        assert_eq!(
+            collect(&[
+                Tag(5, 0, at(1)),
+                Tag(5, 0, at(2)),
+                Tag(5, 0, at(3)),
+                Tag(7, 0, 0)
+            ]),
+            Err(DmaError::CallStackOverflow { addr: at(2) })
+        );
+
+        let mut memory = memory(&[Tag(3, 0, 0)]);
+        memory[7] |= 0x80;
+        assert_eq!(
+            DmaChainParser::new(&memory, BASE).collect_all(),
+            Err(DmaError::Scratchpad { addr: BASE })
+        );
+    }
+}
@@ -0,0 +1,498 @@
+#![doc = include_str!("../README.md")]
+#![forbid(missing_docs, unsafe_code)]
+#![warn(clippy::all, clippy::pedantic)]
+
+use ps2_bytes::{get_u16_le, get_u32_le};
+use ps2_compression::lzs;
+use std::{
+    error::Error as StdError,
+    fmt::{self, Display},
+};
+
+mod patch;
+mod symbols;
+#[cfg(test)]
+mod test_elf;
+
+pub use self::{
+    patch::Ps2ElfMut,
+    symbols::{Symbol, SymbolBinding, SymbolTable},
+};
+
+/// `ET_EXEC` object file type
+const ET_EXEC: u16 = 2;
+
+/// `EM_MIPS` machine type
+const EM_MIPS: u16 = 8;
+
+/// `PT_LOAD` segment type, the segment is loaded into memory
+const PT_LOAD: u32 = 1;
+
+/// `SHT_SYMTAB` section type, the section contains the symbol table
+const SHT_SYMTAB: u32 = 2;
+
+/// `SHT_NOBITS` section type, the section occupies no space in the file
+const SHT_NOBITS: u32 = 8;
+
+/// Section flag marking an LZS compressed section
+///
+/// Neither the ELF specification nor the PS2 SDK define a flag for compressed sections, so this uses the
+/// OS-specific range (`SHF_MASKOS`) which isn't used by MIPS or GNU tooling. `SHF_COMPRESSED` (`0x800`) isn't used
+/// since it refers to zlib compressed sections with a compression header
+pub const SHF_PS2_LZS: u32 = 0x0800_0000;
+
+/// Size of the ELF header
+const ELF_HEADER_SIZE: usize = 52;
+
+/// Size of a single program header entry
+const PROGRAM_HEADER_SIZE: usize = 32;
+
+/// Size of a single section header entry
+const SECTION_HEADER_SIZE: usize = 40;
+
+/// Errors that might occur when parsing an ELF file
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum ElfError {
+    /// The file is too short or one of its tables points outside of the file
+    Truncated,
+
+    /// The file doesn't start with the ELF magic
+    InvalidMagic,
+
+    /// The file isn't a 32-bit little-endian ELF file
+    UnsupportedFormat,
+
+    /// The file isn't a MIPS file
+    NotMips,
+
+    /// The file isn't an executable (`ET_EXEC`) or not of the requested object type
+    NotExecutable,
+
+    /// No section with the requested name exists
+    SectionNotFound,
+
+    /// The compressed data of a section is invalid
+    InvalidCompression,
+
+    /// The patched range lies outside of every loaded segment
+    OutOfBounds,
+}
+
+impl Display for ElfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+impl StdError for ElfError {}
+
+/// Segment described by a program header
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct ProgramSegment<'a> {
+    /// Type of the segment (`p_type`)
+    pub kind: u32,
+
+    /// Flags of the segment (`p_flags`)
+    pub flags: u32,
+
+    /// Virtual address the segment is loaded to
+    pub virtual_address: u32,
+
+    /// Physical address the segment is loaded to
+    pub physical_address: u32,
+
+    /// Size of the segment in memory, might be larger than the data in the file
+    pub memory_size: u32,
+
+    /// Offset of the segment in the file
+    pub offset: u32,
+
+    /// Data of the segment in the file
+    pub data: &'a [u8],
+}
+
+/// Section header entry
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct SectionHeader {
+    /// Offset of the section name in the section name string table (`sh_name`)
+    pub name_offset: u32,
+
+    /// Type of the section (`sh_type`)
+    pub kind: u32,
+
+    /// Flags of the section (`sh_flags`)
+    pub flags: u32,
+
+    /// Virtual address of the section in memory
+    pub address: u32,
+
+    /// Offset of the section in the file
+    pub offset: u32,
+
+    /// Size of the section
+    pub size: u32,
+
+    /// Index of an associated section (`sh_link`)
+    pub link: u32,
+
+    /// Extra information, depending on the section type (`sh_info`)
+    pub info: u32,
+
+    /// Alignment of the section
+    pub alignment: u32,
+
+    /// Size of the entries, if the section contains a table
+    pub entry_size: u32,
+}
+
+/// Parsed PS2 ELF executable
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Ps2Elf<'a> {
+    bytes: &'a [u8],
+    entry_point: u32,
+    segments: Vec<ProgramSegment<'a>>,
+    sections: Vec<SectionHeader>,
+    section_names: Option<&'a [u8]>,
+}
+
+impl<'a> Ps2Elf<'a> {
+    /// Parse a PS2 ELF executable
+    ///
+    /// # Errors
+    ///
+    /// - The file isn't a 32-bit little-endian ELF file
+    /// - The file isn't a MIPS executable
+    /// - The file is truncated
+    pub fn parse(bytes: &'a [u8]) -> Result<Self, ElfError> {
+        Self::parse_with_type(bytes, ET_EXEC)
+    }
+
+    /// Parse a MIPS ELF file of the given object type (`e_type`), such as IOP modules
+    ///
+    /// # Errors
+    ///
+    /// - The file isn't a 32-bit little-endian ELF file
+    /// - The file isn't a MIPS file of the given object type
+    /// - The file is truncated
+    pub fn parse_with_type(bytes: &'a [u8], object_type: u16) -> Result<Self, ElfError> {
+        let ident = bytes.get(..16).ok_or(ElfError::Truncated)?;
+        if ident[..4] != *b"\x7FELF" {
+            return Err(ElfError::InvalidMagic);
+        }
+        // ELFCLASS32, ELFDATA2LSB
+        if ident[4] != 1 || ident[5] != 1 {
+            return Err(ElfError::UnsupportedFormat);
+        }
+        if bytes.len() < ELF_HEADER_SIZE {
+            return Err(ElfError::Truncated);
+        }
+
+        if read_u16(bytes, 18)? != EM_MIPS {
+            return Err(ElfError::NotMips);
+        }
+        if read_u16(bytes, 16)? != object_type {
+            return Err(ElfError::NotExecutable);
+        }
+
+        let entry_point = read_u32(bytes, 24)?;
+        let program_headers = table(
+            bytes,
+            read_u32(bytes, 28)?,
+            read_u16(bytes, 42)?,
+            read_u16(bytes, 44)?,
+            PROGRAM_HEADER_SIZE,
+        )?;
+        let section_headers = table(
+            bytes,
+            read_u32(bytes, 32)?,
+            read_u16(bytes, 46)?,
+            read_u16(bytes, 48)?,
+            SECTION_HEADER_SIZE,
+        )?;
+        let section_names_index = read_u16(bytes, 50)?;
+
+        let segments = program_headers
+            .map(|header| {
+                Ok(ProgramSegment {
+                    kind: read_u32(header, 0)?,
+                    flags: read_u32(header, 24)?,
+                    virtual_address: read_u32(header, 8)?,
+                    physical_address: read_u32(header, 12)?,
+                    memory_size: read_u32(header, 20)?,
+                    offset: read_u32(header, 4)?,
+                    data: slice(bytes, read_u32(header, 4)?, read_u32(header, 16)?)?,
+                })
+            })
+            .collect::<Result<Vec<_>, ElfError>>()?;
+
+        let sections = section_headers
+            .map(|header| {
+                let section = SectionHeader {
+                    name_offset: read_u32(header, 0)?,
+                    kind: read_u32(header, 4)?,
+                    flags: read_u32(header, 8)?,
+                    address: read_u32(header, 12)?,
+                    offset: read_u32(header, 16)?,
+                    size: read_u32(header, 20)?,
+                    link: read_u32(header, 24)?,
+                    info: read_u32(header, 28)?,
+                    alignment: read_u32(header, 32)?,
+                    entry_size: read_u32(header, 36)?,
+                };
+                if section.kind != SHT_NOBITS {
+                    slice(bytes, section.offset, section.size)?;
+                }
+
+                Ok(section)
+            })
+            .collect::<Result<Vec<_>, ElfError>>()?;
+
+        // `SHN_UNDEF` (0) means there is no section name string table
+        let section_names = match sections.get(usize::from(section_names_index)) {
+            Some(section) if section_names_index != 0 => {
+                Some(slice(bytes, section.offset, section.size)?)
+            }
+            _ => None,
+        };
+
+        Ok(Self {
+            bytes,
+            entry_point,
+            segments,
+            sections,
+            section_names,
+        })
+    }
+
+    /// Virtual address of the entry point
+    #[must_use]
+    pub fn entry_point(&self) -> u32 {
+        self.entry_point
+    }
+
+    /// Iterate over the segments described by the program headers
+    pub fn segments(&self) -> impl Iterator<Item = ProgramSegment<'a>> + '_ {
+        self.segments.iter().copied()
+    }
+
+    /// Iterate over the section headers
+    pub fn sections(&self) -> impl Iterator<Item = SectionHeader> + '_ {
+        self.sections.iter().copied()
+    }
+
+    /// Look up the name of a section in the section name string table
+    #[must_use]
+    pub fn section_name(&self, section: &SectionHeader) -> Option<&'a str> {
+        read_str(self.section_names?, section.name_offset)
+    }
+
+    /// Retrieve the data of a section in the file
+    ///
+    /// Sections that occupy no space in the file (such as `.bss`) return an empty slice
+    #[must_use]
+    pub fn section_data(&self, section: &SectionHeader) -> &'a [u8] {
+        if section.kind == SHT_NOBITS {
+            return &[];
+        }
+
+        // The bounds of every section in the file are validated while parsing
+        slice(self.bytes, section.offset, section.size).unwrap_or_default()
+    }
+
+    /// Find a section by its name and copy its data
+    ///
+    /// Sections flagged with [`SHF_PS2_LZS`] are decompressed automatically with [`ps2_compression::lzs`]
+    ///
+    /// # Errors
+    ///
+    /// - No section with the name exists
+    /// - The section is compressed and its data is invalid
+    pub fn extract_section_bytes(&self, name: &str) -> Result<Vec<u8>, ElfError> {
+        let section = self
+            .sections
+            .iter()
+            .find(|section| self.section_name(section) == Some(name))
+            .ok_or(ElfError::SectionNotFound)?;
+
+        let data = self.section_data(section);
+        if section.flags & SHF_PS2_LZS != 0 {
+            lzs::decompress(data).map_err(|_| ElfError::InvalidCompression)
+        } else {
+            Ok(data.to_vec())
+        }
+    }
+
+    /// Retrieve the symbol table
+    ///
+    /// Returns `None` if the executable has been stripped
+    #[must_use]
+    pub fn symbol_table(&self) -> Option<SymbolTable<'a>> {
+        let symbols = self
+            .sections
+            .iter()
+            .find(|section| section.kind == SHT_SYMTAB)?;
+        let strings = self.sections.get(symbols.link as usize)?;
+
+        Some(SymbolTable::new(
+            self.section_data(symbols),
+            self.section_data(strings),
+        ))
+    }
+}
+
+/// Split a table of `count` entries of `entry_size` bytes at `offset` into its entries
+fn table(
+    bytes: &[u8],
+    offset: u32,
+    entry_size: u16,
+    count: u16,
+    min_entry_size: usize,
+) -> Result<impl Iterator<Item = &[u8]>, ElfError> {
+    let entry_size = usize::from(entry_size);
+    if count == 0 {
+        return Ok([].chunks_exact(min_entry_size));
+    }
+    if entry_size < min_entry_size {
+        return Err(ElfError::Truncated);
+    }
+
+    let len = u32::try_from(entry_size * usize::from(count)).map_err(|_| ElfError::Truncated)?;
+    Ok(slice(bytes, offset, len)?.chunks_exact(entry_size))
+}
+
+/// Retrieve `len` bytes at `offset`
+fn slice(bytes: &[u8], offset: u32, len: u32) -> Result<&[u8], ElfError> {
+    bytes
+        .get(offset as usize..)
+        .and_then(|rest| rest.get(..len as usize))
+        .ok_or(ElfError::Truncated)
+}
+
+/// Read a NUL-terminated string at `offset` of a string table
+fn read_str(table: &[u8], offset: u32) -> Option<&str> {
+    let rest = table.get(offset as usize..)?;
+    let len = rest.iter().position(|&b| b == 0)?;
+    std::str::from_utf8(&rest[..len]).ok()
+}
+
+/// [`get_u16_le`] for headers and tables, which the file can end in the middle of
+fn read_u16(bytes: &[u8], offset: usize) -> Result<u16, ElfError> {
+    get_u16_le(bytes, offset).ok_or(ElfError::Truncated)
+}
+
+/// [`get_u32_le`] for headers and tables, which the file can end in the middle of
+fn read_u32(bytes: &[u8], offset: usize) -> Result<u32, ElfError> {
+    get_u32_le(bytes, offset).ok_or(ElfError::Truncated)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        test_elf::{ElfBuilder, Section},
+        ElfError, Ps2Elf, SHF_PS2_LZS,
+    };
+    use ps2_compression::lzs;
+
+    #[test]
+    fn parse() {
+        let bytes = ElfBuilder::new()
+            .segment(0x0010_0000, &[0x01, 0x02, 0x03, 0x04])
+            .segment(0x0020_0000, &[0xAA; 16])
+            .section(".text", 1, 0x0010_0000, &[0x01, 0x02, 0x03, 0x04])
+            .section(".data", 1, 0x0020_0000, &[0xAA; 16])
+            .build();
+        let elf = Ps2Elf::parse(&bytes).unwrap();
+
+        assert_eq!(elf.entry_point(), 0x0010_0000);
+
+        let segments = elf.segments().collect::<Vec<_>>();
+        assert_eq!(segments.len(), 2);
+        assert_eq!(segments[0].virtual_address, 0x0010_0000);
+        assert_eq!(segments[0].physical_address, 0x0010_0000);
+        assert_eq!(segments[0].data, [0x01, 0x02, 0x03, 0x04]);
+        assert_eq!(segments[1].virtual_address, 0x0020_0000);
+        assert_eq!(segments[1].data, [0xAA; 16]);
+
+        let names = elf
+            .sections()
+            .map(|section| elf.section_name(&section).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["", ".text", ".data", ".shstrtab"]);
+
+        let data = elf.sections().nth(2).unwrap();
+        assert_eq!(data.address, 0x0020_0000);
+        assert_eq!(elf.section_data(&data), [0xAA; 16]);
+    }
+
+    #[test]
+    fn extract_section_bytes() {
+        let bytes = ElfBuilder::new()
+            .section(".data", 1, 0x0020_0000, b"plain data")
+            .raw_section(Section {
+                name: ".lzs",
+                kind: 1,
+                flags: SHF_PS2_LZS,
+                address: 0x0030_0000,
+                data: vec![0x07, b'a', b'b', b'c', 0xEE, 0xF3],
+                link: 0,
+                entry_size: 0,
+            })
+            .build();
+        let elf = Ps2Elf::parse(&bytes).unwrap();
+
+        assert_eq!(elf.extract_section_bytes(".data").unwrap(), b"plain data");
+        assert_eq!(elf.extract_section_bytes(".lzs").unwrap(), b"abcabcabc");
+        assert_eq!(
+            elf.extract_section_bytes(".missing"),
+            Err(ElfError::SectionNotFound)
+        );
+    }
+
+    #[test]
+    fn lzs_section_matches_ps2_compression() {
+        let text = b"BOOT2 = cdrom0:\\SLUS_213.48;1\r\nVER = 1.00\r\nVMODE = NTSC\r\n".repeat(100);
+        let vectors = [
+            vec![0x07, b'a', b'b', b'c', 0xEE, 0xF3],
+            vec![0b10, 0x00, 0x01, b'a'],
+            lzs::compress(&text),
+            // Truncated back-reference
+            vec![0x00, 0xEE],
+        ];
+        for vector in vectors {
+            let bytes = ElfBuilder::new()
+                .raw_section(Section {
+                    name: ".lzs",
+                    kind: 1,
+                    flags: SHF_PS2_LZS,
+                    address: 0x0030_0000,
+                    data: vector.clone(),
+                    link: 0,
+                    entry_size: 0,
+                })
+                .build();
+            let elf = Ps2Elf::parse(&bytes).unwrap();
+
+            assert_eq!(
+                elf.extract_section_bytes(".lzs"),
+                lzs::decompress(&vector).map_err(|_| ElfError::InvalidCompression)
+            );
+        }
+    }
+
+    #[test]
+    fn reject_invalid() {
+        assert_eq!(Ps2Elf::parse(b"\x7FELF"), Err(ElfError::Truncated));
+        assert_eq!(Ps2Elf::parse(&[0; 64]), Err(ElfError::InvalidMagic));
+
+        let x86 = ElfBuilder::new().machine(3).build();
+        assert_eq!(Ps2Elf::parse(&x86), Err(ElfError::NotMips));
+
+        let relocatable = ElfBuilder::new().kind(1).build();
+        assert_eq!(Ps2Elf::parse(&relocatable), Err(ElfError::NotExecutable));
+        assert!(Ps2Elf::parse_with_type(&relocatable, 1).is_ok());
+
+        let mut truncated = ElfBuilder::new().segment(0x0010_0000, &[0; 64]).build();
+        truncated.truncate(100);
+        assert_eq!(Ps2Elf::parse(&truncated), Err(ElfError::Truncated));
+    }
+}
@@ -0,0 +1,123 @@
+use crate::{ElfError, Ps2Elf, PT_LOAD};
+
+/// Loaded segment of a [`Ps2ElfMut`]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+struct LoadedSegment {
+    virtual_address: u32,
+    offset: usize,
+    len: usize,
+}
+
+/// Owned PS2 ELF executable that can be patched in memory
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Ps2ElfMut {
+    bytes: Vec<u8>,
+    segments: Vec<LoadedSegment>,
+}
+
+impl Ps2ElfMut {
+    /// Parse an owned PS2 ELF executable
+    ///
+    /// # Errors
+    ///
+    /// See [`Ps2Elf::parse`]
+    pub fn parse(bytes: Vec<u8>) -> Result<Self, ElfError> {
+        let segments = Ps2Elf::parse(&bytes)?
+            .segments()
+            .filter(|segment| segment.kind == PT_LOAD)
+            .map(|segment| LoadedSegment {
+                virtual_address: segment.virtual_address,
+                offset: segment.offset as usize,
+                len: segment.data.len(),
+            })
+            .collect();
+
+        Ok(Self { bytes, segments })
+    }
+
+    /// Overwrite the bytes at the virtual address `vaddr` with `data`
+    ///
+    /// # Errors
+    ///
+    /// - The range doesn't lie entirely within the file data of a single loaded segment
+    pub fn apply_patch(&mut self, vaddr: u32, data: &[u8]) -> Result<(), ElfError> {
+        let start = u64::from(vaddr);
+        let end = start + data.len() as u64;
+
+        let segment = self
+            .segments
+            .iter()
+            .find(|segment| {
+                let segment_start = u64::from(segment.virtual_address);
+                start >= segment_start && end <= segment_start + segment.len as u64
+            })
+            .ok_or(ElfError::OutOfBounds)?;
+
+        let offset = segment.offset + (vaddr - segment.virtual_address) as usize;
+        self.bytes[offset..offset + data.len()].copy_from_slice(data);
+
+        Ok(())
+    }
+
+    /// Serialise the (patched) executable
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.bytes.clone()
+    }
+
+    /// Consume the executable and return its bytes
+    #[must_use]
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{test_elf::ElfBuilder, ElfError, Ps2Elf, Ps2ElfMut};
+
+    fn elf() -> Vec<u8> {
+        ElfBuilder::new()
+            .segment(
+                0x0010_0000,
+                &[0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77],
+            )
+            .segment(0x0020_0000, &[0xAA; 4])
+            .build()
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut elf = Ps2ElfMut::parse(elf()).unwrap();
+        elf.apply_patch(0x0010_0002, &[0xDE, 0xAD]).unwrap();
+        elf.apply_patch(0x0020_0003, &[0xBE]).unwrap();
+
+        let bytes = elf.to_bytes();
+        let reparsed = Ps2Elf::parse(&bytes).unwrap();
+        let segments = reparsed.segments().collect::<Vec<_>>();
+        assert_eq!(
+            segments[0].data,
+            [0x00, 0x11, 0xDE, 0xAD, 0x44, 0x55, 0x66, 0x77]
+        );
+        assert_eq!(segments[1].data, [0xAA, 0xAA, 0xAA, 0xBE]);
+    }
+
+    #[test]
+    fn out_of_bounds() {
+        let mut elf = Ps2ElfMut::parse(elf()).unwrap();
+
+        assert_eq!(
+            elf.apply_patch(0x000F_FFFF, &[0; 2]),
+            Err(ElfError::OutOfBounds)
+        );
+        assert_eq!(
+            elf.apply_patch(0x0010_0007, &[0; 2]),
+            Err(ElfError::OutOfBounds)
+        );
+        assert_eq!(
+            elf.apply_patch(0xFFFF_FFFF, &[0; 2]),
+            Err(ElfError::OutOfBounds)
+        );
+        assert_eq!(elf.to_bytes(), super::test::elf());
+    }
+}
@@ -0,0 +1,173 @@
+use crate::{read_str, read_u16, read_u32};
+
+/// Size of a single symbol table entry
+const SYMBOL_SIZE: usize = 16;
+
+/// `SHN_UNDEF` section index, the symbol is undefined
+const SHN_UNDEF: u16 = 0;
+
+/// Binding of a symbol
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum SymbolBinding {
+    /// Only visible inside the object file that defines it (`STB_LOCAL`)
+    Local,
+
+    /// Visible to all object files (`STB_GLOBAL`)
+    Global,
+
+    /// Global symbol with a lower precedence (`STB_WEAK`)
+    Weak,
+
+    /// Any other binding
+    Other(u8),
+}
+
+impl From<u8> for SymbolBinding {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => Self::Local,
+            1 => Self::Global,
+            2 => Self::Weak,
+            other => Self::Other(other),
+        }
+    }
+}
+
+/// Entry of the symbol table
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Symbol<'a> {
+    /// Name of the symbol
+    pub name: &'a str,
+
+    /// Value of the symbol, the virtual address for functions and objects
+    pub value: u32,
+
+    /// Size of the object the symbol refers to
+    pub size: u32,
+
+    /// Binding of the symbol
+    pub binding: SymbolBinding,
+
+    /// Index of the section the symbol is defined in (`0` if it is undefined)
+    pub section_index: u16,
+}
+
+/// Symbol table (`.symtab`) of an ELF file
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct SymbolTable<'a> {
+    entries: &'a [u8],
+    strings: &'a [u8],
+}
+
+impl<'a> SymbolTable<'a> {
+    pub(crate) fn new(entries: &'a [u8], strings: &'a [u8]) -> Self {
+        Self { entries, strings }
+    }
+
+    /// Iterate over all symbols of the table
+    pub fn iter(&self) -> impl Iterator<Item = Symbol<'a>> + '_ {
+        self.entries.chunks_exact(SYMBOL_SIZE).filter_map(|entry| {
+            Some(Symbol {
+                name: read_str(self.strings, read_u32(entry, 0).ok()?).unwrap_or_default(),
+                value: read_u32(entry, 4).ok()?,
+                size: read_u32(entry, 8).ok()?,
+                binding: SymbolBinding::from(entry[12] >> 4),
+                section_index: read_u16(entry, 14).ok()?,
+            })
+        })
+    }
+
+    /// Look up the virtual address of a defined symbol by its name
+    #[must_use]
+    pub fn lookup(&self, name: &str) -> Option<u32> {
+        self.iter()
+            .find(|symbol| symbol.section_index != SHN_UNDEF && symbol.name == name)
+            .map(|symbol| symbol.value)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        test_elf::{ElfBuilder, Section},
+        Ps2Elf, SymbolBinding,
+    };
+
+    fn symbol(name: u32, value: u32, size: u32, info: u8, section_index: u16) -> Vec<u8> {
+        let mut entry = Vec::new();
+        entry.extend_from_slice(&name.to_le_bytes());
+        entry.extend_from_slice(&value.to_le_bytes());
+        entry.extend_from_slice(&size.to_le_bytes());
+        entry.extend_from_slice(&[info, 0]);
+        entry.extend_from_slice(&section_index.to_le_bytes());
+        entry
+    }
+
+    fn elf_with_symbols() -> Vec<u8> {
+        let strtab = b"\0main\0helper\0printf\0";
+        let symtab = [
+            symbol(0, 0, 0, 0, 0),
+            // main: global function in .text
+            symbol(1, 0x0010_0000, 0x40, 0x12, 1),
+            // helper: local function in .text
+            symbol(6, 0x0010_0040, 0x20, 0x02, 1),
+            // printf: undefined global
+            symbol(13, 0, 0, 0x12, 0),
+        ]
+        .concat();
+
+        ElfBuilder::new()
+            .segment(0x0010_0000, &[0; 0x60])
+            .section(".text", 1, 0x0010_0000, &[0; 0x60])
+            .raw_section(Section {
+                name: ".symtab",
+                kind: 2,
+                flags: 0,
+                address: 0,
+                data: symtab,
+                link: 3,
+                entry_size: 16,
+            })
+            .section(".strtab", 3, 0, strtab)
+            .build()
+    }
+
+    #[test]
+    fn symbols() {
+        let bytes = elf_with_symbols();
+        let elf = Ps2Elf::parse(&bytes).unwrap();
+        let symbol_table = elf.symbol_table().unwrap();
+
+        let symbols = symbol_table.iter().collect::<Vec<_>>();
+        assert_eq!(symbols.len(), 4);
+        assert_eq!(symbols[1].name, "main");
+        assert_eq!(symbols[1].value, 0x0010_0000);
+        assert_eq!(symbols[1].size, 0x40);
+        assert_eq!(symbols[1].binding, SymbolBinding::Global);
+        assert_eq!(symbols[2].name, "helper");
+        assert_eq!(symbols[2].binding, SymbolBinding::Local);
+    }
+
+    #[test]
+    fn lookup() {
+        let bytes = elf_with_symbols();
+        let elf = Ps2Elf::parse(&bytes).unwrap();
+        let symbol_table = elf.symbol_table().unwrap();
+
+        assert_eq!(symbol_table.lookup("main"), Some(0x0010_0000));
+        assert_eq!(symbol_table.lookup("helper"), Some(0x0010_0040));
+        assert_eq!(symbol_table.lookup("printf"), None);
+        assert_eq!(symbol_table.lookup("missing"), None);
+    }
+
+    #[test]
+    fn stripped() {
+        let bytes = ElfBuilder::new()
+            .segment(0x0010_0000, &[0; 4])
+            .section(".text", 1, 0x0010_0000, &[0; 4])
+            .build();
+        let elf = Ps2Elf::parse(&bytes).unwrap();
+
+        assert_eq!(elf.symbol_table(), None);
+    }
+}
@@ -0,0 +1,158 @@
+//! `ElfBuilder`, assembling little-endian MIPS executables
+//!
+//! Segments become `PT_LOAD` program headers, sections are appended after them together with a `.shstrtab`. The
+//! entry point defaults to `0x0010_0000`, the usual load address of PS2 games
+
+pub struct Section {
+    pub name: &'static str,
+    pub kind: u32,
+    pub flags: u32,
+    pub address: u32,
+    pub data: Vec<u8>,
+    pub link: u32,
+    pub entry_size: u32,
+}
+
+pub struct ElfBuilder {
+    machine: u16,
+    kind: u16,
+    entry: u32,
+    segments: Vec<(u32, Vec<u8>)>,
+    sections: Vec<Section>,
+}
+
+impl ElfBuilder {
+    pub fn new() -> Self {
+        Self {
+            machine: 8,
+            kind: 2,
+            entry: 0x0010_0000,
+            segments: Vec::new(),
+            sections: Vec::new(),
+        }
+    }
+
+    pub fn machine(mut self, machine: u16) -> Self {
+        self.machine = machine;
+        self
+    }
+
+    pub fn kind(mut self, kind: u16) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    pub fn segment(mut self, address: u32, data: &[u8]) -> Self {
+        self.segments.push((address, data.to_vec()));
+        self
+    }
+
+    pub fn section(self, name: &'static str, kind: u32, address: u32, data: &[u8]) -> Self {
+        self.raw_section(Section {
+            name,
+            kind,
+            flags: 0,
+            address,
+            data: data.to_vec(),
+            link: 0,
+            entry_size: 0,
+        })
+    }
+
+    pub fn raw_section(mut self, section: Section) -> Self {
+        self.sections.push(section);
+        self
+    }
+
+    pub fn build(self) -> Vec<u8> {
+        let mut shstrtab = vec![0];
+        let mut name_offsets = Vec::new();
+        for name in self.sections.iter().map(|s| s.name).chain([".shstrtab"]) {
+            name_offsets.push(u32::try_from(shstrtab.len()).unwrap());
+            shstrtab.extend_from_slice(name.as_bytes());
+            shstrtab.push(0);
+        }
+
+        let phoff = 52;
+        let mut data = Vec::new();
+        let mut offset = phoff + 32 * self.segments.len();
+
+        let mut segment_offsets = Vec::new();
+        for (_, segment) in &self.segments {
+            segment_offsets.push(offset);
+            offset += segment.len();
+            data.extend_from_slice(segment);
+        }
+        let mut section_offsets = Vec::new();
+        for section in &self.sections {
+            section_offsets.push(offset);
+            offset += section.data.len();
+            data.extend_from_slice(&section.data);
+        }
+        let shstrtab_offset = offset;
+        offset += shstrtab.len();
+        data.extend_from_slice(&shstrtab);
+        let shoff = offset;
+
+        let u16 = |value: usize| u16::try_from(value).unwrap().to_le_bytes();
+        let u32 = |value: usize| u32::try_from(value).unwrap().to_le_bytes();
+
+        let mut out = Vec::new();
+        out.extend_from_slice(b"\x7FELF\x01\x01\x01\x00");
+        out.extend_from_slice(&[0; 8]);
+        out.extend_from_slice(&self.kind.to_le_bytes());
+        out.extend_from_slice(&self.machine.to_le_bytes());
+        out.extend_from_slice(&1_u32.to_le_bytes());
+        out.extend_from_slice(&self.entry.to_le_bytes());
+        out.extend_from_slice(&u32(phoff));
+        out.extend_from_slice(&u32(shoff));
+        out.extend_from_slice(&0x2092_4001_u32.to_le_bytes());
+        out.extend_from_slice(&u16(52));
+        out.extend_from_slice(&u16(32));
+        out.extend_from_slice(&u16(self.segments.len()));
+        out.extend_from_slice(&u16(40));
+        out.extend_from_slice(&u16(self.sections.len() + 2));
+        out.extend_from_slice(&u16(self.sections.len() + 1));
+
+        for ((address, segment), offset) in self.segments.iter().zip(segment_offsets) {
+            // PT_LOAD, readable + executable
+            for value in [1, offset, *address as usize, *address as usize] {
+                out.extend_from_slice(&u32(value));
+            }
+            out.extend_from_slice(&u32(segment.len()));
+            out.extend_from_slice(&u32(segment.len()));
+            out.extend_from_slice(&u32(5));
+            out.extend_from_slice(&u32(16));
+        }
+
+        out.extend_from_slice(&data);
+
+        // SHN_UNDEF
+        out.extend_from_slice(&[0; 40]);
+        for ((section, offset), name) in
+            self.sections.iter().zip(section_offsets).zip(&name_offsets)
+        {
+            out.extend_from_slice(&name.to_le_bytes());
+            out.extend_from_slice(&section.kind.to_le_bytes());
+            out.extend_from_slice(&section.flags.to_le_bytes());
+            out.extend_from_slice(&section.address.to_le_bytes());
+            out.extend_from_slice(&u32(offset));
+            out.extend_from_slice(&u32(section.data.len()));
+            out.extend_from_slice(&section.link.to_le_bytes());
+            out.extend_from_slice(&0_u32.to_le_bytes());
+            out.extend_from_slice(&4_u32.to_le_bytes());
+            out.extend_from_slice(&section.entry_size.to_le_bytes());
+        }
+        // .shstrtab (SHT_STRTAB)
+        out.extend_from_slice(&name_offsets.last().unwrap().to_le_bytes());
+        out.extend_from_slice(&3_u32.to_le_bytes());
+        out.extend_from_slice(&[0; 8]);
+        out.extend_from_slice(&u32(shstrtab_offset));
+        out.extend_from_slice(&u32(shstrtab.len()));
+        out.extend_from_slice(&[0; 8]);
+        out.extend_from_slice(&1_u32.to_le_bytes());
+        out.extend_from_slice(&0_u32.to_le_bytes());
+
+        out
+    }
+}
@@ -0,0 +1,291 @@
+#![doc = include_str!("../README.md")]
+#![forbid(missing_docs, unsafe_code)]
+#![warn(clippy::all, clippy::pedantic)]
+
+use std::{
+    error::Error as StdError,
+    fmt::{self, Display},
+};
+
+/// Mask of the address bits in the first half of a code
+const ADDRESS_MASK: u32 = 0x0FFF_FFFF;
+
+/// Errors that might occur when parsing cheat codes
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum GameSharkError {
+    /// A line doesn't consist of exactly 16 hexadecimal digits
+    InvalidLength {
+        /// 1-based line number the problem occurred on
+        line: usize,
+    },
+
+    /// A line contains something other than hexadecimal digits and whitespace
+    InvalidDigit {
+        /// 1-based line number the problem occurred on
+        line: usize,
+    },
+
+    /// The top nibble of a code doesn't identify a known code type
+    UnknownCodeType {
+        /// 1-based line number the problem occurred on
+        line: usize,
+    },
+}
+
+impl Display for GameSharkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+impl StdError for GameSharkError {}
+
+/// Single decrypted code, written as `TAAAAAAA VVVVVVVV`
+///
+/// The top nibble `T` selects the code type, the remaining 28 bits `A` are the address:
+///
+/// | Type | Code                       |
+/// |------|----------------------------|
+/// | `2`  | [`GameSharkCode::Write8`]  |
+/// | `3`  | [`GameSharkCode::Write16`] |
+/// | `4`  | [`GameSharkCode::Write32`] |
+/// | `9`  | [`GameSharkCode::Master`]  |
+///
+/// Other types, such as the copy (`5`), pointer write (`6`) and timer (`B`) codes of the Codebreaker and Action
+/// Replay 2 format, aren't supported. Neither are increment, decrement and serial check codes
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum GameSharkCode {
+    /// Master code, hooking the game at the given address
+    Master {
+        /// Address of the hook
+        address: u32,
+
+        /// Instruction found at the address
+        value: u32,
+    },
+
+    /// Write the lowest 8 bits of the value
+    Write8 {
+        /// Address written to
+        address: u32,
+
+        /// Written value
+        value: u8,
+    },
+
+    /// Write the lowest 16 bits of the value
+    Write16 {
+        /// Address written to
+        address: u32,
+
+        /// Written value
+        value: u16,
+    },
+
+    /// Write the whole 32 bit value
+    Write32 {
+        /// Address written to
+        address: u32,
+
+        /// Written value
+        value: u32,
+    },
+}
+
+impl GameSharkCode {
+    /// Parse a list of codes, one per line
+    ///
+    /// Whitespace inside of a code (`2034A5C0 00000063`) is ignored. Blank lines and comment lines starting
+    /// with `//` or `#` are skipped
+    ///
+    /// # Errors
+    ///
+    /// - A line doesn't contain exactly 16 hexadecimal digits
+    /// - A code has an unknown type
+    pub fn parse(text: &str) -> Result<Vec<Self>, GameSharkError> {
+        let mut codes = Vec::new();
+        for (line_number, line) in (1..).zip(text.lines()) {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with("//") || line.starts_with('#') {
+                continue;
+            }
+
+            let digits = line
+                .chars()
+                .filter(|c| !c.is_whitespace())
+                .collect::<String>();
+            if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Err(GameSharkError::InvalidDigit { line: line_number });
+            }
+            if digits.len() != 16 {
+                return Err(GameSharkError::InvalidLength { line: line_number });
+            }
+
+            let parse = |digits| {
+                u32::from_str_radix(digits, 16)
+                    .map_err(|_| GameSharkError::InvalidDigit { line: line_number })
+            };
+            let code = Self::from_parts(parse(&digits[..8])?, parse(&digits[8..])?)
+                .ok_or(GameSharkError::UnknownCodeType { line: line_number })?;
+            codes.push(code);
+        }
+
+        Ok(codes)
+    }
+
+    /// Decode a code from its two halves, `None` if the type is unknown
+    #[must_use]
+    pub fn from_parts(code: u32, value: u32) -> Option<Self> {
+        let address = code & ADDRESS_MASK;
+        let [low, high, ..] = value.to_le_bytes();
+
+        let code = match code >> 28 {
+            0x2 => Self::Write8 {
+                address,
+                value: low,
+            },
+            0x3 => Self::Write16 {
+                address,
+                value: u16::from_le_bytes([low, high]),
+            },
+            0x4 => Self::Write32 { address, value },
+            0x9 => Self::Master { address, value },
+            _ => return None,
+        };
+
+        Some(code)
+    }
+}
+
+/// Execute a list of codes against `memory`, which starts at address 0
+///
+/// `master_verified` signals that the hook of the master code has been reached. Without it, lists containing
+/// a master code aren't executed at all. Codes outside of the memory are skipped, values are little-endian
+///
+/// Returns the number of codes that modified the memory
+pub fn apply(codes: &[GameSharkCode], memory: &mut [u8], master_verified: bool) -> usize {
+    let has_master = codes
+        .iter()
+        .any(|code| matches!(code, GameSharkCode::Master { .. }));
+    if has_master && !master_verified {
+        return 0;
+    }
+
+    codes.iter().filter(|code| execute(**code, memory)).count()
+}
+
+/// Execute a single code, returning whether the memory was modified
+fn execute(code: GameSharkCode, memory: &mut [u8]) -> bool {
+    let (address, bytes, size) = match code {
+        GameSharkCode::Write8 { address, value } => (address, u32::from(value), 1),
+        GameSharkCode::Write16 { address, value } => (address, u32::from(value), 2),
+        GameSharkCode::Write32 { address, value } => (address, value, 4),
+        GameSharkCode::Master { .. } => return false,
+    };
+
+    let start = address as usize;
+    match memory.get_mut(start..start + size) {
+        Some(target) => {
+            target.copy_from_slice(&bytes.to_le_bytes()[..size]);
+            true
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{apply, GameSharkCode, GameSharkError};
+
+    const CODES: &str = "// Master code
+9029BEAC 0C0A9225
+
+2034A5C0 00000063
+3034A5C4 0000270F
+4034A5C8 05F5E0FF
+";
+
+    #[test]
+    fn parse() {
+        let codes = GameSharkCode::parse(CODES).unwrap();
+        assert_eq!(
+            codes,
+            [
+                GameSharkCode::Master {
+                    address: 0x0029_BEAC,
+                    value: 0x0C0A_9225,
+                },
+                GameSharkCode::Write8 {
+                    address: 0x0034_A5C0,
+                    value: 99,
+                },
+                GameSharkCode::Write16 {
+                    address: 0x0034_A5C4,
+                    value: 9999,
+                },
+                GameSharkCode::Write32 {
+                    address: 0x0034_A5C8,
+                    value: 99_999_999,
+                },
+            ]
+        );
+
+        // Whitespace inside of a code is optional
+        assert_eq!(
+            GameSharkCode::parse("2034A5C000000063").unwrap(),
+            codes[1..2]
+        );
+    }
+
+    #[test]
+    fn apply_codes() {
+        let codes = GameSharkCode::parse(CODES).unwrap();
+        let mut memory = vec![0; 0x0034_A5CC];
+
+        // The master code hasn't been reached yet
+        assert_eq!(apply(&codes, &mut memory, false), 0);
+        assert!(memory[0x0034_A5C0..0x0034_A5CC].iter().all(|&b| b == 0));
+
+        assert_eq!(apply(&codes, &mut memory, true), 3);
+        assert_eq!(memory[0x0034_A5C0], 99);
+        assert_eq!(memory[0x0034_A5C4..0x0034_A5C6], 9999_u16.to_le_bytes());
+        assert_eq!(
+            memory[0x0034_A5C8..0x0034_A5CC],
+            99_999_999_u32.to_le_bytes()
+        );
+
+        // Codes without a master code don't need verification, codes outside of the memory are skipped
+        let codes = GameSharkCode::parse("20000001 000000AB\n40000010 12345678").unwrap();
+        let mut small = vec![0; 0x10];
+        assert_eq!(apply(&codes, &mut small, false), 1);
+        assert_eq!(small[1], 0xAB);
+    }
+
+    #[test]
+    fn invalid() {
+        assert_eq!(
+            GameSharkCode::parse("2034A5C0 000063"),
+            Err(GameSharkError::InvalidLength { line: 1 })
+        );
+        assert_eq!(
+            GameSharkCode::parse("\n2034A5C0 0000006G"),
+            Err(GameSharkError::InvalidDigit { line: 2 })
+        );
+        assert_eq!(
+            GameSharkCode::parse("F034A5C0 00000063"),
+            Err(GameSharkError::UnknownCodeType { line: 1 })
+        );
+
+        // Copy, pointer write and timer codes
+        for code in [
+            "5034A5CC 00000010",
+            "6034A5D0 00000001",
+            "B0000000 00021348",
+        ] {
+            assert_eq!(
+                GameSharkCode::parse(code),
+                Err(GameSharkError::UnknownCodeType { line: 1 })
+            );
+        }
+    }
+}
@@ -0,0 +1,285 @@
+use crate::{
+    packed::{self, INITIAL_Q},
+    GifFlag, GifTag, GsReg, DESCRIPTOR_AD, MAX_NLOOP, MAX_REGS,
+};
+
+/// Builder for a GIF packet consisting of a single GIF tag and its data
+///
+/// In PACKED mode registers with a descriptor of their own (e.g. `PRIM` or `XYZ2`) are converted into the PACKED
+/// layout of the descriptor, any other register is written through A+D. As the PACKED form of RGBAQ takes its Q from
+/// the last ST write, every ST write carries the Q of the next RGBAQ write, or 1.0 if there is none before the next
+/// ST write
+#[derive(Clone, Debug)]
+pub struct GifPacketBuilder {
+    tag: GifTag,
+    regs: Vec<GsReg>,
+    values: Vec<u64>,
+    image: Vec<u128>,
+}
+
+impl GifPacketBuilder {
+    /// Create a builder for a packet that writes `regs` `nloop` times
+    ///
+    /// In IMAGE mode `regs` is ignored and `nloop` is the number of quadwords of image data
+    ///
+    /// # Panics
+    ///
+    /// - `nloop` doesn't fit into 15 bits
+    /// - There are no or more than 16 registers (unless in IMAGE mode)
+    /// - A register without a descriptor of its own is used in REGLIST mode
+    #[must_use]
+    pub fn new(nloop: u16, flag: GifFlag, regs: &[GsReg]) -> Self {
+        assert!(nloop <= MAX_NLOOP, "NLOOP has to fit into 15 bits");
+        let regs = if flag == GifFlag::Image {
+            Vec::new()
+        } else {
+            regs.to_vec()
+        };
+        if flag != GifFlag::Image {
+            assert!(
+                (1..=MAX_REGS).contains(&regs.len()),
+                "a GIF tag has between 1 and 16 register descriptors"
+            );
+        }
+        if flag == GifFlag::Reglist {
+            assert!(
+                regs.iter().all(|reg| reg.descriptor() != DESCRIPTOR_AD),
+                "REGLIST mode can't write registers through A+D"
+            );
+        }
+
+        Self {
+            tag: GifTag {
+                nloop,
+                eop: false,
+                prim: None,
+                flag,
+                regs: regs.iter().map(|reg| reg.descriptor()).collect(),
+            },
+            regs,
+            values: Vec::new(),
+            image: Vec::new(),
+        }
+    }
+
+    /// Mark the packet as the last one of the transfer
+    #[must_use]
+    pub fn end_of_packet(mut self) -> Self {
+        self.tag.eop = true;
+        self
+    }
+
+    /// Set the PRIM register before transferring the data
+    ///
+    /// # Panics
+    ///
+    /// - The packet isn't in PACKED mode, the GIF ignores PRE in the other modes
+    #[must_use]
+    pub fn prim(mut self, prim: u16) -> Self {
+        assert!(
+            self.tag.flag == GifFlag::Packed,
+            "PRIM can only be set in PACKED mode"
+        );
+        self.tag.prim = Some(prim);
+        self
+    }
+
+    /// Write the next register value
+    ///
+    /// # Panics
+    ///
+    /// - The packet is in IMAGE mode
+    /// - `reg` isn't the next register of the descriptor list
+    /// - All register values have been written already
+    pub fn write_reg(&mut self, reg: GsReg, value: u64) {
+        assert!(
+            self.tag.flag != GifFlag::Image,
+            "IMAGE mode carries no register values"
+        );
+        assert!(
+            self.values.len() < self.expected_values(),
+            "all register values have been written already"
+        );
+        let expected = self.regs[self.values.len() % self.regs.len()];
+        assert_eq!(
+            reg, expected,
+            "registers have to be written in the order of the descriptors"
+        );
+
+        self.values.push(value);
+    }
+
+    /// Append raw image data
+    ///
+    /// # Panics
+    ///
+    /// - The packet isn't in IMAGE mode
+    /// - The data exceeds NLOOP quadwords
+    pub fn write_image(&mut self, data: &[u128]) {
+        assert!(
+            self.tag.flag == GifFlag::Image,
+            "image data can only be written in IMAGE mode"
+        );
+        assert!(
+            self.image.len() + data.len() <= usize::from(self.tag.nloop),
+            "the image data exceeds NLOOP quadwords"
+        );
+
+        self.image.extend_from_slice(data);
+    }
+
+    /// Finish the packet, returning the GIF tag followed by its data
+    ///
+    /// # Panics
+    ///
+    /// - Not all register values or image data have been written
+    #[must_use]
+    pub fn finish(self) -> Vec<u128> {
+        let mut packet = vec![self.tag.encode()];
+        match self.tag.flag {
+            GifFlag::Packed => {
+                assert_eq!(
+                    self.values.len(),
+                    self.expected_values(),
+                    "missing register values"
+                );
+                let writes = self
+                    .regs
+                    .iter()
+                    .copied()
+                    .cycle()
+                    .zip(self.values.iter().copied())
+                    .collect::<Vec<_>>();
+                packet.extend(writes.iter().enumerate().map(|(i, &(reg, value))| {
+                    let q = writes[i + 1..]
+                        .iter()
+                        .take_while(|&&(reg, _)| reg != GsReg::St)
+                        .find(|&&(reg, _)| reg == GsReg::Rgbaq)
+                        .map_or(INITIAL_Q, |&(_, rgbaq)| u32::try_from(rgbaq >> 32).unwrap());
+                    packed::encode(reg, value, q)
+                }));
+            }
+            GifFlag::Reglist => {
+                assert_eq!(
+                    self.values.len(),
+                    self.expected_values(),
+                    "missing register values"
+                );
+                // An odd number of values is padded with an unused upper half
+                packet.extend(self.values.chunks(2).map(|values| {
+                    u128::from(values[0]) | u128::from(values.get(1).copied().unwrap_or(0)) << 64
+                }));
+            }
+            GifFlag::Image => {
+                assert_eq!(
+                    self.image.len(),
+                    usize::from(self.tag.nloop),
+                    "missing image data"
+                );
+                packet.extend_from_slice(&self.image);
+            }
+        }
+
+        packet
+    }
+
+    /// Number of register values the packet holds
+    fn expected_values(&self) -> usize {
+        usize::from(self.tag.nloop) * self.regs.len()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{GifFlag, GifPacketBuilder, GsReg};
+
+    #[test]
+    fn packed() {
+        let mut builder =
+            GifPacketBuilder::new(1, GifFlag::Packed, &[GsReg::Prim, GsReg::Frame]).end_of_packet();
+        builder.write_reg(GsReg::Prim, 0x6);
+        builder.write_reg(GsReg::Frame, 0x0010_0000);
+        let packet = builder.finish();
+
+        assert_eq!(packet.len(), 3);
+        assert_eq!(packet[0], 1 | 1 << 15 | 2 << 60 | 0xE0 << 64);
+        assert_eq!(packet[1], 0x6);
+        assert_eq!(packet[2], 0x0010_0000 | 0x4C << 64);
+
+        let mut builder = GifPacketBuilder::new(1, GifFlag::Packed, &[GsReg::Xyz2]).prim(0x3);
+        builder.write_reg(GsReg::Xyz2, 0);
+        assert_eq!(
+            builder.finish()[0],
+            1 | 1 << 46 | 0x3 << 47 | 1 << 60 | 0x5 << 64
+        );
+    }
+
+    #[test]
+    fn packed_q() {
+        let regs = [GsReg::St, GsReg::Rgbaq, GsReg::Xyz2];
+        let mut builder = GifPacketBuilder::new(2, GifFlag::Packed, &regs);
+        builder.write_reg(GsReg::St, 0x3F00_0000_3E80_0000);
+        builder.write_reg(GsReg::Rgbaq, 0x4000_0000_8080_8080);
+        builder.write_reg(GsReg::Xyz2, 0);
+        builder.write_reg(GsReg::St, 0);
+        builder.write_reg(GsReg::Rgbaq, 0x8080_8080);
+        builder.write_reg(GsReg::Xyz2, 0);
+        let packet = builder.finish();
+
+        // ST carries the Q of the following RGBAQ write
+        assert_eq!(packet[1], 0x3F00_0000_3E80_0000 | 0x4000_0000 << 64);
+        assert_eq!(packet[4], 0);
+
+        // Without an RGBAQ write Q is 1.0
+        let mut builder = GifPacketBuilder::new(1, GifFlag::Packed, &[GsReg::St]);
+        builder.write_reg(GsReg::St, 0);
+        assert_eq!(builder.finish()[1], 0x3F80_0000 << 64);
+    }
+
+    #[test]
+    fn reglist() {
+        let mut builder =
+            GifPacketBuilder::new(3, GifFlag::Reglist, &[GsReg::Rgbaq, GsReg::Xyz2, GsReg::Uv]);
+        for i in 0..3 {
+            builder.write_reg(GsReg::Rgbaq, 0x10 + i);
+            builder.write_reg(GsReg::Xyz2, 0x20 + i);
+            builder.write_reg(GsReg::Uv, 0x30 + i);
+        }
+        let packet = builder.finish();
+
+        // 9 values take up 5 quadwords
+        assert_eq!(packet.len(), 6);
+        assert_eq!(packet[0], 3 | 1 << 58 | 3 << 60 | 0x351 << 64);
+        assert_eq!(packet[1], 0x10 | 0x20 << 64);
+        assert_eq!(packet[2], 0x30 | 0x11 << 64);
+        assert_eq!(packet[5], 0x32);
+    }
+
+    #[test]
+    fn image() {
+        let mut builder = GifPacketBuilder::new(2, GifFlag::Image, &[]);
+        builder.write_image(&[0x1234, u128::MAX]);
+        let packet = builder.finish();
+
+        assert_eq!(packet, [2 << 58 | 2, 0x1234, u128::MAX]);
+    }
+
+    #[test]
+    #[should_panic = "registers have to be written in the order of the descriptors"]
+    fn wrong_register() {
+        let mut builder = GifPacketBuilder::new(1, GifFlag::Packed, &[GsReg::Prim, GsReg::Frame]);
+        builder.write_reg(GsReg::Frame, 0);
+    }
+
+    #[test]
+    #[should_panic = "REGLIST mode can't write registers through A+D"]
+    fn reglist_ad() {
+        let _ = GifPacketBuilder::new(1, GifFlag::Reglist, &[GsReg::Zbuf]);
+    }
+
+    #[test]
+    #[should_panic = "PRIM can only be set in PACKED mode"]
+    fn reglist_prim() {
+        let _ = GifPacketBuilder::new(1, GifFlag::Reglist, &[GsReg::Xyz2]).prim(0x3);
+    }
+}
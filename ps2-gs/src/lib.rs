@@ -0,0 +1,329 @@
+#![doc = include_str!("../README.md")]
+#![forbid(missing_docs, unsafe_code)]
+#![warn(clippy::all, clippy::pedantic)]
+
+use std::{
+    error::Error as StdError,
+    fmt::{self, Display},
+};
+
+mod builder;
+mod packed;
+mod parser;
+
+pub use self::{
+    builder::GifPacketBuilder,
+    parser::{GifPacket, GifPacketParser, GsWrite},
+};
+
+/// Register descriptor that skips a quadword
+const DESCRIPTOR_NOP: u8 = 0x0F;
+
+/// Register descriptor that writes the address-data pair in the lower and upper half of a quadword
+const DESCRIPTOR_AD: u8 = 0x0E;
+
+/// Maximum number of register descriptors in a GIF tag
+const MAX_REGS: usize = 16;
+
+/// Maximum value of the 15 bit NLOOP field
+const MAX_NLOOP: u16 = 0x7FFF;
+
+/// Errors that might occur when parsing a GIF stream
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum GsError {
+    /// The stream isn't made up of whole quadwords
+    Unaligned,
+
+    /// The stream ends before all data of a GIF tag
+    Truncated,
+
+    /// The last GIF tag of the stream doesn't have the EOP flag set
+    MissingEndOfPacket,
+}
+
+impl Display for GsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+impl StdError for GsError {}
+
+/// Data format of the data following a GIF tag
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum GifFlag {
+    /// Every register write takes up a full quadword
+    Packed,
+
+    /// Register values are packed as 64 bit words, two per quadword
+    Reglist,
+
+    /// Raw image data transferred to `HWREG`
+    Image,
+}
+
+impl GifFlag {
+    /// Value of the FLG field
+    fn bits(self) -> u8 {
+        match self {
+            Self::Packed => 0,
+            Self::Reglist => 1,
+            Self::Image => 2,
+        }
+    }
+
+    /// Decode the FLG field, the unused value 3 behaves like IMAGE
+    fn from_bits(bits: u8) -> Self {
+        match bits & 0b11 {
+            0 => Self::Packed,
+            1 => Self::Reglist,
+            _ => Self::Image,
+        }
+    }
+}
+
+/// GS register, the texture and drawing environment registers refer to context 1
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum GsReg {
+    /// Drawing primitive setting
+    Prim,
+
+    /// Vertex colour and texture coordinate Q
+    Rgbaq,
+
+    /// Texture coordinates of the vertex
+    St,
+
+    /// Texel coordinates of the vertex
+    Uv,
+
+    /// Vertex coordinates and fog coefficient, kicking the drawing
+    Xyzf2,
+
+    /// Vertex coordinates, kicking the drawing
+    Xyz2,
+
+    /// Vertex fog coefficient
+    Fog,
+
+    /// Vertex coordinates and fog coefficient, without kicking the drawing
+    Xyzf3,
+
+    /// Vertex coordinates, without kicking the drawing
+    Xyz3,
+
+    /// Texture information (`TEX0_1`)
+    Tex0,
+
+    /// Frame buffer setting (`FRAME_1`)
+    Frame,
+
+    /// Z buffer setting (`ZBUF_1`)
+    Zbuf,
+
+    /// Alpha blending setting (`ALPHA_1`)
+    Alpha,
+
+    /// Scissoring area (`SCISSOR_1`)
+    Scissor,
+
+    /// Pixel test control (`TEST_1`)
+    Test,
+
+    /// Any other register, identified by its address
+    Other(u8),
+}
+
+impl GsReg {
+    /// Address of the register
+    #[must_use]
+    pub fn address(self) -> u8 {
+        match self {
+            Self::Prim => 0x00,
+            Self::Rgbaq => 0x01,
+            Self::St => 0x02,
+            Self::Uv => 0x03,
+            Self::Xyzf2 => 0x04,
+            Self::Xyz2 => 0x05,
+            Self::Tex0 => 0x06,
+            Self::Fog => 0x0A,
+            Self::Xyzf3 => 0x0C,
+            Self::Xyz3 => 0x0D,
+            Self::Scissor => 0x40,
+            Self::Alpha => 0x42,
+            Self::Test => 0x47,
+            Self::Frame => 0x4C,
+            Self::Zbuf => 0x4E,
+            Self::Other(address) => address,
+        }
+    }
+
+    /// Look up the register at `address`, unnamed registers are returned as [`GsReg::Other`]
+    #[must_use]
+    pub fn from_address(address: u8) -> Self {
+        match address {
+            0x00 => Self::Prim,
+            0x01 => Self::Rgbaq,
+            0x02 => Self::St,
+            0x03 => Self::Uv,
+            0x04 => Self::Xyzf2,
+            0x05 => Self::Xyz2,
+            0x06 => Self::Tex0,
+            0x0A => Self::Fog,
+            0x0C => Self::Xyzf3,
+            0x0D => Self::Xyz3,
+            0x40 => Self::Scissor,
+            0x42 => Self::Alpha,
+            0x47 => Self::Test,
+            0x4C => Self::Frame,
+            0x4E => Self::Zbuf,
+            address => Self::Other(address),
+        }
+    }
+
+    /// Register descriptor of the GIF tag, registers without one of their own are written through A+D
+    fn descriptor(self) -> u8 {
+        match self.address() {
+            address if address < DESCRIPTOR_AD => address,
+            _ => DESCRIPTOR_AD,
+        }
+    }
+}
+
+/// Tag at the start of every GIF packet, describing the data that follows
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct GifTag {
+    /// Number of times the register descriptors are repeated, or the number of quadwords in IMAGE mode
+    pub nloop: u16,
+
+    /// Whether this is the last tag of the packet
+    pub eop: bool,
+
+    /// Value written to the PRIM register before the data is transferred, only used in PACKED mode
+    pub prim: Option<u16>,
+
+    /// Format of the data
+    pub flag: GifFlag,
+
+    /// 4 bit register descriptors, at most 16
+    pub regs: Vec<u8>,
+}
+
+impl GifTag {
+    /// Decode a tag from its quadword form
+    #[must_use]
+    // All extracted fields are masked to fit
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn decode(tag: u128) -> Self {
+        let bits = |shift: u32, mask: u128| tag >> shift & mask;
+        let nreg = match bits(60, 0xF) {
+            0 => MAX_REGS,
+            nreg => nreg as usize,
+        };
+
+        Self {
+            nloop: bits(0, u128::from(MAX_NLOOP)) as u16,
+            eop: bits(15, 1) == 1,
+            prim: (bits(46, 1) == 1).then(|| bits(47, 0x7FF) as u16),
+            flag: GifFlag::from_bits(bits(58, 0b11) as u8),
+            regs: (0..nreg)
+                .map(|i| bits(64 + 4 * i as u32, 0xF) as u8)
+                .collect(),
+        }
+    }
+
+    /// Encode the tag into its quadword form
+    #[must_use]
+    pub fn encode(&self) -> u128 {
+        let mut tag = u128::from(self.nloop & MAX_NLOOP);
+        tag |= u128::from(self.eop) << 15;
+        if let Some(prim) = self.prim {
+            tag |= 1 << 46;
+            tag |= u128::from(prim & 0x7FF) << 47;
+        }
+        tag |= u128::from(self.flag.bits()) << 58;
+        // An NREG of 0 stands for 16 descriptors
+        tag |= ((self.regs.len() % MAX_REGS) as u128) << 60;
+        for (i, &reg) in self.regs.iter().take(MAX_REGS).enumerate() {
+            tag |= u128::from(reg & 0x0F) << (64 + i * 4);
+        }
+
+        tag
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{GifFlag, GifTag, GsReg};
+
+    #[test]
+    fn tag_layout() {
+        let tag = GifTag {
+            nloop: 3,
+            eop: true,
+            prim: Some(0x6),
+            flag: GifFlag::Reglist,
+            regs: vec![0x1, 0x5],
+        };
+
+        // NLOOP 0-14, EOP 15, PRE 46, PRIM 47-57, FLG 58-59, NREG 60-63, REGS 64-127
+        let expected = 3 | 1 << 15 | 1 << 46 | 0x6 << 47 | 1 << 58 | 2 << 60 | 0x51 << 64;
+        assert_eq!(tag.encode(), expected);
+
+        let tag = GifTag {
+            nloop: 0x7FFF,
+            eop: false,
+            prim: None,
+            flag: GifFlag::Image,
+            regs: Vec::new(),
+        };
+        assert_eq!(tag.encode(), 0x7FFF | 2 << 58);
+
+        // 16 descriptors are encoded as an NREG of 0
+        let tag = GifTag {
+            regs: vec![0xE; 16],
+            flag: GifFlag::Packed,
+            ..tag
+        };
+        assert_eq!(tag.encode(), 0x7FFF | 0xEEEE_EEEE_EEEE_EEEE << 64);
+        assert_eq!(GifTag::decode(tag.encode()), tag);
+    }
+
+    #[test]
+    fn decode_tag() {
+        let tag =
+            GifTag::decode(3 | 1 << 15 | 1 << 46 | 0x6 << 47 | 1 << 58 | 2 << 60 | 0x51 << 64);
+        assert_eq!(
+            tag,
+            GifTag {
+                nloop: 3,
+                eop: true,
+                prim: Some(0x6),
+                flag: GifFlag::Reglist,
+                regs: vec![0x1, 0x5],
+            }
+        );
+
+        // NREG 0 means 16 descriptors, FLG 3 behaves like IMAGE
+        let tag = GifTag::decode(3 << 58);
+        assert_eq!(tag.regs.len(), 16);
+        assert_eq!(tag.flag, GifFlag::Image);
+    }
+
+    #[test]
+    fn register_descriptors() {
+        assert_eq!(GsReg::Prim.descriptor(), 0x00);
+        assert_eq!(GsReg::Xyz2.descriptor(), 0x05);
+        assert_eq!(GsReg::Tex0.descriptor(), 0x06);
+        assert_eq!(GsReg::Frame.descriptor(), 0x0E);
+        assert_eq!(GsReg::Frame.address(), 0x4C);
+        assert_eq!(GsReg::Test.address(), 0x47);
+        assert_eq!(GsReg::Other(0x50).descriptor(), 0x0E);
+
+        for address in 0..=0x7F {
+            assert_eq!(GsReg::from_address(address).address(), address);
+        }
+        assert_eq!(GsReg::from_address(0x4C), GsReg::Frame);
+        assert_eq!(GsReg::from_address(0x50), GsReg::Other(0x50));
+    }
+}
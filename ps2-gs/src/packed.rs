@@ -0,0 +1,157 @@
+//! Conversion between register values and the PACKED layout of their descriptors
+
+use crate::{GsReg, GsWrite, DESCRIPTOR_AD, DESCRIPTOR_NOP};
+
+/// Q value the GS starts out with (1.0)
+pub(crate) const INITIAL_Q: u32 = 0x3F80_0000;
+
+/// Convert a register value into the quadword written with the register's descriptor
+///
+/// Q can't be written through the PACKED form of RGBAQ (it's taken from the last ST write instead) and is dropped.
+/// ST writes carry `q` instead
+pub(crate) fn encode(reg: GsReg, value: u64, q: u32) -> u128 {
+    let bits = |shift: u32, mask: u64| u128::from(value >> shift & mask);
+    match reg {
+        GsReg::Rgbaq => {
+            bits(0, 0xFF) | bits(8, 0xFF) << 32 | bits(16, 0xFF) << 64 | bits(24, 0xFF) << 96
+        }
+        GsReg::St => u128::from(value) | u128::from(q) << 64,
+        GsReg::Uv => bits(0, 0x3FFF) | bits(16, 0x3FFF) << 32,
+        GsReg::Xyzf2 | GsReg::Xyzf3 => {
+            bits(0, 0xFFFF)
+                | bits(16, 0xFFFF) << 32
+                | bits(32, 0xFF_FFFF) << 68
+                | bits(56, 0xFF) << 100
+        }
+        GsReg::Xyz2 | GsReg::Xyz3 => {
+            bits(0, 0xFFFF) | bits(16, 0xFFFF) << 32 | bits(32, 0xFFFF_FFFF) << 64
+        }
+        GsReg::Fog => bits(56, 0xFF) << 100,
+        _ if reg.descriptor() == DESCRIPTOR_AD => {
+            u128::from(value) | u128::from(reg.address()) << 64
+        }
+        // PRIM, TEX0 and CLAMP use the register layout in the lower 64 bits
+        _ => u128::from(value),
+    }
+}
+
+/// Decode a quadword written with `descriptor`, `q` carries the Q value set by ST writes over to RGBAQ
+pub(crate) fn decode(descriptor: u8, qword: u128, q: &mut u32) -> Option<GsWrite> {
+    // All extracted fields are masked to fit
+    #[allow(clippy::cast_possible_truncation)]
+    let bits = |shift: u32, mask: u64| (qword >> shift) as u64 & mask;
+    // Redirects XYZ2 and XYZF2 writes to XYZ3 and XYZF3
+    let adc = bits(111, 1) == 1;
+
+    let (reg, value) = match descriptor {
+        DESCRIPTOR_NOP => return None,
+        DESCRIPTOR_AD => (
+            GsReg::from_address(bits(64, 0xFF).to_le_bytes()[0]),
+            bits(0, u64::MAX),
+        ),
+        0x01 => (
+            GsReg::Rgbaq,
+            bits(0, 0xFF)
+                | bits(32, 0xFF) << 8
+                | bits(64, 0xFF) << 16
+                | bits(96, 0xFF) << 24
+                | u64::from(*q) << 32,
+        ),
+        0x02 => {
+            *q = u32::try_from(bits(64, 0xFFFF_FFFF)).unwrap();
+            (GsReg::St, bits(0, u64::MAX))
+        }
+        0x03 => (GsReg::Uv, bits(0, 0x3FFF) | bits(32, 0x3FFF) << 16),
+        0x04 | 0x0C => (
+            if descriptor == 0x0C || adc {
+                GsReg::Xyzf3
+            } else {
+                GsReg::Xyzf2
+            },
+            bits(0, 0xFFFF)
+                | bits(32, 0xFFFF) << 16
+                | bits(68, 0xFF_FFFF) << 32
+                | bits(100, 0xFF) << 56,
+        ),
+        0x05 | 0x0D => (
+            if descriptor == 0x0D || adc {
+                GsReg::Xyz3
+            } else {
+                GsReg::Xyz2
+            },
+            bits(0, 0xFFFF) | bits(32, 0xFFFF) << 16 | bits(64, 0xFFFF_FFFF) << 32,
+        ),
+        0x0A => (GsReg::Fog, bits(100, 0xFF) << 56),
+        descriptor => (GsReg::from_address(descriptor), bits(0, u64::MAX)),
+    };
+
+    Some(GsWrite { reg, value })
+}
+
+#[cfg(test)]
+mod test {
+    use super::{decode, encode, INITIAL_Q};
+    use crate::{GsReg, GsWrite};
+
+    #[test]
+    fn round_trip() {
+        for (reg, value) in [
+            (GsReg::Prim, 0x0000_0000_0000_0156),
+            (GsReg::Rgbaq, 0x0000_0000_8040_20FF),
+            (GsReg::St, 0x3F00_0000_3E80_0000),
+            (GsReg::Uv, 0x0000_0000_0123_0456),
+            (GsReg::Xyzf2, 0x7F12_3456_1000_2000),
+            (GsReg::Xyz2, 0x1234_5678_1000_2000),
+            (GsReg::Xyz3, 0x1234_5678_1000_2000),
+            (GsReg::Fog, 0x7F00_0000_0000_0000),
+            (GsReg::Tex0, 0x1234_5678_9ABC_DEF0),
+            (GsReg::Frame, 0x0000_0000_0010_0000),
+            (GsReg::Other(0x50), 0x1234),
+        ] {
+            let mut q = INITIAL_Q;
+            let expected = match reg {
+                GsReg::Rgbaq => value | u64::from(INITIAL_Q) << 32,
+                _ => value,
+            };
+            assert_eq!(
+                decode(reg.descriptor(), encode(reg, value, INITIAL_Q), &mut q),
+                Some(GsWrite {
+                    reg,
+                    value: expected
+                }),
+                "{reg:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn packed_layout() {
+        // R, G, B and A each take up 32 bits
+        assert_eq!(
+            encode(GsReg::Rgbaq, 0x8040_20FF, INITIAL_Q),
+            0xFF | 0x20 << 32 | 0x40 << 64 | 0x80 << 96
+        );
+        assert_eq!(
+            encode(GsReg::Frame, 0x0010_0000, INITIAL_Q),
+            0x0010_0000 | 0x4C << 64
+        );
+
+        // ST sets the Q used by the following RGBAQ writes
+        let mut q = INITIAL_Q;
+        decode(0x02, 0x4000_0000 << 64, &mut q);
+        let rgbaq = decode(0x01, 0, &mut q).unwrap();
+        assert_eq!(rgbaq.value, 0x4000_0000 << 32);
+
+        // The Q of ST writes survives the round trip
+        let st = encode(GsReg::St, 0x3F00_0000_3E80_0000, 0x4000_0000);
+        assert_eq!(st >> 64, 0x4000_0000);
+        let mut q = INITIAL_Q;
+        decode(0x02, st, &mut q);
+        assert_eq!(q, 0x4000_0000);
+
+        // ADC turns XYZ2 writes into XYZ3 writes
+        let mut q = INITIAL_Q;
+        assert_eq!(decode(0x05, 1 << 111, &mut q).unwrap().reg, GsReg::Xyz3);
+        assert_eq!(decode(0x0F, u128::MAX, &mut q), None);
+    }
+}
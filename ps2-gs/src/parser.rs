@@ -0,0 +1,280 @@
+use crate::{
+    packed::{self, INITIAL_Q},
+    GifFlag, GifTag, GsError, GsReg, DESCRIPTOR_AD, DESCRIPTOR_NOP,
+};
+use std::mem;
+
+/// Single register write
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct GsWrite {
+    /// Register that is written
+    pub reg: GsReg,
+
+    /// Value written to the register, in the layout of the register
+    pub value: u64,
+}
+
+/// GIF tags up to and including one with the EOP flag set, together with their decoded data
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct GifPacket {
+    /// Tags of the packet
+    pub tags: Vec<GifTag>,
+
+    /// Register writes of all tags in order, including the PRIM writes of PACKED tags with PRE set
+    pub writes: Vec<GsWrite>,
+
+    /// Image data transferred by tags in IMAGE mode
+    pub image: Vec<u128>,
+}
+
+/// Parser for GIF streams, such as captured GIF DMA transfers
+#[derive(Clone, Copy, Debug)]
+pub struct GifPacketParser;
+
+impl GifPacketParser {
+    /// Parse a stream of little-endian quadwords into the packets it consists of
+    ///
+    /// # Errors
+    ///
+    /// - The stream isn't made up of whole quadwords
+    /// - A tag is followed by less data than it describes
+    /// - The stream doesn't end with a tag that has the EOP flag set
+    pub fn parse(data: &[u8]) -> Result<Vec<GifPacket>, GsError> {
+        if !data.len().is_multiple_of(16) {
+            return Err(GsError::Unaligned);
+        }
+
+        let mut qwords = data.chunks_exact(16).map(|qword| {
+            let mut bytes = [0; 16];
+            bytes.copy_from_slice(qword);
+            u128::from_le_bytes(bytes)
+        });
+        let mut packets = Vec::new();
+        let mut packet = GifPacket::default();
+        let mut q = INITIAL_Q;
+        while let Some(tag) = qwords.next() {
+            let tag = GifTag::decode(tag);
+            // The GIF ignores PRE outside of PACKED mode
+            if let Some(prim) = tag.prim.filter(|_| tag.flag == GifFlag::Packed) {
+                packet.writes.push(GsWrite {
+                    reg: GsReg::Prim,
+                    value: prim.into(),
+                });
+            }
+
+            let nloop = usize::from(tag.nloop);
+            match tag.flag {
+                GifFlag::Packed => {
+                    for &descriptor in tag.regs.iter().cycle().take(nloop * tag.regs.len()) {
+                        let qword = qwords.next().ok_or(GsError::Truncated)?;
+                        packet
+                            .writes
+                            .extend(packed::decode(descriptor, qword, &mut q));
+                    }
+                }
+                GifFlag::Reglist => {
+                    let count = nloop * tag.regs.len();
+                    let words = qwords
+                        .by_ref()
+                        .take(count.div_ceil(2))
+                        .flat_map(halves)
+                        .collect::<Vec<_>>();
+                    if words.len() < count {
+                        return Err(GsError::Truncated);
+                    }
+
+                    for (&descriptor, &word) in tag.regs.iter().cycle().zip(&words).take(count) {
+                        // A+D carries no address in REGLIST mode and is skipped like NOP
+                        if descriptor == DESCRIPTOR_AD || descriptor == DESCRIPTOR_NOP {
+                            continue;
+                        }
+
+                        packet.writes.push(GsWrite {
+                            reg: GsReg::from_address(descriptor),
+                            value: word,
+                        });
+                    }
+                }
+                GifFlag::Image => {
+                    for _ in 0..nloop {
+                        packet.image.push(qwords.next().ok_or(GsError::Truncated)?);
+                    }
+                }
+            }
+
+            let eop = tag.eop;
+            packet.tags.push(tag);
+            if eop {
+                packets.push(mem::take(&mut packet));
+            }
+        }
+
+        if !packet.tags.is_empty() {
+            return Err(GsError::MissingEndOfPacket);
+        }
+
+        Ok(packets)
+    }
+}
+
+/// Split a quadword into its lower and upper 64 bits
+#[allow(clippy::cast_possible_truncation)]
+fn halves(qword: u128) -> [u64; 2] {
+    [qword as u64, (qword >> 64) as u64]
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{GifFlag, GifPacketBuilder, GifPacketParser, GsError, GsReg, GsWrite};
+
+    fn to_bytes(qwords: &[u128]) -> Vec<u8> {
+        qwords
+            .iter()
+            .flat_map(|qword| qword.to_le_bytes())
+            .collect()
+    }
+
+    #[test]
+    fn parse() {
+        // Set up the drawing environment, then draw a sprite and upload a tiny texture
+        let mut env =
+            GifPacketBuilder::new(1, GifFlag::Packed, &[GsReg::Frame, GsReg::Zbuf]).end_of_packet();
+        env.write_reg(GsReg::Frame, 0x0010_0000);
+        env.write_reg(GsReg::Zbuf, 0x0100_0070);
+
+        let mut sprite = GifPacketBuilder::new(2, GifFlag::Reglist, &[GsReg::Rgbaq, GsReg::Xyz2]);
+        for (i, xyz) in [0x1000_1000, 0x2000_2000].into_iter().enumerate() {
+            sprite.write_reg(GsReg::Rgbaq, 0x8000_00FF + i as u64);
+            sprite.write_reg(GsReg::Xyz2, xyz);
+        }
+        let mut image = GifPacketBuilder::new(1, GifFlag::Image, &[]).end_of_packet();
+        image.write_image(&[0x1234]);
+
+        let mut sprite = sprite.finish();
+        // PRE is ignored in REGLIST mode
+        sprite[0] |= 1 << 46 | 0x6 << 47;
+        let stream = [env.finish(), sprite, image.finish()].concat();
+        let packets = GifPacketParser::parse(&to_bytes(&stream)).unwrap();
+
+        assert_eq!(packets.len(), 2);
+        assert_eq!(packets[0].tags.len(), 1);
+        assert_eq!(
+            packets[0].writes,
+            [
+                GsWrite {
+                    reg: GsReg::Frame,
+                    value: 0x0010_0000
+                },
+                GsWrite {
+                    reg: GsReg::Zbuf,
+                    value: 0x0100_0070
+                },
+            ]
+        );
+
+        assert_eq!(packets[1].tags.len(), 2);
+        let regs = packets[1]
+            .writes
+            .iter()
+            .map(|write| (write.reg, write.value))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            regs,
+            [
+                (GsReg::Rgbaq, 0x8000_00FF),
+                (GsReg::Xyz2, 0x1000_1000),
+                (GsReg::Rgbaq, 0x8000_0100),
+                (GsReg::Xyz2, 0x2000_2000),
+            ]
+        );
+        assert_eq!(packets[1].image, [0x1234]);
+    }
+
+    #[test]
+    fn parse_stream() {
+        let packets = GifPacketParser::parse(include_bytes!("../fixtures/stream.bin")).unwrap();
+        let regs = |packet: usize| {
+            packets[packet]
+                .writes
+                .iter()
+                .map(|write| (write.reg, write.value))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(packets.len(), 3);
+        assert_eq!(
+            packets
+                .iter()
+                .map(|packet| packet.tags.len())
+                .collect::<Vec<_>>(),
+            [1, 2, 2]
+        );
+
+        assert_eq!(
+            regs(0),
+            [
+                (GsReg::Frame, 0x000A_0000),
+                (GsReg::Zbuf, 0x0100_0070),
+                (GsReg::Scissor, 0x01BF_0000_027F_0000),
+                (GsReg::Test, 0x0005_0000),
+            ]
+        );
+
+        // Q of the RGBAQ writes is taken from the preceding ST write (1.0, 0.5 and 0.25)
+        assert_eq!(
+            regs(1),
+            [
+                (GsReg::Prim, 0x1B),
+                (GsReg::St, 0x0000_0000_0000_0000),
+                (GsReg::Rgbaq, 0x3F80_0000_8000_00FF),
+                (GsReg::Xyz3, 0x0000_1000_7000_6C00),
+                (GsReg::St, 0x0000_0000_3F80_0000),
+                (GsReg::Rgbaq, 0x3F00_0000_8000_FF00),
+                (GsReg::Xyz2, 0x0000_1000_7000_8C00),
+                (GsReg::St, 0x3F80_0000_0000_0000),
+                (GsReg::Rgbaq, 0x3E80_0000_80FF_0000),
+                (GsReg::Xyz2, 0x0000_2000_8C00_6C00),
+                (GsReg::Rgbaq, 0x3F80_0000_8080_8080),
+                (GsReg::Xyz2, 0x0000_3000_8C00_8C00),
+                (GsReg::Rgbaq, 0x3F80_0000_8040_2010),
+                (GsReg::Xyz2, 0x0000_3000_9000_7800),
+            ]
+        );
+        assert!(packets[1].image.is_empty());
+
+        assert_eq!(
+            regs(2),
+            [
+                (GsReg::Other(0x50), 0x0001_0020_0000_0000),
+                (GsReg::Other(0x51), 0),
+                (GsReg::Other(0x52), 0x0000_0002_0000_0002),
+                (GsReg::Other(0x53), 0),
+            ]
+        );
+        assert_eq!(
+            packets[2].image,
+            [0xFF00_00FF_FF00_FF00_FFFF_0000_FF00_00FF]
+        );
+        assert_eq!(packets[2].tags[1].flag, GifFlag::Image);
+    }
+
+    #[test]
+    fn reject_invalid() {
+        let mut builder = GifPacketBuilder::new(2, GifFlag::Image, &[]);
+        builder.write_image(&[1, 2]);
+        let bytes = to_bytes(&builder.finish());
+
+        assert_eq!(
+            GifPacketParser::parse(&bytes[..20]),
+            Err(GsError::Unaligned)
+        );
+        assert_eq!(
+            GifPacketParser::parse(&bytes[..32]),
+            Err(GsError::Truncated)
+        );
+        assert_eq!(
+            GifPacketParser::parse(&bytes),
+            Err(GsError::MissingEndOfPacket)
+        );
+        assert_eq!(GifPacketParser::parse(&[]), Ok(Vec::new()));
+    }
+}
@@ -0,0 +1,406 @@
+use crate::{checksum, ApaError, SECTOR_SIZE};
+use ps2_bytes::{read_u16_le, read_u32_le};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Size of a partition header, spanning two sectors
+pub(crate) const HEADER_SIZE: usize = 1024;
+
+/// Magic at offset 4 of every header
+pub(crate) const MAGIC: &[u8; 4] = b"APA\0";
+
+/// Flag of headers describing a sub-partition
+const FLAG_SUB: u16 = 0x0001;
+
+/// Offset of the sub-partition list in the header of a main partition
+const SUBS_OFFSET: usize = 0x200;
+
+/// Maximum number of sub-partitions per main partition
+const MAX_SUBS: usize = 64;
+
+/// Creation date and time of a partition
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct ApaTimestamp {
+    /// Year, e.g. 2004
+    pub year: u16,
+
+    /// Month (1-12)
+    pub month: u8,
+
+    /// Day of the month (1-31)
+    pub day: u8,
+
+    /// Hour (0-23)
+    pub hour: u8,
+
+    /// Minute (0-59)
+    pub minute: u8,
+
+    /// Second (0-59)
+    pub second: u8,
+}
+
+impl ApaTimestamp {
+    /// Parse the 8 byte on-disk form, which is shared with PFS
+    pub(crate) fn parse(bytes: &[u8]) -> Self {
+        // The first byte is unused
+        Self {
+            second: bytes[1],
+            minute: bytes[2],
+            hour: bytes[3],
+            day: bytes[4],
+            month: bytes[5],
+            year: read_u16_le(bytes, 6),
+        }
+    }
+
+    /// Encode into the 8 byte on-disk form
+    pub(crate) fn to_bytes(self) -> [u8; 8] {
+        let [year_low, year_high] = self.year.to_le_bytes();
+        [
+            0,
+            self.second,
+            self.minute,
+            self.hour,
+            self.day,
+            self.month,
+            year_low,
+            year_high,
+        ]
+    }
+
+    /// Current time in UTC
+    pub(crate) fn now() -> Self {
+        let since_epoch = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        Self::from_unix(since_epoch.as_secs())
+    }
+
+    /// Convert seconds since the Unix epoch, years past 65535 are clamped
+    pub(crate) fn from_unix(seconds: u64) -> Self {
+        // Civil calendar conversion with eras of 400 years, starting on March 1st of year 0
+        let days = seconds / 86400 + 719_468;
+        let era = days / 146_097;
+        let day_of_era = days % 146_097;
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let month_index = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+        let month = if month_index < 10 {
+            month_index + 3
+        } else {
+            month_index - 9
+        };
+        let year = era * 400 + year_of_era + u64::from(month <= 2);
+
+        let seconds_of_day = seconds % 86400;
+        let small = |value: u64| u8::try_from(value).unwrap();
+        Self {
+            year: u16::try_from(year).unwrap_or(u16::MAX),
+            month: small(month),
+            day: small(day),
+            hour: small(seconds_of_day / 3600),
+            minute: small(seconds_of_day / 60 % 60),
+            second: small(seconds_of_day % 60),
+        }
+    }
+}
+
+/// Sub-partition extending a main partition
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct SubPartition {
+    /// First sector of the sub-partition
+    pub start_lba: u32,
+
+    /// Length in sectors
+    pub length_sectors: u32,
+}
+
+/// Main partition of the APA table
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct ApaPartition {
+    /// Name of the partition, e.g. `__mbr` or `PP.SLUS-21348..GAME`
+    pub name: String,
+
+    /// Type of the partition, e.g. `0x0100` for PFS
+    pub type_id: u16,
+
+    /// Raw flags of the header
+    pub flags: u16,
+
+    /// First sector of the partition, where its header is located
+    pub start_lba: u32,
+
+    /// Length in sectors
+    pub length_sectors: u32,
+
+    /// When the partition was created
+    pub created: ApaTimestamp,
+
+    /// Sub-partitions extending the partition
+    pub sub_partitions: Vec<SubPartition>,
+}
+
+impl ApaPartition {
+    /// Total length in sectors, including the sub-partitions
+    #[must_use]
+    pub fn total_sectors(&self) -> u64 {
+        self.sub_partitions
+            .iter()
+            .map(|sub| u64::from(sub.length_sectors))
+            .sum::<u64>()
+            + u64::from(self.length_sectors)
+    }
+
+    /// Whether the partition is free space (`__empty`, type 0)
+    #[must_use]
+    pub fn is_free(&self) -> bool {
+        self.type_id == 0
+    }
+
+    fn parse(header: &[u8]) -> Self {
+        let name = &header[0x10..0x30];
+        let name_len = name.iter().position(|&b| b == 0).unwrap_or(name.len());
+        // Clamped, so malformed counts only lose sub-partitions
+        let sub_count = (read_u32_le(header, 0x4C) as usize).min(MAX_SUBS);
+
+        Self {
+            name: String::from_utf8_lossy(&name[..name_len]).into_owned(),
+            type_id: read_u16_le(header, 0x48),
+            flags: read_u16_le(header, 0x4A),
+            start_lba: read_u32_le(header, 0x40),
+            length_sectors: read_u32_le(header, 0x44),
+            created: ApaTimestamp::parse(&header[0x50..0x58]),
+            sub_partitions: header[SUBS_OFFSET..]
+                .chunks_exact(8)
+                .take(sub_count)
+                .map(|sub| SubPartition {
+                    start_lba: read_u32_le(sub, 0),
+                    length_sectors: read_u32_le(sub, 4),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// APA partition table of an HDD image
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct ApaTable {
+    partitions: Vec<ApaPartition>,
+}
+
+impl ApaTable {
+    /// Parse the partition table by following the linked list of headers starting at sector 0
+    ///
+    /// The list ends once a header links back to sector 0. Headers of sub-partitions are validated but
+    /// not listed separately, they're part of [`ApaPartition::sub_partitions`] of their main partition
+    ///
+    /// # Errors
+    ///
+    /// - The image ends before a header
+    /// - A header has an invalid magic or checksum
+    /// - The list contains a cycle not passing through sector 0
+    pub fn parse(bytes: &[u8]) -> Result<Self, ApaError> {
+        let mut partitions = Vec::new();
+        let mut visited = Vec::new();
+        let mut sector = 0;
+        loop {
+            let header = header(bytes, sector)?;
+            visited.push(sector);
+
+            let partition = ApaPartition::parse(header);
+            if partition.flags & FLAG_SUB == 0 {
+                partitions.push(partition);
+            }
+
+            let next = read_u32_le(header, 0x08);
+            if next == 0 {
+                break;
+            }
+            if visited.contains(&next) {
+                return Err(ApaError::InvalidLink { sector });
+            }
+            sector = next;
+        }
+
+        Ok(Self { partitions })
+    }
+
+    /// Main partitions in the order they're linked
+    #[must_use]
+    pub fn partitions(&self) -> &[ApaPartition] {
+        &self.partitions
+    }
+
+    /// Look up a main partition by its name
+    #[must_use]
+    pub fn partition(&self, name: &str) -> Option<&ApaPartition> {
+        self.partitions
+            .iter()
+            .find(|partition| partition.name == name)
+    }
+}
+
+/// Validated header located at `sector`
+fn header(bytes: &[u8], sector: u32) -> Result<&[u8], ApaError> {
+    let start = sector as usize * SECTOR_SIZE;
+    let header = bytes
+        .get(start..start + HEADER_SIZE)
+        .ok_or(ApaError::Truncated { sector })?;
+
+    if &header[4..8] != MAGIC {
+        return Err(ApaError::InvalidMagic { sector });
+    }
+    if read_u32_le(header, 0) != checksum(header) {
+        return Err(ApaError::InvalidChecksum { sector });
+    }
+
+    Ok(header)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        test_apa::{header, image, Header},
+        ApaError, ApaTable, ApaTimestamp, SubPartition, SECTOR_SIZE,
+    };
+
+    #[test]
+    fn parse() {
+        let table = ApaTable::parse(&image()).unwrap();
+        let partitions = table.partitions();
+        assert_eq!(partitions.len(), 10);
+
+        assert_eq!(partitions[0].name, "__mbr");
+        assert_eq!(partitions[0].type_id, 0x0001);
+        assert_eq!(partitions[0].start_lba, 0);
+        assert_eq!(
+            partitions[0].created,
+            ApaTimestamp {
+                year: 2004,
+                month: 3,
+                day: 15,
+                hour: 12,
+                minute: 34,
+                second: 56,
+            }
+        );
+
+        let names = partitions
+            .iter()
+            .map(|partition| partition.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            [
+                "__mbr",
+                "__net",
+                "__system",
+                "__sysconf",
+                "__common",
+                "PP.SLUS-21348..GAME",
+                "+OPL",
+                "__.POPS",
+                "PP.SCES-50051..GAME",
+                "__empty",
+            ]
+        );
+
+        let game = table.partition("PP.SLUS-21348..GAME").unwrap();
+        assert_eq!(game.type_id, 0x0100);
+        assert_eq!(game.start_lba, 10);
+        assert_eq!(game.length_sectors, 2);
+        assert_eq!(
+            game.sub_partitions,
+            [
+                SubPartition {
+                    start_lba: 22,
+                    length_sectors: 2,
+                },
+                SubPartition {
+                    start_lba: 24,
+                    length_sectors: 2,
+                },
+            ]
+        );
+        assert_eq!(game.total_sectors(), 6);
+        assert!(!game.is_free());
+        assert!(partitions[9].is_free());
+        assert!(table.partition("__missing").is_none());
+    }
+
+    #[test]
+    fn invalid() {
+        let mut bytes = image();
+        bytes[10 * SECTOR_SIZE + 0x44] ^= 1;
+        assert_eq!(
+            ApaTable::parse(&bytes),
+            Err(ApaError::InvalidChecksum { sector: 10 })
+        );
+
+        let mut bytes = image();
+        bytes[12 * SECTOR_SIZE + 4] = b'X';
+        assert_eq!(
+            ApaTable::parse(&bytes),
+            Err(ApaError::InvalidMagic { sector: 12 })
+        );
+
+        let bytes = image();
+        assert_eq!(
+            ApaTable::parse(&bytes[..5 * SECTOR_SIZE]),
+            Err(ApaError::Truncated { sector: 4 })
+        );
+
+        // Linking back to a header other than the first one
+        let mut bytes = header(&Header {
+            next: 2,
+            ..Header::new("__mbr", 0, 0x0001)
+        });
+        bytes.extend(header(&Header {
+            next: 2,
+            ..Header::new("__net", 2, 0x0100)
+        }));
+        assert_eq!(
+            ApaTable::parse(&bytes),
+            Err(ApaError::InvalidLink { sector: 2 })
+        );
+    }
+
+    #[test]
+    fn from_unix() {
+        assert_eq!(
+            ApaTimestamp::from_unix(0),
+            ApaTimestamp {
+                year: 1970,
+                month: 1,
+                day: 1,
+                hour: 0,
+                minute: 0,
+                second: 0,
+            }
+        );
+        assert_eq!(
+            ApaTimestamp::from_unix(951_868_799),
+            ApaTimestamp {
+                year: 2000,
+                month: 2,
+                day: 29,
+                hour: 23,
+                minute: 59,
+                second: 59,
+            }
+        );
+        assert_eq!(
+            ApaTimestamp::from_unix(1_079_354_096),
+            ApaTimestamp {
+                year: 2004,
+                month: 3,
+                day: 15,
+                hour: 12,
+                minute: 34,
+                second: 56,
+            }
+        );
+    }
+}
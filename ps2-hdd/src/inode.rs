@@ -0,0 +1,150 @@
+use crate::{checksum, ApaTimestamp, PfsError};
+use ps2_bytes::{read_u16_le, read_u32_le};
+
+/// Size of an inode, located at the start of its zone
+pub(crate) const INODE_SIZE: usize = 1024;
+
+/// Magic of inodes (`SEGD`)
+const SEGD_MAGIC: u32 = 0x5345_4744;
+
+/// Maximum number of data extents of an inode, the first one starting with the inode itself
+pub(crate) const MAX_EXTENTS: usize = 114;
+
+/// Mask of the file type bits of the mode
+pub(crate) const MODE_TYPE: u16 = 0xF000;
+
+/// File type of directories
+pub(crate) const MODE_DIRECTORY: u16 = 0x1000;
+
+/// File type of regular files
+pub(crate) const MODE_FILE: u16 = 0x2000;
+
+/// File type of symbolic links
+pub(crate) const MODE_SYMLINK: u16 = 0x4000;
+
+/// Run of consecutive zones
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub(crate) struct BlockInfo {
+    /// First zone
+    pub number: u32,
+
+    /// Sub-partition the zones are located in
+    pub subpart: u16,
+
+    /// Number of zones
+    pub count: u16,
+}
+
+impl BlockInfo {
+    pub(crate) fn parse(bytes: &[u8]) -> Self {
+        Self {
+            number: read_u32_le(bytes, 0),
+            subpart: read_u16_le(bytes, 4),
+            count: read_u16_le(bytes, 6),
+        }
+    }
+
+    pub(crate) fn write(self, out: &mut [u8]) {
+        out[..4].copy_from_slice(&self.number.to_le_bytes());
+        out[4..6].copy_from_slice(&self.subpart.to_le_bytes());
+        out[6..8].copy_from_slice(&self.count.to_le_bytes());
+    }
+}
+
+/// Inode of a file or directory
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub(crate) struct Inode {
+    /// Extents of the data, the first zone of the first one holds the inode itself
+    pub extents: Vec<BlockInfo>,
+    pub mode: u16,
+    pub attributes: u16,
+    pub uid: u16,
+    pub gid: u16,
+    pub accessed: ApaTimestamp,
+    pub created: ApaTimestamp,
+    pub modified: ApaTimestamp,
+    pub size: u64,
+}
+
+impl Inode {
+    /// Parse the inode located at `zone`
+    pub(crate) fn parse(bytes: &[u8], zone: u32) -> Result<Self, PfsError> {
+        let bytes = bytes
+            .get(..INODE_SIZE)
+            .ok_or(PfsError::InvalidInode { zone })?;
+        if read_u32_le(bytes, 4) != SEGD_MAGIC || read_u32_le(bytes, 0) != checksum(bytes) {
+            return Err(PfsError::InvalidInode { zone });
+        }
+
+        // Inodes with more extents than fit into a single one continue in indirect segments
+        if read_u32_le(bytes, 16) != 0 {
+            return Err(PfsError::Unsupported);
+        }
+
+        let extent_count = read_u32_le(bytes, 996) as usize;
+        if extent_count == 0 || extent_count > MAX_EXTENTS {
+            return Err(PfsError::InvalidInode { zone });
+        }
+
+        let extents = bytes[40..40 + extent_count * 8]
+            .chunks_exact(8)
+            .map(BlockInfo::parse)
+            .collect::<Vec<_>>();
+        if extents.iter().any(|extent| extent.count == 0) {
+            return Err(PfsError::InvalidInode { zone });
+        }
+
+        Ok(Self {
+            extents,
+            mode: read_u16_le(bytes, 952),
+            attributes: read_u16_le(bytes, 954),
+            uid: read_u16_le(bytes, 956),
+            gid: read_u16_le(bytes, 958),
+            accessed: ApaTimestamp::parse(&bytes[960..968]),
+            created: ApaTimestamp::parse(&bytes[968..976]),
+            modified: ApaTimestamp::parse(&bytes[976..984]),
+            size: u64::from(read_u32_le(bytes, 984)) | u64::from(read_u32_le(bytes, 988)) << 32,
+        })
+    }
+
+    /// Encode the inode, including its checksum
+    ///
+    /// # Panics
+    ///
+    /// - The inode has no extents or more than [`MAX_EXTENTS`]
+    pub(crate) fn encode(&self) -> [u8; INODE_SIZE] {
+        assert!((1..=MAX_EXTENTS).contains(&self.extents.len()));
+
+        let mut bytes = [0; INODE_SIZE];
+        bytes[4..8].copy_from_slice(&SEGD_MAGIC.to_le_bytes());
+        // Single segment, so its own block is also the last segment
+        self.extents[0].write(&mut bytes[8..16]);
+        self.extents[0].write(&mut bytes[24..32]);
+        for (extent, out) in self.extents.iter().zip(bytes[40..952].chunks_exact_mut(8)) {
+            extent.write(out);
+        }
+
+        bytes[952..954].copy_from_slice(&self.mode.to_le_bytes());
+        bytes[954..956].copy_from_slice(&self.attributes.to_le_bytes());
+        bytes[956..958].copy_from_slice(&self.uid.to_le_bytes());
+        bytes[958..960].copy_from_slice(&self.gid.to_le_bytes());
+        bytes[960..968].copy_from_slice(&self.accessed.to_bytes());
+        bytes[968..976].copy_from_slice(&self.created.to_bytes());
+        bytes[976..984].copy_from_slice(&self.modified.to_bytes());
+        bytes[984..992].copy_from_slice(&self.size.to_le_bytes());
+
+        let zones = self
+            .extents
+            .iter()
+            .map(|extent| u32::from(extent.count))
+            .sum::<u32>();
+        bytes[992..996].copy_from_slice(&zones.to_le_bytes());
+        bytes[996..1000].copy_from_slice(&u32::try_from(self.extents.len()).unwrap().to_le_bytes());
+        // Number of segments
+        bytes[1000..1004].copy_from_slice(&1_u32.to_le_bytes());
+
+        let sum = checksum(&bytes);
+        bytes[..4].copy_from_slice(&sum.to_le_bytes());
+        bytes
+    }
+}
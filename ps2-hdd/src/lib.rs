@@ -0,0 +1,122 @@
+#![doc = include_str!("../README.md")]
+#![forbid(missing_docs, unsafe_code)]
+#![warn(clippy::all, clippy::pedantic)]
+
+use std::{
+    error::Error as StdError,
+    fmt::{self, Display},
+};
+
+mod apa;
+mod inode;
+mod pfs;
+mod pfs_mut;
+#[cfg(test)]
+mod test_apa;
+#[cfg(test)]
+mod test_pfs;
+
+pub use self::{
+    apa::{ApaPartition, ApaTable, ApaTimestamp, SubPartition},
+    pfs::{PfsEntry, PfsEntryKind, PfsVolume},
+    pfs_mut::PfsVolumeMut,
+};
+
+/// Size of a sector of the HDD
+pub const SECTOR_SIZE: usize = 512;
+
+/// Errors that might occur when reading an HDD image
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum ApaError {
+    /// The image ends before the header at the given sector
+    Truncated {
+        /// Sector of the header
+        sector: u32,
+    },
+
+    /// The header at the given sector doesn't start with the `APA\0` magic
+    InvalidMagic {
+        /// Sector of the header
+        sector: u32,
+    },
+
+    /// The checksum of the header at the given sector doesn't match
+    InvalidChecksum {
+        /// Sector of the header
+        sector: u32,
+    },
+
+    /// The partition list links back to a header other than the first one
+    InvalidLink {
+        /// Sector of the header containing the link
+        sector: u32,
+    },
+}
+
+impl Display for ApaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+impl StdError for ApaError {}
+
+/// Errors that might occur when reading or modifying a PFS volume
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum PfsError {
+    /// The partition ends before the superblock, the bitmap or some data
+    Truncated,
+
+    /// The superblock has an invalid magic or zone size, or the root isn't a directory
+    InvalidSuperblock,
+
+    /// The inode at the given zone has an invalid magic, checksum or extent list
+    InvalidInode {
+        /// Zone of the inode
+        zone: u32,
+    },
+
+    /// A record of the directory whose inode is at the given zone is malformed
+    InvalidDirectory {
+        /// Zone of the directory's inode
+        zone: u32,
+    },
+
+    /// The path doesn't exist
+    NotFound,
+
+    /// A component of the path isn't a directory
+    NotADirectory,
+
+    /// The path points to something other than a regular file
+    NotAFile,
+
+    /// Something already exists at the path
+    AlreadyExists,
+
+    /// The name is empty, `.`, `..` or longer than 255 bytes
+    InvalidName,
+
+    /// There aren't enough free zones left, or they are too fragmented to fit into the extents of an inode
+    NoSpace,
+
+    /// The volume uses sub-partitions or inodes spanning several segments
+    Unsupported,
+}
+
+impl Display for PfsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+impl StdError for PfsError {}
+
+/// Checksum of an APA header or PFS inode, the wrapping sum of all little-endian words of the 1024 byte block
+/// except the first one, which holds the checksum itself
+fn checksum(block: &[u8]) -> u32 {
+    block[4..1024]
+        .chunks_exact(4)
+        .map(|word| u32::from_le_bytes(word.try_into().unwrap()))
+        .fold(0, u32::wrapping_add)
+}
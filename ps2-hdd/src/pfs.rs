@@ -0,0 +1,472 @@
+use crate::{
+    inode::{BlockInfo, Inode, INODE_SIZE, MODE_DIRECTORY, MODE_FILE, MODE_SYMLINK, MODE_TYPE},
+    ApaTimestamp, PfsError, SECTOR_SIZE,
+};
+use ps2_bytes::{read_u16_le, read_u32_le};
+use std::ops::Range;
+
+/// Offset of the superblock within the partition, following the area reserved for the APA header
+pub(crate) const SUPERBLOCK_OFFSET: usize = 8192 * SECTOR_SIZE;
+
+/// Magic of the superblock
+pub(crate) const SUPERBLOCK_MAGIC: u32 = 0x5046_5300;
+
+/// Size of the blocks directory entries are grouped into, entries never cross them
+pub(crate) const DENTRY_BLOCK_SIZE: usize = 512;
+
+/// Mask of the permission bits of the mode
+const MODE_PERMISSIONS: u16 = 0o777;
+
+/// Type of a [`PfsEntry`]
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum PfsEntryKind {
+    /// Regular file
+    File,
+
+    /// Directory
+    Directory,
+
+    /// Symbolic link
+    Symlink,
+}
+
+/// Entry of a directory, see [`PfsVolume::list_dir`]
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct PfsEntry {
+    /// Name of the entry
+    pub name: String,
+
+    /// Size of the entry's data in bytes
+    pub size: u64,
+
+    /// Type of the entry
+    pub kind: PfsEntryKind,
+
+    /// Unix-style permission bits, e.g. `0o777`
+    pub permissions: u16,
+
+    /// When the entry was created
+    pub created: ApaTimestamp,
+
+    /// When the entry was last modified
+    pub modified: ApaTimestamp,
+
+    /// When the entry was last accessed
+    pub accessed: ApaTimestamp,
+}
+
+impl PfsEntry {
+    /// Whether the entry is a directory
+    #[must_use]
+    pub fn is_dir(&self) -> bool {
+        self.kind == PfsEntryKind::Directory
+    }
+
+    fn new(name: &[u8], inode: &Inode) -> Self {
+        let kind = match inode.mode & MODE_TYPE {
+            MODE_DIRECTORY => PfsEntryKind::Directory,
+            MODE_SYMLINK => PfsEntryKind::Symlink,
+            _ => PfsEntryKind::File,
+        };
+
+        Self {
+            name: String::from_utf8_lossy(name).into_owned(),
+            size: inode.size,
+            kind,
+            permissions: inode.mode & MODE_PERMISSIONS,
+            created: inode.created,
+            modified: inode.modified,
+            accessed: inode.accessed,
+        }
+    }
+}
+
+/// Record of a directory
+pub(crate) struct Dentry<'d> {
+    /// Zone of the entry's inode, 0 for unused records
+    pub inode: u32,
+    pub subpart: u8,
+    pub name: &'d [u8],
+
+    /// Offset of the record within the directory's data
+    pub offset: usize,
+
+    /// Length of the record, which may be larger than needed for its name
+    pub len: usize,
+}
+
+/// Length of a record holding a name of the given length
+pub(crate) fn dentry_len(name_len: usize) -> usize {
+    (8 + name_len).next_multiple_of(4)
+}
+
+/// Encode a record into `out`, which has to be the record's full length
+pub(crate) fn write_dentry(out: &mut [u8], inode: u32, name: &[u8], mode: u16) {
+    out.fill(0);
+    out[..4].copy_from_slice(&inode.to_le_bytes());
+    out[5] = u8::try_from(name.len()).unwrap();
+    out[8..8 + name.len()].copy_from_slice(name);
+    set_dentry_len(out, out.len(), mode);
+}
+
+/// Change the length of the record at the start of `out`
+pub(crate) fn set_dentry_len(out: &mut [u8], len: usize, mode: u16) {
+    let a_len = u16::try_from(len).unwrap() | (mode & MODE_TYPE);
+    out[6..8].copy_from_slice(&a_len.to_le_bytes());
+}
+
+/// Parse all records of a directory whose inode is located at `zone`, including unused ones
+pub(crate) fn records(data: &[u8], zone: u32) -> Result<Vec<Dentry<'_>>, PfsError> {
+    let mut records = Vec::new();
+    for (block_index, block) in data.chunks(DENTRY_BLOCK_SIZE).enumerate() {
+        let mut offset = 0;
+        while offset < block.len() {
+            let record = &block[offset..];
+            if record.len() < 8 {
+                return Err(PfsError::InvalidDirectory { zone });
+            }
+
+            let name_len = usize::from(record[5]);
+            let len = usize::from(read_u16_le(record, 6) & !MODE_TYPE);
+            if len < dentry_len(name_len) || len > record.len() || len % 4 != 0 {
+                return Err(PfsError::InvalidDirectory { zone });
+            }
+
+            records.push(Dentry {
+                inode: read_u32_le(record, 0),
+                subpart: record[4],
+                name: &record[8..8 + name_len],
+                offset: block_index * DENTRY_BLOCK_SIZE + offset,
+                len,
+            });
+            offset += len;
+        }
+    }
+
+    Ok(records)
+}
+
+/// Parse the records of a directory whose inode is located at `zone`
+///
+/// Unused records (inode 0) are skipped
+pub(crate) fn dentries(data: &[u8], zone: u32) -> Result<Vec<Dentry<'_>>, PfsError> {
+    let mut entries = records(data, zone)?;
+    entries.retain(|entry| entry.inode != 0);
+    Ok(entries)
+}
+
+/// Split a path into its components, ignoring empty ones
+pub(crate) fn components(path: &str) -> impl Iterator<Item = &str> {
+    path.split('/').filter(|component| !component.is_empty())
+}
+
+/// PFS volume stored in the main partition of an APA partition
+///
+/// Only volumes without sub-partitions and inodes with a single segment are supported
+#[derive(Clone, Copy, Debug)]
+pub struct PfsVolume<'a> {
+    pub(crate) data: &'a [u8],
+    pub(crate) zone_size: usize,
+    pub(crate) zones: u32,
+    pub(crate) root: BlockInfo,
+}
+
+impl<'a> PfsVolume<'a> {
+    /// Open the PFS volume stored in the data of a partition
+    ///
+    /// # Errors
+    ///
+    /// - The partition ends before the superblock or the allocation bitmap
+    /// - The superblock has an invalid magic or zone size
+    /// - The root directory is invalid
+    pub fn open(partition_data: &'a [u8]) -> Result<Self, PfsError> {
+        let superblock = partition_data
+            .get(SUPERBLOCK_OFFSET..SUPERBLOCK_OFFSET + SECTOR_SIZE)
+            .ok_or(PfsError::Truncated)?;
+        if read_u32_le(superblock, 0) != SUPERBLOCK_MAGIC {
+            return Err(PfsError::InvalidSuperblock);
+        }
+
+        let zone_size = read_u32_le(superblock, 0x10) as usize;
+        if !zone_size.is_power_of_two() || !(INODE_SIZE..=SUPERBLOCK_OFFSET).contains(&zone_size) {
+            return Err(PfsError::InvalidSuperblock);
+        }
+
+        let volume = Self {
+            data: partition_data,
+            zone_size,
+            zones: u32::try_from(partition_data.len() / zone_size)
+                .map_err(|_| PfsError::Unsupported)?,
+            root: BlockInfo::parse(&superblock[0x20..0x28]),
+        };
+        if partition_data.len() < volume.bitmap().end {
+            return Err(PfsError::Truncated);
+        }
+        if volume.inode(volume.root.number, volume.root.subpart)?.mode & MODE_TYPE != MODE_DIRECTORY
+        {
+            return Err(PfsError::InvalidSuperblock);
+        }
+
+        Ok(volume)
+    }
+
+    /// Size of a zone, the unit space is allocated in
+    #[must_use]
+    pub fn zone_size(&self) -> usize {
+        self.zone_size
+    }
+
+    /// Number of zones that aren't allocated according to the allocation bitmap
+    #[must_use]
+    pub fn free_zones(&self) -> u32 {
+        let bitmap = &self.data[self.bitmap()];
+        (0..self.zones)
+            .filter(|&zone| bitmap[zone as usize / 8] & (1 << (zone % 8)) == 0)
+            .fold(0, |free, _| free + 1)
+    }
+
+    /// List the entries of the directory at the given path, without `.` and `..`
+    ///
+    /// Paths are separated by `/` and matched case-sensitively, an empty path or `/` is the root directory
+    ///
+    /// # Errors
+    ///
+    /// - The path doesn't exist or doesn't point to a directory
+    /// - An inode or directory on the way is invalid
+    pub fn list_dir(&self, path: &str) -> Result<Vec<PfsEntry>, PfsError> {
+        let (zone, inode) = self.lookup(path)?;
+        if inode.mode & MODE_TYPE != MODE_DIRECTORY {
+            return Err(PfsError::NotADirectory);
+        }
+
+        let data = self.read_data(&inode)?;
+        dentries(&data, zone)?
+            .into_iter()
+            .filter(|entry| entry.name != b"." && entry.name != b"..")
+            .map(|entry| {
+                Ok(PfsEntry::new(
+                    entry.name,
+                    &self.inode(entry.inode, entry.subpart.into())?,
+                ))
+            })
+            .collect()
+    }
+
+    /// Read the contents of the file at the given path, see [`PfsVolume::list_dir`] for the path syntax
+    ///
+    /// # Errors
+    ///
+    /// - The path doesn't exist or doesn't point to a regular file
+    /// - An inode or directory on the way is invalid
+    /// - The file's data lies outside of the partition
+    pub fn read_file(&self, path: &str) -> Result<Vec<u8>, PfsError> {
+        let (_, inode) = self.lookup(path)?;
+        if inode.mode & MODE_TYPE != MODE_FILE {
+            return Err(PfsError::NotAFile);
+        }
+
+        self.read_data(&inode)
+    }
+
+    /// Byte range of the allocation bitmap, which follows the superblock and has one bit per zone
+    pub(crate) fn bitmap(&self) -> Range<usize> {
+        let start = (SUPERBLOCK_OFFSET / self.zone_size + 1) * self.zone_size;
+        start..start + (self.zones as usize).div_ceil(8)
+    }
+
+    /// Byte range of `count` zones starting at `zone`
+    pub(crate) fn zone_range(&self, zone: u32, count: u16) -> Result<Range<usize>, PfsError> {
+        let start = zone as usize * self.zone_size;
+        let end = start + usize::from(count) * self.zone_size;
+        if end > self.data.len() {
+            return Err(PfsError::Truncated);
+        }
+
+        Ok(start..end)
+    }
+
+    /// Parse the inode at `zone`, whose extents all lie within the volume
+    pub(crate) fn inode(&self, zone: u32, subpart: u16) -> Result<Inode, PfsError> {
+        if subpart != 0 {
+            return Err(PfsError::Unsupported);
+        }
+
+        let inode = Inode::parse(&self.data[self.zone_range(zone, 1)?], zone)?;
+        let inside = |extent: &BlockInfo| {
+            extent
+                .number
+                .checked_add(u32::from(extent.count))
+                .is_some_and(|end| end <= self.zones)
+        };
+        if !inode.extents.iter().all(inside) {
+            return Err(PfsError::InvalidInode { zone });
+        }
+
+        Ok(inode)
+    }
+
+    /// Zone and inode of the given path
+    pub(crate) fn lookup(&self, path: &str) -> Result<(u32, Inode), PfsError> {
+        let mut zone = self.root.number;
+        let mut inode = self.inode(zone, self.root.subpart)?;
+        for component in components(path) {
+            if inode.mode & MODE_TYPE != MODE_DIRECTORY {
+                return Err(PfsError::NotADirectory);
+            }
+
+            let data = self.read_data(&inode)?;
+            let entry = dentries(&data, zone)?
+                .into_iter()
+                .find(|entry| entry.name == component.as_bytes())
+                .ok_or(PfsError::NotFound)?;
+            zone = entry.inode;
+            inode = self.inode(entry.inode, entry.subpart.into())?;
+        }
+
+        Ok((zone, inode))
+    }
+
+    /// Read the data of an inode, which starts right after the inode in the first extent
+    pub(crate) fn read_data(&self, inode: &Inode) -> Result<Vec<u8>, PfsError> {
+        let zone = inode.extents[0].number;
+        let size = usize::try_from(inode.size).map_err(|_| PfsError::InvalidInode { zone })?;
+        // The size is only trusted once the extents are known to hold that much
+        let recorded = inode
+            .extents
+            .iter()
+            .map(|extent| usize::from(extent.count) * self.zone_size)
+            .sum::<usize>();
+        if size > recorded - self.zone_size {
+            return Err(PfsError::InvalidInode { zone });
+        }
+
+        let mut data = Vec::with_capacity(size);
+        for (index, extent) in inode.extents.iter().enumerate() {
+            if extent.subpart != 0 {
+                return Err(PfsError::Unsupported);
+            }
+
+            let range = self.zone_range(extent.number, extent.count)?;
+            let skip = if index == 0 {
+                self.zone_size.min(range.len())
+            } else {
+                0
+            };
+            data.extend_from_slice(&self.data[range][skip..]);
+            if data.len() >= size {
+                break;
+            }
+        }
+
+        data.truncate(size);
+        Ok(data)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        inode::{Inode, INODE_SIZE},
+        test_pfs::{big, volume, CREATED, MODIFIED, SYSTEM_CNF, ZONES},
+        PfsEntryKind, PfsError, PfsVolume,
+    };
+
+    #[test]
+    fn list_dir() {
+        let data = volume();
+        let volume = PfsVolume::open(&data).unwrap();
+        assert_eq!(volume.zone_size(), 8192);
+
+        let root = volume.list_dir("/").unwrap();
+        let names = root
+            .iter()
+            .map(|entry| entry.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["SYSTEM.CNF", "DATA"]);
+        assert_eq!(root[0].kind, PfsEntryKind::File);
+        assert_eq!(root[0].size, SYSTEM_CNF.len() as u64);
+        assert_eq!(root[0].permissions, 0o644);
+        assert_eq!(root[0].created, CREATED);
+        assert_eq!(root[0].modified, MODIFIED);
+        assert!(root[1].is_dir());
+        assert_eq!(root[1].permissions, 0o777);
+        assert_eq!(volume.list_dir("").unwrap(), root);
+
+        let data_dir = volume.list_dir("DATA").unwrap();
+        let names = data_dir
+            .iter()
+            .map(|entry| entry.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["BIG.BIN", "EMPTY"]);
+        assert_eq!(data_dir[0].size, 3 * 8192 + 100);
+        assert_eq!(volume.list_dir("/DATA/").unwrap(), data_dir);
+        assert_eq!(volume.list_dir("/DATA/../DATA").unwrap(), data_dir);
+    }
+
+    #[test]
+    fn read_file() {
+        let data = volume();
+        let volume = PfsVolume::open(&data).unwrap();
+
+        assert_eq!(volume.read_file("SYSTEM.CNF").unwrap(), SYSTEM_CNF);
+        // Spans two extents
+        assert_eq!(volume.read_file("/DATA/BIG.BIN").unwrap(), big());
+        assert_eq!(volume.read_file("DATA/EMPTY").unwrap(), b"");
+    }
+
+    #[test]
+    fn free_zones() {
+        let data = volume();
+        let volume = PfsVolume::open(&data).unwrap();
+        // The reserved area, superblock, bitmap and 12 zones of inodes and data are allocated
+        assert_eq!(volume.free_zones(), ZONES - 514 - 12);
+    }
+
+    #[test]
+    fn errors() {
+        let data = volume();
+        let volume = PfsVolume::open(&data).unwrap();
+
+        assert_eq!(volume.read_file("MISSING"), Err(PfsError::NotFound));
+        assert_eq!(volume.read_file("system.cnf"), Err(PfsError::NotFound));
+        assert_eq!(volume.read_file("DATA"), Err(PfsError::NotAFile));
+        assert_eq!(volume.list_dir("SYSTEM.CNF"), Err(PfsError::NotADirectory));
+        assert_eq!(
+            volume.list_dir("SYSTEM.CNF/X"),
+            Err(PfsError::NotADirectory)
+        );
+
+        assert_eq!(
+            PfsVolume::open(&data[..4 * 1024 * 1024]).unwrap_err(),
+            PfsError::Truncated
+        );
+
+        let mut corrupted = data.clone();
+        corrupted[crate::pfs::SUPERBLOCK_OFFSET] ^= 0xFF;
+        assert_eq!(
+            PfsVolume::open(&corrupted).unwrap_err(),
+            PfsError::InvalidSuperblock
+        );
+
+        // A size larger than the extents of SYSTEM.CNF
+        let mut corrupted = data.clone();
+        let start = 516 * 8192;
+        let mut inode = Inode::parse(&corrupted[start..], 516).unwrap();
+        inode.size = 1 << 63;
+        corrupted[start..start + INODE_SIZE].copy_from_slice(&inode.encode());
+        let volume = PfsVolume::open(&corrupted).unwrap();
+        assert_eq!(
+            volume.read_file("SYSTEM.CNF"),
+            Err(PfsError::InvalidInode { zone: 516 })
+        );
+
+        // Corrupt the inode of SYSTEM.CNF
+        let mut corrupted = data;
+        corrupted[516 * 8192 + 984] ^= 0xFF;
+        let volume = PfsVolume::open(&corrupted).unwrap();
+        assert_eq!(
+            volume.read_file("SYSTEM.CNF"),
+            Err(PfsError::InvalidInode { zone: 516 })
+        );
+    }
+}
@@ -0,0 +1,570 @@
+use crate::{
+    inode::{BlockInfo, Inode, INODE_SIZE, MAX_EXTENTS, MODE_DIRECTORY, MODE_FILE, MODE_TYPE},
+    pfs::{dentries, dentry_len, records, set_dentry_len, write_dentry, DENTRY_BLOCK_SIZE},
+    ApaTimestamp, PfsError, PfsVolume,
+};
+use ps2_bytes::read_u16_le;
+
+/// Mode of newly created files
+const NEW_FILE_MODE: u16 = MODE_FILE | 0o777;
+
+/// Longest name a directory record can hold
+const MAX_NAME_LEN: usize = u8::MAX as usize;
+
+/// PFS volume that files can be created, written and deleted in
+///
+/// Has the same limitations as [`PfsVolume`]. Inodes are re-encoded when they are modified, which drops any
+/// fields not covered by [`PfsEntry`](crate::PfsEntry)
+#[derive(Clone, Debug)]
+pub struct PfsVolumeMut {
+    data: Vec<u8>,
+    zone_size: usize,
+    zones: u32,
+    root: BlockInfo,
+    time: Option<ApaTimestamp>,
+}
+
+impl PfsVolumeMut {
+    /// Open the PFS volume stored in the data of a partition
+    ///
+    /// # Errors
+    ///
+    /// - See [`PfsVolume::open`]
+    pub fn open(partition_data: Vec<u8>) -> Result<Self, PfsError> {
+        let volume = PfsVolume::open(&partition_data)?;
+        let (zone_size, zones, root) = (volume.zone_size, volume.zones, volume.root);
+
+        Ok(Self {
+            data: partition_data,
+            zone_size,
+            zones,
+            root,
+            time: None,
+        })
+    }
+
+    /// Read-only view of the volume
+    #[must_use]
+    pub fn as_volume(&self) -> PfsVolume<'_> {
+        PfsVolume {
+            data: &self.data,
+            zone_size: self.zone_size,
+            zones: self.zones,
+            root: self.root,
+        }
+    }
+
+    /// Data of the partition, including all modifications
+    #[must_use]
+    pub fn into_inner(self) -> Vec<u8> {
+        self.data
+    }
+
+    /// Use a fixed time for all following modifications instead of the current time in UTC
+    pub fn set_time(&mut self, time: ApaTimestamp) {
+        self.time = Some(time);
+    }
+
+    /// Create a new file at the given path, see [`PfsVolume::list_dir`] for the path syntax
+    ///
+    /// The modification time of the parent directory is updated
+    ///
+    /// # Errors
+    ///
+    /// - The parent directory doesn't exist or something on the way isn't a directory
+    /// - Something already exists at the path
+    /// - The name is empty, `.`, `..` or longer than 255 bytes
+    /// - There aren't enough free zones left or they are too fragmented
+    /// - An inode or directory on the way is invalid
+    pub fn create_file(&mut self, path: &str, data: &[u8]) -> Result<(), PfsError> {
+        let (parent, name) = split_path(path)?;
+        let (dir_zone, mut dir) = self.directory(parent)?;
+        let mut dir_data = self.as_volume().read_data(&dir)?;
+        if dentries(&dir_data, dir_zone)?
+            .iter()
+            .any(|entry| entry.name == name)
+        {
+            return Err(PfsError::AlreadyExists);
+        }
+
+        let time = self.time();
+        let extents = self.find_free(self.zones_for(data.len())?)?;
+        self.mark(&extents, true);
+        let mut inode = Inode {
+            extents,
+            mode: NEW_FILE_MODE,
+            created: time,
+            ..Inode::default()
+        };
+        self.write_data(&mut inode, data, time)?;
+
+        let zone = inode.extents[0].number;
+        insert_dentry(&mut dir_data, dir_zone, zone, name, NEW_FILE_MODE)?;
+        if let Err(error) = self.write_data(&mut dir, &dir_data, time) {
+            self.mark(&inode.extents, false);
+            return Err(error);
+        }
+
+        Ok(())
+    }
+
+    /// Replace the contents of the file at the given path, see [`PfsVolume::list_dir`] for the path syntax
+    ///
+    /// Zones are allocated or freed at the end of the file as needed and the modification time of the file is
+    /// updated
+    ///
+    /// # Errors
+    ///
+    /// - The path doesn't exist or doesn't point to a regular file
+    /// - There aren't enough free zones left or they are too fragmented
+    /// - An inode or directory on the way is invalid
+    pub fn write_file(&mut self, path: &str, data: &[u8]) -> Result<(), PfsError> {
+        let (_, mut inode) = self.as_volume().lookup(path)?;
+        if inode.mode & MODE_TYPE != MODE_FILE {
+            return Err(PfsError::NotAFile);
+        }
+
+        let time = self.time();
+        self.write_data(&mut inode, data, time)
+    }
+
+    /// Delete the file at the given path, see [`PfsVolume::list_dir`] for the path syntax
+    ///
+    /// The zones of the file are freed and the modification time of the parent directory is updated
+    ///
+    /// # Errors
+    ///
+    /// - The path doesn't exist or doesn't point to a regular file
+    /// - An inode or directory on the way is invalid
+    pub fn delete(&mut self, path: &str) -> Result<(), PfsError> {
+        let (parent, name) = split_path(path)?;
+        let (dir_zone, mut dir) = self.directory(parent)?;
+        let mut dir_data = self.as_volume().read_data(&dir)?;
+
+        let records = records(&dir_data, dir_zone)?;
+        let index = records
+            .iter()
+            .position(|record| record.inode != 0 && record.name == name)
+            .ok_or(PfsError::NotFound)?;
+        let record = &records[index];
+        let inode = self
+            .as_volume()
+            .inode(record.inode, record.subpart.into())?;
+        if inode.mode & MODE_TYPE != MODE_FILE {
+            return Err(PfsError::NotAFile);
+        }
+
+        // Merge the record into the previous one of the same block, or mark it as unused if it's the first one
+        let merged = (record.offset % DENTRY_BLOCK_SIZE != 0).then(|| {
+            let previous = &records[index - 1];
+            (previous.offset, previous.len + record.len)
+        });
+        let offset = record.offset;
+        match merged {
+            Some((previous, len)) => {
+                let a_len = read_u16_le(&dir_data, previous + 6);
+                set_dentry_len(&mut dir_data[previous..], len, a_len);
+            }
+            None => dir_data[offset..offset + 4].fill(0),
+        }
+
+        let time = self.time();
+        self.write_data(&mut dir, &dir_data, time)?;
+        self.mark(&inode.extents, false);
+        Ok(())
+    }
+
+    fn time(&self) -> ApaTimestamp {
+        self.time.unwrap_or_else(ApaTimestamp::now)
+    }
+
+    /// Zone and inode of the directory at the given path
+    fn directory(&self, path: &str) -> Result<(u32, Inode), PfsError> {
+        let (zone, inode) = self.as_volume().lookup(path)?;
+        if inode.mode & MODE_TYPE != MODE_DIRECTORY {
+            return Err(PfsError::NotADirectory);
+        }
+
+        Ok((zone, inode))
+    }
+
+    /// Number of zones needed for the inode and `len` bytes of data
+    fn zones_for(&self, len: usize) -> Result<u32, PfsError> {
+        u32::try_from(1 + len.div_ceil(self.zone_size)).map_err(|_| PfsError::NoSpace)
+    }
+
+    fn is_free(&self, zone: u32) -> bool {
+        let bitmap = &self.data[self.as_volume().bitmap()];
+        bitmap[zone as usize / 8] & (1 << (zone % 8)) == 0
+    }
+
+    /// Mark the zones of the extents as allocated or free in the bitmap
+    fn mark(&mut self, extents: &[BlockInfo], allocated: bool) {
+        let start = self.as_volume().bitmap().start;
+        for extent in extents {
+            for zone in extent.number..extent.number + u32::from(extent.count) {
+                let byte = &mut self.data[start + zone as usize / 8];
+                if allocated {
+                    *byte |= 1 << (zone % 8);
+                } else {
+                    *byte &= !(1 << (zone % 8));
+                }
+            }
+        }
+    }
+
+    /// Find `count` free zones, preferring the lowest ones, without allocating them
+    fn find_free(&self, count: u32) -> Result<Vec<BlockInfo>, PfsError> {
+        let mut extents = Vec::<BlockInfo>::new();
+        let mut missing = count;
+        for zone in (0..self.zones).filter(|&zone| self.is_free(zone)) {
+            if missing == 0 {
+                break;
+            }
+
+            let full = extents.len() == MAX_EXTENTS;
+            match extents.last_mut() {
+                Some(last)
+                    if last.number + u32::from(last.count) == zone && last.count < u16::MAX =>
+                {
+                    last.count += 1;
+                }
+                _ if full => return Err(PfsError::NoSpace),
+                _ => extents.push(BlockInfo {
+                    number: zone,
+                    subpart: 0,
+                    count: 1,
+                }),
+            }
+            missing -= 1;
+        }
+
+        if missing > 0 {
+            return Err(PfsError::NoSpace);
+        }
+        Ok(extents)
+    }
+
+    /// Extents of an inode grown or shrunk to `count` zones, without updating the bitmap
+    fn resized(&self, extents: &[BlockInfo], count: u32) -> Result<Vec<BlockInfo>, PfsError> {
+        let current = extents
+            .iter()
+            .map(|extent| u32::from(extent.count))
+            .sum::<u32>();
+        let mut extents = extents.to_vec();
+
+        if count > current {
+            for extent in self.find_free(count - current)? {
+                match extents.last_mut() {
+                    Some(last)
+                        if last.number + u32::from(last.count) == extent.number
+                            && last.count.checked_add(extent.count).is_some() =>
+                    {
+                        last.count += extent.count;
+                    }
+                    _ => extents.push(extent),
+                }
+            }
+            if extents.len() > MAX_EXTENTS {
+                return Err(PfsError::NoSpace);
+            }
+        } else {
+            let mut excess = current - count;
+            while excess > 0 {
+                let last = extents.last_mut().unwrap();
+                let freed =
+                    u16::try_from(excess).map_or(last.count, |excess| excess.min(last.count));
+                last.count -= freed;
+                if last.count == 0 {
+                    extents.pop();
+                }
+                excess -= u32::from(freed);
+            }
+        }
+
+        Ok(extents)
+    }
+
+    /// Replace the data of an inode and write the inode itself, which mustn't move
+    ///
+    /// Nothing is modified if there aren't enough free zones
+    fn write_data(
+        &mut self,
+        inode: &mut Inode,
+        contents: &[u8],
+        time: ApaTimestamp,
+    ) -> Result<(), PfsError> {
+        let extents = self.resized(&inode.extents, self.zones_for(contents.len())?)?;
+        let ranges = extents
+            .iter()
+            .map(|extent| self.as_volume().zone_range(extent.number, extent.count))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // The zones kept by the inode are freed and allocated again
+        self.mark(&inode.extents, false);
+        self.mark(&extents, true);
+        inode.extents = extents;
+        inode.size = contents.len() as u64;
+        inode.modified = time;
+        inode.accessed = time;
+
+        let start = ranges[0].start;
+        let mut rest = contents;
+        for (index, range) in ranges.into_iter().enumerate() {
+            let skip = if index == 0 {
+                self.zone_size.min(range.len())
+            } else {
+                0
+            };
+            let out = &mut self.data[range][skip..];
+            let len = rest.len().min(out.len());
+            out[..len].copy_from_slice(&rest[..len]);
+            rest = &rest[len..];
+        }
+
+        self.data[start..start + INODE_SIZE].copy_from_slice(&inode.encode());
+        Ok(())
+    }
+}
+
+/// Split a path into the path of the parent directory and the name
+fn split_path(path: &str) -> Result<(&str, &[u8]), PfsError> {
+    let path = path.trim_end_matches('/');
+    let (parent, name) = path.rsplit_once('/').unwrap_or(("", path));
+    if name.is_empty() || name == "." || name == ".." || name.len() > MAX_NAME_LEN {
+        return Err(PfsError::InvalidName);
+    }
+
+    Ok((parent, name.as_bytes()))
+}
+
+/// Add a record to the data of the directory whose inode is at `zone`
+///
+/// The record is placed into the first unused space large enough for it, otherwise a new block is appended
+fn insert_dentry(
+    dir_data: &mut Vec<u8>,
+    zone: u32,
+    inode: u32,
+    name: &[u8],
+    mode: u16,
+) -> Result<(), PfsError> {
+    let needed = dentry_len(name.len());
+    let free = records(dir_data, zone)?.into_iter().find_map(|record| {
+        let used = if record.inode == 0 {
+            0
+        } else {
+            dentry_len(record.name.len())
+        };
+        (record.len - used >= needed).then_some((record.offset, record.len, used))
+    });
+
+    match free {
+        Some((offset, len, 0)) => {
+            write_dentry(&mut dir_data[offset..offset + len], inode, name, mode);
+        }
+        Some((offset, len, used)) => {
+            // Keep the type bits of the shortened record
+            let a_len = read_u16_le(dir_data, offset + 6);
+            set_dentry_len(&mut dir_data[offset..], used, a_len);
+            write_dentry(
+                &mut dir_data[offset + used..offset + len],
+                inode,
+                name,
+                mode,
+            );
+        }
+        None => {
+            let offset = dir_data.len();
+            dir_data.resize(offset + DENTRY_BLOCK_SIZE, 0);
+            write_dentry(&mut dir_data[offset..], inode, name, mode);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        inode::{BlockInfo, Inode, INODE_SIZE},
+        test_pfs::{big, volume, MODIFIED, SYSTEM_CNF, ZONES, ZONE_SIZE},
+        ApaTimestamp, PfsError, PfsVolume, PfsVolumeMut,
+    };
+
+    const NOW: ApaTimestamp = ApaTimestamp {
+        year: 2024,
+        month: 6,
+        day: 7,
+        hour: 8,
+        minute: 9,
+        second: 10,
+    };
+
+    fn open() -> PfsVolumeMut {
+        let mut volume = PfsVolumeMut::open(volume()).unwrap();
+        volume.set_time(NOW);
+        volume
+    }
+
+    fn names(volume: &PfsVolume<'_>, path: &str) -> Vec<String> {
+        volume
+            .list_dir(path)
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.name)
+            .collect()
+    }
+
+    #[test]
+    fn create_file() {
+        let mut volume = open();
+        let contents = (0..2 * ZONE_SIZE + 10)
+            .map(|i| u8::try_from(i % 7).unwrap())
+            .collect::<Vec<_>>();
+        // The free zone between the extents of BIG.BIN is reused first
+        volume.create_file("EMPTY.TXT", b"").unwrap();
+        assert_eq!(volume.as_volume().lookup("EMPTY.TXT").unwrap().0, 522);
+        volume.create_file("/DATA/NEW.BIN", &contents).unwrap();
+
+        let data = volume.into_inner();
+        let volume = PfsVolume::open(&data).unwrap();
+        assert_eq!(volume.read_file("DATA/NEW.BIN").unwrap(), contents);
+        assert_eq!(volume.read_file("EMPTY.TXT").unwrap(), b"");
+        assert_eq!(volume.read_file("DATA/BIG.BIN").unwrap(), big());
+        assert_eq!(volume.free_zones(), ZONES - 514 - 12 - 4 - 1);
+        assert_eq!(names(&volume, "/"), ["SYSTEM.CNF", "DATA", "EMPTY.TXT"]);
+        assert_eq!(names(&volume, "DATA"), ["BIG.BIN", "EMPTY", "NEW.BIN"]);
+
+        let root = volume.list_dir("/").unwrap();
+        assert_eq!(root[0].modified, MODIFIED);
+        assert_eq!(root[2].created, NOW);
+        assert_eq!(root[2].modified, NOW);
+        assert_eq!(root[2].permissions, 0o777);
+        // `DATA` was modified by adding `NEW.BIN`
+        assert_eq!(root[1].modified, NOW);
+    }
+
+    #[test]
+    fn grow_directory() {
+        let mut volume = open();
+        // Two records fit into a block, so the root directory outgrows its single data zone
+        let created = (0..33).map(|i| format!("{i:0>200}")).collect::<Vec<_>>();
+        for name in &created {
+            volume.create_file(name, b"").unwrap();
+        }
+        assert_eq!(volume.as_volume().free_zones(), 0);
+        assert_eq!(volume.create_file("FULL", b""), Err(PfsError::NoSpace));
+
+        let data = volume.into_inner();
+        let volume = PfsVolume::open(&data).unwrap();
+        let root = names(&volume, "");
+        assert_eq!(root[..2], ["SYSTEM.CNF", "DATA"]);
+        assert_eq!(root[2..], created);
+        for name in &created {
+            assert_eq!(volume.read_file(name).unwrap(), b"");
+        }
+    }
+
+    #[test]
+    fn write_file() {
+        let mut volume = open();
+        let mut grown = big();
+        grown.extend(SYSTEM_CNF);
+        grown.extend(vec![0xAA; 2 * ZONE_SIZE]);
+        volume.write_file("DATA/BIG.BIN", &grown).unwrap();
+        assert_eq!(volume.as_volume().free_zones(), ZONES - 514 - 12 - 2);
+
+        volume.write_file("SYSTEM.CNF", b"").unwrap();
+        assert_eq!(volume.as_volume().free_zones(), ZONES - 514 - 12 - 2 + 1);
+        volume.write_file("DATA/BIG.BIN", b"small").unwrap();
+        assert_eq!(volume.as_volume().free_zones(), ZONES - 514 - 12 + 1 + 3);
+
+        volume.write_file("DATA/BIG.BIN", &grown).unwrap();
+        let data = volume.into_inner();
+        let volume = PfsVolume::open(&data).unwrap();
+        assert_eq!(volume.read_file("DATA/BIG.BIN").unwrap(), grown);
+        assert_eq!(volume.read_file("SYSTEM.CNF").unwrap(), b"");
+
+        let data_dir = volume.list_dir("DATA").unwrap();
+        assert_eq!(data_dir[0].modified, NOW);
+        assert_eq!(data_dir[1].modified, MODIFIED);
+    }
+
+    #[test]
+    fn delete() {
+        let mut volume = open();
+        volume.delete("SYSTEM.CNF").unwrap();
+        volume.delete("/DATA/EMPTY").unwrap();
+        assert_eq!(volume.as_volume().free_zones(), ZONES - 514 - 12 + 3);
+        assert_eq!(volume.delete("SYSTEM.CNF"), Err(PfsError::NotFound));
+
+        volume.create_file("DATA/NEW", SYSTEM_CNF).unwrap();
+        let data = volume.into_inner();
+        let volume = PfsVolume::open(&data).unwrap();
+        assert_eq!(names(&volume, ""), ["DATA"]);
+        assert_eq!(names(&volume, "DATA"), ["BIG.BIN", "NEW"]);
+        assert_eq!(volume.read_file("DATA/NEW").unwrap(), SYSTEM_CNF);
+        assert_eq!(volume.read_file("DATA/BIG.BIN").unwrap(), big());
+        assert_eq!(volume.list_dir("").unwrap()[0].modified, NOW);
+    }
+
+    #[test]
+    fn errors() {
+        let mut volume = open();
+        let free = volume.as_volume().free_zones();
+
+        assert_eq!(
+            volume.create_file("SYSTEM.CNF", b""),
+            Err(PfsError::AlreadyExists)
+        );
+        assert_eq!(
+            volume.create_file("MISSING/FILE", b""),
+            Err(PfsError::NotFound)
+        );
+        assert_eq!(
+            volume.create_file("SYSTEM.CNF/FILE", b""),
+            Err(PfsError::NotADirectory)
+        );
+        for path in ["", "/", "DATA/..", &"A".repeat(256)] {
+            assert_eq!(volume.create_file(path, b""), Err(PfsError::InvalidName));
+        }
+        assert_eq!(
+            volume.create_file("HUGE", &vec![0; ZONES as usize * ZONE_SIZE]),
+            Err(PfsError::NoSpace)
+        );
+
+        assert_eq!(volume.write_file("MISSING", b""), Err(PfsError::NotFound));
+        assert_eq!(volume.write_file("DATA", b""), Err(PfsError::NotAFile));
+        assert_eq!(
+            volume.write_file("SYSTEM.CNF", &vec![0; ZONES as usize * ZONE_SIZE]),
+            Err(PfsError::NoSpace)
+        );
+        assert_eq!(volume.delete("DATA"), Err(PfsError::NotAFile));
+
+        assert_eq!(volume.as_volume().free_zones(), free);
+        assert_eq!(volume.into_inner(), crate::test_pfs::volume());
+    }
+
+    #[test]
+    fn invalid_extents() {
+        // Zones past the end of the volume, an overflowing extent and an empty one on SYSTEM.CNF
+        let extents = [(100_000_000, 1), (u32::MAX, 2), (600, 0)];
+        for (number, count) in extents {
+            let mut data = volume();
+            let start = 516 * ZONE_SIZE;
+            let mut inode = Inode::parse(&data[start..], 516).unwrap();
+            inode.extents.push(BlockInfo {
+                number,
+                subpart: 0,
+                count,
+            });
+            data[start..start + INODE_SIZE].copy_from_slice(&inode.encode());
+
+            let mut volume = PfsVolumeMut::open(data.clone()).unwrap();
+            let invalid = Err(PfsError::InvalidInode { zone: 516 });
+            assert_eq!(volume.delete("SYSTEM.CNF"), invalid);
+            assert_eq!(volume.write_file("SYSTEM.CNF", b""), invalid);
+            assert_eq!(volume.into_inner(), data);
+        }
+    }
+}
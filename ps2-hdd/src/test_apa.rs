@@ -0,0 +1,117 @@
+//! APA partition headers and an image with the partitions a PS2 formats the HDD with, plus a game and OPL
+
+use crate::{
+    apa::{HEADER_SIZE, MAGIC},
+    checksum, SECTOR_SIZE,
+};
+
+/// Fields of a partition header
+pub struct Header<'a> {
+    pub name: &'a str,
+    pub start: u32,
+    pub length: u32,
+    pub type_id: u16,
+    pub flags: u16,
+    pub next: u32,
+    pub prev: u32,
+    pub main: u32,
+    pub subs: Vec<(u32, u32)>,
+}
+
+impl<'a> Header<'a> {
+    /// Header of a two sector partition without links
+    pub fn new(name: &'a str, start: u32, type_id: u16) -> Self {
+        Self {
+            name,
+            start,
+            length: 2,
+            type_id,
+            flags: 0,
+            next: 0,
+            prev: 0,
+            main: 0,
+            subs: Vec::new(),
+        }
+    }
+}
+
+/// Encode a partition header, including its checksum
+pub fn header(fields: &Header<'_>) -> Vec<u8> {
+    let mut header = vec![0; HEADER_SIZE];
+    header[4..8].copy_from_slice(MAGIC);
+    header[0x08..0x0C].copy_from_slice(&fields.next.to_le_bytes());
+    header[0x0C..0x10].copy_from_slice(&fields.prev.to_le_bytes());
+    header[0x10..0x10 + fields.name.len()].copy_from_slice(fields.name.as_bytes());
+    header[0x40..0x44].copy_from_slice(&fields.start.to_le_bytes());
+    header[0x44..0x48].copy_from_slice(&fields.length.to_le_bytes());
+    header[0x48..0x4A].copy_from_slice(&fields.type_id.to_le_bytes());
+    header[0x4A..0x4C].copy_from_slice(&fields.flags.to_le_bytes());
+    header[0x4C..0x50].copy_from_slice(&u32::try_from(fields.subs.len()).unwrap().to_le_bytes());
+    // 2004-03-15 12:34:56
+    header[0x50..0x58].copy_from_slice(&[0, 56, 34, 12, 15, 3, 0xD4, 0x07]);
+    header[0x58..0x5C].copy_from_slice(&fields.main.to_le_bytes());
+    for (index, (start, length)) in fields.subs.iter().enumerate() {
+        let offset = 0x200 + index * 8;
+        header[offset..offset + 4].copy_from_slice(&start.to_le_bytes());
+        header[offset + 4..offset + 8].copy_from_slice(&length.to_le_bytes());
+    }
+
+    let sum = checksum(&header);
+    header[..4].copy_from_slice(&sum.to_le_bytes());
+    header
+}
+
+/// Image with 10 two sector main partitions, the 6th one (`PP.SLUS-21348..GAME`) being extended by two
+/// sub-partitions located after all main partitions
+pub fn image() -> Vec<u8> {
+    let main = [
+        ("__mbr", 0x0001),
+        ("__net", 0x0100),
+        ("__system", 0x0100),
+        ("__sysconf", 0x0100),
+        ("__common", 0x0100),
+        ("PP.SLUS-21348..GAME", 0x0100),
+        ("+OPL", 0x0100),
+        ("__.POPS", 0x0100),
+        ("PP.SCES-50051..GAME", 0x0100),
+        ("__empty", 0x0000),
+    ];
+    let game = 10;
+    let subs = [22, 24];
+
+    // Main partitions in order, with the sub-partitions linked after the game partition
+    let mut order = (0..10u32).map(|index| index * 2).collect::<Vec<_>>();
+    order.splice(6..6, subs);
+
+    let mut image = vec![0; 26 * SECTOR_SIZE];
+    for (position, &start) in order.iter().enumerate() {
+        let next = order.get(position + 1).copied().unwrap_or(0);
+        let prev = order[(position + order.len() - 1) % order.len()];
+        let fields = if subs.contains(&start) {
+            Header {
+                flags: 0x0001,
+                main: game,
+                next,
+                prev,
+                ..Header::new("", start, 0x0100)
+            }
+        } else {
+            let (name, type_id) = main[start as usize / 2];
+            Header {
+                next,
+                prev,
+                subs: if start == game {
+                    subs.iter().map(|&sub| (sub, 2)).collect()
+                } else {
+                    Vec::new()
+                },
+                ..Header::new(name, start, type_id)
+            }
+        };
+
+        let offset = start as usize * SECTOR_SIZE;
+        image[offset..offset + HEADER_SIZE].copy_from_slice(&header(&fields));
+    }
+
+    image
+}
@@ -0,0 +1,152 @@
+//! PFS inodes, directory blocks and a small volume holding a game's `SYSTEM.CNF` and a fragmented file
+
+use crate::{
+    inode::{BlockInfo, Inode, MODE_DIRECTORY, MODE_FILE},
+    pfs::{dentry_len, write_dentry, DENTRY_BLOCK_SIZE, SUPERBLOCK_MAGIC, SUPERBLOCK_OFFSET},
+    ApaTimestamp,
+};
+
+/// Zone size of [`volume`]
+pub const ZONE_SIZE: usize = 8192;
+
+/// Number of zones of [`volume`]
+pub const ZONES: u32 = 560;
+
+/// Creation time of every inode of [`volume`]
+pub const CREATED: ApaTimestamp = ApaTimestamp {
+    year: 2004,
+    month: 3,
+    day: 15,
+    hour: 12,
+    minute: 34,
+    second: 56,
+};
+
+/// Modification time of every inode of [`volume`]
+pub const MODIFIED: ApaTimestamp = ApaTimestamp {
+    year: 2005,
+    month: 1,
+    day: 2,
+    hour: 3,
+    minute: 4,
+    second: 5,
+};
+
+/// Contents of `SYSTEM.CNF` in [`volume`]
+pub const SYSTEM_CNF: &[u8] = b"BOOT2 = pfs:/SLUS_213.48;1\r\nVER = 1.00\r\nVMODE = NTSC\r\n";
+
+/// Contents of `DATA/BIG.BIN` in [`volume`]
+pub fn big() -> Vec<u8> {
+    (0..3 * ZONE_SIZE + 100)
+        .map(|i| u8::try_from(i % 251).unwrap())
+        .collect()
+}
+
+/// Mark zones as allocated in the bitmap
+pub fn allocate(data: &mut [u8], zones: std::ops::Range<u32>) {
+    let bitmap = (SUPERBLOCK_OFFSET / ZONE_SIZE + 1) * ZONE_SIZE;
+    for zone in zones {
+        data[bitmap + zone as usize / 8] |= 1 << (zone % 8);
+    }
+}
+
+/// Write an inode and its data
+fn write(data: &mut [u8], extents: &[(u32, u16)], mode: u16, contents: &[u8]) {
+    let inode = Inode {
+        extents: extents
+            .iter()
+            .map(|&(number, count)| BlockInfo {
+                number,
+                subpart: 0,
+                count,
+            })
+            .collect(),
+        mode,
+        created: CREATED,
+        modified: MODIFIED,
+        accessed: MODIFIED,
+        size: contents.len() as u64,
+        ..Inode::default()
+    };
+    let zone = extents[0].0 as usize * ZONE_SIZE;
+    data[zone..zone + 1024].copy_from_slice(&inode.encode());
+
+    let mut rest = contents;
+    for (index, &(number, count)) in extents.iter().enumerate() {
+        let skip = usize::from(index == 0);
+        let start = (number as usize + skip) * ZONE_SIZE;
+        let len = rest.len().min((usize::from(count) - skip) * ZONE_SIZE);
+        data[start..start + len].copy_from_slice(&rest[..len]);
+        rest = &rest[len..];
+        allocate(data, number..number + u32::from(count));
+    }
+}
+
+/// Directory data holding the given entries, all fitting into a single block
+pub fn directory(entries: &[(&str, u32, u16)]) -> Vec<u8> {
+    let mut block = vec![0; DENTRY_BLOCK_SIZE];
+    let mut offset = 0;
+    for (index, &(name, inode, mode)) in entries.iter().enumerate() {
+        let len = if index + 1 == entries.len() {
+            DENTRY_BLOCK_SIZE - offset
+        } else {
+            dentry_len(name.len())
+        };
+        write_dentry(
+            &mut block[offset..offset + len],
+            inode,
+            name.as_bytes(),
+            mode,
+        );
+        offset += len;
+    }
+
+    block
+}
+
+/// Volume laid out as follows:
+///
+/// - Zones 0-513: reserved area, superblock and bitmap
+/// - Zones 514-515: root directory
+/// - Zones 516-517: `SYSTEM.CNF`
+/// - Zones 518-519: `DATA` directory
+/// - Zones 520-521 and 523-525: `DATA/BIG.BIN`, zone 522 is free
+/// - Zone 526: `DATA/EMPTY`
+pub fn volume() -> Vec<u8> {
+    let mut data = vec![0; ZONES as usize * ZONE_SIZE];
+
+    let superblock = &mut data[SUPERBLOCK_OFFSET..];
+    superblock[..4].copy_from_slice(&SUPERBLOCK_MAGIC.to_le_bytes());
+    superblock[4..8].copy_from_slice(&3_u32.to_le_bytes());
+    superblock[0x10..0x14].copy_from_slice(&u32::try_from(ZONE_SIZE).unwrap().to_le_bytes());
+    BlockInfo {
+        number: 514,
+        subpart: 0,
+        count: 1,
+    }
+    .write(&mut superblock[0x20..0x28]);
+    allocate(&mut data, 0..514);
+
+    let dir = MODE_DIRECTORY | 0o777;
+    let file = MODE_FILE | 0o644;
+    let root = directory(&[
+        (".", 514, dir),
+        ("..", 514, dir),
+        ("SYSTEM.CNF", 516, file),
+        ("DATA", 518, dir),
+    ]);
+    write(&mut data, &[(514, 2)], dir, &root);
+    write(&mut data, &[(516, 2)], file, SYSTEM_CNF);
+
+    let data_dir = directory(&[
+        (".", 518, dir),
+        ("..", 514, dir),
+        ("BIG.BIN", 520, file),
+        ("EMPTY", 526, file),
+    ]);
+    write(&mut data, &[(518, 2)], dir, &data_dir);
+    write(&mut data, &[(520, 2), (523, 3)], file, &big());
+    write(&mut data, &[(526, 1)], file, &[]);
+
+    data
+}
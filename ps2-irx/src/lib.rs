@@ -0,0 +1,317 @@
+#![doc = include_str!("../README.md")]
+#![forbid(missing_docs, unsafe_code)]
+#![warn(clippy::all, clippy::pedantic)]
+
+use ps2_bytes::get_u32_le;
+use ps2_elf::{ElfError, Ps2Elf, SymbolTable};
+use std::{
+    error::Error as StdError,
+    fmt::{self, Display},
+};
+
+#[cfg(test)]
+mod test_irx;
+
+/// `ET_SCE_IOPRELEXEC` object type, relocatable IOP executable
+pub const ET_SCE_IOPRELEXEC: u16 = 0xFF80;
+
+/// `SHT_SCE_IOPMOD` section type, the section contains the module information
+const SHT_SCE_IOPMOD: u32 = 0x7000_0080;
+
+/// `SHT_NOBITS` section type, the section occupies no space in the file
+const SHT_NOBITS: u32 = 8;
+
+/// `SHF_ALLOC` section flag, the section is loaded into memory
+const SHF_ALLOC: u32 = 0x2;
+
+/// Magic number at the start of an export table
+const EXPORT_MAGIC: u32 = 0x41E0_0000;
+
+/// Size of the export table header preceding the function pointers
+const EXPORT_HEADER_SIZE: usize = 20;
+
+/// Offset of the version in the module information
+const MODULE_INFO_VERSION: usize = 24;
+
+/// Prefix of the symbol marking the export table
+const EXPORT_SYMBOL_PREFIX: &str = "_exp_";
+
+/// Errors that might occur when parsing an IRX module
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum IrxError {
+    /// The file isn't a valid IOP module ELF file
+    Elf(ElfError),
+
+    /// The file has no `.iopmod` section
+    MissingModuleInfo,
+
+    /// The `.iopmod` section is truncated or its name isn't valid UTF-8
+    InvalidModuleInfo,
+
+    /// The export table is truncated or isn't terminated
+    InvalidExportTable,
+}
+
+impl Display for IrxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+impl StdError for IrxError {}
+
+impl From<ElfError> for IrxError {
+    fn from(value: ElfError) -> Self {
+        Self::Elf(value)
+    }
+}
+
+/// Function exported by an IRX module
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct IrxExport<'a> {
+    /// Index of the function in the export table, used by importers to reference it
+    pub index: u16,
+
+    /// Name of the function, if the module has a symbol table
+    pub name: Option<&'a str>,
+
+    /// Offset of the function relative to the start of the module
+    pub offset: u32,
+}
+
+/// Export table of a module
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+struct ExportTable<'a> {
+    library: &'a str,
+    functions: &'a [u8],
+}
+
+/// Parsed IRX module
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct IrxModule<'a> {
+    /// Name of the module
+    pub name: &'a str,
+
+    /// Version of the module, the major version in the upper byte and the minor version in the lower byte
+    pub version: u16,
+
+    export_table: Option<ExportTable<'a>>,
+    symbols: Option<SymbolTable<'a>>,
+}
+
+impl<'a> IrxModule<'a> {
+    /// Parse an IRX module
+    ///
+    /// # Errors
+    ///
+    /// - The file isn't a MIPS ELF file of type [`ET_SCE_IOPRELEXEC`]
+    /// - The module information is missing or invalid
+    /// - The export table is invalid
+    pub fn parse(bytes: &'a [u8]) -> Result<Self, IrxError> {
+        let elf = Ps2Elf::parse_with_type(bytes, ET_SCE_IOPRELEXEC)?;
+
+        let module_info = elf
+            .sections()
+            .find(|section| section.kind == SHT_SCE_IOPMOD)
+            .map(|section| elf.section_data(&section))
+            .ok_or(IrxError::MissingModuleInfo)?;
+        let version = module_info
+            .get(MODULE_INFO_VERSION..MODULE_INFO_VERSION + 2)
+            .map(|raw| u16::from_le_bytes([raw[0], raw[1]]))
+            .ok_or(IrxError::InvalidModuleInfo)?;
+        let name = module_info
+            .get(MODULE_INFO_VERSION + 2..)
+            .and_then(c_str)
+            .ok_or(IrxError::InvalidModuleInfo)?;
+
+        let symbols = elf.symbol_table();
+        let export_table = symbols
+            .and_then(|symbols| {
+                symbols.iter().find(|symbol| {
+                    symbol.section_index != 0 && symbol.name.starts_with(EXPORT_SYMBOL_PREFIX)
+                })
+            })
+            .and_then(|symbol| data_at(&elf, symbol.value))
+            .or_else(|| find_export_table(&elf))
+            .map(parse_export_table)
+            .transpose()?;
+
+        Ok(Self {
+            name,
+            version,
+            export_table,
+            symbols,
+        })
+    }
+
+    /// Name of the library exported by the module
+    ///
+    /// Returns `None` if the module doesn't export any functions
+    #[must_use]
+    pub fn export_library(&self) -> Option<&'a str> {
+        self.export_table.map(|table| table.library)
+    }
+
+    /// Iterate over the functions exported by the module
+    pub fn exports(&self) -> impl Iterator<Item = IrxExport<'a>> + '_ {
+        let functions = self.export_table.map_or(&[][..], |table| table.functions);
+
+        (0..).zip(functions.chunks_exact(4)).map(|(index, raw)| {
+            let offset = u32::from_le_bytes([raw[0], raw[1], raw[2], raw[3]]);
+            let name = self.symbols.and_then(|symbols| {
+                symbols
+                    .iter()
+                    .find(|symbol| {
+                        symbol.section_index != 0
+                            && symbol.value == offset
+                            && !symbol.name.is_empty()
+                            && !symbol.name.starts_with(EXPORT_SYMBOL_PREFIX)
+                    })
+                    .map(|symbol| symbol.name)
+            });
+
+            IrxExport {
+                index,
+                name,
+                offset,
+            }
+        })
+    }
+}
+
+/// Read a NUL-terminated UTF-8 string
+fn c_str(bytes: &[u8]) -> Option<&str> {
+    let len = bytes.iter().position(|&byte| byte == 0)?;
+    std::str::from_utf8(&bytes[..len]).ok()
+}
+
+/// Retrieve the data of the loaded section containing `address`, starting at `address`
+fn data_at<'a>(elf: &Ps2Elf<'a>, address: u32) -> Option<&'a [u8]> {
+    elf.sections()
+        .filter(|section| section.kind != SHT_NOBITS && section.flags & SHF_ALLOC != 0)
+        .find(|section| {
+            address >= section.address
+                && u64::from(address) < u64::from(section.address) + u64::from(section.size)
+        })
+        .and_then(|section| {
+            elf.section_data(&section)
+                .get((address - section.address) as usize..)
+        })
+}
+
+/// Search the loaded sections for an export table, used if the module has been stripped
+fn find_export_table<'a>(elf: &Ps2Elf<'a>) -> Option<&'a [u8]> {
+    elf.sections()
+        .filter(|section| section.kind != SHT_NOBITS && section.flags & SHF_ALLOC != 0)
+        .find_map(|section| {
+            let data = elf.section_data(&section);
+            (0..data.len())
+                .step_by(4)
+                .find(|&offset| {
+                    get_u32_le(data, offset) == Some(EXPORT_MAGIC)
+                        && get_u32_le(data, offset + 4) == Some(0)
+                })
+                .map(|offset| &data[offset..])
+        })
+}
+
+/// Parse the export table at the start of `data`
+fn parse_export_table(data: &[u8]) -> Result<ExportTable<'_>, IrxError> {
+    if get_u32_le(data, 0) != Some(EXPORT_MAGIC) {
+        return Err(IrxError::InvalidExportTable);
+    }
+
+    let library = data
+        .get(12..EXPORT_HEADER_SIZE)
+        .map(|raw| {
+            let len = raw.iter().position(|&byte| byte == 0).unwrap_or(raw.len());
+            std::str::from_utf8(&raw[..len])
+        })
+        .and_then(Result::ok)
+        .ok_or(IrxError::InvalidExportTable)?;
+
+    let functions = &data[EXPORT_HEADER_SIZE..];
+    let count = functions
+        .chunks_exact(4)
+        .position(|raw| raw == [0; 4])
+        .ok_or(IrxError::InvalidExportTable)?;
+
+    Ok(ExportTable {
+        library,
+        functions: &functions[..count * 4],
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{test_irx::IrxBuilder, IrxError, IrxExport, IrxModule};
+    use ps2_elf::ElfError;
+
+    #[test]
+    fn parse() {
+        let bytes = IrxBuilder::new().symbols(true).build();
+        let module = IrxModule::parse(&bytes).unwrap();
+
+        assert_eq!(module.name, "ioman");
+        assert_eq!(module.version, 0x0102);
+        assert_eq!(module.export_library(), Some("ioman"));
+        assert_eq!(
+            module.exports().collect::<Vec<_>>(),
+            [
+                IrxExport {
+                    index: 0,
+                    name: Some("open"),
+                    offset: 0x40,
+                },
+                IrxExport {
+                    index: 1,
+                    name: Some("close"),
+                    offset: 0x60,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn stripped() {
+        let bytes = IrxBuilder::new().build();
+        let module = IrxModule::parse(&bytes).unwrap();
+
+        assert_eq!(module.export_library(), Some("ioman"));
+        assert_eq!(
+            module
+                .exports()
+                .map(|export| (export.name, export.offset))
+                .collect::<Vec<_>>(),
+            [(None, 0x40), (None, 0x60)]
+        );
+    }
+
+    #[test]
+    fn no_exports() {
+        let bytes = IrxBuilder::new().export_table(false).build();
+        let module = IrxModule::parse(&bytes).unwrap();
+
+        assert_eq!(module.name, "ioman");
+        assert_eq!(module.export_library(), None);
+        assert_eq!(module.exports().count(), 0);
+    }
+
+    #[test]
+    fn reject_invalid() {
+        let executable = IrxBuilder::new().kind(2).build();
+        assert_eq!(
+            IrxModule::parse(&executable),
+            Err(IrxError::Elf(ElfError::NotExecutable))
+        );
+
+        let missing = IrxBuilder::new().module_info(false).build();
+        assert_eq!(IrxModule::parse(&missing), Err(IrxError::MissingModuleInfo));
+
+        let unterminated = IrxBuilder::new().terminated(false).build();
+        assert_eq!(
+            IrxModule::parse(&unterminated),
+            Err(IrxError::InvalidExportTable)
+        );
+    }
+}
@@ -0,0 +1,167 @@
+//! `IrxBuilder`, assembling IOP modules: ELF files of type `0xFF80` with an `.iopmod` section and symbols on request
+//!
+//! The module exports the library `ioman` with the functions `open` (`0x40`) and `close` (`0x60`)
+
+#[allow(clippy::struct_excessive_bools)]
+pub struct IrxBuilder {
+    kind: u16,
+    symbols: bool,
+    module_info: bool,
+    export_table: bool,
+    terminated: bool,
+}
+
+impl IrxBuilder {
+    pub fn new() -> Self {
+        Self {
+            kind: 0xFF80,
+            symbols: false,
+            module_info: true,
+            export_table: true,
+            terminated: true,
+        }
+    }
+
+    pub fn kind(mut self, kind: u16) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    pub fn symbols(mut self, symbols: bool) -> Self {
+        self.symbols = symbols;
+        self
+    }
+
+    pub fn module_info(mut self, module_info: bool) -> Self {
+        self.module_info = module_info;
+        self
+    }
+
+    pub fn export_table(mut self, export_table: bool) -> Self {
+        self.export_table = export_table;
+        self
+    }
+
+    pub fn terminated(mut self, terminated: bool) -> Self {
+        self.terminated = terminated;
+        self
+    }
+
+    fn text(&self) -> Vec<u8> {
+        let mut text = Vec::new();
+        if self.export_table {
+            text.extend_from_slice(&0x41E0_0000_u32.to_le_bytes());
+            text.extend_from_slice(&0_u32.to_le_bytes());
+            text.extend_from_slice(&0x0101_u16.to_le_bytes());
+            text.extend_from_slice(&0_u16.to_le_bytes());
+            text.extend_from_slice(b"ioman\0\0\0");
+            text.extend_from_slice(&0x40_u32.to_le_bytes());
+            text.extend_from_slice(&0x60_u32.to_le_bytes());
+            if self.terminated {
+                text.extend_from_slice(&0_u32.to_le_bytes());
+            }
+        }
+        text.resize(0x80, 0xFF);
+        text
+    }
+
+    fn module_info_data() -> Vec<u8> {
+        let mut data = Vec::new();
+        // Module information pointer, entry point, `$gp`, text size, data size, BSS size
+        for value in [0xFFFF_FFFF_u32, 0x40, 0, 0x80, 0, 0] {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+        data.extend_from_slice(&0x0102_u16.to_le_bytes());
+        data.extend_from_slice(b"ioman\0");
+        data
+    }
+
+    fn symbol_table() -> (Vec<u8>, Vec<u8>) {
+        let strtab = b"\0_exp_ioman\0open\0close\0".to_vec();
+        let mut symtab = vec![0; 16];
+        for (name, value) in [(1_u32, 0_u32), (12, 0x40), (17, 0x60)] {
+            symtab.extend_from_slice(&name.to_le_bytes());
+            symtab.extend_from_slice(&value.to_le_bytes());
+            symtab.extend_from_slice(&0_u32.to_le_bytes());
+            // STB_GLOBAL, defined in .text
+            symtab.extend_from_slice(&[0x10, 0]);
+            symtab.extend_from_slice(&1_u16.to_le_bytes());
+        }
+        (symtab, strtab)
+    }
+
+    pub fn build(self) -> Vec<u8> {
+        // (name, type, flags, data, link, entry size)
+        let mut sections = vec![(".text", 1_u32, 0x6_u32, self.text(), 0_u32, 0_u32)];
+        if self.module_info {
+            sections.push((".iopmod", 0x7000_0080, 0, Self::module_info_data(), 0, 0));
+        }
+        if self.symbols {
+            let (symtab, strtab) = Self::symbol_table();
+            let strtab_index = u32::try_from(sections.len() + 2).unwrap();
+            sections.push((".symtab", 2, 0, symtab, strtab_index, 16));
+            sections.push((".strtab", 3, 0, strtab, 0, 0));
+        }
+
+        let mut shstrtab = vec![0];
+        let mut name_offsets = Vec::new();
+        for name in sections
+            .iter()
+            .map(|section| section.0)
+            .chain([".shstrtab"])
+        {
+            name_offsets.push(u32::try_from(shstrtab.len()).unwrap());
+            shstrtab.extend_from_slice(name.as_bytes());
+            shstrtab.push(0);
+        }
+        sections.push((".shstrtab", 3, 0, shstrtab, 0, 0));
+
+        let u16 = |value: usize| u16::try_from(value).unwrap().to_le_bytes();
+        let u32 = |value: usize| u32::try_from(value).unwrap().to_le_bytes();
+
+        let mut data = Vec::new();
+        let mut section_offsets = Vec::new();
+        for section in &sections {
+            section_offsets.push(52 + data.len());
+            data.extend_from_slice(&section.3);
+        }
+        let shoff = 52 + data.len();
+
+        let mut out = Vec::new();
+        out.extend_from_slice(b"\x7FELF\x01\x01\x01\x00");
+        out.extend_from_slice(&[0; 8]);
+        out.extend_from_slice(&self.kind.to_le_bytes());
+        out.extend_from_slice(&8_u16.to_le_bytes());
+        out.extend_from_slice(&1_u32.to_le_bytes());
+        out.extend_from_slice(&0x40_u32.to_le_bytes());
+        out.extend_from_slice(&u32(0));
+        out.extend_from_slice(&u32(shoff));
+        out.extend_from_slice(&0_u32.to_le_bytes());
+        out.extend_from_slice(&u16(52));
+        out.extend_from_slice(&u16(32));
+        out.extend_from_slice(&u16(0));
+        out.extend_from_slice(&u16(40));
+        out.extend_from_slice(&u16(sections.len() + 1));
+        out.extend_from_slice(&u16(sections.len()));
+
+        out.extend_from_slice(&data);
+
+        // SHN_UNDEF
+        out.extend_from_slice(&[0; 40]);
+        for ((section, offset), name) in sections.iter().zip(section_offsets).zip(name_offsets) {
+            let (_, kind, flags, data, link, entry_size) = section;
+            out.extend_from_slice(&name.to_le_bytes());
+            out.extend_from_slice(&kind.to_le_bytes());
+            out.extend_from_slice(&flags.to_le_bytes());
+            out.extend_from_slice(&0_u32.to_le_bytes());
+            out.extend_from_slice(&u32(offset));
+            out.extend_from_slice(&u32(data.len()));
+            out.extend_from_slice(&link.to_le_bytes());
+            out.extend_from_slice(&0_u32.to_le_bytes());
+            out.extend_from_slice(&4_u32.to_le_bytes());
+            out.extend_from_slice(&entry_size.to_le_bytes());
+        }
+
+        out
+    }
+}
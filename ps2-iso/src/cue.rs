@@ -0,0 +1,191 @@
+use crate::{IsoError, Ps2Disc, SECTOR_SIZE};
+use std::borrow::Cow;
+
+/// Number of frames (sectors) per second in an `MM:SS:FF` position
+const FRAMES_PER_SECOND: usize = 75;
+
+/// Layout of the sectors of a data track
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct TrackMode {
+    /// Size of a raw sector
+    sector_size: usize,
+
+    /// Offset of the 2048 bytes of user data in a raw sector
+    data_offset: usize,
+}
+
+impl TrackMode {
+    /// Parse a CUE track type such as `MODE2/2352`, `None` for audio and unsupported tracks
+    fn parse(mode: &str) -> Option<Self> {
+        let (sector_size, data_offset) = match mode.to_ascii_uppercase().as_str() {
+            "MODE1/2048" | "MODE2/2048" => (2048, 0),
+            // Sync pattern and header
+            "MODE1/2352" => (2352, 16),
+            // Sync pattern, header and the Form 1 subheader
+            "MODE2/2352" => (2352, 24),
+            "MODE2/2336" => (2336, 8),
+            _ => return None,
+        };
+        Some(Self {
+            sector_size,
+            data_offset,
+        })
+    }
+}
+
+/// Parse an `MM:SS:FF` position into a sector number
+fn parse_position(position: &str) -> Option<usize> {
+    let mut parts = position.split(':').map(|part| part.parse::<usize>().ok());
+    let (minutes, seconds, frames) = (parts.next()??, parts.next()??, parts.next()??);
+    if parts.next().is_some() || seconds >= 60 || frames >= FRAMES_PER_SECOND {
+        return None;
+    }
+    minutes
+        .checked_mul(60 * FRAMES_PER_SECOND)?
+        .checked_add(seconds * FRAMES_PER_SECOND + frames)
+}
+
+/// Find the first data track, returning its mode and first sector in the BIN file
+fn data_track(cue: &str) -> Result<(TrackMode, usize), IsoError> {
+    let mut mode = None;
+    for line in cue.lines() {
+        let tokens = line.split_whitespace().collect::<Vec<_>>();
+        match tokens[..] {
+            [command, _, track_type] if command.eq_ignore_ascii_case("TRACK") => {
+                // A data track without an `INDEX 01` is followed by another track
+                if mode.is_some() {
+                    break;
+                }
+                mode = TrackMode::parse(track_type);
+            }
+            [command, "01", position] if command.eq_ignore_ascii_case("INDEX") => {
+                if let Some(mode) = mode {
+                    let start = parse_position(position).ok_or(IsoError::InvalidCueSheet)?;
+                    return Ok((mode, start));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Err(IsoError::InvalidCueSheet)
+}
+
+impl<'a> Ps2Disc<'a> {
+    /// Open a disc image stored as a BIN file with raw sectors, described by a CUE sheet
+    ///
+    /// The first data track is used, `MODE1/2352`, `MODE2/2352`, `MODE2/2336` and 2048 byte tracks are supported.
+    /// Only single-file images are supported, the positions in the CUE sheet are taken as offsets into `bin`
+    ///
+    /// # Errors
+    ///
+    /// - The CUE sheet doesn't describe a supported data track
+    /// - See [`Ps2Disc::open`]
+    pub fn open_bin_cue(bin: &'a [u8], cue: &str) -> Result<Self, IsoError> {
+        let (mode, start) = data_track(cue)?;
+        let track = start
+            .checked_mul(mode.sector_size)
+            .and_then(|offset| bin.get(offset..))
+            .ok_or(IsoError::InvalidCueSheet)?;
+
+        let data = if mode.sector_size == SECTOR_SIZE {
+            Cow::Borrowed(track)
+        } else {
+            let sectors = track.chunks_exact(mode.sector_size);
+            let mut data = Vec::with_capacity(sectors.len() * SECTOR_SIZE);
+            for sector in sectors {
+                data.extend_from_slice(&sector[mode.data_offset..mode.data_offset + SECTOR_SIZE]);
+            }
+            Cow::Owned(data)
+        };
+
+        Self::from_sectors(data)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{test_iso, IsoError, Ps2Disc, SECTOR_SIZE};
+
+    /// Wrap 2048 byte sectors into raw sectors with recognisable filler around the user data
+    fn raw_sectors(image: &[u8], sector_size: usize, data_offset: usize) -> Vec<u8> {
+        let mut bin = Vec::new();
+        for sector in image.chunks(SECTOR_SIZE) {
+            let start = bin.len();
+            bin.resize(start + data_offset, 0xAA);
+            bin.extend_from_slice(sector);
+            bin.resize(start + sector_size, 0xEE);
+        }
+        bin
+    }
+
+    #[test]
+    fn mode2() {
+        let image = test_iso::image();
+        let bin = raw_sectors(&image, 2352, 24);
+        let cue = "FILE \"GAME.BIN\" BINARY\r\n  TRACK 01 MODE2/2352\r\n    INDEX 01 00:00:00\r\n";
+
+        let disc = Ps2Disc::open_bin_cue(&bin, cue).unwrap();
+        assert_eq!(disc, Ps2Disc::open(&image).unwrap());
+        assert_eq!(disc.read_file("SYSTEM.CNF").unwrap(), test_iso::SYSTEM_CNF);
+        assert_eq!(disc.read_file("SLUS_213.48").unwrap(), test_iso::elf());
+    }
+
+    #[test]
+    fn mode1_after_audio() {
+        let image = test_iso::image();
+        // Two seconds of audio in front of the data track
+        let mut bin = vec![0x55; 150 * 2352];
+        bin.extend(raw_sectors(&image, 2352, 16));
+        let cue = "FILE \"GAME.BIN\" BINARY\n  TRACK 01 AUDIO\n    INDEX 01 00:00:00\n  \
+                   TRACK 02 MODE1/2352\n    INDEX 00 00:01:74\n    INDEX 01 00:02:00\n";
+
+        let disc = Ps2Disc::open_bin_cue(&bin, cue).unwrap();
+        assert_eq!(disc.read_file("/MODULES/IOPRP.IMG").unwrap(), b"IOP!");
+    }
+
+    #[test]
+    fn iso_track() {
+        let image = test_iso::image();
+        let cue = "FILE \"GAME.ISO\" BINARY\n  TRACK 01 MODE1/2048\n    INDEX 01 00:00:00\n";
+        assert_eq!(
+            Ps2Disc::open_bin_cue(&image, cue).unwrap(),
+            Ps2Disc::open(&image).unwrap()
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        let bin = raw_sectors(&test_iso::image(), 2352, 24);
+        let invalid = |cue: &str| Ps2Disc::open_bin_cue(&bin, cue);
+
+        assert_eq!(invalid(""), Err(IsoError::InvalidCueSheet));
+        assert_eq!(
+            invalid("FILE \"A.BIN\" BINARY\n  TRACK 01 AUDIO\n    INDEX 01 00:00:00\n"),
+            Err(IsoError::InvalidCueSheet)
+        );
+        assert_eq!(
+            invalid("FILE \"A.BIN\" BINARY\n  TRACK 01 MODE2/2352\n    INDEX 01 00:0A:00\n"),
+            Err(IsoError::InvalidCueSheet)
+        );
+        assert_eq!(
+            invalid("FILE \"A.BIN\" BINARY\n  TRACK 01 MODE2/2352\n    INDEX 01 10:00:00\n"),
+            Err(IsoError::InvalidCueSheet)
+        );
+        for position in [
+            "00:60:00",
+            "00:00:75",
+            "99999999999999999:00:00",
+            "2000000000000000:00:00",
+        ] {
+            let cue =
+                format!("FILE \"A.BIN\" BINARY\n  TRACK 01 MODE2/2352\n    INDEX 01 {position}\n");
+            assert_eq!(invalid(&cue), Err(IsoError::InvalidCueSheet));
+        }
+        // The wrong mode moves the user data, so the volume descriptor isn't found
+        assert_eq!(
+            invalid("FILE \"A.BIN\" BINARY\n  TRACK 01 MODE1/2352\n    INDEX 01 00:00:00\n"),
+            Err(IsoError::InvalidDescriptor)
+        );
+    }
+}
@@ -0,0 +1,101 @@
+use crate::IsoError;
+use ps2_bytes::read_u32_le;
+
+/// Flag of directory records describing a hidden entry
+pub(crate) const FLAG_HIDDEN: u8 = 0x01;
+
+/// Flag of directory records describing a directory
+pub(crate) const FLAG_DIRECTORY: u8 = 0x02;
+
+/// Recording date and time of a directory entry
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Timestamp {
+    /// Year, e.g. 2004
+    pub year: u16,
+
+    /// Month (1-12)
+    pub month: u8,
+
+    /// Day of the month (1-31)
+    pub day: u8,
+
+    /// Hour (0-23)
+    pub hour: u8,
+
+    /// Minute (0-59)
+    pub minute: u8,
+
+    /// Second (0-59)
+    pub second: u8,
+
+    /// Offset from GMT in 15 minute intervals
+    pub gmt_offset: i8,
+}
+
+/// Entry of a directory, see [`Ps2Disc::list_dir`](crate::Ps2Disc::list_dir)
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct DirEntry {
+    /// Name of the entry, without the `;1` file version suffix
+    pub name: String,
+
+    /// First sector of the entry's data
+    pub extent: u32,
+
+    /// Size of the entry's data in bytes
+    pub size: u32,
+
+    /// Raw file flags of the directory record
+    pub flags: u8,
+
+    /// When the entry was recorded
+    pub recorded: Timestamp,
+}
+
+impl DirEntry {
+    /// Whether the entry is a directory
+    #[must_use]
+    pub fn is_dir(&self) -> bool {
+        self.flags & FLAG_DIRECTORY != 0
+    }
+
+    /// Whether the entry is hidden
+    #[must_use]
+    pub fn is_hidden(&self) -> bool {
+        self.flags & FLAG_HIDDEN != 0
+    }
+
+    /// Parse a directory record, returning `None` for the `.` and `..` entries
+    pub(crate) fn parse(record: &[u8], sector: u32) -> Result<Option<Self>, IsoError> {
+        let name_len = usize::from(
+            *record
+                .get(32)
+                .ok_or(IsoError::InvalidDirectory { sector })?,
+        );
+        let name = record
+            .get(33..33 + name_len)
+            .ok_or(IsoError::InvalidDirectory { sector })?;
+        if let [0x00 | 0x01] = name {
+            return Ok(None);
+        }
+
+        let name = String::from_utf8_lossy(name);
+        let name = name.strip_suffix(";1").unwrap_or(&name).to_owned();
+        let date = &record[18..25];
+
+        Ok(Some(Self {
+            name,
+            extent: read_u32_le(record, 2),
+            size: read_u32_le(record, 10),
+            flags: record[25],
+            recorded: Timestamp {
+                year: 1900 + u16::from(date[0]),
+                month: date[1],
+                day: date[2],
+                hour: date[3],
+                minute: date[4],
+                second: date[5],
+                gmt_offset: i8::from_le_bytes([date[6]]),
+            },
+        }))
+    }
+}
@@ -0,0 +1,626 @@
+#![doc = include_str!("../README.md")]
+#![forbid(missing_docs, unsafe_code)]
+#![warn(clippy::all, clippy::pedantic)]
+
+use ps2_bytes::{read_u16_le, read_u32_le};
+use std::{
+    borrow::Cow,
+    error::Error as StdError,
+    fmt::{self, Display},
+};
+
+mod cue;
+mod directory;
+mod serial;
+#[cfg(test)]
+mod test_iso;
+mod udf;
+
+pub use self::{
+    directory::{DirEntry, Timestamp},
+    serial::{game_serial, GameSerial},
+};
+pub use system_cnf::Region;
+
+/// Size of a logical sector
+pub const SECTOR_SIZE: usize = 2048;
+
+/// Sector of the primary volume descriptor, following the system area
+const PRIMARY_VOLUME_DESCRIPTOR: u32 = 16;
+
+/// Standard identifier of volume descriptors
+const STANDARD_IDENTIFIER: &[u8] = b"CD001";
+
+/// System identifier of PS1 and PS2 discs
+const SYSTEM_IDENTIFIER: &str = "PLAYSTATION";
+
+/// Errors that might occur when reading a disc image
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum IsoError {
+    /// The image ends before the given sector
+    Truncated {
+        /// Sector that couldn't be read
+        sector: u32,
+    },
+
+    /// The primary volume descriptor is missing or malformed
+    InvalidDescriptor,
+
+    /// The system identifier isn't `PLAYSTATION`
+    NotPs2Disc,
+
+    /// The path table is malformed
+    InvalidPathTable,
+
+    /// A directory record is malformed
+    InvalidDirectory {
+        /// Sector of the directory record
+        sector: u32,
+    },
+
+    /// The path doesn't exist
+    NotFound,
+
+    /// A component of the path isn't a directory
+    NotADirectory,
+
+    /// The path points to a directory instead of a file
+    NotAFile,
+
+    /// Neither `SYSTEM.CNF` nor the volume identifier contain a game serial
+    MissingSerial,
+
+    /// The CUE sheet doesn't describe a supported data track within the BIN file
+    InvalidCueSheet,
+
+    /// A UDF descriptor is malformed or uses an unsupported feature
+    InvalidUdf {
+        /// Sector of the descriptor
+        sector: u32,
+    },
+
+    /// The file is stored in non-consecutive extents of the UDF file system and can't be borrowed from the image
+    FragmentedFile,
+}
+
+impl Display for IsoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+impl StdError for IsoError {}
+
+/// File system a [`Ps2Disc`] is read through
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum FileSystem {
+    /// ISO 9660, present on all CD based and most DVD based discs
+    Iso9660,
+
+    /// UDF, only used for DVD images without an ISO 9660 file system
+    Udf,
+}
+
+/// Directory listed in the path table
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+struct PathTableEntry {
+    name: String,
+    extent: u32,
+    /// 1-based index of the parent directory
+    parent: u16,
+}
+
+/// ISO 9660 volume
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+struct Iso9660 {
+    root_size: u32,
+    path_table: Vec<PathTableEntry>,
+}
+
+/// File system the files are read from
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+enum Volume {
+    Iso9660(Iso9660),
+    Udf(udf::Udf),
+}
+
+/// PS2 disc image
+///
+/// The ISO 9660 file system is read if there is one. DVD based discs usually also have a UDF file system
+/// describing the same files, which is only read if the ISO 9660 one is missing
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Ps2Disc<'a> {
+    data: Cow<'a, [u8]>,
+    system_id: String,
+    volume_id: String,
+    volume: Volume,
+}
+
+impl<'a> Ps2Disc<'a> {
+    /// Open a disc image consisting of 2048 byte sectors
+    ///
+    /// # Errors
+    ///
+    /// - The image is truncated
+    /// - The primary volume descriptor or the path table is malformed
+    /// - The system identifier isn't `PLAYSTATION`
+    /// - Without an ISO 9660 file system: the UDF descriptors are malformed or there's no `SYSTEM.CNF`
+    pub fn open(data: &'a [u8]) -> Result<Self, IsoError> {
+        Self::from_sectors(Cow::Borrowed(data))
+    }
+
+    /// Open a disc from its 2048 byte sectors
+    fn from_sectors(data: Cow<'a, [u8]>) -> Result<Self, IsoError> {
+        let descriptor = sector(&data, PRIMARY_VOLUME_DESCRIPTOR)?;
+        if &descriptor[1..6] != STANDARD_IDENTIFIER {
+            return Self::from_udf(data);
+        }
+        if descriptor[0] != 1 || descriptor[6] != 1 {
+            return Err(IsoError::InvalidDescriptor);
+        }
+        if usize::from(read_u16_le(descriptor, 128)) != SECTOR_SIZE {
+            return Err(IsoError::InvalidDescriptor);
+        }
+
+        let system_id = identifier(&descriptor[8..40]);
+        if system_id != SYSTEM_IDENTIFIER {
+            return Err(IsoError::NotPs2Disc);
+        }
+        let volume_id = identifier(&descriptor[40..72]);
+
+        let path_table_size = read_u32_le(descriptor, 132) as usize;
+        let path_table_sector = read_u32_le(descriptor, 140);
+        let path_table = data
+            .get(path_table_sector as usize * SECTOR_SIZE..)
+            .and_then(|table| table.get(..path_table_size))
+            .ok_or(IsoError::Truncated {
+                sector: path_table_sector,
+            })?;
+
+        let root = &descriptor[156..190];
+        let root_size = read_u32_le(root, 10);
+        let mut path_table = parse_path_table(path_table)?;
+        // The path table of a valid image starts with the root directory
+        match path_table.first_mut() {
+            Some(entry) if entry.extent == read_u32_le(root, 2) && entry.parent == 1 => {
+                entry.name.clear();
+            }
+            _ => return Err(IsoError::InvalidPathTable),
+        }
+
+        Ok(Self {
+            data,
+            system_id,
+            volume_id,
+            volume: Volume::Iso9660(Iso9660 {
+                root_size,
+                path_table,
+            }),
+        })
+    }
+
+    /// Open a disc without an ISO 9660 file system through its UDF file system
+    fn from_udf(data: Cow<'a, [u8]>) -> Result<Self, IsoError> {
+        let udf = udf::Udf::open(&data)?.ok_or(IsoError::InvalidDescriptor)?;
+        // UDF has no system identifier, every PS2 disc has a `SYSTEM.CNF` though
+        let has_system_cnf = udf
+            .list_dir(&data, &[])?
+            .iter()
+            .any(|entry| !entry.is_dir() && entry.name.eq_ignore_ascii_case("SYSTEM.CNF"));
+        if !has_system_cnf {
+            return Err(IsoError::NotPs2Disc);
+        }
+
+        Ok(Self {
+            data,
+            system_id: String::new(),
+            volume_id: udf.volume_id.clone(),
+            volume: Volume::Udf(udf),
+        })
+    }
+
+    /// File system the files are read from
+    #[must_use]
+    pub fn file_system(&self) -> FileSystem {
+        match self.volume {
+            Volume::Iso9660(_) => FileSystem::Iso9660,
+            Volume::Udf(_) => FileSystem::Udf,
+        }
+    }
+
+    /// System identifier of the volume, empty for discs read through their UDF file system
+    #[must_use]
+    pub fn system_id(&self) -> &str {
+        &self.system_id
+    }
+
+    /// Volume identifier (label), the logical volume identifier for UDF
+    #[must_use]
+    pub fn volume_id(&self) -> &str {
+        &self.volume_id
+    }
+
+    /// List the entries of a directory, excluding `.` and `..`
+    ///
+    /// Paths may use `/` or `\` as separators and are matched case-insensitively.
+    /// A device prefix such as `cdrom0:` is ignored
+    ///
+    /// # Errors
+    ///
+    /// - The directory doesn't exist
+    /// - A directory record is malformed or lies outside of the image
+    pub fn list_dir(&self, path: &str) -> Result<Vec<DirEntry>, IsoError> {
+        let components = components(path).collect::<Vec<_>>();
+        match &self.volume {
+            Volume::Iso9660(iso) => iso.directory(&self.data, &components),
+            Volume::Udf(udf) => udf.list_dir(&self.data, &components),
+        }
+    }
+
+    /// Read the contents of a file, see [`Ps2Disc::list_dir`] for the accepted paths
+    ///
+    /// The `;1` file version suffix is optional
+    ///
+    /// # Errors
+    ///
+    /// - The file doesn't exist or is a directory
+    /// - A directory record is malformed or the file lies outside of the image
+    /// - The file is fragmented on a disc read through its UDF file system
+    pub fn read_file(&self, path: &str) -> Result<&[u8], IsoError> {
+        let components = components(path).collect::<Vec<_>>();
+        let (name, parent) = components.split_last().ok_or(IsoError::NotAFile)?;
+        let name = name.strip_suffix(";1").unwrap_or(name);
+
+        let iso = match &self.volume {
+            Volume::Iso9660(iso) => iso,
+            Volume::Udf(udf) => return udf.read_file(&self.data, parent, name),
+        };
+        let entry = iso
+            .directory(&self.data, parent)?
+            .into_iter()
+            .find(|entry| entry.name.eq_ignore_ascii_case(name))
+            .ok_or(IsoError::NotFound)?;
+        if entry.is_dir() {
+            return Err(IsoError::NotAFile);
+        }
+
+        let start = entry.extent as usize * SECTOR_SIZE;
+        self.data
+            .get(start..start + entry.size as usize)
+            .ok_or(IsoError::Truncated {
+                sector: entry.extent,
+            })
+    }
+}
+
+impl Iso9660 {
+    /// Entries of the directory at the given path
+    fn directory(&self, data: &[u8], components: &[&str]) -> Result<Vec<DirEntry>, IsoError> {
+        // Path table indices are 1-based, the root is its own parent
+        let mut index = 1;
+        for &component in components {
+            let child = (1..=u16::MAX).zip(&self.path_table).find(|(_, entry)| {
+                entry.parent == index && entry.name.eq_ignore_ascii_case(component)
+            });
+            index = match child {
+                Some((child, _)) if child != index => child,
+                _ => {
+                    let entries = self.records(data, index)?;
+                    return Err(
+                        if entries
+                            .iter()
+                            .any(|entry| entry.name.eq_ignore_ascii_case(component))
+                        {
+                            IsoError::NotADirectory
+                        } else {
+                            IsoError::NotFound
+                        },
+                    );
+                }
+            };
+        }
+
+        self.records(data, index)
+    }
+
+    /// Directory records of the directory at the given path table index
+    fn records(&self, data: &[u8], index: u16) -> Result<Vec<DirEntry>, IsoError> {
+        let extent = self.path_table[usize::from(index) - 1].extent;
+        let size = if index == 1 {
+            self.root_size
+        } else {
+            // The `.` record describes the directory itself
+            read_u32_le(sector(data, extent)?, 10)
+        };
+
+        let mut entries = Vec::new();
+        for sector_number in sectors(extent, size) {
+            let sector_number =
+                sector_number.ok_or(IsoError::InvalidDirectory { sector: extent })?;
+            let sector = sector(data, sector_number)?;
+
+            let mut offset = 0;
+            // Records don't cross sectors, the rest of a sector is zero-filled
+            while let Some(&len) = sector.get(offset).filter(|&&len| len != 0) {
+                let record = sector
+                    .get(offset..offset + usize::from(len))
+                    .filter(|record| record.len() >= 33)
+                    .ok_or(IsoError::InvalidDirectory {
+                        sector: sector_number,
+                    })?;
+                entries.extend(DirEntry::parse(record, sector_number)?);
+                offset += usize::from(len);
+            }
+        }
+
+        Ok(entries)
+    }
+}
+
+/// Parse the little-endian path table
+fn parse_path_table(mut table: &[u8]) -> Result<Vec<PathTableEntry>, IsoError> {
+    let mut entries = Vec::new();
+    while !table.is_empty() {
+        let name_len = usize::from(table[0]);
+        // Names are padded to an even length
+        let len = 8 + name_len + name_len % 2;
+        let entry = table.get(..len).ok_or(IsoError::InvalidPathTable)?;
+        let parent = read_u16_le(entry, 6);
+        // Parents are referenced by 16-bit numbers, so there are at most 65535 entries
+        if parent == 0
+            || usize::from(parent) > entries.len() + 1
+            || entries.len() == usize::from(u16::MAX)
+        {
+            return Err(IsoError::InvalidPathTable);
+        }
+
+        entries.push(PathTableEntry {
+            name: String::from_utf8_lossy(&entry[8..8 + name_len]).into_owned(),
+            extent: read_u32_le(entry, 2),
+            parent,
+        });
+        table = &table[len..];
+    }
+
+    Ok(entries)
+}
+
+/// Components of a path, ignoring a device prefix
+fn components(path: &str) -> impl Iterator<Item = &str> {
+    let path = path.split_once(':').map_or(path, |(_, path)| path);
+    path.split(['/', '\\'])
+        .filter(|component| !component.is_empty())
+}
+
+/// Sector at the given logical block address
+fn sector(data: &[u8], sector: u32) -> Result<&[u8], IsoError> {
+    let start = sector as usize * SECTOR_SIZE;
+    data.get(start..start + SECTOR_SIZE)
+        .ok_or(IsoError::Truncated { sector })
+}
+
+/// Numbers of the sectors holding `len` bytes starting at sector `start`, `None` past the last sector number
+fn sectors(start: u32, len: u32) -> impl Iterator<Item = Option<u32>> {
+    #[allow(clippy::cast_possible_truncation)] // Sectors are 2048 bytes
+    let count = len.div_ceil(SECTOR_SIZE as u32);
+    (0..count).map(move |index| start.checked_add(index))
+}
+
+/// Identifier padded with spaces
+fn identifier(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes)
+        .trim_end_matches([' ', '\0'])
+        .to_owned()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        game_serial, parse_path_table, test_iso, FileSystem, IsoError, Ps2Disc, Timestamp,
+        SECTOR_SIZE,
+    };
+
+    #[test]
+    fn open() {
+        let image = test_iso::image();
+        let disc = Ps2Disc::open(&image).unwrap();
+        assert_eq!(disc.system_id(), "PLAYSTATION");
+        assert_eq!(disc.volume_id(), "SLUS_21348");
+        assert_eq!(disc.file_system(), FileSystem::Iso9660);
+    }
+
+    #[test]
+    fn list_dir() {
+        let image = test_iso::image();
+        let disc = Ps2Disc::open(&image).unwrap();
+
+        let root = disc.list_dir("/").unwrap();
+        let names = root
+            .iter()
+            .map(|entry| entry.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["MODULES", "SLUS_213.48", "SYSTEM.CNF"]);
+        assert!(root[0].is_dir());
+        assert!(!root[1].is_dir());
+        assert_eq!(root[1].size, 3000);
+        assert_eq!(root[1].extent, 22);
+        assert_eq!(
+            root[1].recorded,
+            Timestamp {
+                year: 2004,
+                month: 3,
+                day: 15,
+                hour: 12,
+                minute: 34,
+                second: 56,
+                gmt_offset: 36,
+            }
+        );
+
+        let modules = disc.list_dir("cdrom0:\\modules\\").unwrap();
+        assert_eq!(modules.len(), 1);
+        assert_eq!(modules[0].name, "IOPRP.IMG");
+        assert!(modules[0].is_hidden());
+
+        assert_eq!(disc.list_dir("/DATA"), Err(IsoError::NotFound));
+        assert_eq!(disc.list_dir("/SYSTEM.CNF"), Err(IsoError::NotADirectory));
+    }
+
+    #[test]
+    fn read_file() {
+        let image = test_iso::image();
+        let disc = Ps2Disc::open(&image).unwrap();
+
+        assert_eq!(disc.read_file("SYSTEM.CNF").unwrap(), test_iso::SYSTEM_CNF);
+        assert_eq!(
+            disc.read_file("cdrom0:\\SLUS_213.48;1").unwrap(),
+            test_iso::elf()
+        );
+        assert_eq!(disc.read_file("/modules/ioprp.img").unwrap(), b"IOP!");
+
+        assert_eq!(disc.read_file("/MODULES"), Err(IsoError::NotAFile));
+        assert_eq!(disc.read_file("/"), Err(IsoError::NotAFile));
+        assert_eq!(disc.read_file("/README.TXT"), Err(IsoError::NotFound));
+        assert_eq!(
+            disc.read_file("/SYSTEM.CNF/FOO"),
+            Err(IsoError::NotADirectory)
+        );
+
+        let truncated = &image[..23 * SECTOR_SIZE];
+        let disc = Ps2Disc::open(truncated).unwrap();
+        assert_eq!(
+            disc.read_file("SLUS_213.48"),
+            Err(IsoError::Truncated { sector: 22 })
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        let mut image = test_iso::image();
+        assert_eq!(
+            Ps2Disc::open(&image[..16 * SECTOR_SIZE]),
+            Err(IsoError::Truncated { sector: 16 })
+        );
+
+        // Root directory at the last sector number
+        let pvd = 16 * SECTOR_SIZE;
+        let mut last = image.clone();
+        last[pvd + 158..pvd + 162].fill(0xFF);
+        last[18 * SECTOR_SIZE + 2..18 * SECTOR_SIZE + 6].fill(0xFF);
+        let disc = Ps2Disc::open(&last).unwrap();
+        assert_eq!(
+            disc.list_dir("/"),
+            Err(IsoError::Truncated { sector: u32::MAX })
+        );
+
+        image[pvd + 8..pvd + 19].copy_from_slice(b"WIN32      ");
+        assert_eq!(Ps2Disc::open(&image), Err(IsoError::NotPs2Disc));
+
+        image[pvd + 1] = b'B';
+        assert_eq!(Ps2Disc::open(&image), Err(IsoError::InvalidDescriptor));
+    }
+
+    #[test]
+    fn path_table_limit() {
+        let entry = [1, 0, 19, 0, 0, 0, 1, 0, b'A', 0];
+        assert_eq!(parse_path_table(&entry.repeat(65535)).unwrap().len(), 65535);
+        assert_eq!(
+            parse_path_table(&entry.repeat(65536)).err(),
+            Some(IsoError::InvalidPathTable)
+        );
+    }
+
+    #[test]
+    fn udf() {
+        let image = test_iso::udf_image();
+        let disc = Ps2Disc::open(&image).unwrap();
+        assert_eq!(disc.file_system(), FileSystem::Udf);
+        assert_eq!(disc.system_id(), "");
+        assert_eq!(disc.volume_id(), "SLUS_21348");
+
+        let root = disc.list_dir("/").unwrap();
+        let names = root
+            .iter()
+            .map(|entry| entry.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["MODULES", "SLUS_213.48", "SYSTEM.CNF", "FRAG.BIN"]);
+        assert!(root[0].is_dir());
+        assert!(!root[1].is_dir());
+        assert_eq!(root[1].size, 3000);
+        assert_eq!(root[1].extent, test_iso::UDF_PARTITION + 8);
+        assert_eq!(
+            root[1].recorded,
+            Timestamp {
+                year: 2004,
+                month: 3,
+                day: 15,
+                hour: 12,
+                minute: 34,
+                second: 56,
+                gmt_offset: 36,
+            }
+        );
+        // Embedded data is located at the file entry
+        assert_eq!(root[2].extent, test_iso::UDF_PARTITION + 6);
+
+        let modules = disc.list_dir("cdrom0:\\modules\\").unwrap();
+        assert_eq!(modules.len(), 1);
+        assert_eq!(modules[0].name, "IOPRP.IMG");
+        assert!(modules[0].is_hidden());
+
+        assert_eq!(disc.read_file("SYSTEM.CNF").unwrap(), test_iso::SYSTEM_CNF);
+        assert_eq!(
+            disc.read_file("cdrom0:\\SLUS_213.48;1").unwrap(),
+            test_iso::elf()
+        );
+        assert_eq!(disc.read_file("/modules/ioprp.img").unwrap(), b"IOP!");
+        assert_eq!(root[3].size, 2058);
+        assert_eq!(disc.read_file("FRAG.BIN"), Err(IsoError::FragmentedFile));
+        assert_eq!(game_serial(&disc).unwrap().to_string(), "SLUS-21348");
+
+        assert_eq!(disc.read_file("/OLD.CNF"), Err(IsoError::NotFound));
+        assert_eq!(disc.read_file("/MODULES"), Err(IsoError::NotAFile));
+        assert_eq!(disc.list_dir("/DATA"), Err(IsoError::NotFound));
+        assert_eq!(disc.list_dir("/SYSTEM.CNF"), Err(IsoError::NotADirectory));
+    }
+
+    #[test]
+    fn udf_invalid() {
+        let partition = test_iso::UDF_PARTITION;
+        let mut image = test_iso::udf_image();
+
+        // Volume descriptor sequence at the last sector number
+        let anchor = 256 * SECTOR_SIZE;
+        let mut last = image.clone();
+        let mut descriptor = last[anchor..anchor + 512].to_vec();
+        descriptor[4] = 0;
+        descriptor[20..24].fill(0xFF);
+        last[anchor..anchor + 512].copy_from_slice(&test_iso::udf_tag(2, 256, descriptor));
+        assert_eq!(
+            Ps2Disc::open(&last),
+            Err(IsoError::Truncated { sector: u32::MAX })
+        );
+
+        let ioprp = (partition + 4) as usize * SECTOR_SIZE;
+        image[ioprp + 100] ^= 0xFF;
+        let disc = Ps2Disc::open(&image).unwrap();
+        assert_eq!(
+            disc.list_dir("/MODULES"),
+            Err(IsoError::InvalidUdf {
+                sector: partition + 4
+            })
+        );
+
+        // Corrupted file set descriptor
+        let file_set = partition as usize * SECTOR_SIZE;
+        image[file_set + 404] = 0x40;
+        assert_eq!(
+            Ps2Disc::open(&image),
+            Err(IsoError::InvalidUdf { sector: partition })
+        );
+
+        // Without an anchor there is no file system at all
+        image[256 * SECTOR_SIZE + 20] ^= 0xFF;
+        assert_eq!(Ps2Disc::open(&image), Err(IsoError::InvalidDescriptor));
+    }
+}
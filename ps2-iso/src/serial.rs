@@ -0,0 +1,116 @@
+use crate::{IsoError, Ps2Disc};
+use std::fmt::{self, Display};
+use system_cnf::{GameId, Region, SystemCnf};
+
+/// Serial of a game, e.g. `SLUS-21348`
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct GameSerial {
+    /// Four letter prefix of the serial (`SLUS`, `SCES`, etc.)
+    pub prefix: String,
+
+    /// Five digit number of the serial
+    pub number: String,
+}
+
+impl GameSerial {
+    /// Region derived from the prefix
+    #[must_use]
+    pub fn region(&self) -> Region {
+        Region::from_prefix(&self.prefix)
+    }
+
+    /// Parse a volume identifier such as `SLUS_21348`, `SLUS-21348` or `SLUS21348`
+    fn from_volume_id(volume_id: &str) -> Option<Self> {
+        let prefix = volume_id.get(..4)?;
+        let number = volume_id[4..].trim_start_matches(['_', '-']);
+
+        let is_prefix = prefix.bytes().all(|b| b.is_ascii_uppercase());
+        let is_number = number.len() == 5 && number.bytes().all(|b| b.is_ascii_digit());
+        (is_prefix && is_number).then(|| Self {
+            prefix: prefix.to_owned(),
+            number: number.to_owned(),
+        })
+    }
+}
+
+impl From<GameId<'_>> for GameSerial {
+    fn from(id: GameId<'_>) -> Self {
+        Self {
+            prefix: id.prefix.to_owned(),
+            number: format!("{:05}", id.code),
+        }
+    }
+}
+
+impl Display for GameSerial {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.prefix, self.number)
+    }
+}
+
+/// Extract the serial of the game on the disc
+///
+/// The serial is taken from the ELF named in the `BOOT2` line of `SYSTEM.CNF`.
+/// If the file is missing or doesn't boot a serial-named ELF, the volume identifier is used instead
+///
+/// # Errors
+///
+/// - Neither `SYSTEM.CNF` nor the volume identifier contain a serial
+pub fn game_serial(disc: &Ps2Disc<'_>) -> Result<GameSerial, IsoError> {
+    let from_system_cnf = disc
+        .read_file("SYSTEM.CNF")
+        .ok()
+        .and_then(|bytes| SystemCnf::from_bytes(bytes).ok())
+        .and_then(|cnf| cnf.game_id().map(GameSerial::from));
+
+    from_system_cnf
+        .or_else(|| GameSerial::from_volume_id(disc.volume_id()))
+        .ok_or(IsoError::MissingSerial)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{game_serial, test_iso, GameSerial, IsoError, Ps2Disc, Region};
+
+    fn serial_of(system_cnf: &str, volume_id: &str) -> Result<GameSerial, IsoError> {
+        let image = test_iso::image_with(system_cnf.as_bytes(), volume_id.as_bytes());
+        game_serial(&Ps2Disc::open(&image).unwrap())
+    }
+
+    #[test]
+    fn from_system_cnf() {
+        let cases = [
+            ("cdrom0:\\SLUS_213.48;1", "SLUS-21348", Region::NorthAmerica),
+            ("cdrom0:\\SCES_500.01;1", "SCES-50001", Region::Europe),
+            ("cdrom0:\\SLPS_250.88;1", "SLPS-25088", Region::Japan),
+            ("cdrom0:\\SCPS_150.17;1", "SCPS-15017", Region::Japan),
+            ("cdrom0:\\DATA\\SLES_820.38;1", "SLES-82038", Region::Europe),
+        ];
+        for (boot2, expected, region) in cases {
+            let system_cnf = format!("BOOT2 = {boot2}\r\nVER = 1.00\r\nVMODE = NTSC\r\n");
+            let serial = serial_of(&system_cnf, "GAME").unwrap();
+            assert_eq!(serial.to_string(), expected);
+            assert_eq!(serial.region(), region);
+        }
+    }
+
+    #[test]
+    fn from_volume_id() {
+        let system_cnf = "BOOT2 = cdrom0:\\MAIN.ELF;1\r\nVER = 1.00\r\nVMODE = PAL\r\n";
+        let serial = serial_of(system_cnf, "SLES_12345").unwrap();
+        assert_eq!(
+            serial,
+            GameSerial {
+                prefix: "SLES".into(),
+                number: "12345".into(),
+            }
+        );
+        assert_eq!(serial.region(), Region::Europe);
+
+        assert_eq!(
+            serial_of("", "SCUS-97113").unwrap().region(),
+            Region::NorthAmerica
+        );
+        assert_eq!(serial_of(system_cnf, "GAME"), Err(IsoError::MissingSerial));
+    }
+}
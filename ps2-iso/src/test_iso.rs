@@ -0,0 +1,269 @@
+//! ISO 9660 records and descriptors, and the same small game laid out on an ISO 9660 and on a UDF-only image
+
+use crate::SECTOR_SIZE;
+
+/// Contents of `SYSTEM.CNF` in [`image`]
+pub const SYSTEM_CNF: &[u8] = b"BOOT2 = cdrom0:\\SLUS_213.48;1\r\nVER = 1.00\r\nVMODE = NTSC\r\n";
+
+/// Contents of the ELF in [`image`], spanning two sectors
+pub fn elf() -> Vec<u8> {
+    (0..3000u16).map(|i| i.to_le_bytes()[0]).collect()
+}
+
+/// Write a both-endian `u32`
+fn both_u32(out: &mut Vec<u8>, value: u32) {
+    out.extend_from_slice(&value.to_le_bytes());
+    out.extend_from_slice(&value.to_be_bytes());
+}
+
+/// Directory record
+pub fn record(name: &[u8], extent: u32, size: u32, flags: u8) -> Vec<u8> {
+    let len = 33 + name.len() + (name.len() + 1) % 2;
+    let mut record = vec![u8::try_from(len).unwrap(), 0];
+    both_u32(&mut record, extent);
+    both_u32(&mut record, size);
+    // 2004-03-15 12:34:56 GMT+9
+    record.extend_from_slice(&[104, 3, 15, 12, 34, 56, 36, flags, 0, 0, 1, 0, 0, 1]);
+    record.push(u8::try_from(name.len()).unwrap());
+    record.extend_from_slice(name);
+    record.resize(len, 0);
+    record
+}
+
+/// Path table entry
+fn path_table_entry(name: &[u8], extent: u32, parent: u16) -> Vec<u8> {
+    let mut entry = vec![u8::try_from(name.len()).unwrap(), 0];
+    entry.extend_from_slice(&extent.to_le_bytes());
+    entry.extend_from_slice(&parent.to_le_bytes());
+    entry.extend_from_slice(name);
+    if name.len() % 2 == 1 {
+        entry.push(0);
+    }
+    entry
+}
+
+/// Primary volume descriptor
+pub fn primary_volume_descriptor(
+    system_id: &[u8],
+    volume_id: &[u8],
+    path_table_size: u32,
+) -> Vec<u8> {
+    let mut pvd = vec![1];
+    pvd.extend_from_slice(b"CD001\x01\0");
+    let mut system = system_id.to_vec();
+    system.resize(32, b' ');
+    pvd.extend_from_slice(&system);
+    let mut volume = volume_id.to_vec();
+    volume.resize(32, b' ');
+    pvd.extend_from_slice(&volume);
+    pvd.resize(80, 0);
+    both_u32(&mut pvd, 25);
+    pvd.resize(128, 0);
+    pvd.extend_from_slice(&2048u16.to_le_bytes());
+    pvd.extend_from_slice(&2048u16.to_be_bytes());
+    both_u32(&mut pvd, path_table_size);
+    pvd.extend_from_slice(&18u32.to_le_bytes());
+    pvd.resize(156, 0);
+    pvd.extend(record(&[0], 19, 2048, 0x02));
+    pvd
+}
+
+/// Image with the layout
+///
+/// - `/SYSTEM.CNF`
+/// - `/SLUS_213.48`
+/// - `/MODULES/IOPRP.IMG` (hidden)
+pub fn image() -> Vec<u8> {
+    image_with(SYSTEM_CNF, b"SLUS_21348")
+}
+
+/// Image with the layout of [`image`], but the given `SYSTEM.CNF` and volume identifier
+pub fn image_with(system_cnf: &[u8], volume_id: &[u8]) -> Vec<u8> {
+    let mut path_table = path_table_entry(&[0], 19, 1);
+    path_table.extend(path_table_entry(b"MODULES", 20, 1));
+
+    let mut root = record(&[0], 19, 2048, 0x02);
+    root.extend(record(&[1], 19, 2048, 0x02));
+    root.extend(record(b"MODULES", 20, 2048, 0x02));
+    root.extend(record(b"SLUS_213.48;1", 22, 3000, 0));
+    root.extend(record(
+        b"SYSTEM.CNF;1",
+        21,
+        u32::try_from(system_cnf.len()).unwrap(),
+        0,
+    ));
+
+    let mut modules = record(&[0], 20, 2048, 0x02);
+    modules.extend(record(&[1], 19, 2048, 0x02));
+    modules.extend(record(b"IOPRP.IMG;1", 24, 4, 0x01));
+
+    let mut image = vec![0; 16 * SECTOR_SIZE];
+    let mut push_sector = |data: &[u8]| {
+        let start = image.len();
+        image.extend_from_slice(data);
+        image.resize(
+            start + data.len().div_ceil(SECTOR_SIZE).max(1) * SECTOR_SIZE,
+            0,
+        );
+    };
+    push_sector(&primary_volume_descriptor(
+        b"PLAYSTATION",
+        volume_id,
+        u32::try_from(path_table.len()).unwrap(),
+    ));
+    push_sector(b"\xFFCD001\x01");
+    push_sector(&path_table);
+    push_sector(&root);
+    push_sector(&modules);
+    push_sector(system_cnf);
+    push_sector(&elf());
+    push_sector(b"IOP!");
+    image
+}
+
+/// First sector of the partition of [`udf_image`]
+pub const UDF_PARTITION: u32 = 257;
+
+/// Give a UDF descriptor its tag, with `descriptor` already being as long as the descriptor
+pub fn udf_tag(id: u16, location: u32, mut descriptor: Vec<u8>) -> Vec<u8> {
+    let crc = crate::udf::crc(&descriptor[16..]);
+    let crc_len = u16::try_from(descriptor.len() - 16).unwrap();
+    descriptor[0..2].copy_from_slice(&id.to_le_bytes());
+    descriptor[2..4].copy_from_slice(&2u16.to_le_bytes());
+    descriptor[8..10].copy_from_slice(&crc.to_le_bytes());
+    descriptor[10..12].copy_from_slice(&crc_len.to_le_bytes());
+    descriptor[12..16].copy_from_slice(&location.to_le_bytes());
+    descriptor[4] = descriptor[..16]
+        .iter()
+        .fold(0u8, |sum, &byte| sum.wrapping_add(byte));
+    descriptor
+}
+
+/// UDF file entry at the given block, `kind` being its allocation descriptor type
+fn udf_file_entry(block: u32, is_dir: bool, size: usize, kind: u16, descriptors: &[u8]) -> Vec<u8> {
+    let mut entry = vec![0; 176];
+    entry[27] = if is_dir { 4 } else { 5 };
+    entry[34..36].copy_from_slice(&kind.to_le_bytes());
+    entry[56..64].copy_from_slice(&(size as u64).to_le_bytes());
+    // 2004-03-15 12:34:56 UTC+9
+    entry[84..96].copy_from_slice(&[0x1C, 0x12, 0xD4, 0x07, 3, 15, 12, 34, 56, 0, 0, 0]);
+    entry[172..176].copy_from_slice(&u32::try_from(descriptors.len()).unwrap().to_le_bytes());
+    entry.extend_from_slice(descriptors);
+    udf_tag(261, block, entry)
+}
+
+/// UDF file identifier descriptor pointing to the file entry at the given block
+fn udf_file_identifier(name: &[u8], characteristics: u8, block: u32) -> Vec<u8> {
+    let mut identifier = vec![0; 38];
+    identifier[16] = 1;
+    identifier[18] = characteristics;
+    identifier[19] = u8::try_from(name.len()).unwrap();
+    identifier[20..24].copy_from_slice(&2048u32.to_le_bytes());
+    identifier[24..28].copy_from_slice(&block.to_le_bytes());
+    identifier.extend_from_slice(name);
+    identifier.resize(identifier.len().next_multiple_of(4), 0);
+    udf_tag(257, 0, identifier)
+}
+
+/// Short allocation descriptors of the given extents
+fn short_ads(extents: &[(u32, u32)]) -> Vec<u8> {
+    extents
+        .iter()
+        .flat_map(|&(len, block)| [len.to_le_bytes(), block.to_le_bytes()].concat())
+        .collect()
+}
+
+/// Contents of `FRAG.BIN` in [`udf_image`], stored in two extents with a gap between them
+pub fn fragmented() -> Vec<u8> {
+    (0..2058u16).map(|i| i.to_le_bytes()[0] ^ 0x5A).collect()
+}
+
+/// Image with only a UDF file system and the layout
+///
+/// - `/MODULES/IOPRP.IMG` (hidden, embedded in its file entry)
+/// - `/SLUS_213.48` (two adjacent extents)
+/// - `/SYSTEM.CNF` (embedded in its file entry)
+/// - `/FRAG.BIN` (two extents with a gap between them)
+///
+/// along with a deleted `/OLD.CNF`. The name of `MODULES` is stored as UTF-16
+pub fn udf_image() -> Vec<u8> {
+    let mut image = vec![0; 16 * SECTOR_SIZE];
+    let mut put = |sector: u32, data: &[u8]| {
+        let start = sector as usize * SECTOR_SIZE;
+        let end = start + data.len().div_ceil(SECTOR_SIZE).max(1) * SECTOR_SIZE;
+        if image.len() < end {
+            image.resize(end, 0);
+        }
+        image[start..start + data.len()].copy_from_slice(data);
+    };
+
+    // Volume recognition sequence
+    put(16, b"\0BEA01\x01");
+    put(17, b"\0NSR02\x01");
+    put(18, b"\0TEA01\x01");
+
+    // Main volume descriptor sequence
+    let mut partition = vec![0; 196];
+    partition[188..192].copy_from_slice(&UDF_PARTITION.to_le_bytes());
+    partition[192..196].copy_from_slice(&13u32.to_le_bytes());
+    put(32, &udf_tag(5, 32, partition));
+    let mut logical_volume = vec![0; 446];
+    logical_volume[84..95].copy_from_slice(b"\x08SLUS_21348");
+    logical_volume[211] = 11;
+    logical_volume[212..216].copy_from_slice(&2048u32.to_le_bytes());
+    logical_volume[248..252].copy_from_slice(&2048u32.to_le_bytes());
+    logical_volume[264..268].copy_from_slice(&6u32.to_le_bytes());
+    logical_volume[268..272].copy_from_slice(&1u32.to_le_bytes());
+    logical_volume[440..446].copy_from_slice(&[1, 6, 1, 0, 0, 0]);
+    put(33, &udf_tag(6, 33, logical_volume));
+    put(34, &udf_tag(8, 34, vec![0; 512]));
+
+    // Anchor pointing to the main volume descriptor sequence
+    let mut anchor = vec![0; 512];
+    anchor[16..20].copy_from_slice(&(3 * 2048u32).to_le_bytes());
+    anchor[20..24].copy_from_slice(&32u32.to_le_bytes());
+    put(256, &udf_tag(2, 256, anchor));
+
+    // Partition, with the file set descriptor pointing to the root at block 1
+    let mut file_set = vec![0; 512];
+    file_set[400..404].copy_from_slice(&2048u32.to_le_bytes());
+    file_set[404..408].copy_from_slice(&1u32.to_le_bytes());
+    let mut root = udf_file_identifier(b"", 0x0A, 1);
+    root.extend(udf_file_identifier(b"\x10\0M\0O\0D\0U\0L\0E\0S", 0x02, 3));
+    root.extend(udf_file_identifier(b"\x08SLUS_213.48", 0, 5));
+    root.extend(udf_file_identifier(b"\x08SYSTEM.CNF", 0, 6));
+    root.extend(udf_file_identifier(b"\x08OLD.CNF", 0x04, 6));
+    root.extend(udf_file_identifier(b"\x08FRAG.BIN", 0, 7));
+    let mut modules = udf_file_identifier(b"", 0x0A, 1);
+    modules.extend(udf_file_identifier(b"\x08IOPRP.IMG", 0x01, 4));
+    let slus = [(2048, 8), (952, 9)]
+        .iter()
+        .flat_map(|&(len, block): &(u32, u32)| {
+            [len.to_le_bytes(), block.to_le_bytes(), [0; 4], [0; 4]].concat()
+        })
+        .collect::<Vec<_>>();
+    let blocks = [
+        udf_tag(256, 0, file_set),
+        udf_file_entry(
+            1,
+            true,
+            root.len(),
+            0,
+            &short_ads(&[(u32::try_from(root.len()).unwrap(), 2)]),
+        ),
+        root,
+        udf_file_entry(3, true, modules.len(), 3, &modules),
+        udf_file_entry(4, false, 4, 3, b"IOP!"),
+        udf_file_entry(5, false, 3000, 1, &slus),
+        udf_file_entry(6, false, SYSTEM_CNF.len(), 3, SYSTEM_CNF),
+        udf_file_entry(7, false, 2058, 0, &short_ads(&[(2048, 10), (10, 12)])),
+    ];
+    for (sector, block) in (UDF_PARTITION..).zip(&blocks) {
+        put(sector, block);
+    }
+    put(UDF_PARTITION + 8, &elf());
+    let fragmented = fragmented();
+    put(UDF_PARTITION + 10, &fragmented[..2048]);
+    put(UDF_PARTITION + 12, &fragmented[2048..]);
+    image
+}
@@ -0,0 +1,519 @@
+//! UDF file system, found next to ISO 9660 on DVD based discs and on its own on some DVD images
+//!
+//! Only what is needed to read files is parsed: the anchor volume descriptor pointer at sector 256, the partition
+//! and logical volume descriptors of the main volume descriptor sequence, the file set descriptor and the file
+//! entries and file identifiers making up the directories. Every descriptor is checked against the checksum and
+//! CRC of its tag
+
+use crate::{
+    directory::{FLAG_DIRECTORY, FLAG_HIDDEN},
+    sector, sectors, DirEntry, IsoError, Timestamp, SECTOR_SIZE,
+};
+use ps2_bytes::{read_u16_le, read_u32_le};
+use std::borrow::Cow;
+
+/// Sector of the anchor volume descriptor pointer
+const ANCHOR_SECTOR: u32 = 256;
+
+/// Size of a descriptor tag
+const TAG_SIZE: usize = 16;
+
+/// Tag identifier of the anchor volume descriptor pointer
+const TAG_ANCHOR: u16 = 2;
+
+/// Tag identifier of the partition descriptor
+const TAG_PARTITION: u16 = 5;
+
+/// Tag identifier of the logical volume descriptor
+const TAG_LOGICAL_VOLUME: u16 = 6;
+
+/// Tag identifier of the terminating descriptor, ending the volume descriptor sequence
+const TAG_TERMINATING: u16 = 8;
+
+/// Tag identifier of the file set descriptor
+const TAG_FILE_SET: u16 = 256;
+
+/// Tag identifier of a file identifier descriptor, an entry of a directory
+const TAG_FILE_IDENTIFIER: u16 = 257;
+
+/// Tag identifier of a file entry
+const TAG_FILE_ENTRY: u16 = 261;
+
+/// File type of a directory in the ICB tag of a file entry
+const FILE_TYPE_DIRECTORY: u8 = 4;
+
+/// Allocation descriptor type of short allocation descriptors (`short_ad`)
+const AD_SHORT: u16 = 0;
+
+/// Allocation descriptor type of long allocation descriptors (`long_ad`)
+const AD_LONG: u16 = 1;
+
+/// Allocation descriptor type of data embedded in the file entry
+const AD_EMBEDDED: u16 = 3;
+
+/// File characteristic of hidden entries
+const CHARACTERISTIC_HIDDEN: u8 = 0x01;
+
+/// File characteristic of directories
+const CHARACTERISTIC_DIRECTORY: u8 = 0x02;
+
+/// File characteristic of deleted entries
+const CHARACTERISTIC_DELETED: u8 = 0x04;
+
+/// File characteristic of the entry pointing to the parent directory
+const CHARACTERISTIC_PARENT: u8 = 0x08;
+
+/// Size of a file identifier descriptor without the implementation use and the identifier
+const FILE_IDENTIFIER_SIZE: usize = 38;
+
+/// Timezone value of timestamps without a timezone
+const TIMEZONE_UNSPECIFIED: i16 = -2047;
+
+/// UDF volume of a disc image
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub(crate) struct Udf {
+    /// First sector of the partition, the logical block numbers of the file set are relative to it
+    partition_start: u32,
+
+    /// Number of sectors of the partition
+    partition_len: u32,
+
+    /// Sector of the file entry of the root directory
+    root: u32,
+
+    /// Logical volume identifier
+    pub(crate) volume_id: String,
+}
+
+/// File entry describing a file or directory
+struct FileEntry<'d> {
+    is_dir: bool,
+    size: u64,
+    modified: Timestamp,
+    data: FileData<'d>,
+}
+
+/// Location of the data of a [`FileEntry`]
+enum FileData<'d> {
+    /// Stored in the file entry itself
+    Embedded(&'d [u8]),
+
+    /// Stored in extents, as their first sector and length in bytes
+    Extents(Vec<(u32, u32)>),
+}
+
+impl Udf {
+    /// Read the volume described by the anchor volume descriptor pointer
+    ///
+    /// Returns `None` if there is no anchor, i.e. the image has no UDF file system
+    pub(crate) fn open(data: &[u8]) -> Result<Option<Self>, IsoError> {
+        let Ok(anchor) = sector(data, ANCHOR_SECTOR) else {
+            return Ok(None);
+        };
+        if tag(anchor, ANCHOR_SECTOR) != Some(TAG_ANCHOR) {
+            return Ok(None);
+        }
+
+        let invalid = |sector| IsoError::InvalidUdf { sector };
+        let mut partitions = Vec::new();
+        let mut logical_volume = None;
+        for number in sectors(read_u32_le(anchor, 20), read_u32_le(anchor, 16)) {
+            let number = number.ok_or(invalid(ANCHOR_SECTOR))?;
+            let descriptor = sector(data, number)?;
+            match tag(descriptor, number).ok_or(invalid(number))? {
+                TAG_PARTITION => partitions.push(descriptor),
+                // Later descriptors of the sequence replace earlier ones
+                TAG_LOGICAL_VOLUME => logical_volume = Some((number, descriptor)),
+                TAG_TERMINATING => break,
+                _ => {}
+            }
+        }
+
+        let (number, logical_volume) = logical_volume.ok_or(invalid(ANCHOR_SECTOR))?;
+        if read_u32_le(logical_volume, 212) as usize != SECTOR_SIZE {
+            return Err(invalid(number));
+        }
+
+        // The file set descriptor is referenced by a `long_ad`, whose partition is an index into the partition maps
+        let file_set_block = read_u32_le(logical_volume, 252);
+        let partition_number = partition_number(logical_volume, read_u16_le(logical_volume, 256))
+            .ok_or(invalid(number))?;
+        let partition = partitions
+            .into_iter()
+            .rev()
+            .find(|partition| read_u16_le(partition, 22) == partition_number)
+            .ok_or(invalid(number))?;
+
+        let mut udf = Self {
+            partition_start: read_u32_le(partition, 188),
+            partition_len: read_u32_le(partition, 192),
+            root: 0,
+            volume_id: dstring(&logical_volume[84..212]),
+        };
+        let file_set_sector = udf.block(file_set_block).ok_or(invalid(number))?;
+        let file_set = sector(data, file_set_sector)?;
+        if tag(file_set, file_set_block) != Some(TAG_FILE_SET) {
+            return Err(invalid(file_set_sector));
+        }
+        udf.root = udf
+            .block(read_u32_le(file_set, 404))
+            .ok_or(invalid(file_set_sector))?;
+
+        Ok(Some(udf))
+    }
+
+    /// Entries of the directory at the given path, see [`Ps2Disc::list_dir`](crate::Ps2Disc::list_dir)
+    pub(crate) fn list_dir(
+        &self,
+        data: &[u8],
+        components: &[&str],
+    ) -> Result<Vec<DirEntry>, IsoError> {
+        let directory = self.find_directory(data, components)?;
+        let entries = self.entries(data, directory)?;
+        Ok(entries.into_iter().map(|(entry, _)| entry).collect())
+    }
+
+    /// Contents of the file `name` in the directory at the path `parent`
+    pub(crate) fn read_file<'d>(
+        &self,
+        data: &'d [u8],
+        parent: &[&str],
+        name: &str,
+    ) -> Result<&'d [u8], IsoError> {
+        let directory = self.find_directory(data, parent)?;
+        let (entry, file_entry) = self
+            .entries(data, directory)?
+            .into_iter()
+            .find(|(entry, _)| entry.name.eq_ignore_ascii_case(name))
+            .ok_or(IsoError::NotFound)?;
+        if entry.is_dir() {
+            return Err(IsoError::NotAFile);
+        }
+
+        match contents(data, &self.file_entry(data, file_entry)?, file_entry)? {
+            Cow::Borrowed(contents) => Ok(contents),
+            Cow::Owned(_) => Err(IsoError::FragmentedFile),
+        }
+    }
+
+    /// Sector of the file entry of the directory at the given path
+    fn find_directory(&self, data: &[u8], components: &[&str]) -> Result<u32, IsoError> {
+        let mut directory = self.root;
+        for &component in components {
+            let (entry, file_entry) = self
+                .entries(data, directory)?
+                .into_iter()
+                .find(|(entry, _)| entry.name.eq_ignore_ascii_case(component))
+                .ok_or(IsoError::NotFound)?;
+            if !entry.is_dir() {
+                return Err(IsoError::NotADirectory);
+            }
+            directory = file_entry;
+        }
+
+        Ok(directory)
+    }
+
+    /// Entries of the directory whose file entry is at the given sector, along with the sectors of their file
+    /// entries
+    fn entries(&self, data: &[u8], directory: u32) -> Result<Vec<(DirEntry, u32)>, IsoError> {
+        let invalid = IsoError::InvalidUdf { sector: directory };
+        let file_entry = self.file_entry(data, directory)?;
+        if !file_entry.is_dir {
+            return Err(invalid);
+        }
+        let contents = contents(data, &file_entry, directory)?;
+
+        let mut entries = Vec::new();
+        let mut rest = &contents[..];
+        while !rest.is_empty() {
+            // File identifiers don't record a meaningful location when embedded or spanning blocks
+            let identifier = rest.get(..FILE_IDENTIFIER_SIZE).ok_or(invalid)?;
+            if tag_unlocated(rest) != Some(TAG_FILE_IDENTIFIER) {
+                return Err(invalid);
+            }
+
+            let characteristics = identifier[18];
+            let name_start = FILE_IDENTIFIER_SIZE + usize::from(read_u16_le(identifier, 36));
+            let name_end = name_start + usize::from(identifier[19]);
+            let name = rest.get(name_start..name_end).ok_or(invalid)?;
+            let icb = self.block(read_u32_le(identifier, 24)).ok_or(invalid)?;
+            // Descriptors are padded to a multiple of 4 bytes
+            rest = rest.get(name_end.next_multiple_of(4)..).unwrap_or_default();
+
+            if characteristics & (CHARACTERISTIC_DELETED | CHARACTERISTIC_PARENT) != 0 {
+                continue;
+            }
+
+            let child = self.file_entry(data, icb)?;
+            let extent = match &child.data {
+                FileData::Extents(extents) => extents.first().map_or(icb, |&(start, _)| start),
+                FileData::Embedded(_) => icb,
+            };
+            let mut flags = 0;
+            if characteristics & CHARACTERISTIC_HIDDEN != 0 {
+                flags |= FLAG_HIDDEN;
+            }
+            if characteristics & CHARACTERISTIC_DIRECTORY != 0 || child.is_dir {
+                flags |= FLAG_DIRECTORY;
+            }
+
+            let entry = DirEntry {
+                name: cs0(name),
+                extent,
+                size: u32::try_from(child.size)
+                    .map_err(|_| IsoError::InvalidUdf { sector: icb })?,
+                flags,
+                recorded: child.modified,
+            };
+            entries.push((entry, icb));
+        }
+
+        Ok(entries)
+    }
+
+    /// Parse the file entry at the given sector
+    fn file_entry<'d>(
+        &self,
+        data: &'d [u8],
+        sector_number: u32,
+    ) -> Result<FileEntry<'d>, IsoError> {
+        let invalid = IsoError::InvalidUdf {
+            sector: sector_number,
+        };
+        let entry = sector(data, sector_number)?;
+        if tag(entry, sector_number - self.partition_start) != Some(TAG_FILE_ENTRY) {
+            return Err(invalid);
+        }
+
+        let attributes_len = read_u32_le(entry, 168) as usize;
+        let descriptors_len = read_u32_le(entry, 172) as usize;
+        let descriptors = entry
+            .get(176 + attributes_len..)
+            .and_then(|descriptors| descriptors.get(..descriptors_len))
+            .ok_or(invalid)?;
+
+        let data = match read_u16_le(entry, 34) & 0x07 {
+            kind @ (AD_SHORT | AD_LONG) => {
+                let size = if kind == AD_SHORT { 8 } else { 16 };
+                let mut extents = Vec::new();
+                for descriptor in descriptors.chunks_exact(size) {
+                    let len = read_u32_le(descriptor, 0);
+                    if len == 0 {
+                        break;
+                    }
+                    // The top two bits are the type of the extent, only recorded extents are supported
+                    if len >> 30 != 0 {
+                        return Err(invalid);
+                    }
+                    let start = self.block(read_u32_le(descriptor, 4)).ok_or(invalid)?;
+                    extents.push((start, len));
+                }
+                FileData::Extents(extents)
+            }
+            AD_EMBEDDED => FileData::Embedded(descriptors),
+            _ => return Err(invalid),
+        };
+
+        Ok(FileEntry {
+            is_dir: entry[27] == FILE_TYPE_DIRECTORY,
+            size: u64::from_le_bytes(entry[56..64].try_into().unwrap()),
+            modified: timestamp(&entry[84..96]),
+            data,
+        })
+    }
+
+    /// Sector of a logical block of the partition, `None` if it lies outside of the partition
+    fn block(&self, block: u32) -> Option<u32> {
+        (block < self.partition_len)
+            .then(|| self.partition_start.checked_add(block))
+            .flatten()
+    }
+}
+
+/// Contents of a file entry located at `sector_number`
+///
+/// Data in consecutive sectors is borrowed from the image, anything else is copied together
+fn contents<'d>(
+    data: &'d [u8],
+    entry: &FileEntry<'d>,
+    sector_number: u32,
+) -> Result<Cow<'d, [u8]>, IsoError> {
+    let invalid = IsoError::InvalidUdf {
+        sector: sector_number,
+    };
+    let size = usize::try_from(entry.size).map_err(|_| invalid)?;
+    let extents = match &entry.data {
+        FileData::Embedded(embedded) => {
+            return embedded.get(..size).map(Cow::Borrowed).ok_or(invalid)
+        }
+        FileData::Extents(extents) => extents,
+    };
+    let recorded = extents.iter().map(|&(_, len)| len as usize).sum::<usize>();
+    if recorded < size {
+        return Err(invalid);
+    }
+
+    let read = |start: u32, len: usize| {
+        let offset = start as usize * SECTOR_SIZE;
+        data.get(offset..offset + len)
+            .ok_or(IsoError::Truncated { sector: start })
+    };
+    let consecutive = extents.windows(2).all(|pair| {
+        let (start, len) = pair[0];
+        start as usize * SECTOR_SIZE + len as usize == pair[1].0 as usize * SECTOR_SIZE
+    });
+    match extents.first() {
+        None => Ok(Cow::Borrowed(&[])),
+        Some(&(start, _)) if consecutive => read(start, size).map(Cow::Borrowed),
+        Some(_) => {
+            // The extents can't hold more than the whole image
+            let mut contents = Vec::with_capacity(recorded.min(data.len()));
+            for &(start, len) in extents {
+                contents.extend_from_slice(read(start, len as usize)?);
+            }
+            contents.truncate(size);
+            Ok(Cow::Owned(contents))
+        }
+    }
+}
+
+/// Partition number of the type 1 partition map at the given index of a logical volume descriptor
+fn partition_number(logical_volume: &[u8], index: u16) -> Option<u16> {
+    let maps_len = read_u32_le(logical_volume, 264) as usize;
+    let mut maps = logical_volume.get(440..440 + maps_len)?;
+    for _ in 0..index {
+        let len = usize::from(*maps.get(1)?);
+        maps = maps.get(len.max(2)..)?;
+    }
+
+    match maps {
+        [1, 6, _, _, low, high, ..] => Some(u16::from_le_bytes([*low, *high])),
+        _ => None,
+    }
+}
+
+/// Identifier of a valid descriptor tag at the start of `bytes`, recorded at the given location
+fn tag(bytes: &[u8], location: u32) -> Option<u16> {
+    tag_unlocated(bytes).filter(|_| read_u32_le(bytes, 12) == location)
+}
+
+/// Identifier of a descriptor tag with a valid checksum and CRC at the start of `bytes`
+fn tag_unlocated(bytes: &[u8]) -> Option<u16> {
+    let tag = bytes.get(..TAG_SIZE)?;
+    let checksum = tag
+        .iter()
+        .enumerate()
+        .filter(|&(index, _)| index != 4)
+        .fold(0_u8, |sum, (_, &byte)| sum.wrapping_add(byte));
+    let crc_len = usize::from(read_u16_le(tag, 10));
+    let protected = bytes.get(TAG_SIZE..TAG_SIZE + crc_len)?;
+
+    (checksum == tag[4] && crc(protected) == read_u16_le(tag, 8)).then(|| read_u16_le(tag, 0))
+}
+
+/// CRC-ITU-T (polynomial `0x1021`, initial value 0) protecting the descriptors
+pub(crate) fn crc(bytes: &[u8]) -> u16 {
+    bytes.iter().fold(0, |crc, &byte| {
+        (0..8).fold(crc ^ (u16::from(byte) << 8), |crc, _| {
+            if crc & 0x8000 == 0 {
+                crc << 1
+            } else {
+                (crc << 1) ^ 0x1021
+            }
+        })
+    })
+}
+
+/// Parse a 12 byte timestamp
+fn timestamp(bytes: &[u8]) -> Timestamp {
+    // The lower 12 bits are the signed offset from UTC in minutes
+    let timezone = i16::from_le_bytes([bytes[0], bytes[1]]) << 4 >> 4;
+    let gmt_offset = if timezone == TIMEZONE_UNSPECIFIED {
+        0
+    } else {
+        i8::try_from(timezone / 15).unwrap_or_default()
+    };
+
+    Timestamp {
+        year: read_u16_le(bytes, 2),
+        month: bytes[4],
+        day: bytes[5],
+        hour: bytes[6],
+        minute: bytes[7],
+        second: bytes[8],
+        gmt_offset,
+    }
+}
+
+/// Decode an OSTA compressed unicode string, starting with its compression ID
+fn cs0(bytes: &[u8]) -> String {
+    match bytes.split_first() {
+        Some((8, chars)) => chars.iter().copied().map(char::from).collect(),
+        Some((16, chars)) => {
+            let units = chars
+                .chunks_exact(2)
+                .map(|unit| u16::from_be_bytes([unit[0], unit[1]]));
+            char::decode_utf16(units)
+                .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+                .collect()
+        }
+        _ => String::new(),
+    }
+}
+
+/// Decode a fixed size `dstring` field, whose last byte is the length of the used part
+fn dstring(field: &[u8]) -> String {
+    match field.split_last() {
+        Some((&len, chars)) => cs0(chars.get(..usize::from(len)).unwrap_or_default()),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{crc, cs0, dstring, timestamp};
+    use crate::Timestamp;
+
+    #[test]
+    fn crc_itu() {
+        assert_eq!(crc(b"123456789"), 0x31C3);
+        assert_eq!(crc(b""), 0);
+    }
+
+    #[test]
+    fn strings() {
+        assert_eq!(cs0(b"\x08SYSTEM.CNF"), "SYSTEM.CNF");
+        assert_eq!(cs0(b"\x10\x00S\x00L\x30\xB2"), "SLゲ");
+        assert_eq!(cs0(b""), "");
+
+        let mut field = [0; 32];
+        field[..11].copy_from_slice(b"\x08SLUS_21348");
+        field[31] = 11;
+        assert_eq!(dstring(&field), "SLUS_21348");
+    }
+
+    #[test]
+    fn timestamps() {
+        // Local time at UTC+9, 2004-03-15 12:34:56
+        let bytes = [0x1C, 0x12, 0xD4, 0x07, 3, 15, 12, 34, 56, 0, 0, 0];
+        assert_eq!(
+            timestamp(&bytes),
+            Timestamp {
+                year: 2004,
+                month: 3,
+                day: 15,
+                hour: 12,
+                minute: 34,
+                second: 56,
+                gmt_offset: 36,
+            }
+        );
+
+        // West of UTC and without a timezone
+        let bytes = [0x10, 0x1F, 0xD4, 0x07, 3, 15, 12, 34, 56, 0, 0, 0];
+        assert_eq!(timestamp(&bytes).gmt_offset, -16);
+        let bytes = [0x01, 0x18, 0xD4, 0x07, 3, 15, 12, 34, 56, 0, 0, 0];
+        assert_eq!(timestamp(&bytes).gmt_offset, 0);
+    }
+}
@@ -0,0 +1,340 @@
+//! Error correction of memory card pages
+//!
+//! Every 128 byte chunk of a page is protected by a 3 byte Hamming code (column parity followed by two bytes of
+//! line parity) stored in the spare area following the page. The code corrects single-bit errors, in both the data
+//! and the code itself, and detects two-bit errors. Despite occasionally being described as such, it isn't a
+//! Reed-Solomon code
+
+use crate::{MemCardError, MemoryCard, Superblock};
+use std::{
+    error::Error as StdError,
+    fmt::{self, Display},
+};
+
+/// Number of bytes protected by a single code
+const CHUNK_SIZE: usize = 128;
+
+/// Size of a single code
+const ECC_SIZE: usize = 3;
+
+/// Masks of the bits contributing to each bit of the column parity
+const COLUMN_PARITY_MASKS: [u8; 7] = [0x55, 0x33, 0x0F, 0x00, 0xAA, 0xCC, 0xF0];
+
+/// Column parity of every byte value
+const COLUMN_PARITY: [u8; 256] = column_parity_table();
+
+const fn column_parity_table() -> [u8; 256] {
+    let mut table = [0; 256];
+    let mut byte = 0;
+    while byte < 256 {
+        let mut i = 0;
+        while i < COLUMN_PARITY_MASKS.len() {
+            #[allow(clippy::cast_possible_truncation)]
+            let masked = byte as u8 & COLUMN_PARITY_MASKS[i];
+            table[byte] |= ((masked.count_ones() & 1) as u8) << i;
+            i += 1;
+        }
+        byte += 1;
+    }
+    table
+}
+
+/// ECC problem of a page
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum EccError {
+    /// The page contains single-bit errors that can be repaired
+    Correctable {
+        /// Index of the page on the card
+        page_index: usize,
+    },
+
+    /// The page contains errors that can't be repaired
+    Uncorrectable {
+        /// Index of the page on the card
+        page_index: usize,
+    },
+}
+
+impl Display for EccError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+impl StdError for EccError {}
+
+/// Result of checking data against its code
+#[derive(Clone, Copy, Debug, Eq, PartialEq, PartialOrd, Ord)]
+enum Check {
+    Ok,
+    Corrected,
+    Failed,
+}
+
+/// Calculate the code of a 128 byte chunk
+pub(crate) fn calculate(chunk: &[u8]) -> [u8; ECC_SIZE] {
+    let mut column_parity = 0x77;
+    let mut line_parity_0 = 0x7F;
+    let mut line_parity_1 = 0x7F;
+
+    for (i, &byte) in (0..=u8::MAX).zip(chunk) {
+        column_parity ^= COLUMN_PARITY[usize::from(byte)];
+        if byte.count_ones() & 1 == 1 {
+            line_parity_0 ^= !i;
+            line_parity_1 ^= i;
+        }
+    }
+
+    [column_parity, line_parity_0 & 0x7F, line_parity_1]
+}
+
+/// Check a chunk against its code, correcting single-bit errors in either of them
+fn check(chunk: &mut [u8], ecc: &mut [u8]) -> Check {
+    let computed = calculate(chunk);
+    if computed == ecc {
+        return Check::Ok;
+    }
+
+    let column_diff = (computed[0] ^ ecc[0]) & 0x77;
+    let line_diff_0 = (computed[1] ^ ecc[1]) & 0x7F;
+    let line_diff_1 = (computed[2] ^ ecc[2]) & 0x7F;
+    let line_complement = line_diff_0 ^ line_diff_1;
+    let column_complement = (column_diff >> 4) ^ (column_diff & 0x07);
+
+    // A single flipped data bit flips exactly one of each complementary parity bit pair
+    if line_complement == 0x7F && column_complement == 0x07 {
+        let Some(byte) = chunk.get_mut(usize::from(line_diff_1)) else {
+            return Check::Failed;
+        };
+        *byte ^= 1 << (column_diff >> 4);
+        return Check::Corrected;
+    }
+
+    // A single flipped bit in the code (or one of its unused bits being set)
+    if (column_diff == 0 && line_diff_0 == 0 && line_diff_1 == 0)
+        || line_complement.count_ones() + column_complement.count_ones() == 1
+    {
+        ecc.copy_from_slice(&computed);
+        return Check::Corrected;
+    }
+
+    Check::Failed
+}
+
+/// Check a page against the codes stored in its spare area
+fn check_page(page: &mut [u8], spare: &mut [u8]) -> Check {
+    let erased = |bytes: &[u8]| bytes.iter().all(|&byte| byte == 0xFF);
+    if erased(page) && erased(spare) {
+        return Check::Ok;
+    }
+
+    page.chunks_mut(CHUNK_SIZE)
+        .zip(spare.chunks_exact_mut(ECC_SIZE))
+        .map(|(chunk, ecc)| check(chunk, ecc))
+        .max()
+        .unwrap_or(Check::Ok)
+}
+
+/// Split the raw page at `index` into its data and spare area
+fn raw_page<'a>(
+    bytes: &'a mut [u8],
+    superblock: &Superblock,
+    spare_size: usize,
+    index: usize,
+) -> (&'a mut [u8], &'a mut [u8]) {
+    let page_len = usize::from(superblock.page_len);
+    let offset = index * (page_len + spare_size);
+    bytes[offset..offset + page_len + spare_size].split_at_mut(page_len)
+}
+
+/// Check the ECC of every page of an image
+pub(crate) fn validate(bytes: &[u8], superblock: &Superblock, spare_size: usize) -> Vec<EccError> {
+    if spare_size == 0 {
+        return Vec::new();
+    }
+
+    let raw_len = usize::from(superblock.page_len) + spare_size;
+    let mut buffer = vec![0; raw_len];
+    (0..superblock.page_count())
+        .filter_map(|page_index| {
+            buffer.copy_from_slice(&bytes[page_index * raw_len..(page_index + 1) * raw_len]);
+            let (page, spare) = raw_page(&mut buffer, superblock, spare_size, 0);
+
+            match check_page(page, spare) {
+                Check::Ok => None,
+                Check::Corrected => Some(EccError::Correctable { page_index }),
+                Check::Failed => Some(EccError::Uncorrectable { page_index }),
+            }
+        })
+        .collect()
+}
+
+/// Owned memory card image that can be repaired in memory
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct MemoryCardMut {
+    bytes: Vec<u8>,
+    superblock: Superblock,
+    spare_size: usize,
+}
+
+impl MemoryCardMut {
+    /// Parse an owned memory card image
+    ///
+    /// Only the superblock is validated, so images with a corrupted file system can still be repaired
+    ///
+    /// # Errors
+    ///
+    /// - The superblock is invalid
+    /// - The image is truncated
+    pub fn parse(bytes: Vec<u8>) -> Result<Self, MemCardError> {
+        let superblock = Superblock::parse(&bytes)?;
+        let spare_size = superblock.image_spare_size(bytes.len())?;
+
+        Ok(Self {
+            bytes,
+            superblock,
+            spare_size,
+        })
+    }
+
+    /// Check the ECC of every page against the ECC stored in its spare area
+    ///
+    /// See [`MemoryCard::validate_ecc`]
+    #[must_use]
+    pub fn validate_ecc(&self) -> Vec<EccError> {
+        validate(&self.bytes, &self.superblock, self.spare_size)
+    }
+
+    /// Correct single-bit errors in every page and its ECC
+    ///
+    /// Returns the number of repaired pages. Pages with uncorrectable errors are left untouched
+    pub fn repair_ecc(&mut self) -> usize {
+        if self.spare_size == 0 {
+            return 0;
+        }
+
+        let mut repaired = 0;
+        for page_index in 0..self.superblock.page_count() {
+            let (page, spare) = raw_page(
+                &mut self.bytes,
+                &self.superblock,
+                self.spare_size,
+                page_index,
+            );
+            let mut repaired_page = page.to_vec();
+            let mut repaired_spare = spare.to_vec();
+
+            if check_page(&mut repaired_page, &mut repaired_spare) == Check::Corrected {
+                page.copy_from_slice(&repaired_page);
+                spare.copy_from_slice(&repaired_spare);
+                repaired += 1;
+            }
+        }
+
+        repaired
+    }
+
+    /// Parse the (repaired) image
+    ///
+    /// # Errors
+    ///
+    /// See [`MemoryCard::parse`]
+    pub fn as_card(&self) -> Result<MemoryCard<'_>, MemCardError> {
+        MemoryCard::parse(&self.bytes)
+    }
+
+    /// Serialise the (repaired) image
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.bytes.clone()
+    }
+
+    /// Consume the image and return its bytes
+    #[must_use]
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::calculate;
+    use crate::{test_card::CardBuilder, EccError, MemoryCard, MemoryCardMut};
+
+    /// Offset of the first byte of the root directory in an image with spare areas
+    const ROOT: usize = 82 * 528;
+
+    fn card() -> Vec<u8> {
+        CardBuilder::new()
+            .file("SAVE", &[0x5A; 600])
+            .build_with_ecc()
+    }
+
+    #[test]
+    fn calculate_known() {
+        assert_eq!(calculate(&[0; 128]), [0x77, 0x7F, 0x7F]);
+        assert_eq!(calculate(&[0xFF; 128]), [0x77, 0x7F, 0x7F]);
+
+        let mut chunk = [0; 128];
+        chunk[0] = 0x01;
+        assert_eq!(calculate(&chunk), [0x70, 0x00, 0x7F]);
+    }
+
+    #[test]
+    fn valid() {
+        let bytes = card();
+        let card = MemoryCard::parse(&bytes).unwrap();
+        assert!(card.has_spare_area());
+        assert_eq!(card.validate_ecc(), []);
+    }
+
+    #[test]
+    fn no_spare_area() {
+        let bytes = CardBuilder::new().build();
+        assert_eq!(MemoryCard::parse(&bytes).unwrap().validate_ecc(), []);
+
+        let mut card = MemoryCardMut::parse(bytes).unwrap();
+        assert_eq!(card.repair_ecc(), 0);
+    }
+
+    #[test]
+    fn repair_data() {
+        let original = card();
+        let mut bytes = original.clone();
+        // Flip a bit in the root directory and one in the ECC of the following page
+        bytes[ROOT + 0x41] ^= 0x10;
+        bytes[ROOT + 528 + 512] ^= 0x01;
+
+        let mut card = MemoryCardMut::parse(bytes).unwrap();
+        assert_eq!(
+            card.validate_ecc(),
+            [
+                EccError::Correctable { page_index: 82 },
+                EccError::Correctable { page_index: 83 },
+            ]
+        );
+
+        assert_eq!(card.repair_ecc(), 2);
+        assert_eq!(card.validate_ecc(), []);
+        assert_eq!(card.to_bytes(), original);
+        assert_eq!(
+            card.as_card().unwrap().read_file("SAVE").unwrap(),
+            [0x5A; 600]
+        );
+    }
+
+    #[test]
+    fn uncorrectable() {
+        let original = card();
+        let mut bytes = original.clone();
+        bytes[ROOT + 0x41] ^= 0x11;
+
+        let mut card = MemoryCardMut::parse(bytes.clone()).unwrap();
+        assert_eq!(
+            card.validate_ecc(),
+            [EccError::Uncorrectable { page_index: 82 }]
+        );
+        assert_eq!(card.repair_ecc(), 0);
+        assert_eq!(card.into_bytes(), bytes);
+    }
+}
@@ -0,0 +1,190 @@
+use crate::until_nul;
+use ps2_bytes::{read_u16_le, read_u32_le};
+use std::fmt::{self, Display};
+
+/// Size of a single directory entry
+pub(crate) const ENTRY_SIZE: usize = 512;
+
+/// Maximum length of the name of an entry in bytes
+pub(crate) const MAX_NAME_LEN: usize = 32;
+
+/// Mode of save directories created by the browser
+pub(crate) const DIRECTORY_MODE: u16 = 0x8427;
+
+/// Mode of save files created by the browser
+pub(crate) const FILE_MODE: u16 = 0x8497;
+
+/// Mode flag set on every entry that is in use
+pub const DF_EXISTS: u16 = 0x8000;
+
+/// Mode flag marking a directory
+pub const DF_DIRECTORY: u16 = 0x0020;
+
+/// Mode flag marking a file
+pub const DF_FILE: u16 = 0x0010;
+
+/// Timestamp of a directory entry
+///
+/// Memory cards store timestamps in Japan Standard Time (UTC+9), they aren't converted
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct McTimestamp {
+    /// Year
+    pub year: u16,
+
+    /// Month (1-12)
+    pub month: u8,
+
+    /// Day of the month (1-31)
+    pub day: u8,
+
+    /// Hour (0-23)
+    pub hour: u8,
+
+    /// Minute (0-59)
+    pub minute: u8,
+
+    /// Second (0-59)
+    pub second: u8,
+}
+
+impl McTimestamp {
+    /// Decode a timestamp from its 8 byte on-card representation
+    pub(crate) fn from_bytes(raw: &[u8]) -> Self {
+        Self {
+            second: raw[1],
+            minute: raw[2],
+            hour: raw[3],
+            day: raw[4],
+            month: raw[5],
+            year: read_u16_le(raw, 6),
+        }
+    }
+
+    /// Encode the timestamp into its 8 byte on-card representation
+    pub(crate) fn to_bytes(self) -> [u8; 8] {
+        let [year_low, year_high] = self.year.to_le_bytes();
+        [
+            0,
+            self.second,
+            self.minute,
+            self.hour,
+            self.day,
+            self.month,
+            year_low,
+            year_high,
+        ]
+    }
+}
+
+impl Display for McTimestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        )
+    }
+}
+
+/// File or directory on a memory card
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct McEntry {
+    /// Name of the entry
+    pub name: String,
+
+    /// Path of the entry relative to the root directory, separated by `/`
+    pub path: String,
+
+    /// Mode flags (see [`DF_DIRECTORY`] and [`DF_FILE`])
+    pub mode: u16,
+
+    /// Size in bytes for files, number of entries (including `.` and `..`) for directories
+    pub size: u32,
+
+    /// Creation timestamp
+    pub created: McTimestamp,
+
+    /// Modification timestamp
+    pub modified: McTimestamp,
+
+    /// First cluster of the entry, relative to the start of the allocatable area
+    pub(crate) cluster: u32,
+}
+
+impl McEntry {
+    /// Decode a directory entry
+    ///
+    /// Returns `None` if the entry isn't in use
+    pub(crate) fn from_bytes(raw: &[u8], parent: &str) -> Option<Self> {
+        let mode = read_u16_le(raw, 0);
+        if mode & DF_EXISTS == 0 {
+            return None;
+        }
+
+        let name = String::from_utf8_lossy(until_nul(&raw[0x40..0x40 + MAX_NAME_LEN])).into_owned();
+        let path = if parent.is_empty() {
+            name.clone()
+        } else {
+            format!("{parent}/{name}")
+        };
+
+        Some(Self {
+            name,
+            path,
+            mode,
+            size: read_u32_le(raw, 4),
+            created: McTimestamp::from_bytes(&raw[0x08..0x10]),
+            modified: McTimestamp::from_bytes(&raw[0x18..0x20]),
+            cluster: read_u32_le(raw, 0x10),
+        })
+    }
+
+    /// Encode the entry, the name is truncated to the 32 bytes available
+    pub(crate) fn to_bytes(&self) -> [u8; ENTRY_SIZE] {
+        let mut raw = [0; ENTRY_SIZE];
+        raw[0x00..0x02].copy_from_slice(&self.mode.to_le_bytes());
+        raw[0x04..0x08].copy_from_slice(&self.size.to_le_bytes());
+        raw[0x08..0x10].copy_from_slice(&self.created.to_bytes());
+        raw[0x10..0x14].copy_from_slice(&self.cluster.to_le_bytes());
+        raw[0x18..0x20].copy_from_slice(&self.modified.to_bytes());
+
+        let name = &self.name.as_bytes()[..self.name.len().min(MAX_NAME_LEN)];
+        raw[0x40..0x40 + name.len()].copy_from_slice(name);
+        raw
+    }
+
+    /// Whether the entry is a directory
+    #[must_use]
+    pub fn is_dir(&self) -> bool {
+        self.mode & DF_DIRECTORY != 0
+    }
+
+    /// Whether the entry is a file
+    #[must_use]
+    pub fn is_file(&self) -> bool {
+        self.mode & DF_FILE != 0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::McTimestamp;
+
+    #[test]
+    fn timestamp() {
+        let timestamp = McTimestamp::from_bytes(&[0, 5, 4, 3, 2, 1, 0xD4, 0x07]);
+        assert_eq!(
+            timestamp,
+            McTimestamp {
+                year: 2004,
+                month: 1,
+                day: 2,
+                hour: 3,
+                minute: 4,
+                second: 5,
+            }
+        );
+        assert_eq!(timestamp.to_string(), "2004-01-02 03:04:05");
+        assert_eq!(timestamp.to_bytes(), [0, 5, 4, 3, 2, 1, 0xD4, 0x07]);
+    }
+}
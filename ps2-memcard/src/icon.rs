@@ -0,0 +1,492 @@
+//! Save game icons
+//!
+//! Every save directory contains an `icon.sys` file describing how the save is presented in the browser and up to
+//! three icon files (`*.ico`) containing animated 3D models
+
+use crate::{until_nul, MemCardError};
+use encoding_rs::SHIFT_JIS;
+use ps2_bytes::{read_u16_le, read_u32_le};
+
+/// Size of an `icon.sys` file
+const ICON_SYS_SIZE: usize = 964;
+
+/// Magic at the start of an `icon.sys` file
+const ICON_SYS_MAGIC: &[u8] = b"PS2D";
+
+/// Magic at the start of an icon file
+const ICON_MAGIC: u32 = 0x0001_0000;
+
+/// Magic at the start of the animation section of an icon file
+const ANIMATION_MAGIC: u32 = 0x01;
+
+/// Texture type flag marking an RLE compressed texture
+const TEXTURE_COMPRESSED: u32 = 0x08;
+
+/// Width and height of an icon texture
+pub const TEXTURE_SIZE: usize = 128;
+
+/// Contents of an `icon.sys` file
+#[derive(Clone, Debug, PartialEq)]
+pub struct IconSys {
+    /// Title of the save, decoded from Shift-JIS
+    pub title: String,
+
+    /// Opacity of the background (`0` to `0x80`)
+    pub background_transparency: u32,
+
+    /// Background colours of the upper left, upper right, lower left and lower right corner (RGBA)
+    pub background_colors: [[u32; 4]; 4],
+
+    /// Directions of the three light sources
+    pub light_directions: [[f32; 4]; 3],
+
+    /// Colours of the three light sources
+    pub light_colors: [[f32; 4]; 3],
+
+    /// Colour of the ambient light
+    pub ambient_light: [f32; 4],
+
+    /// Filename of the icon shown in the save browser
+    pub normal_icon: String,
+
+    /// Filename of the icon shown while copying the save
+    pub copy_icon: String,
+
+    /// Filename of the icon shown while deleting the save
+    pub delete_icon: String,
+
+    /// Byte offset of the line break in the decoded title
+    line_break: usize,
+}
+
+impl IconSys {
+    /// Parse an `icon.sys` file
+    ///
+    /// # Errors
+    ///
+    /// - The file is truncated or doesn't start with the `PS2D` magic
+    pub fn parse(bytes: &[u8]) -> Result<Self, MemCardError> {
+        if bytes.len() < ICON_SYS_SIZE || !bytes.starts_with(ICON_SYS_MAGIC) {
+            return Err(MemCardError::InvalidIconSys);
+        }
+
+        let raw_title = until_nul(&bytes[0xC0..0x104]);
+        let raw_line_break = usize::from(read_u16_le(bytes, 6)).min(raw_title.len());
+        let (first_line, _, _) = SHIFT_JIS.decode(&raw_title[..raw_line_break]);
+        let (second_line, _, _) = SHIFT_JIS.decode(&raw_title[raw_line_break..]);
+
+        let u32_at = |offset: usize| read_u32_le(bytes, offset);
+        let f32_at = |offset: usize| f32::from_bits(read_u32_le(bytes, offset));
+        let vector = |offset: usize| std::array::from_fn(|i| f32_at(offset + i * 4));
+
+        Ok(Self {
+            title: format!("{first_line}{second_line}"),
+            background_transparency: u32_at(0x0C),
+            background_colors: std::array::from_fn(|corner| {
+                std::array::from_fn(|i| u32_at(0x10 + corner * 16 + i * 4))
+            }),
+            light_directions: std::array::from_fn(|light| vector(0x50 + light * 16)),
+            light_colors: std::array::from_fn(|light| vector(0x80 + light * 16)),
+            ambient_light: vector(0xB0),
+            normal_icon: ascii(&bytes[0x104..0x144]),
+            copy_icon: ascii(&bytes[0x144..0x184]),
+            delete_icon: ascii(&bytes[0x184..0x1C4]),
+            line_break: first_line.len(),
+        })
+    }
+
+    /// Split the title into the two lines shown in the save browser
+    #[must_use]
+    pub fn title_lines(&self) -> (&str, &str) {
+        self.title.split_at(self.line_break)
+    }
+}
+
+/// Vertex of an icon model
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct IconVertex {
+    /// Position of the vertex in every animation shape (fixed-point, `4096` is `1.0`)
+    pub positions: Vec<[i16; 4]>,
+
+    /// Normal of the vertex (fixed-point, `4096` is `1.0`)
+    pub normal: [i16; 4],
+
+    /// Texture coordinate of the vertex (fixed-point, `4096` is `1.0`)
+    pub uv: [i16; 2],
+
+    /// Vertex colour (RGBA)
+    pub color: [u8; 4],
+}
+
+/// Animation key frame
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub struct IconKey {
+    /// Time of the key
+    pub time: f32,
+
+    /// Weight of the shape at that time
+    pub value: f32,
+}
+
+/// Animation frame, describing the weight of an animation shape over time
+#[derive(Clone, Debug, PartialEq)]
+pub struct IconFrame {
+    /// Index of the animation shape
+    pub shape_id: u32,
+
+    /// Keys of the frame
+    pub keys: Vec<IconKey>,
+}
+
+/// Animation of an icon model
+#[derive(Clone, Debug, PartialEq)]
+pub struct IconAnimation {
+    /// Length of the animation in frames
+    pub frame_length: u32,
+
+    /// Playback speed
+    pub speed: f32,
+
+    /// Frame the playback starts at
+    pub play_offset: u32,
+
+    /// Frames of the animation
+    pub frames: Vec<IconFrame>,
+}
+
+/// Animated 3D icon (`*.ico`)
+#[derive(Clone, Debug, PartialEq)]
+pub struct SaveIcon {
+    /// Number of animation shapes, every vertex has one position per shape
+    pub shape_count: u32,
+
+    /// Type of the texture
+    pub texture_type: u32,
+
+    /// Vertices of the model, every three vertices form a triangle
+    pub vertices: Vec<IconVertex>,
+
+    /// Animation of the model
+    pub animation: IconAnimation,
+
+    /// 128x128 texture in the PS2's 16-bit format (`A1B5G5R5`), row by row
+    pub texture: Vec<u16>,
+}
+
+impl SaveIcon {
+    /// Parse an icon file
+    ///
+    /// Compressed textures are decompressed
+    ///
+    /// # Errors
+    ///
+    /// - The file is truncated or doesn't start with the icon magic
+    /// - The texture is compressed and its data is invalid
+    pub fn parse(bytes: &[u8]) -> Result<Self, MemCardError> {
+        let mut reader = Reader { bytes, offset: 0 };
+        if reader.u32()? != ICON_MAGIC {
+            return Err(MemCardError::InvalidIcon);
+        }
+
+        let shape_count = reader.u32()?;
+        let texture_type = reader.u32()?;
+        reader.u32()?;
+        let vertex_count = reader.u32()?;
+
+        let mut vertices = Vec::new();
+        for _ in 0..vertex_count {
+            let positions = (0..shape_count)
+                .map(|_| reader.i16s())
+                .collect::<Result<_, _>>()?;
+            let normal = reader.i16s()?;
+            let uv = reader.i16s()?;
+            let color = reader.take(4)?;
+
+            vertices.push(IconVertex {
+                positions,
+                normal,
+                uv,
+                color: [color[0], color[1], color[2], color[3]],
+            });
+        }
+
+        if reader.u32()? != ANIMATION_MAGIC {
+            return Err(MemCardError::InvalidIcon);
+        }
+        let frame_length = reader.u32()?;
+        let speed = f32::from_bits(reader.u32()?);
+        let play_offset = reader.u32()?;
+        let frame_count = reader.u32()?;
+
+        let mut frames = Vec::new();
+        for _ in 0..frame_count {
+            let shape_id = reader.u32()?;
+            let key_count = reader.u32()?;
+            reader.u32()?;
+            reader.u32()?;
+
+            let keys = (0..key_count)
+                .map(|_| {
+                    Ok(IconKey {
+                        time: f32::from_bits(reader.u32()?),
+                        value: f32::from_bits(reader.u32()?),
+                    })
+                })
+                .collect::<Result<_, MemCardError>>()?;
+            frames.push(IconFrame { shape_id, keys });
+        }
+
+        let texture = if texture_type & TEXTURE_COMPRESSED == 0 {
+            reader
+                .take(TEXTURE_SIZE * TEXTURE_SIZE * 2)?
+                .chunks_exact(2)
+                .map(|raw| u16::from_le_bytes([raw[0], raw[1]]))
+                .collect()
+        } else {
+            let len = reader.u32()? as usize;
+            decompress_texture(reader.take(len)?)?
+        };
+
+        Ok(Self {
+            shape_count,
+            texture_type,
+            vertices,
+            animation: IconAnimation {
+                frame_length,
+                speed,
+                play_offset,
+                frames,
+            },
+            texture,
+        })
+    }
+}
+
+/// Cursor over the bytes of an icon file
+struct Reader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], MemCardError> {
+        let data = self
+            .bytes
+            .get(self.offset..self.offset + len)
+            .ok_or(MemCardError::InvalidIcon)?;
+        self.offset += len;
+        Ok(data)
+    }
+
+    fn u32(&mut self) -> Result<u32, MemCardError> {
+        Ok(read_u32_le(self.take(4)?, 0))
+    }
+
+    fn i16s<const N: usize>(&mut self) -> Result<[i16; N], MemCardError> {
+        let raw = self.take(N * 2)?;
+        Ok(std::array::from_fn(|i| {
+            i16::from_le_bytes([raw[i * 2], raw[i * 2 + 1]])
+        }))
+    }
+}
+
+/// Decompress an RLE compressed texture
+///
+/// The data consists of 16-bit codes, each followed by pixels:
+///
+/// - A code with the highest bit set is followed by `0x8000 - (code & 0x7FFF)` pixels that are copied as-is
+/// - Any other code is followed by a single pixel that is repeated `code` times
+fn decompress_texture(data: &[u8]) -> Result<Vec<u16>, MemCardError> {
+    let pixel_count = TEXTURE_SIZE * TEXTURE_SIZE;
+    let mut words = data
+        .chunks_exact(2)
+        .map(|raw| u16::from_le_bytes([raw[0], raw[1]]));
+
+    let mut texture = Vec::with_capacity(pixel_count);
+    while texture.len() < pixel_count {
+        let Some(code) = words.next() else {
+            break;
+        };
+
+        if code & 0x8000 == 0 {
+            let pixel = words.next().ok_or(MemCardError::InvalidIcon)?;
+            texture.extend(std::iter::repeat_n(pixel, usize::from(code)));
+        } else {
+            for _ in 0..0x8000 - usize::from(code & 0x7FFF) {
+                texture.push(words.next().ok_or(MemCardError::InvalidIcon)?);
+            }
+        }
+    }
+
+    if texture.len() != pixel_count {
+        return Err(MemCardError::InvalidIcon);
+    }
+
+    Ok(texture)
+}
+
+/// Decode a NUL-terminated ASCII string
+fn ascii(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(until_nul(bytes)).into_owned()
+}
+
+#[cfg(test)]
+// The compared values are exactly representable
+#[allow(clippy::float_cmp)]
+mod test {
+    use super::{decompress_texture, TEXTURE_SIZE};
+    use crate::{IconKey, IconSys, MemCardError, SaveIcon};
+
+    fn icon_sys() -> Vec<u8> {
+        let mut bytes = vec![0; 964];
+        bytes[..4].copy_from_slice(b"PS2D");
+        // The line break is after "ＡＢ", 4 bytes into the title
+        bytes[6..8].copy_from_slice(&4_u16.to_le_bytes());
+        bytes[0x0C..0x10].copy_from_slice(&0x40_u32.to_le_bytes());
+        bytes[0x10..0x14].copy_from_slice(&0x80_u32.to_le_bytes());
+        bytes[0x50..0x54].copy_from_slice(&0.5_f32.to_le_bytes());
+        bytes[0xB0..0xB4].copy_from_slice(&1.0_f32.to_le_bytes());
+        // "ＡＢ日本" in Shift-JIS
+        bytes[0xC0..0xC8].copy_from_slice(&[0x82, 0x60, 0x82, 0x61, 0x93, 0xFA, 0x96, 0x7B]);
+        bytes[0x104..0x10C].copy_from_slice(b"list.ico");
+        bytes[0x144..0x14C].copy_from_slice(b"copy.ico");
+        bytes[0x184..0x18B].copy_from_slice(b"del.ico");
+        bytes
+    }
+
+    fn icon(compressed: bool) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let texture_type: u32 = if compressed { 0x0F } else { 0x07 };
+        for value in [0x0001_0000, 2, texture_type, 0x3F80_0000, 3] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        for vertex in 0..3_i16 {
+            for shape in 0..2_i16 {
+                for component in [vertex, shape, 0, 4096] {
+                    bytes.extend_from_slice(&component.to_le_bytes());
+                }
+            }
+            for component in [0_i16, 0, 4096, 0, 4096, 2048] {
+                bytes.extend_from_slice(&component.to_le_bytes());
+            }
+            bytes.extend_from_slice(&[0xFF, 0x80, 0x40, 0x80]);
+        }
+
+        for value in [0x01_u32, 60, 1.0_f32.to_bits(), 0, 1] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        for value in [1_u32, 2, 0, 0, 0.0_f32.to_bits(), 1.0_f32.to_bits()] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        for value in [30.0_f32, 0.5] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+
+        if compressed {
+            // One row of literal pixels followed by a run filling the rest of the texture
+            let mut data = Vec::new();
+            data.extend_from_slice(&(0x8000_u16 | (0x8000 - 128)).to_le_bytes());
+            for pixel in 0..128_u16 {
+                data.extend_from_slice(&pixel.to_le_bytes());
+            }
+            data.extend_from_slice(&(128_u16 * 127).to_le_bytes());
+            data.extend_from_slice(&0x7FFF_u16.to_le_bytes());
+
+            bytes.extend_from_slice(&u32::try_from(data.len()).unwrap().to_le_bytes());
+            bytes.extend_from_slice(&data);
+        } else {
+            for pixel in 0..128 * 128_u16 {
+                bytes.extend_from_slice(&(pixel % 128).to_le_bytes());
+            }
+        }
+
+        bytes
+    }
+
+    #[test]
+    fn icon_sys_parse() {
+        let icon_sys = IconSys::parse(&icon_sys()).unwrap();
+
+        assert_eq!(icon_sys.title, "ＡＢ日本");
+        assert_eq!(icon_sys.title_lines(), ("ＡＢ", "日本"));
+        assert_eq!(icon_sys.background_transparency, 0x40);
+        assert_eq!(icon_sys.background_colors[0], [0x80, 0, 0, 0]);
+        assert_eq!(icon_sys.light_directions[0], [0.5, 0.0, 0.0, 0.0]);
+        assert_eq!(icon_sys.ambient_light, [1.0, 0.0, 0.0, 0.0]);
+        assert_eq!(icon_sys.normal_icon, "list.ico");
+        assert_eq!(icon_sys.copy_icon, "copy.ico");
+        assert_eq!(icon_sys.delete_icon, "del.ico");
+    }
+
+    #[test]
+    fn icon_sys_invalid() {
+        assert_eq!(
+            IconSys::parse(&icon_sys()[..100]),
+            Err(MemCardError::InvalidIconSys)
+        );
+
+        let mut bytes = icon_sys();
+        bytes[0] = b'X';
+        assert_eq!(IconSys::parse(&bytes), Err(MemCardError::InvalidIconSys));
+    }
+
+    #[test]
+    fn icon_parse() {
+        let icon = SaveIcon::parse(&icon(false)).unwrap();
+
+        assert_eq!(icon.shape_count, 2);
+        assert_eq!(icon.vertices.len(), 3);
+        assert_eq!(
+            icon.vertices[2].positions,
+            [[2, 0, 0, 4096], [2, 1, 0, 4096]]
+        );
+        assert_eq!(icon.vertices[0].normal, [0, 0, 4096, 0]);
+        assert_eq!(icon.vertices[0].uv, [4096, 2048]);
+        assert_eq!(icon.vertices[0].color, [0xFF, 0x80, 0x40, 0x80]);
+
+        assert_eq!(icon.animation.frame_length, 60);
+        assert_eq!(icon.animation.speed, 1.0);
+        assert_eq!(icon.animation.frames.len(), 1);
+        assert_eq!(icon.animation.frames[0].shape_id, 1);
+        assert_eq!(
+            icon.animation.frames[0].keys,
+            [
+                IconKey {
+                    time: 0.0,
+                    value: 1.0,
+                },
+                IconKey {
+                    time: 30.0,
+                    value: 0.5,
+                },
+            ]
+        );
+
+        assert_eq!(icon.texture.len(), TEXTURE_SIZE * TEXTURE_SIZE);
+        assert_eq!(icon.texture[129], 1);
+    }
+
+    #[test]
+    fn icon_compressed_texture() {
+        let icon = SaveIcon::parse(&icon(true)).unwrap();
+
+        assert_eq!(icon.texture.len(), TEXTURE_SIZE * TEXTURE_SIZE);
+        assert_eq!(icon.texture[..128], (0..128).collect::<Vec<_>>());
+        assert!(icon.texture[128..].iter().all(|&pixel| pixel == 0x7FFF));
+    }
+
+    #[test]
+    fn icon_invalid() {
+        assert_eq!(SaveIcon::parse(&[0; 4]), Err(MemCardError::InvalidIcon));
+
+        let bytes = icon(false);
+        assert_eq!(
+            SaveIcon::parse(&bytes[..bytes.len() - 1]),
+            Err(MemCardError::InvalidIcon)
+        );
+        assert_eq!(
+            decompress_texture(&[0x10, 0x00]),
+            Err(MemCardError::InvalidIcon)
+        );
+    }
+}
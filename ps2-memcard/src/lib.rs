@@ -0,0 +1,658 @@
+#![doc = include_str!("../README.md")]
+#![forbid(missing_docs, unsafe_code)]
+#![warn(clippy::all, clippy::pedantic)]
+
+use ps2_bytes::{get_u32_le, read_u16_le, read_u32_le};
+use std::{
+    collections::HashSet,
+    error::Error as StdError,
+    fmt::{self, Display},
+};
+
+mod ecc;
+mod entry;
+mod icon;
+mod max;
+mod psu;
+mod save;
+#[cfg(test)]
+mod test_card;
+
+use self::entry::ENTRY_SIZE;
+pub use self::{
+    ecc::{EccError, MemoryCardMut},
+    entry::{McEntry, McTimestamp, DF_DIRECTORY, DF_EXISTS, DF_FILE},
+    icon::{IconAnimation, IconFrame, IconKey, IconSys, IconVertex, SaveIcon, TEXTURE_SIZE},
+    max::{MaxError, MaxSave},
+    psu::{Psu, PsuError},
+    save::{ConvertError, McSave, McSaveData, McSaveFile},
+};
+
+/// Magic at the start of the superblock
+const MAGIC: &[u8] = b"Sony PS2 Memory Card Format ";
+
+/// Size of the superblock fields that are read
+const SUPERBLOCK_SIZE: usize = 0x152;
+
+/// Number of entries in the indirect FAT cluster list
+const IFC_LIST_LEN: usize = 32;
+
+/// FAT entry flag marking an allocated cluster
+const FAT_ALLOCATED: u32 = 0x8000_0000;
+
+/// FAT entry marking the end of a cluster chain
+const FAT_END: u32 = 0xFFFF_FFFF;
+
+/// Maximum nesting of directories, bounds the recursion on corrupted images
+const MAX_DEPTH: usize = 32;
+
+/// Errors that might occur when parsing a memory card image
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum MemCardError {
+    /// The image is smaller than the card described by the superblock
+    Truncated,
+
+    /// The superblock doesn't start with the memory card magic
+    InvalidMagic,
+
+    /// The superblock describes an unsupported card geometry
+    InvalidSuperblock,
+
+    /// The file allocation table is corrupted or refers to clusters outside of the card
+    InvalidFat,
+
+    /// A directory is corrupted
+    InvalidDirectory,
+
+    /// No file or directory exists at the requested path
+    NotFound,
+
+    /// An `icon.sys` file is truncated or has an invalid magic
+    InvalidIconSys,
+
+    /// An icon file is truncated or corrupted
+    InvalidIcon,
+}
+
+impl Display for MemCardError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+impl StdError for MemCardError {}
+
+/// Superblock of a memory card, stored in the first page
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Superblock {
+    /// Version of the format (e.g. `1.2.0.0`)
+    pub version: String,
+
+    /// Size of a page in bytes, excluding the spare area
+    pub page_len: u16,
+
+    /// Number of pages per cluster
+    pub pages_per_cluster: u16,
+
+    /// Number of pages per erase block
+    pub pages_per_erase_block: u16,
+
+    /// Number of clusters on the card
+    pub clusters_per_card: u32,
+
+    /// Cluster the allocatable area starts at, cluster numbers in the FAT and directory entries are relative to it
+    pub alloc_offset: u32,
+
+    /// Number of allocatable clusters
+    pub alloc_end: u32,
+
+    /// First cluster of the root directory, relative to the allocatable area
+    pub rootdir_cluster: u32,
+
+    /// Clusters containing the indirect FAT entries
+    pub ifc_list: [u32; IFC_LIST_LEN],
+
+    /// Type of the card (`2` for PS2 memory cards)
+    pub card_type: u8,
+
+    /// Flags describing the features of the card
+    pub card_flags: u8,
+}
+
+impl Superblock {
+    /// Parse the superblock at the start of the image
+    fn parse(bytes: &[u8]) -> Result<Self, MemCardError> {
+        let raw = bytes
+            .get(..SUPERBLOCK_SIZE)
+            .ok_or(MemCardError::Truncated)?;
+        if !raw.starts_with(MAGIC) {
+            return Err(MemCardError::InvalidMagic);
+        }
+
+        let version = String::from_utf8_lossy(until_nul(&raw[MAGIC.len()..0x28])).into_owned();
+
+        let mut ifc_list = [0; IFC_LIST_LEN];
+        for (i, cluster) in ifc_list.iter_mut().enumerate() {
+            *cluster = read_u32_le(raw, 0x50 + i * 4);
+        }
+
+        let superblock = Self {
+            version,
+            page_len: read_u16_le(raw, 0x28),
+            pages_per_cluster: read_u16_le(raw, 0x2A),
+            pages_per_erase_block: read_u16_le(raw, 0x2C),
+            clusters_per_card: read_u32_le(raw, 0x30),
+            alloc_offset: read_u32_le(raw, 0x34),
+            alloc_end: read_u32_le(raw, 0x38),
+            rootdir_cluster: read_u32_le(raw, 0x3C),
+            ifc_list,
+            card_type: raw[0x150],
+            card_flags: raw[0x151],
+        };
+
+        let cluster_size = superblock.cluster_size();
+        if cluster_size == 0 || !cluster_size.is_multiple_of(ENTRY_SIZE) {
+            return Err(MemCardError::InvalidSuperblock);
+        }
+
+        Ok(superblock)
+    }
+
+    /// Size of a cluster in bytes
+    #[must_use]
+    pub fn cluster_size(&self) -> usize {
+        usize::from(self.page_len) * usize::from(self.pages_per_cluster)
+    }
+
+    /// Size of the spare area following every page in raw images
+    #[must_use]
+    pub fn spare_size(&self) -> usize {
+        usize::from(self.page_len).div_ceil(128) * 4
+    }
+
+    /// Number of pages on the card
+    #[must_use]
+    pub fn page_count(&self) -> usize {
+        self.clusters_per_card as usize * usize::from(self.pages_per_cluster)
+    }
+
+    /// Detect whether an image of `len` bytes contains the spare areas and return their size
+    fn image_spare_size(&self, len: usize) -> Result<usize, MemCardError> {
+        let page_len = usize::from(self.page_len);
+        let raw_len = self
+            .page_count()
+            .checked_mul(page_len + self.spare_size())
+            .ok_or(MemCardError::InvalidSuperblock)?;
+
+        if len >= raw_len {
+            Ok(self.spare_size())
+        } else if len >= self.page_count() * page_len {
+            Ok(0)
+        } else {
+            Err(MemCardError::Truncated)
+        }
+    }
+}
+
+/// Parsed memory card image
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct MemoryCard<'a> {
+    bytes: &'a [u8],
+    superblock: Superblock,
+    spare_size: usize,
+    entries: Vec<McEntry>,
+}
+
+impl<'a> MemoryCard<'a> {
+    /// Parse a memory card image
+    ///
+    /// The image may or may not contain the spare area of each page, this is detected from its size
+    ///
+    /// # Errors
+    ///
+    /// - The superblock is invalid
+    /// - The image is truncated
+    /// - The file allocation table or a directory is corrupted
+    pub fn parse(bytes: &'a [u8]) -> Result<Self, MemCardError> {
+        let superblock = Superblock::parse(bytes)?;
+        let spare_size = superblock.image_spare_size(bytes.len())?;
+
+        let mut card = Self {
+            bytes,
+            superblock,
+            spare_size,
+            entries: Vec::new(),
+        };
+
+        let root = card.superblock.rootdir_cluster;
+        let dot = card.read_chain(root, ENTRY_SIZE)?;
+        let count = read_u32_le(&dot, 4) as usize;
+        let mut entries = Vec::new();
+        card.read_directory(root, count, "", 0, &mut HashSet::new(), &mut entries)?;
+        card.entries = entries;
+
+        Ok(card)
+    }
+
+    /// Superblock of the card
+    #[must_use]
+    pub fn superblock(&self) -> &Superblock {
+        &self.superblock
+    }
+
+    /// Whether the image contains the spare area of each page
+    #[must_use]
+    pub fn has_spare_area(&self) -> bool {
+        self.spare_size != 0
+    }
+
+    /// Check the ECC of every page against the ECC stored in its spare area
+    ///
+    /// Images without spare areas have no ECC and never report errors. Erased pages (all bytes `0xFF`) are skipped
+    #[must_use]
+    pub fn validate_ecc(&self) -> Vec<EccError> {
+        ecc::validate(self.bytes, &self.superblock, self.spare_size)
+    }
+
+    /// Iterate over all files and directories on the card
+    ///
+    /// Directories are directly followed by their contents
+    pub fn entries(&self) -> impl Iterator<Item = &McEntry> + '_ {
+        self.entries.iter()
+    }
+
+    /// Read the contents of the file at `path`
+    ///
+    /// Paths are relative to the root directory and separated by `/` (e.g. `BESLES-12345/icon.sys`)
+    ///
+    /// # Errors
+    ///
+    /// - No file exists at the path
+    /// - The file allocation table is corrupted
+    pub fn read_file(&self, path: &str) -> Result<Vec<u8>, MemCardError> {
+        let entry = self
+            .find(path)
+            .filter(|entry| entry.is_file())
+            .ok_or(MemCardError::NotFound)?;
+
+        self.read_chain(entry.cluster, entry.size as usize)
+    }
+
+    /// List the contents of the directory at `path`, excluding `.` and `..`
+    ///
+    /// The root directory is addressed by an empty path or `/`
+    ///
+    /// # Errors
+    ///
+    /// - No directory exists at the path
+    pub fn read_dir(&self, path: &str) -> Result<Vec<McEntry>, MemCardError> {
+        let path = path.trim_matches('/');
+        if !path.is_empty() && !self.find(path).is_some_and(McEntry::is_dir) {
+            return Err(MemCardError::NotFound);
+        }
+
+        Ok(self
+            .entries
+            .iter()
+            .filter(|entry| entry.path.rsplit_once('/').map_or("", |(parent, _)| parent) == path)
+            .cloned()
+            .collect())
+    }
+
+    /// Read the save directory at `path` including the contents of all of its files
+    ///
+    /// Saves don't contain subdirectories, they are skipped
+    ///
+    /// # Errors
+    ///
+    /// - No directory exists at the path
+    /// - The file allocation table is corrupted
+    pub fn read_save(&self, path: &str) -> Result<McSaveData, MemCardError> {
+        let mut directory = self
+            .find(path)
+            .filter(|entry| entry.is_dir())
+            .ok_or(MemCardError::NotFound)?
+            .clone();
+
+        let files = self
+            .read_dir(path)?
+            .into_iter()
+            .filter(McEntry::is_file)
+            .map(|mut entry| {
+                let data = self.read_chain(entry.cluster, entry.size as usize)?;
+                // Cluster numbers are meaningless outside of the card
+                entry.cluster = 0;
+                Ok(McSaveFile { entry, data })
+            })
+            .collect::<Result<Vec<_>, MemCardError>>()?;
+
+        directory.cluster = 0;
+        directory.size =
+            u32::try_from(files.len() + 2).map_err(|_| MemCardError::InvalidDirectory)?;
+
+        Ok(McSaveData { directory, files })
+    }
+
+    /// Find the entry at `path`
+    fn find(&self, path: &str) -> Option<&McEntry> {
+        let path = path.trim_matches('/');
+        self.entries.iter().find(|entry| entry.path == path)
+    }
+
+    /// Read the data of a page, excluding the spare area
+    fn page(&self, index: usize) -> Result<&'a [u8], MemCardError> {
+        let page_len = usize::from(self.superblock.page_len);
+        let offset = index
+            .checked_mul(page_len + self.spare_size)
+            .ok_or(MemCardError::InvalidFat)?;
+
+        self.bytes
+            .get(offset..offset + page_len)
+            .ok_or(MemCardError::InvalidFat)
+    }
+
+    /// Read the data of an absolute cluster
+    fn cluster(&self, cluster: u32) -> Result<Vec<u8>, MemCardError> {
+        if cluster >= self.superblock.clusters_per_card {
+            return Err(MemCardError::InvalidFat);
+        }
+
+        let pages_per_cluster = usize::from(self.superblock.pages_per_cluster);
+        let first = cluster as usize * pages_per_cluster;
+        let mut data = Vec::with_capacity(self.superblock.cluster_size());
+        for page in first..first + pages_per_cluster {
+            data.extend_from_slice(self.page(page)?);
+        }
+
+        Ok(data)
+    }
+
+    /// Read the `index`th `u32` of an absolute cluster
+    fn cluster_u32(&self, cluster: u32, index: usize) -> Result<u32, MemCardError> {
+        if cluster >= self.superblock.clusters_per_card {
+            return Err(MemCardError::InvalidFat);
+        }
+
+        let page_len = usize::from(self.superblock.page_len);
+        let offset = index * 4;
+        let page =
+            cluster as usize * usize::from(self.superblock.pages_per_cluster) + offset / page_len;
+        let data = self.page(page)?;
+
+        get_u32_le(data, offset % page_len).ok_or(MemCardError::InvalidFat)
+    }
+
+    /// Look up the FAT entry of a cluster relative to the allocatable area
+    fn fat_entry(&self, cluster: u32) -> Result<u32, MemCardError> {
+        let entries_per_cluster = self.superblock.cluster_size() / 4;
+        let cluster = cluster as usize;
+
+        let indirect_index = cluster / entries_per_cluster;
+        let indirect_cluster = *self
+            .superblock
+            .ifc_list
+            .get(indirect_index / entries_per_cluster)
+            .ok_or(MemCardError::InvalidFat)?;
+        let fat_cluster =
+            self.cluster_u32(indirect_cluster, indirect_index % entries_per_cluster)?;
+
+        self.cluster_u32(fat_cluster, cluster % entries_per_cluster)
+    }
+
+    /// Read `len` bytes from the cluster chain starting at `first`
+    fn read_chain(&self, first: u32, len: usize) -> Result<Vec<u8>, MemCardError> {
+        let cluster_size = self.superblock.cluster_size();
+        if len > self.superblock.clusters_per_card as usize * cluster_size {
+            return Err(MemCardError::InvalidFat);
+        }
+
+        let mut data = Vec::with_capacity(len);
+        let mut cluster = first;
+        while data.len() < len {
+            let absolute = self
+                .superblock
+                .alloc_offset
+                .checked_add(cluster)
+                .ok_or(MemCardError::InvalidFat)?;
+            data.extend_from_slice(&self.cluster(absolute)?);
+            if data.len() >= len {
+                break;
+            }
+
+            let entry = self.fat_entry(cluster)?;
+            if entry == FAT_END || entry & FAT_ALLOCATED == 0 {
+                return Err(MemCardError::InvalidFat);
+            }
+            cluster = entry & !FAT_ALLOCATED;
+        }
+
+        data.truncate(len);
+        Ok(data)
+    }
+
+    /// Read the `count` entries of the directory starting at `cluster` (including `.` and `..`) into `entries`
+    ///
+    /// `visited` holds the first clusters of the directories read so far. A directory found a second time
+    /// makes the tree a cycle or a graph that could take exponential time to walk, so it's invalid
+    fn read_directory(
+        &self,
+        cluster: u32,
+        count: usize,
+        parent: &str,
+        depth: usize,
+        visited: &mut HashSet<u32>,
+        entries: &mut Vec<McEntry>,
+    ) -> Result<(), MemCardError> {
+        if count < 2 || depth > MAX_DEPTH || !visited.insert(cluster) {
+            return Err(MemCardError::InvalidDirectory);
+        }
+
+        let data = self.read_chain(cluster, count * ENTRY_SIZE)?;
+        for raw in data.chunks_exact(ENTRY_SIZE).skip(2) {
+            let Some(entry) = McEntry::from_bytes(raw, parent) else {
+                continue;
+            };
+
+            let subdirectory = entry
+                .is_dir()
+                .then(|| (entry.cluster, entry.size as usize, entry.path.clone()));
+            entries.push(entry);
+
+            if let Some((cluster, count, path)) = subdirectory {
+                self.read_directory(cluster, count, &path, depth + 1, visited, entries)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Cut bytes off at the first NUL byte, if there is one
+fn until_nul(bytes: &[u8]) -> &[u8] {
+    let len = bytes
+        .iter()
+        .position(|&byte| byte == 0)
+        .unwrap_or(bytes.len());
+    &bytes[..len]
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{test_card::CardBuilder, MemCardError, MemoryCard};
+
+    fn card() -> Vec<u8> {
+        CardBuilder::new()
+            .directory(
+                "BESLES-12345",
+                CardBuilder::new()
+                    .file("icon.sys", &[0x11; 964])
+                    .file("BESLES-12345", &[0x22; 3000]),
+            )
+            .file("empty", &[])
+            .build()
+    }
+
+    #[test]
+    fn parse() {
+        let bytes = card();
+        assert_eq!(bytes.len(), 8 * 1024 * 1024);
+
+        let card = MemoryCard::parse(&bytes).unwrap();
+        assert_eq!(card.superblock().version, "1.2.0.0");
+        assert_eq!(card.superblock().cluster_size(), 1024);
+        assert!(!card.has_spare_area());
+
+        let entries = card
+            .entries()
+            .map(|entry| (entry.path.as_str(), entry.is_dir(), entry.size))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            entries,
+            [
+                ("BESLES-12345", true, 4),
+                ("BESLES-12345/icon.sys", false, 964),
+                ("BESLES-12345/BESLES-12345", false, 3000),
+                ("empty", false, 0),
+            ]
+        );
+
+        let entry = card.entries().next().unwrap();
+        assert_eq!(entry.name, "BESLES-12345");
+        assert_eq!(entry.created.to_string(), "2004-01-02 03:04:05");
+        assert_eq!(entry.modified.to_string(), "2005-06-07 08:09:10");
+    }
+
+    #[test]
+    fn read_file() {
+        let bytes = card();
+        let card = MemoryCard::parse(&bytes).unwrap();
+
+        assert_eq!(
+            card.read_file("BESLES-12345/icon.sys").unwrap(),
+            [0x11; 964]
+        );
+        // Spans three clusters
+        let save = card.read_file("/BESLES-12345/BESLES-12345").unwrap();
+        assert_eq!(save.len(), 3000);
+        assert!(save.iter().all(|&byte| byte == 0x22));
+        assert_eq!(card.read_file("empty").unwrap(), []);
+
+        assert_eq!(
+            card.read_file("BESLES-12345/missing"),
+            Err(MemCardError::NotFound)
+        );
+        assert_eq!(card.read_file("BESLES-12345"), Err(MemCardError::NotFound));
+    }
+
+    #[test]
+    fn read_dir() {
+        let bytes = card();
+        let card = MemoryCard::parse(&bytes).unwrap();
+
+        let names = |path| {
+            card.read_dir(path)
+                .unwrap()
+                .into_iter()
+                .map(|entry| entry.name)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names(""), ["BESLES-12345", "empty"]);
+        assert_eq!(names("/"), ["BESLES-12345", "empty"]);
+        assert_eq!(names("BESLES-12345"), ["icon.sys", "BESLES-12345"]);
+
+        assert_eq!(card.read_dir("missing"), Err(MemCardError::NotFound));
+        assert_eq!(card.read_dir("empty"), Err(MemCardError::NotFound));
+    }
+
+    #[test]
+    fn read_save() {
+        let bytes = card();
+        let card = MemoryCard::parse(&bytes).unwrap();
+
+        let save = card.read_save("BESLES-12345").unwrap();
+        assert_eq!(save.directory.name, "BESLES-12345");
+        assert_eq!(save.directory.size, 4);
+        assert_eq!(save.files.len(), 2);
+        assert_eq!(save.file("icon.sys").unwrap(), [0x11; 964]);
+        assert_eq!(save.files[1].entry.path, "BESLES-12345/BESLES-12345");
+        assert_eq!(save.files[1].data, [0x22; 3000]);
+
+        assert_eq!(card.read_save("empty"), Err(MemCardError::NotFound));
+    }
+
+    #[test]
+    fn spare_area() {
+        let bytes = card();
+        let with_spare = bytes
+            .chunks(512)
+            .flat_map(|page| [page, &[0; 16]].concat())
+            .collect::<Vec<_>>();
+
+        let card = MemoryCard::parse(&with_spare).unwrap();
+        let expected = MemoryCard::parse(&bytes).unwrap();
+        assert!(card.has_spare_area());
+        assert!(card.entries().eq(expected.entries()));
+        assert_eq!(
+            card.read_file("BESLES-12345/BESLES-12345"),
+            expected.read_file("BESLES-12345/BESLES-12345")
+        );
+    }
+
+    #[test]
+    fn reject_invalid() {
+        assert_eq!(MemoryCard::parse(&[0; 16]), Err(MemCardError::Truncated));
+        assert_eq!(
+            MemoryCard::parse(&[0; 1024]),
+            Err(MemCardError::InvalidMagic)
+        );
+
+        let mut bytes = card();
+        bytes.truncate(4 * 1024 * 1024);
+        assert_eq!(MemoryCard::parse(&bytes), Err(MemCardError::Truncated));
+    }
+
+    #[test]
+    fn corrupted_fat() {
+        let mut bytes = card();
+        // Point the FAT entry of the first root directory cluster outside of the card
+        let fat = 9 * 1024;
+        bytes[fat..fat + 4].copy_from_slice(&0x8000_FFFF_u32.to_le_bytes());
+        // Grow the root directory so the chain has to be followed
+        let root = 41 * 1024;
+        bytes[root + 4..root + 8].copy_from_slice(&8_u32.to_le_bytes());
+
+        assert_eq!(MemoryCard::parse(&bytes), Err(MemCardError::InvalidFat));
+    }
+
+    #[test]
+    fn directory_cycle() {
+        let mut bytes = CardBuilder::new()
+            .directory("A", CardBuilder::new())
+            .directory("B", CardBuilder::new())
+            .build();
+        let root = 41 * 1024;
+        let point = |bytes: &mut [u8], entry: usize, cluster: u32| {
+            let entry = root + entry * 512;
+            bytes[entry + 0x10..entry + 0x14].copy_from_slice(&cluster.to_le_bytes());
+        };
+
+        // Both directories are the same one
+        let mut shared = bytes.clone();
+        let a = u32::from_le_bytes(bytes[root + 2 * 512 + 0x10..][..4].try_into().unwrap());
+        point(&mut shared, 3, a);
+        assert_eq!(
+            MemoryCard::parse(&shared),
+            Err(MemCardError::InvalidDirectory)
+        );
+
+        // Both directories are the root directory, which contains 4 entries
+        for entry in [2, 3] {
+            bytes[root + entry * 512 + 4..][..4].copy_from_slice(&4_u32.to_le_bytes());
+            point(&mut bytes, entry, 0);
+        }
+        assert_eq!(
+            MemoryCard::parse(&bytes),
+            Err(MemCardError::InvalidDirectory)
+        );
+    }
+}
@@ -0,0 +1,356 @@
+//! `.max` save exports of the Action Replay Max / Max Drive
+//!
+//! The header contains the magic, a CRC-32 of the whole file (calculated with the field itself zeroed), the name of
+//! the save directory, the game title, the size of the compressed payload and the number of files. The payload is
+//! compressed with [LZARI](ps2_compression::lzari), its stream starts at 0x58 with the decompressed size. It
+//! contains every file as its size and name followed by its contents, padded until the offset in the payload is 8
+//! bytes short of a multiple of 16. Timestamps aren't preserved
+
+use crate::{
+    entry::{DIRECTORY_MODE, FILE_MODE, MAX_NAME_LEN},
+    until_nul, IconSys, McEntry, McSaveData, McSaveFile, McTimestamp,
+};
+use encoding_rs::SHIFT_JIS;
+use flate2::Crc;
+use ps2_bytes::read_u32_le;
+use ps2_compression::lzari;
+use std::{
+    error::Error as StdError,
+    fmt::{self, Display},
+};
+
+/// Magic at the start of the header
+const MAGIC: &[u8] = b"Ps2PowerSave";
+
+/// Size of the header, including the decompressed size at the start of the compressed payload
+const HEADER_SIZE: usize = 0x5C;
+
+/// Offset of the compressed payload
+const PAYLOAD_OFFSET: usize = 0x58;
+
+/// Size of the header preceding every file in the payload (size and name)
+const FILE_HEADER_SIZE: usize = 4 + MAX_NAME_LEN;
+
+/// Alignment of the files in the payload, which are padded to 8 bytes short of a multiple of it
+const FILE_ALIGNMENT: usize = 16;
+
+/// Errors that might occur when reading or writing a `.max` file
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum MaxError {
+    /// The file is smaller than its header or the compressed payload
+    Truncated,
+
+    /// The header doesn't start with the `.max` magic
+    InvalidMagic,
+
+    /// The CRC-32 of the file doesn't match the one stored in the header
+    InvalidChecksum,
+
+    /// The payload can't be decompressed or doesn't contain the files described by the header
+    InvalidPayload,
+
+    /// A name is longer than the 32 bytes available
+    InvalidName,
+
+    /// The save is too large to be stored
+    TooLarge,
+}
+
+impl Display for MaxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+impl StdError for MaxError {}
+
+/// Parsed `.max` file
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct MaxSave {
+    title: String,
+    save: McSaveData,
+}
+
+impl MaxSave {
+    /// Parse a `.max` file and decompress its payload
+    ///
+    /// # Errors
+    ///
+    /// - The file is truncated
+    /// - The magic or checksum is invalid
+    /// - The payload is corrupted
+    pub fn parse(bytes: &[u8]) -> Result<Self, MaxError> {
+        let header = bytes.get(..HEADER_SIZE).ok_or(MaxError::Truncated)?;
+        if !header.starts_with(MAGIC) {
+            return Err(MaxError::InvalidMagic);
+        }
+        if read_u32_le(header, 0x0C) != checksum(bytes) {
+            return Err(MaxError::InvalidChecksum);
+        }
+
+        let name = String::from_utf8_lossy(until_nul(&header[0x10..0x30])).into_owned();
+        let (title, _, _) = SHIFT_JIS.decode(until_nul(&header[0x30..0x50]));
+        let compressed_size = read_u32_le(header, 0x50) as usize;
+        let file_count = read_u32_le(header, 0x54);
+
+        // The compressed size includes the decompressed size that starts the stream
+        let compressed = bytes
+            .get(PAYLOAD_OFFSET..PAYLOAD_OFFSET + compressed_size)
+            .ok_or(MaxError::Truncated)?;
+        let payload = lzari::decompress(compressed).map_err(|_| MaxError::InvalidPayload)?;
+
+        let mut files = Vec::new();
+        let mut offset = 0;
+        for _ in 0..file_count {
+            let file_header = payload
+                .get(offset..offset + FILE_HEADER_SIZE)
+                .ok_or(MaxError::InvalidPayload)?;
+            let size = read_u32_le(file_header, 0);
+            let file_name = String::from_utf8_lossy(until_nul(&file_header[4..])).into_owned();
+            offset += FILE_HEADER_SIZE;
+
+            let data = payload
+                .get(offset..offset + size as usize)
+                .ok_or(MaxError::InvalidPayload)?
+                .to_vec();
+            offset = align(offset + data.len());
+
+            files.push(McSaveFile {
+                entry: entry(FILE_MODE, size, file_name, &name),
+                data,
+            });
+        }
+
+        let count = file_count.checked_add(2).ok_or(MaxError::InvalidPayload)?;
+        Ok(Self {
+            title: title.into_owned(),
+            save: McSaveData {
+                directory: entry(DIRECTORY_MODE, count, name, ""),
+                files,
+            },
+        })
+    }
+
+    /// Title of the game, usually taken from the save's `icon.sys`
+    #[must_use]
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    /// Directory entry of the save
+    ///
+    /// The format doesn't store the mode or timestamps, they are filled in with the values of a regular save
+    #[must_use]
+    pub fn directory(&self) -> &McEntry {
+        &self.save.directory
+    }
+
+    /// Files contained in the save
+    #[must_use]
+    pub fn files(&self) -> &[McSaveFile] {
+        &self.save.files
+    }
+
+    /// Convert into the format independent representation
+    #[must_use]
+    pub fn to_mc_save(&self) -> McSaveData {
+        self.save.clone()
+    }
+
+    /// Format independent representation of the save
+    pub(crate) fn save_data(&self) -> &McSaveData {
+        &self.save
+    }
+
+    /// Serialise a save into a `.max` file
+    ///
+    /// The title is taken from the save's `icon.sys` if it contains one, the directory name otherwise
+    ///
+    /// # Errors
+    ///
+    /// - A name doesn't fit into 32 bytes
+    /// - The save is too large
+    pub fn from_mc_save(save: &McSaveData) -> Result<Vec<u8>, MaxError> {
+        let mut payload = Vec::new();
+        for file in &save.files {
+            let size = u32::try_from(file.data.len()).map_err(|_| MaxError::TooLarge)?;
+            payload.extend_from_slice(&size.to_le_bytes());
+            payload.extend_from_slice(&encode_name(&file.entry.name)?);
+            payload.extend_from_slice(&file.data);
+            payload.resize(align(payload.len()), 0);
+        }
+        if u32::try_from(payload.len()).is_err() {
+            return Err(MaxError::TooLarge);
+        }
+        let compressed = lzari::compress(&payload);
+
+        let title = save
+            .file("icon.sys")
+            .and_then(|icon_sys| IconSys::parse(icon_sys).ok())
+            .map_or_else(|| save.directory.name.clone(), |icon_sys| icon_sys.title);
+
+        let size = |len: usize| u32::try_from(len).map_err(|_| MaxError::TooLarge);
+        let mut bytes = vec![0; PAYLOAD_OFFSET];
+        bytes[..MAGIC.len()].copy_from_slice(MAGIC);
+        bytes[0x10..0x30].copy_from_slice(&encode_name(&save.directory.name)?);
+        bytes[0x30..0x50].copy_from_slice(&encode_title(&title));
+        bytes[0x50..0x54].copy_from_slice(&size(compressed.len())?.to_le_bytes());
+        bytes[0x54..0x58].copy_from_slice(&size(save.files.len())?.to_le_bytes());
+        bytes.extend_from_slice(&compressed);
+
+        let crc = checksum(&bytes);
+        bytes[0x0C..0x10].copy_from_slice(&crc.to_le_bytes());
+
+        Ok(bytes)
+    }
+}
+
+/// Build the entry of a file or directory in the save
+fn entry(mode: u16, size: u32, name: String, parent: &str) -> McEntry {
+    let path = if parent.is_empty() {
+        name.clone()
+    } else {
+        format!("{parent}/{name}")
+    };
+
+    McEntry {
+        name,
+        path,
+        mode,
+        size,
+        created: McTimestamp::default(),
+        modified: McTimestamp::default(),
+        cluster: 0,
+    }
+}
+
+/// Offset of the next file in the payload after one ending at `offset`
+fn align(offset: usize) -> usize {
+    (offset + FILE_ALIGNMENT / 2).next_multiple_of(FILE_ALIGNMENT) - FILE_ALIGNMENT / 2
+}
+
+/// CRC-32 of a file with the checksum field zeroed
+fn checksum(bytes: &[u8]) -> u32 {
+    let mut crc = Crc::new();
+    crc.update(&bytes[..0x0C]);
+    crc.update(&[0; 4]);
+    crc.update(&bytes[0x10..]);
+    crc.sum()
+}
+
+/// Encode a name into its nul-padded 32 byte representation
+fn encode_name(name: &str) -> Result<[u8; MAX_NAME_LEN], MaxError> {
+    if name.len() > MAX_NAME_LEN {
+        return Err(MaxError::InvalidName);
+    }
+
+    let mut raw = [0; MAX_NAME_LEN];
+    raw[..name.len()].copy_from_slice(name.as_bytes());
+    Ok(raw)
+}
+
+/// Encode a title as Shift-JIS, dropping characters that don't fit into the 32 bytes available
+fn encode_title(title: &str) -> [u8; MAX_NAME_LEN] {
+    let mut title = title.to_owned();
+    let encoded = loop {
+        let (encoded, _, _) = SHIFT_JIS.encode(&title);
+        if encoded.len() < MAX_NAME_LEN {
+            break encoded.into_owned();
+        }
+        title.pop();
+    };
+
+    let mut raw = [0; MAX_NAME_LEN];
+    raw[..encoded.len()].copy_from_slice(&encoded);
+    raw
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{test_card::CardBuilder, MaxError, MaxSave, MemoryCard, Psu};
+    use ps2_bytes::read_u32_le;
+    use ps2_compression::lzari;
+
+    fn psu() -> Vec<u8> {
+        let bytes = CardBuilder::new()
+            .directory(
+                "BASLUS-12345",
+                CardBuilder::new()
+                    .file("BASLUS-12345", &[0x22; 1500])
+                    .file("view.ico", &(0..=255).collect::<Vec<u8>>())
+                    .file("empty", &[]),
+            )
+            .build();
+        let save = MemoryCard::parse(&bytes)
+            .unwrap()
+            .read_save("BASLUS-12345")
+            .unwrap();
+
+        Psu::from_mc_save(&save).unwrap()
+    }
+
+    #[test]
+    fn round_trip() {
+        let psu = Psu::parse(&psu()).unwrap();
+        let bytes = MaxSave::from_mc_save(&psu.to_mc_save()).unwrap();
+        assert!(bytes.starts_with(b"Ps2PowerSave"));
+
+        let max = MaxSave::parse(&bytes).unwrap();
+        assert_eq!(max.title(), "BASLUS-12345");
+        assert_eq!(max.directory().name, "BASLUS-12345");
+        assert_eq!(max.directory().size, 5);
+
+        let converted = Psu::parse(&Psu::from_mc_save(&max.to_mc_save()).unwrap()).unwrap();
+        let contents = |files: &[crate::McSaveFile]| {
+            files
+                .iter()
+                .map(|file| (file.entry.path.clone(), file.data.clone()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(contents(converted.files()), contents(psu.files()));
+    }
+
+    #[test]
+    fn layout() {
+        let psu = Psu::parse(&psu()).unwrap();
+        let bytes = MaxSave::from_mc_save(&psu.to_mc_save()).unwrap();
+
+        // The compressed size covers the LZARI stream from 0x58 on, including its decompressed size
+        assert_eq!(read_u32_le(&bytes, 0x50) as usize, bytes.len() - 0x58);
+        assert_eq!(read_u32_le(&bytes, 0x54), 3);
+        let payload = lzari::decompress(&bytes[0x58..]).unwrap();
+        assert_eq!(read_u32_le(&bytes, 0x58) as usize, payload.len());
+
+        // 36 bytes of header and 1500 bytes of data, padded to 8 bytes short of a multiple of 16
+        assert_eq!(read_u32_le(&payload, 0), 1500);
+        assert_eq!(&payload[4..16], b"BASLUS-12345");
+        assert_eq!(read_u32_le(&payload, 1544), 256);
+        assert_eq!(&payload[1548..1556], b"view.ico");
+        assert_eq!(read_u32_le(&payload, 1848), 0);
+        assert_eq!(&payload[1852..1858], b"empty\0");
+        assert_eq!(payload.len(), 1896);
+    }
+
+    #[test]
+    fn reject_invalid() {
+        let psu = Psu::parse(&psu()).unwrap();
+        let bytes = MaxSave::from_mc_save(&psu.to_mc_save()).unwrap();
+
+        assert_eq!(MaxSave::parse(&bytes[..64]), Err(MaxError::Truncated));
+        assert_eq!(
+            MaxSave::parse(&bytes[..bytes.len() - 1]),
+            Err(MaxError::InvalidChecksum)
+        );
+
+        let mut corrupted = bytes.clone();
+        corrupted[0] = b'X';
+        assert_eq!(MaxSave::parse(&corrupted), Err(MaxError::InvalidMagic));
+
+        // Claim more files than the payload contains and fix up the checksum
+        let mut corrupted = bytes;
+        corrupted[0x54] = 4;
+        let crc = super::checksum(&corrupted);
+        corrupted[0x0C..0x10].copy_from_slice(&crc.to_le_bytes());
+        assert_eq!(MaxSave::parse(&corrupted), Err(MaxError::InvalidPayload));
+    }
+}
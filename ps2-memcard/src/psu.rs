@@ -0,0 +1,225 @@
+//! `.psu` save exports
+//!
+//! The format written by the EMS Memory Adapter software (and uLaunchELF) consists of the directory entry of the save,
+//! the `.` and `..` entries and every file as its directory entry followed by its contents padded to 1024 bytes
+
+use crate::{
+    entry::{ENTRY_SIZE, MAX_NAME_LEN},
+    McEntry, McSaveData, McSaveFile, DF_DIRECTORY, DF_EXISTS,
+};
+use std::{
+    error::Error as StdError,
+    fmt::{self, Display},
+};
+
+/// Alignment of the file contents
+const PSU_CLUSTER_SIZE: usize = 1024;
+
+/// Errors that might occur when reading or writing a `.psu` file
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum PsuError {
+    /// The file ends in the middle of an entry or file
+    Truncated,
+
+    /// The first entry isn't a valid directory entry
+    InvalidDirectory,
+
+    /// One of the entries following the directory isn't a file
+    InvalidFile,
+
+    /// A name is longer than the 32 bytes a directory entry can hold
+    InvalidName,
+
+    /// The save contains a file larger than 4 GiB or too many files
+    TooLarge,
+}
+
+impl Display for PsuError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+impl StdError for PsuError {}
+
+/// Parsed `.psu` file
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Psu {
+    save: McSaveData,
+}
+
+impl Psu {
+    /// Parse a `.psu` file
+    ///
+    /// # Errors
+    ///
+    /// - The file is truncated
+    /// - The directory entry is invalid
+    /// - The save contains something other than files
+    pub fn parse(bytes: &[u8]) -> Result<Self, PsuError> {
+        let directory = bytes.get(..ENTRY_SIZE).ok_or(PsuError::Truncated)?;
+        let directory = McEntry::from_bytes(directory, "")
+            .filter(McEntry::is_dir)
+            .ok_or(PsuError::InvalidDirectory)?;
+        let count = directory.size as usize;
+        if count < 2 {
+            return Err(PsuError::InvalidDirectory);
+        }
+
+        // The `.` and `..` entries carry no information
+        let mut offset = 3 * ENTRY_SIZE;
+        if bytes.len() < offset {
+            return Err(PsuError::Truncated);
+        }
+
+        let mut files = Vec::new();
+        for _ in 2..count {
+            let raw = bytes
+                .get(offset..offset + ENTRY_SIZE)
+                .ok_or(PsuError::Truncated)?;
+            let entry = McEntry::from_bytes(raw, &directory.name)
+                .filter(McEntry::is_file)
+                .ok_or(PsuError::InvalidFile)?;
+            offset += ENTRY_SIZE;
+
+            let len = entry.size as usize;
+            let data = bytes
+                .get(offset..offset + len)
+                .ok_or(PsuError::Truncated)?
+                .to_vec();
+            offset += len.next_multiple_of(PSU_CLUSTER_SIZE);
+
+            files.push(McSaveFile { entry, data });
+        }
+
+        Ok(Self {
+            save: McSaveData { directory, files },
+        })
+    }
+
+    /// Directory entry of the save
+    #[must_use]
+    pub fn directory(&self) -> &McEntry {
+        &self.save.directory
+    }
+
+    /// Files contained in the save
+    #[must_use]
+    pub fn files(&self) -> &[McSaveFile] {
+        &self.save.files
+    }
+
+    /// Convert into the format independent representation
+    #[must_use]
+    pub fn to_mc_save(&self) -> McSaveData {
+        self.save.clone()
+    }
+
+    /// Format independent representation of the save
+    pub(crate) fn save_data(&self) -> &McSaveData {
+        &self.save
+    }
+
+    /// Serialise a save into a `.psu` file
+    ///
+    /// # Errors
+    ///
+    /// - A name doesn't fit into a directory entry
+    /// - A file is too large
+    pub fn from_mc_save(save: &McSaveData) -> Result<Vec<u8>, PsuError> {
+        let names_valid = std::iter::once(&save.directory)
+            .chain(save.files.iter().map(|file| &file.entry))
+            .all(|entry| entry.name.len() <= MAX_NAME_LEN);
+        if !names_valid {
+            return Err(PsuError::InvalidName);
+        }
+
+        let mut directory = save.directory.clone();
+        directory.mode |= DF_EXISTS | DF_DIRECTORY;
+        directory.size = u32::try_from(save.files.len() + 2).map_err(|_| PsuError::TooLarge)?;
+        directory.cluster = 0;
+
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&directory.to_bytes());
+        for name in [".", ".."] {
+            let entry = McEntry {
+                name: name.into(),
+                size: 0,
+                ..directory.clone()
+            };
+            bytes.extend_from_slice(&entry.to_bytes());
+        }
+
+        for file in &save.files {
+            let mut entry = file.entry.clone();
+            entry.size = u32::try_from(file.data.len()).map_err(|_| PsuError::TooLarge)?;
+            entry.cluster = 0;
+
+            bytes.extend_from_slice(&entry.to_bytes());
+            bytes.extend_from_slice(&file.data);
+            let padding = file.data.len().next_multiple_of(PSU_CLUSTER_SIZE) - file.data.len();
+            bytes.resize(bytes.len() + padding, 0xFF);
+        }
+
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{test_card::CardBuilder, McSaveData, MemoryCard, Psu, PsuError};
+
+    fn save() -> McSaveData {
+        let bytes = CardBuilder::new()
+            .directory(
+                "BASLUS-12345",
+                CardBuilder::new()
+                    .file("icon.sys", &[0x11; 964])
+                    .file("BASLUS-12345", &[0x22; 1500])
+                    .file("empty", &[]),
+            )
+            .build();
+
+        MemoryCard::parse(&bytes)
+            .unwrap()
+            .read_save("BASLUS-12345")
+            .unwrap()
+    }
+
+    #[test]
+    fn round_trip() {
+        let save = save();
+        let bytes = Psu::from_mc_save(&save).unwrap();
+        // Three entries for the directory, `.` and `..`, then every file padded to 1024 bytes
+        assert_eq!(bytes.len(), 3 * 512 + (512 + 1024) + (512 + 2048) + 512);
+        assert_eq!(&bytes[0x40..0x4C], b"BASLUS-12345");
+        assert_eq!(&bytes[512 + 0x40..512 + 0x42], b".\0");
+
+        let psu = Psu::parse(&bytes).unwrap();
+        assert_eq!(psu.directory().name, "BASLUS-12345");
+        assert_eq!(psu.files().len(), 3);
+        assert_eq!(psu.files()[0].entry.path, "BASLUS-12345/icon.sys");
+        assert_eq!(psu.to_mc_save(), save);
+    }
+
+    #[test]
+    fn reject_invalid() {
+        let bytes = Psu::from_mc_save(&save()).unwrap();
+
+        assert_eq!(Psu::parse(&[]), Err(PsuError::Truncated));
+        assert_eq!(Psu::parse(&[0; 512]), Err(PsuError::InvalidDirectory));
+        assert_eq!(
+            Psu::parse(&bytes[..bytes.len() - 1024]),
+            Err(PsuError::Truncated)
+        );
+
+        let mut corrupted = bytes.clone();
+        corrupted[3 * 512] = 0;
+        corrupted[3 * 512 + 1] = 0;
+        assert_eq!(Psu::parse(&corrupted), Err(PsuError::InvalidFile));
+
+        let mut save = save();
+        save.files[0].entry.name = "a".repeat(33);
+        assert_eq!(Psu::from_mc_save(&save), Err(PsuError::InvalidName));
+    }
+}
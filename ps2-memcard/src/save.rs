@@ -0,0 +1,185 @@
+use crate::{MaxError, MaxSave, McEntry, Psu, PsuError};
+use std::{
+    error::Error as StdError,
+    fmt::{self, Display},
+};
+
+/// Errors that might occur when converting a save into another format
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum ConvertError {
+    /// Writing a `.psu` file failed
+    Psu(PsuError),
+
+    /// Writing a `.max` file failed
+    Max(MaxError),
+}
+
+impl Display for ConvertError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+impl StdError for ConvertError {}
+
+impl From<PsuError> for ConvertError {
+    fn from(err: PsuError) -> Self {
+        Self::Psu(err)
+    }
+}
+
+impl From<MaxError> for ConvertError {
+    fn from(err: MaxError) -> Self {
+        Self::Max(err)
+    }
+}
+
+/// File of a save
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct McSaveFile {
+    /// Directory entry of the file
+    pub entry: McEntry,
+
+    /// Contents of the file
+    pub data: Vec<u8>,
+}
+
+/// Save directory independent of the format it was stored in
+///
+/// Saves can be read from a memory card image ([`MemoryCard::read_save`](crate::MemoryCard::read_save)) or from
+/// one of the export formats
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct McSaveData {
+    /// Directory entry of the save directory
+    pub directory: McEntry,
+
+    /// Files contained in the save directory
+    pub files: Vec<McSaveFile>,
+}
+
+impl McSaveData {
+    /// Look up the contents of the file called `name`
+    #[must_use]
+    pub fn file(&self, name: &str) -> Option<&[u8]> {
+        self.files
+            .iter()
+            .find(|file| file.entry.name == name)
+            .map(|file| file.data.as_slice())
+    }
+}
+
+/// Save in any of the supported formats
+///
+/// Allows converting between the formats without caring about their details
+pub trait McSave {
+    /// Format independent representation of the save
+    fn save_data(&self) -> &McSaveData;
+
+    /// Directory entry of the save directory
+    fn directory_entry(&self) -> &McEntry {
+        &self.save_data().directory
+    }
+
+    /// Names and contents of the files contained in the save
+    fn files(&self) -> impl Iterator<Item = (&str, &[u8])> {
+        self.save_data()
+            .files
+            .iter()
+            .map(|file| (file.entry.name.as_str(), file.data.as_slice()))
+    }
+
+    /// Serialise the save into a `.psu` file
+    ///
+    /// # Errors
+    ///
+    /// See [`Psu::from_mc_save`]
+    fn to_psu(&self) -> Result<Vec<u8>, ConvertError> {
+        Ok(Psu::from_mc_save(self.save_data())?)
+    }
+
+    /// Serialise the save into a `.max` file
+    ///
+    /// # Errors
+    ///
+    /// See [`MaxSave::from_mc_save`]
+    fn to_max(&self) -> Result<Vec<u8>, ConvertError> {
+        Ok(MaxSave::from_mc_save(self.save_data())?)
+    }
+}
+
+impl McSave for McSaveData {
+    fn save_data(&self) -> &McSaveData {
+        self
+    }
+}
+
+impl McSave for Psu {
+    fn save_data(&self) -> &McSaveData {
+        self.save_data()
+    }
+}
+
+impl McSave for MaxSave {
+    fn save_data(&self) -> &McSaveData {
+        self.save_data()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        test_card::CardBuilder, ConvertError, MaxError, MaxSave, McSave, McTimestamp, MemoryCard,
+        Psu, PsuError,
+    };
+
+    fn contents(save: &impl McSave) -> Vec<(String, Vec<u8>)> {
+        save.files()
+            .map(|(name, data)| (name.to_owned(), data.to_vec()))
+            .collect()
+    }
+
+    #[test]
+    fn convert() {
+        let bytes = CardBuilder::new()
+            .directory(
+                "BASLUS-12345",
+                CardBuilder::new()
+                    .file("icon.sys", &[0x11; 964])
+                    .file("BASLUS-12345", &[0x22; 3000])
+                    .file("empty", &[]),
+            )
+            .build();
+        let card = MemoryCard::parse(&bytes).unwrap();
+        let save = card.read_save("BASLUS-12345").unwrap();
+
+        let psu = Psu::parse(&save.to_psu().unwrap()).unwrap();
+        let max = MaxSave::parse(&psu.to_max().unwrap()).unwrap();
+        let psu_again = Psu::parse(&max.to_psu().unwrap()).unwrap();
+
+        let expected = contents(&save);
+        assert_eq!(expected.len(), 3);
+        for converted in [contents(&psu), contents(&max), contents(&psu_again)] {
+            assert_eq!(converted, expected);
+        }
+        assert_eq!(max.directory_entry().name, "BASLUS-12345");
+        // Timestamps are lost in the `.max` file
+        assert_eq!(psu.directory_entry(), save.directory_entry());
+        assert_eq!(psu_again.directory_entry().created, McTimestamp::default());
+    }
+
+    #[test]
+    fn convert_error() {
+        let mut save = MemoryCard::parse(
+            &CardBuilder::new()
+                .directory("SAVE", CardBuilder::new())
+                .build(),
+        )
+        .unwrap()
+        .read_save("SAVE")
+        .unwrap();
+        save.directory.name = "a".repeat(33);
+
+        assert_eq!(save.to_psu(), Err(ConvertError::Psu(PsuError::InvalidName)));
+        assert_eq!(save.to_max(), Err(ConvertError::Max(MaxError::InvalidName)));
+    }
+}
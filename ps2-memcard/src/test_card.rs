@@ -0,0 +1,182 @@
+//! `CardBuilder`, writing a directory tree onto an 8 MB card image with or without ECC bytes
+//!
+//! The layout matches a freshly formatted card: the superblock in cluster 0, the indirect FAT cluster in cluster 8,
+//! the FAT in clusters 9 to 40 and the allocatable area starting at cluster 41
+//!
+//! No real card image is included, so the tests check files against the data given to the builder rather than
+//! against hashes of known saves
+
+const PAGE_LEN: usize = 512;
+const CLUSTER_SIZE: usize = 1024;
+const CLUSTERS: usize = 8192;
+const IFC_CLUSTER: usize = 8;
+const FAT_CLUSTER: usize = 9;
+const ALLOC_OFFSET: usize = 41;
+const ENTRY_SIZE: usize = 512;
+
+const DIRECTORY_MODE: u16 = 0x8427;
+const FILE_MODE: u16 = 0x8497;
+
+/// Creation timestamp of every entry, 2004-01-02 03:04:05
+const CREATED: [u8; 8] = [0, 5, 4, 3, 2, 1, 0xD4, 0x07];
+
+/// Modification timestamp of every entry, 2005-06-07 08:09:10
+const MODIFIED: [u8; 8] = [0, 10, 9, 8, 7, 6, 0xD5, 0x07];
+
+enum Node {
+    File(&'static str, Vec<u8>),
+    Directory(&'static str, CardBuilder),
+}
+
+pub struct CardBuilder {
+    nodes: Vec<Node>,
+}
+
+struct Image {
+    bytes: Vec<u8>,
+    fat: Vec<u32>,
+    next: usize,
+}
+
+impl Image {
+    fn allocate(&mut self, len: usize) -> u32 {
+        let first = self.next;
+        let count = len.div_ceil(CLUSTER_SIZE).max(1);
+        for cluster in first..first + count {
+            self.fat[cluster] = if cluster + 1 == first + count {
+                0xFFFF_FFFF
+            } else {
+                0x8000_0000 | u32::try_from(cluster + 1).unwrap()
+            };
+        }
+        self.next += count;
+
+        u32::try_from(first).unwrap()
+    }
+
+    fn write(&mut self, first: u32, data: &[u8]) {
+        let offset = (ALLOC_OFFSET + first as usize) * CLUSTER_SIZE;
+        self.bytes[offset..offset + data.len()].copy_from_slice(data);
+    }
+
+    fn directory(&mut self, nodes: &[Node]) -> u32 {
+        let count = nodes.len() + 2;
+        let first = self.allocate(count * ENTRY_SIZE);
+
+        let mut data = entry(DIRECTORY_MODE, count, first, ".");
+        data.extend(entry(DIRECTORY_MODE, 0, 0, ".."));
+        for node in nodes {
+            match node {
+                Node::File(name, contents) => {
+                    let cluster = if contents.is_empty() {
+                        0xFFFF_FFFF
+                    } else {
+                        let cluster = self.allocate(contents.len());
+                        self.write(cluster, contents);
+                        cluster
+                    };
+                    data.extend(entry(FILE_MODE, contents.len(), cluster, name));
+                }
+                Node::Directory(name, builder) => {
+                    let cluster = self.directory(&builder.nodes);
+                    data.extend(entry(
+                        DIRECTORY_MODE,
+                        builder.nodes.len() + 2,
+                        cluster,
+                        name,
+                    ));
+                }
+            }
+        }
+        self.write(first, &data);
+
+        first
+    }
+}
+
+fn entry(mode: u16, len: usize, cluster: u32, name: &str) -> Vec<u8> {
+    let mut entry = vec![0; ENTRY_SIZE];
+    entry[0..2].copy_from_slice(&mode.to_le_bytes());
+    entry[4..8].copy_from_slice(&u32::try_from(len).unwrap().to_le_bytes());
+    entry[0x08..0x10].copy_from_slice(&CREATED);
+    entry[0x10..0x14].copy_from_slice(&cluster.to_le_bytes());
+    entry[0x18..0x20].copy_from_slice(&MODIFIED);
+    entry[0x40..0x40 + name.len()].copy_from_slice(name.as_bytes());
+    entry
+}
+
+impl CardBuilder {
+    pub fn new() -> Self {
+        Self { nodes: Vec::new() }
+    }
+
+    pub fn file(mut self, name: &'static str, data: &[u8]) -> Self {
+        self.nodes.push(Node::File(name, data.to_vec()));
+        self
+    }
+
+    pub fn directory(mut self, name: &'static str, contents: CardBuilder) -> Self {
+        self.nodes.push(Node::Directory(name, contents));
+        self
+    }
+
+    pub fn build(self) -> Vec<u8> {
+        let mut image = Image {
+            bytes: vec![0xFF; CLUSTERS * CLUSTER_SIZE],
+            fat: vec![0x7FFF_FFFF; CLUSTERS - ALLOC_OFFSET],
+            next: 0,
+        };
+        image.directory(&self.nodes);
+
+        let u16 = |value: usize| u16::try_from(value).unwrap().to_le_bytes();
+        let u32 = |value: usize| u32::try_from(value).unwrap().to_le_bytes();
+
+        let mut superblock = vec![0; CLUSTER_SIZE];
+        superblock[..40].copy_from_slice(b"Sony PS2 Memory Card Format 1.2.0.0\0\0\0\0\0");
+        superblock[0x28..0x2A].copy_from_slice(&u16(PAGE_LEN));
+        superblock[0x2A..0x2C].copy_from_slice(&u16(CLUSTER_SIZE / PAGE_LEN));
+        superblock[0x2C..0x2E].copy_from_slice(&u16(16));
+        superblock[0x2E..0x30].copy_from_slice(&0xFF00_u16.to_le_bytes());
+        superblock[0x30..0x34].copy_from_slice(&u32(CLUSTERS));
+        superblock[0x34..0x38].copy_from_slice(&u32(ALLOC_OFFSET));
+        superblock[0x38..0x3C].copy_from_slice(&u32(8135));
+        superblock[0x3C..0x40].copy_from_slice(&u32(0));
+        superblock[0x40..0x44].copy_from_slice(&u32(1023));
+        superblock[0x44..0x48].copy_from_slice(&u32(1022));
+        superblock[0x50..0x54].copy_from_slice(&u32(IFC_CLUSTER));
+        superblock[0x150] = 2;
+        superblock[0x151] = 0x2B;
+        image.bytes[..CLUSTER_SIZE].copy_from_slice(&superblock);
+
+        let fat_clusters = (CLUSTERS - ALLOC_OFFSET).div_ceil(CLUSTER_SIZE / 4);
+        let ifc = IFC_CLUSTER * CLUSTER_SIZE;
+        image.bytes[ifc..ifc + CLUSTER_SIZE].fill(0);
+        for i in 0..fat_clusters {
+            image.bytes[ifc + i * 4..ifc + i * 4 + 4].copy_from_slice(&u32(FAT_CLUSTER + i));
+        }
+
+        let fat = FAT_CLUSTER * CLUSTER_SIZE;
+        for (i, value) in image.fat.iter().enumerate() {
+            image.bytes[fat + i * 4..fat + i * 4 + 4].copy_from_slice(&value.to_le_bytes());
+        }
+
+        image.bytes
+    }
+
+    /// Build the image with a spare area containing the ECC after every page
+    pub fn build_with_ecc(self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for page in self.build().chunks(PAGE_LEN) {
+            bytes.extend_from_slice(page);
+            if page.iter().all(|&byte| byte == 0xFF) {
+                bytes.extend_from_slice(&[0xFF; 16]);
+            } else {
+                for chunk in page.chunks(128) {
+                    bytes.extend_from_slice(&crate::ecc::calculate(chunk));
+                }
+                bytes.extend_from_slice(&[0; 4]);
+            }
+        }
+        bytes
+    }
+}
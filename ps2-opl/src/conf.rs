@@ -0,0 +1,185 @@
+use crate::OplError;
+use std::fmt::{self, Display};
+
+/// Compatibility modes of a game (`$Compatibility`), a bitmask of modes 1 through 8
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct CompatibilityModes(pub u8);
+
+impl CompatibilityModes {
+    /// Raw value of the bitmask
+    #[must_use]
+    pub fn bits(self) -> u8 {
+        self.0
+    }
+
+    /// Whether the given mode (1-8) is enabled
+    #[must_use]
+    pub fn is_enabled(self, mode: u8) -> bool {
+        (1..=8).contains(&mode) && self.0 & (1 << (mode - 1)) != 0
+    }
+}
+
+/// Per-game settings of OPL, stored as `KEY=value` lines in `CFG/<serial>.cfg` (e.g. `CFG/SLUS_213.48.cfg`)
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct OplGameConf {
+    /// Enabled compatibility modes (`$Compatibility`)
+    pub compatibility: CompatibilityModes,
+
+    /// Disc ID used to bypass the DNAS check (`$DNAS`)
+    pub dnas: Option<String>,
+
+    /// Alternative ELF to boot instead of the one in `SYSTEM.CNF` (`$AltStartup`)
+    pub alt_startup: Option<String>,
+
+    /// Whether the Graphics Synthesizer mode selector is enabled (`$EnableGSM`)
+    pub gsm: bool,
+
+    /// Index of the video mode forced by the mode selector (`$GSMVMode`)
+    pub gsm_video_mode: Option<u32>,
+
+    /// Whether cheats are enabled (`$EnableCheat`)
+    pub cheats: bool,
+
+    /// Cheat mode, 0 automatically enables all cheats and 1 lets the user select them (`$CheatMode`)
+    pub cheat_mode: Option<u32>,
+
+    /// Unrecognised key-value pairs (such as `Title`), in the order they appeared in the file
+    pub extras: Vec<(String, String)>,
+}
+
+impl OplGameConf {
+    /// Parse a per-game configuration file
+    ///
+    /// Blank lines and lines starting with `#` are skipped. Keys are matched case-sensitively, like OPL does
+    ///
+    /// # Errors
+    ///
+    /// - A line isn't a `KEY=value` pair
+    /// - The value of a known key is invalid
+    pub fn parse(text: &str) -> Result<Self, OplError> {
+        let mut conf = Self::default();
+        for (line_number, line) in (1..).zip(text.lines()) {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (key, value) = line
+                .split_once('=')
+                .ok_or(OplError::MalformedLine { line: line_number })?;
+            let invalid = || OplError::InvalidValue { line: line_number };
+            let number = || value.parse::<u32>().map_err(|_| invalid());
+            let flag = || match value {
+                "0" => Ok(false),
+                "1" => Ok(true),
+                _ => Err(invalid()),
+            };
+
+            match key {
+                "$Compatibility" => {
+                    conf.compatibility = CompatibilityModes(value.parse().map_err(|_| invalid())?);
+                }
+                "$DNAS" => conf.dnas = Some(value.to_owned()),
+                "$AltStartup" => conf.alt_startup = Some(value.to_owned()),
+                "$EnableGSM" => conf.gsm = flag()?,
+                "$GSMVMode" => conf.gsm_video_mode = Some(number()?),
+                "$EnableCheat" => conf.cheats = flag()?,
+                "$CheatMode" => conf.cheat_mode = Some(number()?),
+                _ => conf.extras.push((key.to_owned(), value.to_owned())),
+            }
+        }
+
+        Ok(conf)
+    }
+}
+
+impl Display for OplGameConf {
+    /// Encode in canonical form, the known keys in a fixed order followed by the extras
+    ///
+    /// Settings left at their default aren't written
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.compatibility.bits() != 0 {
+            writeln!(f, "$Compatibility={}", self.compatibility.bits())?;
+        }
+        if let Some(dnas) = &self.dnas {
+            writeln!(f, "$DNAS={dnas}")?;
+        }
+        if let Some(alt_startup) = &self.alt_startup {
+            writeln!(f, "$AltStartup={alt_startup}")?;
+        }
+        if self.gsm {
+            writeln!(f, "$EnableGSM=1")?;
+        }
+        if let Some(gsm_video_mode) = self.gsm_video_mode {
+            writeln!(f, "$GSMVMode={gsm_video_mode}")?;
+        }
+        if self.cheats {
+            writeln!(f, "$EnableCheat=1")?;
+        }
+        if let Some(cheat_mode) = self.cheat_mode {
+            writeln!(f, "$CheatMode={cheat_mode}")?;
+        }
+        for (key, value) in &self.extras {
+            writeln!(f, "{key}={value}")?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{CompatibilityModes, OplError, OplGameConf};
+
+    #[test]
+    fn all_options() {
+        let txt = "Title=Example Game\r\n$CheatMode=1\r\n$Compatibility=5\r\n$DNAS=SLUS_213.48\r\n\
+                   $AltStartup=SLUS_213.49\r\n$EnableGSM=1\r\n$GSMVMode=3\r\n$EnableCheat=1\r\n";
+        let conf = OplGameConf::parse(txt).unwrap();
+
+        assert_eq!(conf.compatibility, CompatibilityModes(0b101));
+        assert!(conf.compatibility.is_enabled(1));
+        assert!(!conf.compatibility.is_enabled(2));
+        assert!(conf.compatibility.is_enabled(3));
+        assert!(!conf.compatibility.is_enabled(9));
+        assert_eq!(conf.dnas.as_deref(), Some("SLUS_213.48"));
+        assert_eq!(conf.alt_startup.as_deref(), Some("SLUS_213.49"));
+        assert!(conf.gsm);
+        assert_eq!(conf.gsm_video_mode, Some(3));
+        assert!(conf.cheats);
+        assert_eq!(conf.cheat_mode, Some(1));
+        assert_eq!(conf.extras, [("Title".into(), "Example Game".into())]);
+
+        let encoded = conf.to_string();
+        assert_eq!(
+            encoded,
+            "$Compatibility=5\n$DNAS=SLUS_213.48\n$AltStartup=SLUS_213.49\n$EnableGSM=1\n$GSMVMode=3\n\
+             $EnableCheat=1\n$CheatMode=1\nTitle=Example Game\n"
+        );
+        assert_eq!(OplGameConf::parse(&encoded).unwrap(), conf);
+    }
+
+    #[test]
+    fn defaults() {
+        let conf = OplGameConf::parse("# Comment\n\n$EnableGSM=0\n$Compatibility=0\n").unwrap();
+        assert_eq!(conf, OplGameConf::default());
+        assert_eq!(conf.to_string(), "");
+        assert_eq!(OplGameConf::parse("").unwrap(), conf);
+    }
+
+    #[test]
+    fn invalid() {
+        assert_eq!(
+            OplGameConf::parse("$EnableGSM=1\n$Compatibility"),
+            Err(OplError::MalformedLine { line: 2 })
+        );
+        assert_eq!(
+            OplGameConf::parse("$EnableGSM=yes"),
+            Err(OplError::InvalidValue { line: 1 })
+        );
+        assert_eq!(
+            OplGameConf::parse("$Compatibility=256"),
+            Err(OplError::InvalidValue { line: 1 })
+        );
+    }
+}
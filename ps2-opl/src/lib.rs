@@ -0,0 +1,55 @@
+#![doc = include_str!("../README.md")]
+#![forbid(missing_docs, unsafe_code)]
+#![warn(clippy::all, clippy::pedantic)]
+
+use std::{
+    error::Error as StdError,
+    fmt::{self, Display},
+};
+
+mod conf;
+mod ul;
+
+pub use self::{
+    conf::{CompatibilityModes, OplGameConf},
+    ul::{ImageType, UlConfig, UlEntry, UlFlags},
+};
+
+/// Errors that might occur when parsing OPL configuration files
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum OplError {
+    /// The file size isn't a multiple of the record size
+    InvalidLength,
+
+    /// A string of the record isn't valid UTF-8
+    InvalidString {
+        /// Index of the record
+        record: usize,
+    },
+
+    /// The image type of the record is neither CD nor DVD
+    UnknownImageType {
+        /// Index of the record
+        record: usize,
+    },
+
+    /// A line of a configuration file isn't a `KEY=value` pair
+    MalformedLine {
+        /// 1-based line number the problem occurred on
+        line: usize,
+    },
+
+    /// The value of a known configuration key is invalid
+    InvalidValue {
+        /// 1-based line number the problem occurred on
+        line: usize,
+    },
+}
+
+impl Display for OplError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+impl StdError for OplError {}
@@ -0,0 +1,330 @@
+use crate::OplError;
+use std::str;
+
+/// Size of a single `ul.cfg` record
+const RECORD_SIZE: usize = 64;
+
+/// Size of the null-padded game name
+const NAME_SIZE: usize = 32;
+
+/// Size of the null-padded startup field (`ul.` followed by the serial)
+const STARTUP_SIZE: usize = 15;
+
+/// Prefix of the startup field
+const STARTUP_PREFIX: &str = "ul.";
+
+/// Offset of the bytes following the flags, whose meaning is unknown
+const RESERVED_OFFSET: usize = 50;
+
+/// Bytes following the flags as written by OPL, the fourth one is always `0x08`
+const DEFAULT_RESERVED: [u8; RECORD_SIZE - RESERVED_OFFSET] =
+    [0, 0, 0, 0x08, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+
+/// Type of the disc a game was dumped from
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum ImageType {
+    /// CD (`0x12`)
+    Cd,
+
+    /// DVD (`0x14`)
+    Dvd,
+}
+
+impl ImageType {
+    fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            0x12 => Some(Self::Cd),
+            0x14 => Some(Self::Dvd),
+            _ => None,
+        }
+    }
+
+    fn to_byte(self) -> u8 {
+        match self {
+            Self::Cd => 0x12,
+            Self::Dvd => 0x14,
+        }
+    }
+}
+
+/// Raw flags byte of a `ul.cfg` record
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct UlFlags(pub u8);
+
+impl UlFlags {
+    /// Raw value of the flags
+    #[must_use]
+    pub fn bits(self) -> u8 {
+        self.0
+    }
+}
+
+/// Game of a `ul.cfg` file
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct UlEntry {
+    /// Name of the game, at most 32 bytes
+    pub name: String,
+
+    /// Serial of the game (e.g. `SLUS_213.48`), at most 11 bytes
+    pub serial: String,
+
+    /// Number of parts the image is split into
+    pub parts: u8,
+
+    /// Type of the disc
+    pub image_type: ImageType,
+
+    /// Flags of the entry
+    pub flags: UlFlags,
+
+    reserved: [u8; RECORD_SIZE - RESERVED_OFFSET],
+}
+
+impl UlEntry {
+    /// Create a new entry without any flags set
+    #[must_use]
+    pub fn new(
+        name: impl Into<String>,
+        serial: impl Into<String>,
+        parts: u8,
+        image_type: ImageType,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            serial: serial.into(),
+            parts,
+            image_type,
+            flags: UlFlags::default(),
+            reserved: DEFAULT_RESERVED,
+        }
+    }
+
+    fn parse(record: &[u8], index: usize) -> Result<Self, OplError> {
+        let name = null_padded(&record[..NAME_SIZE], index)?;
+        let startup = null_padded(&record[NAME_SIZE..NAME_SIZE + STARTUP_SIZE], index)?;
+        let image_type =
+            ImageType::from_byte(record[48]).ok_or(OplError::UnknownImageType { record: index })?;
+
+        Ok(Self {
+            name: name.to_owned(),
+            serial: startup
+                .strip_prefix(STARTUP_PREFIX)
+                .unwrap_or(startup)
+                .to_owned(),
+            parts: record[47],
+            image_type,
+            flags: UlFlags(record[49]),
+            reserved: record[RESERVED_OFFSET..].try_into().unwrap(),
+        })
+    }
+
+    /// Encode into a record, truncating the name and serial if they are too long
+    fn to_record(&self) -> [u8; RECORD_SIZE] {
+        let mut record = [0; RECORD_SIZE];
+
+        // Truncate on character boundaries, so the record stays valid UTF-8
+        let name_len = self.name.floor_char_boundary(NAME_SIZE);
+        record[..name_len].copy_from_slice(&self.name.as_bytes()[..name_len]);
+
+        let startup = format!("{STARTUP_PREFIX}{}", self.serial);
+        let startup_len = startup.floor_char_boundary(STARTUP_SIZE - 1);
+        record[NAME_SIZE..NAME_SIZE + startup_len]
+            .copy_from_slice(&startup.as_bytes()[..startup_len]);
+
+        record[47] = self.parts;
+        record[48] = self.image_type.to_byte();
+        record[49] = self.flags.bits();
+        record[RESERVED_OFFSET..].copy_from_slice(&self.reserved);
+        record
+    }
+}
+
+/// Read a null-padded string
+fn null_padded(bytes: &[u8], record: usize) -> Result<&str, OplError> {
+    let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    str::from_utf8(&bytes[..end]).map_err(|_| OplError::InvalidString { record })
+}
+
+/// `ul.cfg` file, listing the games split into parts by USBUtil/USBExtreme
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct UlConfig {
+    entries: Vec<UlEntry>,
+}
+
+impl UlConfig {
+    /// Parse a `ul.cfg` file
+    ///
+    /// # Errors
+    ///
+    /// - The file size isn't a multiple of 64 bytes
+    /// - A name or serial isn't valid UTF-8
+    /// - An image type is unknown
+    pub fn parse(bytes: &[u8]) -> Result<Self, OplError> {
+        let records = bytes.chunks_exact(RECORD_SIZE);
+        if !records.remainder().is_empty() {
+            return Err(OplError::InvalidLength);
+        }
+
+        let entries = records
+            .enumerate()
+            .map(|(index, record)| UlEntry::parse(record, index))
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self { entries })
+    }
+
+    /// Games in the order they are listed
+    pub fn entries(&self) -> impl Iterator<Item = &UlEntry> {
+        self.entries.iter()
+    }
+
+    /// Mutable access to the games, e.g. for renaming them
+    pub fn entries_mut(&mut self) -> impl Iterator<Item = &mut UlEntry> {
+        self.entries.iter_mut()
+    }
+
+    /// Append a game
+    pub fn push(&mut self, entry: UlEntry) {
+        self.entries.push(entry);
+    }
+
+    /// Remove the game at the given index
+    ///
+    /// # Panics
+    ///
+    /// - The index is out of bounds
+    pub fn remove(&mut self, index: usize) -> UlEntry {
+        self.entries.remove(index)
+    }
+
+    /// Encode into a `ul.cfg` file
+    ///
+    /// Names longer than 32 bytes and serials longer than 11 bytes are truncated. Unknown bytes of parsed
+    /// records are preserved
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.entries.iter().flat_map(UlEntry::to_record).collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::RECORD_SIZE;
+    use crate::{ImageType, OplError, UlConfig, UlEntry, UlFlags};
+
+    fn record(name: &str, startup: &str, parts: u8, media: u8) -> [u8; RECORD_SIZE] {
+        let mut record = [0; RECORD_SIZE];
+        record[..name.len()].copy_from_slice(name.as_bytes());
+        record[32..32 + startup.len()].copy_from_slice(startup.as_bytes());
+        record[47] = parts;
+        record[48] = media;
+        record[53] = 0x08;
+        record
+    }
+
+    #[test]
+    fn parse() {
+        let mut bytes = record("Example Game", "ul.SLUS_213.48", 3, 0x14).to_vec();
+        bytes.extend(record(
+            "A name that is exactly 32 bytes!",
+            "ul.SCES_500.51",
+            1,
+            0x12,
+        ));
+        bytes[RECORD_SIZE + 49] = 0x01;
+
+        let config = UlConfig::parse(&bytes).unwrap();
+        let entries = config.entries().collect::<Vec<_>>();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].name, "Example Game");
+        assert_eq!(entries[0].serial, "SLUS_213.48");
+        assert_eq!(entries[0].parts, 3);
+        assert_eq!(entries[0].image_type, ImageType::Dvd);
+        assert_eq!(entries[0].flags, UlFlags(0));
+        assert_eq!(entries[1].name, "A name that is exactly 32 bytes!");
+        assert_eq!(entries[1].image_type, ImageType::Cd);
+        assert_eq!(entries[1].flags.bits(), 0x01);
+
+        assert_eq!(config.to_bytes(), bytes);
+        assert_eq!(UlConfig::parse(&[]).unwrap(), UlConfig::default());
+    }
+
+    #[test]
+    fn modify() {
+        let mut config =
+            UlConfig::parse(&record("Example Game", "ul.SLUS_213.48", 3, 0x14)).unwrap();
+        config.push(UlEntry::new(
+            "Another Game",
+            "SCES_500.51",
+            1,
+            ImageType::Cd,
+        ));
+        for entry in config.entries_mut() {
+            entry.name.make_ascii_uppercase();
+        }
+        config.remove(0);
+
+        assert_eq!(
+            config.to_bytes(),
+            record("ANOTHER GAME", "ul.SCES_500.51", 1, 0x12)
+        );
+
+        // Names and serials that are too long get truncated
+        config.push(UlEntry::new(
+            "X".repeat(40),
+            "SLUS_213.48.EXTRA",
+            1,
+            ImageType::Dvd,
+        ));
+        let reparsed = UlConfig::parse(&config.to_bytes()).unwrap();
+        let entry = reparsed.entries().nth(1).unwrap();
+        assert_eq!(entry.name, "X".repeat(32));
+        assert_eq!(entry.serial, "SLUS_213.48");
+    }
+
+    #[test]
+    fn non_ascii() {
+        let mut config = UlConfig::default();
+        config.push(UlEntry::new(
+            "ドラゴンクエストVIII",
+            "SLPM_655.55",
+            2,
+            ImageType::Dvd,
+        ));
+        // 31 bytes, followed by a 3 byte character crossing the end of the name
+        config.push(UlEntry::new(
+            format!("{}ゲーム", "X".repeat(31)),
+            "SLPM_655.55",
+            1,
+            ImageType::Dvd,
+        ));
+        config.push(UlEntry::new("Game", "SLP_ゲーム", 1, ImageType::Cd));
+
+        let reparsed = UlConfig::parse(&config.to_bytes()).unwrap();
+        let entries = reparsed.entries().collect::<Vec<_>>();
+        assert_eq!(entries[0].name, "ドラゴンクエストVIII");
+        assert_eq!(entries[0].serial, "SLPM_655.55");
+        assert_eq!(entries[1].name, "X".repeat(31));
+        assert_eq!(entries[2].serial, "SLP_ゲー");
+        assert_eq!(reparsed.to_bytes(), config.to_bytes());
+    }
+
+    #[test]
+    fn invalid() {
+        assert_eq!(UlConfig::parse(&[0; 65]), Err(OplError::InvalidLength));
+
+        let mut bytes = record("Example Game", "ul.SLUS_213.48", 1, 0x14).to_vec();
+        bytes.extend(record("Example Game", "ul.SLUS_213.48", 1, 0x13));
+        assert_eq!(
+            UlConfig::parse(&bytes),
+            Err(OplError::UnknownImageType { record: 1 })
+        );
+
+        bytes[RECORD_SIZE + 48] = 0x12;
+        bytes[RECORD_SIZE] = 0xFF;
+        assert_eq!(
+            UlConfig::parse(&bytes),
+            Err(OplError::InvalidString { record: 1 })
+        );
+    }
+}
@@ -0,0 +1,354 @@
+#![doc = include_str!("../README.md")]
+#![forbid(missing_docs, unsafe_code)]
+#![warn(clippy::all, clippy::pedantic)]
+
+use std::{
+    error::Error as StdError,
+    fmt::{self, Display},
+};
+
+mod patch;
+
+use self::patch::PatchLine;
+pub use self::patch::{CpuTarget, DataType, PatchEntry, PatchPlace};
+
+/// Errors that might occur when parsing a PNACH file
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum PnachError {
+    /// A line isn't a `key=value` directive or a patch has the wrong number of fields
+    Malformed {
+        /// 1-based line number the problem occurred on
+        line: usize,
+    },
+
+    /// A patch targets a CPU other than `EE` or `IOP`
+    UnknownCpu {
+        /// 1-based line number the problem occurred on
+        line: usize,
+    },
+
+    /// A patch uses an unknown data type
+    UnknownDataType {
+        /// 1-based line number the problem occurred on
+        line: usize,
+    },
+
+    /// An address or value isn't a hexadecimal number
+    InvalidNumber {
+        /// 1-based line number the problem occurred on
+        line: usize,
+    },
+
+    /// A conditional code uses a comparison other than "equal"
+    UnsupportedCondition {
+        /// 1-based line number the problem occurred on
+        line: usize,
+    },
+}
+
+impl Display for PnachError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+impl StdError for PnachError {}
+
+/// Parsed PNACH file
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Pnach {
+    game_title: Option<String>,
+    comment: Option<String>,
+    patches: Vec<PatchEntry>,
+}
+
+impl Pnach {
+    /// Parse a PNACH file
+    ///
+    /// Blank lines, comment lines (starting with `//`), section headers (`[...]`) and unknown directives
+    /// (such as `author`) are skipped. Comments at the end of `patch` lines are stripped
+    ///
+    /// Extended `D` codes are combined with the following `patch` line into a [`PatchEntry::Conditional`]
+    ///
+    /// # Errors
+    ///
+    /// - A line isn't a `key=value` directive
+    /// - A patch is malformed
+    /// - A conditional code isn't followed by the patch it guards
+    pub fn parse(text: &str) -> Result<Self, PnachError> {
+        let mut pnach = Self::default();
+        let mut condition = None;
+        for (line_number, line) in (1..).zip(text.lines()) {
+            let line = line.trim();
+            if line.is_empty()
+                || line.starts_with("//")
+                || (line.starts_with('[') && line.ends_with(']'))
+            {
+                continue;
+            }
+
+            let (key, value) = line
+                .split_once('=')
+                .ok_or(PnachError::Malformed { line: line_number })?;
+            let value = value.trim();
+            match key.trim() {
+                key if key.eq_ignore_ascii_case("gametitle") => {
+                    pnach.game_title = Some(value.to_owned());
+                }
+                key if key.eq_ignore_ascii_case("comment") => {
+                    pnach.comment = Some(value.to_owned());
+                }
+                key if key.eq_ignore_ascii_case("patch") => {
+                    let value = value.split_once("//").map_or(value, |(value, _)| value);
+                    let patch = PatchLine::parse(value, line_number)?;
+                    match condition.take() {
+                        // Conditions can't be nested
+                        Some(_) if patch.is_condition() => {
+                            return Err(PnachError::Malformed { line: line_number });
+                        }
+                        Some((_, condition)) => {
+                            pnach
+                                .patches
+                                .push(PatchEntry::conditional(condition, patch));
+                        }
+                        None if patch.is_condition() => condition = Some((line_number, patch)),
+                        None => pnach.patches.push(patch.into()),
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if let Some((line, _)) = condition {
+            return Err(PnachError::Malformed { line });
+        }
+
+        Ok(pnach)
+    }
+
+    /// Title of the game (`gametitle`)
+    #[must_use]
+    pub fn game_title(&self) -> Option<&str> {
+        self.game_title.as_deref()
+    }
+
+    /// Description of the patches (`comment`)
+    #[must_use]
+    pub fn comment(&self) -> Option<&str> {
+        self.comment.as_deref()
+    }
+
+    /// Patches in the order they appear in the file
+    #[must_use]
+    pub fn patches(&self) -> &[PatchEntry] {
+        &self.patches
+    }
+
+    /// Apply the EE patches to the EE memory, see [`Pnach::apply_to`]
+    pub fn apply(&self, memory: &mut [u8], base: u32) {
+        self.apply_to(CpuTarget::Ee, memory, base);
+    }
+
+    /// Apply the patches of the given CPU to `memory`, which starts at address `base`
+    ///
+    /// Values are written little-endian. Patches outside of the memory, extended codes other than
+    /// the 8, 16 and 32 bit writes and conditional patches whose condition doesn't hold are skipped.
+    /// Returns the number of patches that were applied
+    pub fn apply_to(&self, cpu: CpuTarget, memory: &mut [u8], base: u32) -> usize {
+        self.patches
+            .iter()
+            .filter(|patch| patch.cpu() == cpu)
+            .filter(|patch| patch.apply(memory, base))
+            .count()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{CpuTarget, DataType, PatchEntry, PatchPlace, Pnach, PnachError};
+
+    const PNACH: &str = "gametitle=Example Game [SLUS-21348] (U)
+comment=Infinite health
+
+// Health
+patch=1,EE,00123456,word,DEADBEEF
+patch=0,EE,00123460,byte,000000FF // Trailing comment
+
+[Moon jump]
+author=Someone
+patch=2,IOP,00001000,short,00001234
+patch=1,EE,20123470,extended,0000ABCD
+";
+
+    #[test]
+    fn parse() {
+        let pnach = Pnach::parse(PNACH).unwrap();
+        assert_eq!(pnach.game_title(), Some("Example Game [SLUS-21348] (U)"));
+        assert_eq!(pnach.comment(), Some("Infinite health"));
+        assert_eq!(pnach.patches().len(), 4);
+        assert_eq!(
+            pnach.patches()[0],
+            PatchEntry::Write {
+                place: PatchPlace::Continuous,
+                cpu: CpuTarget::Ee,
+                address: 0x0012_3456,
+                data_type: DataType::Word,
+                value: 0xDEAD_BEEF,
+            }
+        );
+        assert_eq!(pnach.patches()[1].place(), PatchPlace::OnBoot);
+        assert!(matches!(
+            pnach.patches()[1],
+            PatchEntry::Write { value: 0xFF, .. }
+        ));
+        assert_eq!(pnach.patches()[2].cpu(), CpuTarget::Iop);
+        assert_eq!(pnach.patches()[2].place(), PatchPlace::Both);
+        assert!(matches!(
+            pnach.patches()[3],
+            PatchEntry::Write {
+                data_type: DataType::Extended,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn apply() {
+        let pnach = Pnach::parse(PNACH).unwrap();
+        let base = 0x0012_3400;
+        let mut memory = vec![0; 0x100];
+        pnach.apply(&mut memory, base);
+
+        assert_eq!(memory[0x56..0x5A], [0xEF, 0xBE, 0xAD, 0xDE]);
+        assert_eq!(memory[0x60..0x62], [0xFF, 0x00]);
+        // Extended 32 bit write
+        assert_eq!(memory[0x70..0x74], [0xCD, 0xAB, 0x00, 0x00]);
+
+        let mut iop = vec![0; 0x2000];
+        assert_eq!(pnach.apply_to(CpuTarget::Iop, &mut iop, 0), 1);
+        assert_eq!(iop[0x1000..0x1002], [0x34, 0x12]);
+
+        // Patches outside of the memory are skipped
+        let mut small = vec![0; 0x10];
+        assert_eq!(pnach.apply_to(CpuTarget::Ee, &mut small, base), 0);
+        assert_eq!(pnach.apply_to(CpuTarget::Ee, &mut small, 0x0050_0000), 0);
+    }
+
+    #[test]
+    fn data_types() {
+        let pnach = Pnach::parse(
+            "patch=1,EE,00000000,byte,1234
+patch=1,EE,00000004,short,12345678
+patch=1,EE,00000008,word,123456789A
+patch=1,EE,00000010,double,0123456789ABCDEF
+patch=1,EE,00000018,extended,000000AB
+patch=1,EE,1000001C,extended,0000ABCD
+patch=1,EE,20000020,extended,89ABCDEF
+patch=1,EE,30000024,extended,00000001
+",
+        )
+        .unwrap();
+        let mut memory = vec![0; 0x28];
+        assert_eq!(pnach.apply_to(CpuTarget::Ee, &mut memory, 0), 7);
+
+        assert_eq!(memory[0x00..0x02], [0x34, 0x00]);
+        assert_eq!(memory[0x04..0x08], [0x78, 0x56, 0x00, 0x00]);
+        assert_eq!(memory[0x08..0x0D], [0x9A, 0x78, 0x56, 0x34, 0x00]);
+        assert_eq!(
+            memory[0x10..0x18],
+            [0xEF, 0xCD, 0xAB, 0x89, 0x67, 0x45, 0x23, 0x01]
+        );
+        assert_eq!(memory[0x18..0x1A], [0xAB, 0x00]);
+        assert_eq!(memory[0x1C..0x1F], [0xCD, 0xAB, 0x00]);
+        assert_eq!(memory[0x20..0x24], [0xEF, 0xCD, 0xAB, 0x89]);
+        // Unsupported extended codes are skipped
+        assert_eq!(memory[0x24..0x28], [0; 4]);
+    }
+
+    #[test]
+    fn conditional() {
+        let pnach = Pnach::parse(
+            "patch=1,EE,D0000010,extended,0000BEEF
+// The guarded write
+patch=1,EE,00000000,word,DEADBEEF
+patch=1,EE,00000008,short,00001234
+",
+        )
+        .unwrap();
+        assert_eq!(
+            pnach.patches(),
+            [
+                PatchEntry::Conditional {
+                    place: PatchPlace::Continuous,
+                    cpu: CpuTarget::Ee,
+                    condition_addr: 0x10,
+                    condition_value: 0xBEEF,
+                    write_addr: 0,
+                    data_type: DataType::Word,
+                    write_value: 0xDEAD_BEEF,
+                },
+                PatchEntry::Write {
+                    place: PatchPlace::Continuous,
+                    cpu: CpuTarget::Ee,
+                    address: 8,
+                    data_type: DataType::Short,
+                    value: 0x1234,
+                },
+            ]
+        );
+
+        // Condition doesn't hold, only the unconditional write happens
+        let mut memory = vec![0; 0x12];
+        assert_eq!(pnach.apply_to(CpuTarget::Ee, &mut memory, 0), 1);
+        assert_eq!(memory[0x00..0x04], [0; 4]);
+        assert_eq!(memory[0x08..0x0A], [0x34, 0x12]);
+
+        memory[0x10..0x12].copy_from_slice(&[0xEF, 0xBE]);
+        assert_eq!(pnach.apply_to(CpuTarget::Ee, &mut memory, 0), 2);
+        assert_eq!(memory[0x00..0x04], [0xEF, 0xBE, 0xAD, 0xDE]);
+
+        // Condition outside of the memory
+        let mut small = vec![0; 0x10];
+        assert_eq!(pnach.apply_to(CpuTarget::Ee, &mut small, 0), 1);
+        assert_eq!(small[0x00..0x04], [0; 4]);
+
+        assert_eq!(
+            Pnach::parse("patch=1,EE,D0000010,extended,0000BEEF"),
+            Err(PnachError::Malformed { line: 1 })
+        );
+        assert_eq!(
+            Pnach::parse(
+                "patch=1,EE,D0000010,extended,0000BEEF\npatch=1,EE,D0000012,extended,0000BEEF"
+            ),
+            Err(PnachError::Malformed { line: 2 })
+        );
+        assert_eq!(
+            Pnach::parse("patch=1,EE,D0000010,extended,0010BEEF"),
+            Err(PnachError::UnsupportedCondition { line: 1 })
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        assert_eq!(
+            Pnach::parse("patch"),
+            Err(PnachError::Malformed { line: 1 })
+        );
+        assert_eq!(
+            Pnach::parse("\npatch=1,EE,00123456,word"),
+            Err(PnachError::Malformed { line: 2 })
+        );
+        assert_eq!(
+            Pnach::parse("patch=1,VU,00123456,word,0"),
+            Err(PnachError::UnknownCpu { line: 1 })
+        );
+        assert_eq!(
+            Pnach::parse("patch=1,EE,00123456,quad,0"),
+            Err(PnachError::UnknownDataType { line: 1 })
+        );
+        assert_eq!(
+            Pnach::parse("patch=1,EE,0012345G,word,0"),
+            Err(PnachError::InvalidNumber { line: 1 })
+        );
+    }
+}
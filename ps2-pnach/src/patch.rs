@@ -0,0 +1,283 @@
+use crate::PnachError;
+
+/// When a patch is applied
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum PatchPlace {
+    /// Once, when the game boots (`0`)
+    OnBoot,
+
+    /// Continuously, once per frame (`1`)
+    Continuous,
+
+    /// Both when the game boots and continuously (`2`)
+    Both,
+}
+
+/// CPU whose memory a patch is applied to
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum CpuTarget {
+    /// Emotion Engine (`EE`)
+    Ee,
+
+    /// I/O processor (`IOP`)
+    Iop,
+}
+
+/// Size of the value a patch writes
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum DataType {
+    /// 8 bit (`byte`)
+    Byte,
+
+    /// 16 bit (`short`)
+    Short,
+
+    /// 32 bit (`word`)
+    Word,
+
+    /// 64 bit (`double`)
+    Double,
+
+    /// `CodeBreaker` style code, with the operation encoded in the top nibble of the address (`extended`)
+    Extended,
+}
+
+impl DataType {
+    /// Parse the name of a data type
+    fn from_name(name: &str) -> Option<Self> {
+        [
+            ("byte", Self::Byte),
+            ("short", Self::Short),
+            ("word", Self::Word),
+            ("double", Self::Double),
+            ("extended", Self::Extended),
+        ]
+        .into_iter()
+        .find_map(|(known, data_type)| name.eq_ignore_ascii_case(known).then_some(data_type))
+    }
+}
+
+/// Fields of a single `patch=` line
+#[derive(Clone, Copy)]
+pub(crate) struct PatchLine {
+    place: PatchPlace,
+    cpu: CpuTarget,
+    address: u32,
+    data_type: DataType,
+    value: u64,
+}
+
+impl PatchLine {
+    /// Parse the value of a `patch=` line, e.g. `1,EE,00123456,word,DEADBEEF`
+    pub(crate) fn parse(value: &str, line: usize) -> Result<Self, PnachError> {
+        let fields = value.split(',').map(str::trim).collect::<Vec<_>>();
+        let [place, cpu, address, data_type, value] = fields[..] else {
+            return Err(PnachError::Malformed { line });
+        };
+
+        let place = match place {
+            "0" => PatchPlace::OnBoot,
+            "1" => PatchPlace::Continuous,
+            "2" => PatchPlace::Both,
+            _ => return Err(PnachError::Malformed { line }),
+        };
+        let cpu = if cpu.eq_ignore_ascii_case("EE") {
+            CpuTarget::Ee
+        } else if cpu.eq_ignore_ascii_case("IOP") {
+            CpuTarget::Iop
+        } else {
+            return Err(PnachError::UnknownCpu { line });
+        };
+        let data_type =
+            DataType::from_name(data_type).ok_or(PnachError::UnknownDataType { line })?;
+
+        let patch = Self {
+            place,
+            cpu,
+            address: u32::from_str_radix(address, 16)
+                .map_err(|_| PnachError::InvalidNumber { line })?,
+            data_type,
+            value: u64::from_str_radix(value, 16)
+                .map_err(|_| PnachError::InvalidNumber { line })?,
+        };
+
+        // Only the "equal" comparison (`00000000` in the upper half of the value) is supported
+        if patch.is_condition() && patch.value >> 16 != 0 {
+            return Err(PnachError::UnsupportedCondition { line });
+        }
+
+        Ok(patch)
+    }
+
+    /// Whether this is an extended `D` code, which only executes the following line if a 16 bit value matches
+    pub(crate) fn is_condition(&self) -> bool {
+        self.data_type == DataType::Extended && self.address >> 28 == 0xD
+    }
+}
+
+impl From<PatchLine> for PatchEntry {
+    fn from(patch: PatchLine) -> Self {
+        Self::Write {
+            place: patch.place,
+            cpu: patch.cpu,
+            address: patch.address,
+            data_type: patch.data_type,
+            value: patch.value,
+        }
+    }
+}
+
+/// Single patch, made up of one or, in case of conditional codes, two `patch=` lines
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum PatchEntry {
+    /// Unconditional write
+    Write {
+        /// When the patch is applied
+        place: PatchPlace,
+
+        /// CPU whose memory is patched
+        cpu: CpuTarget,
+
+        /// Address the value is written to
+        address: u32,
+
+        /// Size of the value
+        data_type: DataType,
+
+        /// Value to write, only the bytes covered by the data type are written
+        value: u64,
+    },
+
+    /// Extended `D` code followed by the write it guards, e.g.
+    ///
+    /// ```text
+    /// patch=1,EE,D0123456,extended,0000FFFF
+    /// patch=1,EE,00123460,word,00000001
+    /// ```
+    Conditional {
+        /// When the patch is applied, taken from the condition
+        place: PatchPlace,
+
+        /// CPU whose memory is patched, taken from the condition
+        cpu: CpuTarget,
+
+        /// Address of the 16 bit value that is compared
+        condition_addr: u32,
+
+        /// Value the 16 bit value has to be equal to for the write to happen
+        condition_value: u16,
+
+        /// Address the value is written to
+        write_addr: u32,
+
+        /// Size of the written value
+        data_type: DataType,
+
+        /// Value to write, only the bytes covered by the data type are written
+        write_value: u64,
+    },
+}
+
+impl PatchEntry {
+    /// Combine a condition with the line following it
+    pub(crate) fn conditional(condition: PatchLine, write: PatchLine) -> Self {
+        #[allow(clippy::cast_possible_truncation)]
+        // The upper half is checked to be zero when parsing
+        let condition_value = condition.value as u16;
+
+        Self::Conditional {
+            place: condition.place,
+            cpu: condition.cpu,
+            condition_addr: condition.address & 0x0FFF_FFFF,
+            condition_value,
+            write_addr: write.address,
+            data_type: write.data_type,
+            write_value: write.value,
+        }
+    }
+
+    /// When the patch is applied
+    #[must_use]
+    pub fn place(&self) -> PatchPlace {
+        match *self {
+            Self::Write { place, .. } | Self::Conditional { place, .. } => place,
+        }
+    }
+
+    /// CPU whose memory is patched
+    #[must_use]
+    pub fn cpu(&self) -> CpuTarget {
+        match *self {
+            Self::Write { cpu, .. } | Self::Conditional { cpu, .. } => cpu,
+        }
+    }
+
+    /// Write the value into `memory`, which starts at address `base`
+    ///
+    /// Returns `false` if the write lies outside of the memory, is an unsupported extended code or its
+    /// condition doesn't hold
+    pub(crate) fn apply(&self, memory: &mut [u8], base: u32) -> bool {
+        let (address, data_type, value) = match *self {
+            Self::Write {
+                address,
+                data_type,
+                value,
+                ..
+            } => (address, data_type, value),
+            Self::Conditional {
+                condition_addr,
+                condition_value,
+                write_addr,
+                data_type,
+                write_value,
+                ..
+            } => {
+                let current = range(memory.len(), base, condition_addr, 2).map(|range| {
+                    u16::from_le_bytes([memory[range.start], memory[range.start + 1]])
+                });
+                if current != Some(condition_value) {
+                    return false;
+                }
+
+                (write_addr, data_type, write_value)
+            }
+        };
+
+        let Some((address, size)) = write_location(address, data_type) else {
+            return false;
+        };
+        match range(memory.len(), base, address, size) {
+            Some(range) => {
+                memory[range].copy_from_slice(&value.to_le_bytes()[..size]);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Address and size of a write, `None` for unsupported extended codes
+fn write_location(address: u32, data_type: DataType) -> Option<(u32, usize)> {
+    match data_type {
+        DataType::Byte => Some((address, 1)),
+        DataType::Short => Some((address, 2)),
+        DataType::Word => Some((address, 4)),
+        DataType::Double => Some((address, 8)),
+        DataType::Extended => {
+            let masked = address & 0x0FFF_FFFF;
+            match address >> 28 {
+                0 => Some((masked, 1)),
+                1 => Some((masked, 2)),
+                2 => Some((masked, 4)),
+                _ => None,
+            }
+        }
+    }
+}
+
+/// Range of `memory` covering `size` bytes at `address`, if they lie inside of it
+fn range(len: usize, base: u32, address: u32, size: usize) -> Option<std::ops::Range<usize>> {
+    let offset = address.checked_sub(base)? as usize;
+    let end = offset.checked_add(size)?;
+    (end <= len).then_some(offset..end)
+}
@@ -0,0 +1,301 @@
+#![doc = include_str!("../README.md")]
+#![forbid(missing_docs, unsafe_code)]
+#![warn(clippy::all, clippy::pedantic)]
+
+use std::{
+    collections::BTreeMap,
+    error::Error as StdError,
+    fmt::{self, Display},
+};
+
+mod madpcm;
+#[cfg(test)]
+mod test_pss;
+
+pub use self::madpcm::MAdpcm;
+
+/// Prefix of every MPEG start code
+const START_CODE_PREFIX: [u8; 3] = [0x00, 0x00, 0x01];
+
+/// Start code of a pack header
+const PACK_HEADER: u8 = 0xBA;
+
+/// Start code of the program end code
+const PROGRAM_END: u8 = 0xB9;
+
+/// Stream ID of private stream 1, carrying the audio
+const PRIVATE_STREAM_1: u8 = 0xBD;
+
+/// Stream ID of padding packets
+const PADDING_STREAM: u8 = 0xBE;
+
+/// Stream ID of the MPEG-2 video
+const VIDEO_STREAM: u8 = 0xE0;
+
+/// Substream ID of the first audio channel, the following channels use the subsequent IDs
+const AUDIO_SUBSTREAM: u8 = 0xA0;
+
+/// Number of audio substreams
+const AUDIO_CHANNELS: u8 = 16;
+
+/// Errors that might occur when parsing a PSS file
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum PssError {
+    /// The file ends in the middle of a header or packet
+    Truncated {
+        /// Offset of the incomplete header or packet
+        offset: usize,
+    },
+
+    /// The data doesn't continue with an MPEG start code
+    InvalidStartCode {
+        /// Offset the start code was expected at
+        offset: usize,
+    },
+
+    /// A pack or packet header is malformed
+    InvalidHeader {
+        /// Offset of the header
+        offset: usize,
+    },
+}
+
+impl Display for PssError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+impl StdError for PssError {}
+
+/// Stream a packet belongs to
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum PssStream {
+    /// MPEG-2 video
+    Video,
+
+    /// ADPCM audio of the given channel
+    Audio(u8),
+
+    /// Padding without any content
+    Padding,
+
+    /// Any other stream (such as the system header), identified by its stream ID
+    Other(u8),
+}
+
+/// Packet of a stream, see [`PssFile::packets`]
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct PssPacket {
+    /// Offset of the packet's start code in the file
+    pub offset: usize,
+
+    /// Stream the packet belongs to
+    pub stream: PssStream,
+
+    /// Number of payload bytes contributed to the stream
+    pub payload_len: usize,
+}
+
+/// Parsed and demuxed PSS file
+///
+/// PSS files are MPEG-2 program streams, split into 2048 byte packs.
+/// The video is carried in stream `0xE0` and each audio channel in a substream (`0xA0` + channel) of private stream 1
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct PssFile {
+    packets: Vec<PssPacket>,
+    video: Vec<u8>,
+    audio: BTreeMap<u8, Vec<u8>>,
+}
+
+impl PssFile {
+    /// Parse a PSS file and demux its streams
+    ///
+    /// Parsing stops at the program end code or the end of the data, trailing zero padding is ignored
+    ///
+    /// # Errors
+    ///
+    /// - The file is truncated
+    /// - The data contains something other than packs and packets
+    /// - A header is malformed
+    pub fn parse(bytes: &[u8]) -> Result<Self, PssError> {
+        let mut pss = Self::default();
+        let mut offset = 0;
+        while offset < bytes.len() {
+            let rest = &bytes[offset..];
+            if rest.iter().all(|&b| b == 0) {
+                break;
+            }
+            if rest.len() < 4 {
+                return Err(PssError::Truncated { offset });
+            }
+            if rest[..3] != START_CODE_PREFIX {
+                return Err(PssError::InvalidStartCode { offset });
+            }
+
+            offset += match rest[3] {
+                PROGRAM_END => break,
+                PACK_HEADER => pack_header_len(rest, offset)?,
+                stream_id => pss.packet(rest, offset, stream_id)?,
+            };
+        }
+
+        Ok(pss)
+    }
+
+    /// Parse a single packet, returning its length
+    fn packet(&mut self, bytes: &[u8], offset: usize, stream_id: u8) -> Result<usize, PssError> {
+        let header = bytes.get(..6).ok_or(PssError::Truncated { offset })?;
+        let len = 6 + usize::from(u16::from_be_bytes([header[4], header[5]]));
+        let packet = bytes.get(..len).ok_or(PssError::Truncated { offset })?;
+
+        let (stream, payload) = match stream_id {
+            VIDEO_STREAM => (PssStream::Video, pes_payload(packet, offset)?),
+            PRIVATE_STREAM_1 => {
+                let payload = pes_payload(packet, offset)?;
+                let (&substream, data) = payload
+                    .split_first()
+                    .ok_or(PssError::InvalidHeader { offset })?;
+                match substream.checked_sub(AUDIO_SUBSTREAM) {
+                    Some(channel) if channel < AUDIO_CHANNELS => (PssStream::Audio(channel), data),
+                    _ => (PssStream::Other(PRIVATE_STREAM_1), &[][..]),
+                }
+            }
+            PADDING_STREAM => (PssStream::Padding, &[][..]),
+            other => (PssStream::Other(other), &[][..]),
+        };
+
+        match stream {
+            PssStream::Video => self.video.extend_from_slice(payload),
+            PssStream::Audio(channel) => self
+                .audio
+                .entry(channel)
+                .or_default()
+                .extend_from_slice(payload),
+            PssStream::Padding | PssStream::Other(..) => {}
+        }
+        self.packets.push(PssPacket {
+            offset,
+            stream,
+            payload_len: payload.len(),
+        });
+
+        Ok(len)
+    }
+
+    /// All packets in the order they appear in the file
+    #[must_use]
+    pub fn packets(&self) -> &[PssPacket] {
+        &self.packets
+    }
+
+    /// Demuxed MPEG-2 video elementary stream
+    #[must_use]
+    pub fn video_stream(&self) -> &[u8] {
+        &self.video
+    }
+
+    /// Demuxed audio of the given channel, `None` if the file doesn't contain the channel
+    #[must_use]
+    pub fn audio_stream(&self, channel: u8) -> Option<&[u8]> {
+        self.audio.get(&channel).map(Vec::as_slice)
+    }
+
+    /// Audio channels contained in the file, in ascending order
+    pub fn audio_channels(&self) -> impl Iterator<Item = u8> + '_ {
+        self.audio.keys().copied()
+    }
+}
+
+/// Length of the pack header at the start of `bytes`
+fn pack_header_len(bytes: &[u8], offset: usize) -> Result<usize, PssError> {
+    let marker = *bytes.get(4).ok_or(PssError::Truncated { offset })?;
+    let len = if marker & 0xC0 == 0x40 {
+        // MPEG-2 pack header, followed by up to 7 stuffing bytes
+        let stuffing = bytes.get(13).ok_or(PssError::Truncated { offset })? & 0x07;
+        14 + usize::from(stuffing)
+    } else if marker & 0xF0 == 0x20 {
+        // MPEG-1 pack header
+        12
+    } else {
+        return Err(PssError::InvalidHeader { offset });
+    };
+
+    if bytes.len() < len {
+        return Err(PssError::Truncated { offset });
+    }
+    Ok(len)
+}
+
+/// Payload of a PES packet with an MPEG-2 header
+fn pes_payload(packet: &[u8], offset: usize) -> Result<&[u8], PssError> {
+    if packet.len() < 9 || packet[6] & 0xC0 != 0x80 {
+        return Err(PssError::InvalidHeader { offset });
+    }
+    packet
+        .get(9 + usize::from(packet[8])..)
+        .ok_or(PssError::InvalidHeader { offset })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{test_pss, PssError, PssFile, PssStream};
+
+    #[test]
+    fn parse() {
+        let bytes = test_pss::file();
+        let pss = PssFile::parse(&bytes).unwrap();
+
+        let count = |stream| {
+            pss.packets()
+                .iter()
+                .filter(|packet| packet.stream == stream)
+                .count()
+        };
+        assert_eq!(count(PssStream::Video), 3);
+        assert_eq!(count(PssStream::Audio(0)), 2);
+        assert_eq!(count(PssStream::Audio(1)), 1);
+        assert_eq!(count(PssStream::Other(0xBB)), 1);
+        assert_eq!(count(PssStream::Padding), 6);
+
+        // Every pack starts on a sector boundary
+        let sectors = pss
+            .packets()
+            .iter()
+            .filter(|packet| packet.stream != PssStream::Padding)
+            .map(|packet| packet.offset / test_pss::SECTOR_SIZE)
+            .collect::<Vec<_>>();
+        assert_eq!(sectors, [0, 0, 1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn demux() {
+        let pss = PssFile::parse(&test_pss::file()).unwrap();
+
+        assert_eq!(pss.video_stream(), test_pss::video_data());
+        assert_eq!(pss.audio_stream(0), Some(&[0xA0; 300][..]));
+        assert_eq!(pss.audio_stream(1), Some(&[0xA1; 100][..]));
+        assert_eq!(pss.audio_stream(2), None);
+        assert_eq!(pss.audio_channels().collect::<Vec<_>>(), [0, 1]);
+    }
+
+    #[test]
+    fn invalid() {
+        let bytes = test_pss::file();
+        assert_eq!(
+            PssFile::parse(&bytes[..100]),
+            Err(PssError::Truncated { offset: 26 })
+        );
+        assert_eq!(
+            PssFile::parse(&bytes[1..]),
+            Err(PssError::InvalidStartCode { offset: 0 })
+        );
+
+        let mut bytes = bytes;
+        bytes[4] = 0xFF;
+        assert_eq!(
+            PssFile::parse(&bytes),
+            Err(PssError::InvalidHeader { offset: 0 })
+        );
+    }
+}
@@ -0,0 +1,197 @@
+use crate::PssError;
+use ps2_vag::{AdpcmDecoder, BLOCK_SIZE as FRAME_SIZE, SAMPLES_PER_BLOCK as SAMPLES_PER_FRAME};
+
+/// Size of an audio block of a single channel
+pub const BLOCK_SIZE: usize = 128;
+
+/// Number of samples encoded in a block
+pub const SAMPLES_PER_BLOCK: usize = BLOCK_SIZE / FRAME_SIZE * SAMPLES_PER_FRAME;
+
+/// Number of prediction filters
+const FILTERS: u8 = 5;
+
+/// Largest valid shift
+const MAX_SHIFT: u8 = 12;
+
+/// Frame flag marking the end of the loop
+const FLAG_LOOP_END: u8 = 0x01;
+
+/// Frame flag marking the start of the loop
+const FLAG_LOOP_START: u8 = 0x04;
+
+/// Decoder for the M-ADPCM audio of a single PSS channel
+///
+/// Each 128 byte block consists of 8 frames of SPU2 ADPCM, decoded with the [`AdpcmDecoder`] of `ps2-vag`. The
+/// filter history carries over from one block to the next, so blocks have to be decoded in order
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct MAdpcm {
+    decoder: AdpcmDecoder,
+    position: usize,
+    loop_start: Option<usize>,
+    loop_end: Option<usize>,
+}
+
+impl MAdpcm {
+    /// Create a decoder at the start of a stream
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Decode the next block
+    ///
+    /// # Errors
+    ///
+    /// - A frame uses an unknown filter or shift
+    pub fn decode_block(
+        &mut self,
+        block: &[u8; BLOCK_SIZE],
+    ) -> Result<[i16; SAMPLES_PER_BLOCK], PssError> {
+        self.decode_block_at(block, 0)
+    }
+
+    /// Decode a whole channel consisting of consecutive blocks
+    ///
+    /// # Errors
+    ///
+    /// - The data doesn't end on a block boundary
+    /// - A frame uses an unknown filter or shift
+    pub fn decode_all_blocks(&mut self, data: &[u8]) -> Result<Vec<i16>, PssError> {
+        let blocks = data.chunks_exact(BLOCK_SIZE);
+        if !blocks.remainder().is_empty() {
+            return Err(PssError::Truncated {
+                offset: data.len() - blocks.remainder().len(),
+            });
+        }
+
+        let mut samples = Vec::with_capacity(data.len() / BLOCK_SIZE * SAMPLES_PER_BLOCK);
+        for (index, block) in blocks.enumerate() {
+            let mut buf = [0; BLOCK_SIZE];
+            buf.copy_from_slice(block);
+            samples.extend_from_slice(&self.decode_block_at(&buf, index * BLOCK_SIZE)?);
+        }
+        Ok(samples)
+    }
+
+    /// Index of the first sample of the loop, if a decoded frame marked it
+    #[must_use]
+    pub fn loop_start(&self) -> Option<usize> {
+        self.loop_start
+    }
+
+    /// Index one past the last sample of the loop, if a decoded frame marked it
+    #[must_use]
+    pub fn loop_end(&self) -> Option<usize> {
+        self.loop_end
+    }
+
+    /// Decode a block located at `offset` in the data, for error reporting
+    fn decode_block_at(
+        &mut self,
+        block: &[u8; BLOCK_SIZE],
+        offset: usize,
+    ) -> Result<[i16; SAMPLES_PER_BLOCK], PssError> {
+        let mut samples = [0; SAMPLES_PER_BLOCK];
+        let (frames, _) = block.as_chunks::<FRAME_SIZE>();
+        for (index, (frame, out)) in frames
+            .iter()
+            .zip(samples.chunks_exact_mut(SAMPLES_PER_FRAME))
+            .enumerate()
+        {
+            // Unlike the SPU2, unknown filters and shifts are rejected instead of clamped
+            if frame[0] >> 4 >= FILTERS || frame[0] & 0x0F > MAX_SHIFT {
+                return Err(PssError::InvalidHeader {
+                    offset: offset + index * FRAME_SIZE,
+                });
+            }
+
+            if frame[1] & FLAG_LOOP_START != 0 {
+                self.loop_start = Some(self.position);
+            }
+            out.copy_from_slice(&self.decoder.decode_block(frame));
+            self.position += SAMPLES_PER_FRAME;
+            if frame[1] & FLAG_LOOP_END != 0 {
+                self.loop_end = Some(self.position);
+            }
+        }
+
+        Ok(samples)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{MAdpcm, PssError};
+
+    /// Block with every filter and a range of shifts, loop start in the first and loop end in the last frame
+    fn block() -> [u8; 128] {
+        let mut block = [0; 128];
+        for (index, frame) in (0u8..).zip(block.chunks_exact_mut(16)) {
+            frame[0] = ((index % 5) << 4) | (index + 3);
+            frame[1] = match index {
+                0 => 0x04,
+                7 => 0x01,
+                _ => 0,
+            };
+            for (i, byte) in (0u8..).zip(&mut frame[2..]) {
+                *byte = i.wrapping_mul(53).wrapping_add(index * 29).wrapping_add(7);
+            }
+        }
+        block
+    }
+
+    #[test]
+    fn decode_block() {
+        let mut decoder = MAdpcm::new();
+        let samples = decoder.decode_block(&block()).unwrap();
+
+        // The first frame doesn't predict, so its samples are the nibbles shifted by 12 - 3 bits (7 << 9 = 3584)
+        assert_eq!(
+            samples[..14],
+            [3584, 0, -2048, 1536, 512, 3584, 3072, -3072, -2560, -1536, 0, 512, 2560, 2048]
+        );
+        // The last frame predicts from the samples of the previous one
+        assert_eq!(
+            samples[210..],
+            [226, 236, 216, 224, 223, 195, 185, 166, 120, 85, 47, 31, 30, -3]
+        );
+        assert_eq!(decoder.loop_start(), Some(0));
+        assert_eq!(decoder.loop_end(), Some(224));
+    }
+
+    #[test]
+    fn decode_all_blocks() {
+        // The second block starts with a predicting filter, so it depends on the history of the first one
+        let mut second = block();
+        second[0] = 0x13;
+        let mut data = block().to_vec();
+        data.extend_from_slice(&second);
+
+        let mut decoder = MAdpcm::new();
+        let samples = decoder.decode_all_blocks(&data).unwrap();
+        assert_eq!(samples.len(), 448);
+        assert_eq!(decoder.loop_start(), Some(224));
+        assert_eq!(decoder.loop_end(), Some(448));
+
+        // The history carries over, so the second block decodes differently than on its own
+        assert_ne!(
+            samples[224..],
+            MAdpcm::new().decode_block(&block()).unwrap()
+        );
+
+        assert_eq!(
+            MAdpcm::new().decode_all_blocks(&data[..200]),
+            Err(PssError::Truncated { offset: 128 })
+        );
+    }
+
+    #[test]
+    fn invalid_filter() {
+        let mut block = block();
+        block[32] = 0x53;
+        assert_eq!(
+            MAdpcm::new().decode_block(&block),
+            Err(PssError::InvalidHeader { offset: 32 })
+        );
+    }
+}
@@ -0,0 +1,59 @@
+//! MPEG program stream packs and PES packets, with a PSS file interleaving video and two ADPCM channels
+
+/// Size of a pack
+pub const SECTOR_SIZE: usize = 2048;
+
+/// Pack containing the given packet, padded to a full sector
+pub fn sector(packet: &[u8]) -> Vec<u8> {
+    // MPEG-2 pack header without stuffing
+    let mut sector = vec![
+        0x00, 0x00, 0x01, 0xBA, 0x44, 0, 0x04, 0, 0x04, 0x01, 0x01, 0x89, 0xC3, 0xF8,
+    ];
+    sector.extend_from_slice(packet);
+
+    let padding = SECTOR_SIZE - sector.len() - 6;
+    sector.extend_from_slice(&[0x00, 0x00, 0x01, 0xBE]);
+    sector.extend_from_slice(&u16::try_from(padding).unwrap().to_be_bytes());
+    sector.resize(SECTOR_SIZE, 0xFF);
+    sector
+}
+
+/// PES packet with an MPEG-2 header carrying a PTS
+pub fn pes(stream_id: u8, payload: &[u8]) -> Vec<u8> {
+    let len = u16::try_from(payload.len() + 8).unwrap();
+    let mut packet = vec![0x00, 0x00, 0x01, stream_id];
+    packet.extend_from_slice(&len.to_be_bytes());
+    packet.extend_from_slice(&[0x81, 0x80, 5, 0x21, 0x00, 0x01, 0x00, 0x01]);
+    packet.extend_from_slice(payload);
+    packet
+}
+
+/// Audio packet of the given channel
+pub fn audio(channel: u8, data: &[u8]) -> Vec<u8> {
+    let mut payload = vec![0xA0 + channel];
+    payload.extend_from_slice(data);
+    pes(0xBD, &payload)
+}
+
+/// Video data spread over the packets of [`file`]
+pub fn video_data() -> Vec<u8> {
+    (0..3000u16).map(|i| i.to_le_bytes()[0]).collect()
+}
+
+/// File with a system header, interleaved video and two audio channels
+pub fn file() -> Vec<u8> {
+    let video = video_data();
+    let mut first = vec![
+        0x00, 0x00, 0x01, 0xBB, 0x00, 0x06, 0x80, 0x00, 0x01, 0x04, 0xE1, 0xFF,
+    ];
+    first.extend(pes(0xE0, &video[..1000]));
+
+    let mut bytes = sector(&first);
+    bytes.extend(sector(&audio(0, &[0xA0; 150])));
+    bytes.extend(sector(&pes(0xE0, &video[1000..2000])));
+    bytes.extend(sector(&audio(1, &[0xA1; 100])));
+    bytes.extend(sector(&audio(0, &[0xA0; 150])));
+    bytes.extend(sector(&pes(0xE0, &video[2000..])));
+    bytes.extend_from_slice(&[0x00, 0x00, 0x01, 0xB9]);
+    bytes
+}
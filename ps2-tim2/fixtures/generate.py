@@ -0,0 +1,66 @@
+import os, struct, zlib
+out = os.path.dirname(os.path.abspath(__file__)) + "/"
+
+def picture(fmt, w, h, pixels, clut=b'', colors=0, clut_type=0):
+    header_size = 48
+    hdr = struct.pack('<IIIHHBBBBHH', header_size + len(pixels) + len(clut), len(clut), len(pixels),
+                      header_size, colors, 0, 1, clut_type, fmt, w, h) + bytes(24)
+    return hdr + pixels + clut
+
+def tim2(*pictures):
+    return b'TIM2' + bytes([4, 0]) + struct.pack('<H', len(pictures)) + bytes(8) + b''.join(pictures)
+
+def png(w, h, rgba):
+    raw = b''.join(b'\0' + bytes(rgba[y * w * 4:(y + 1) * w * 4]) for y in range(h))
+    chunk = lambda kind, data: struct.pack('>I', len(data)) + kind + data + struct.pack('>I', zlib.crc32(kind + data))
+    return (b'\x89PNG\r\n\x1a\n' + chunk(b'IHDR', struct.pack('>IIBBBBB', w, h, 8, 6, 0, 0, 0))
+            + chunk(b'IDAT', zlib.compress(raw, 9)) + chunk(b'IEND', b''))
+
+alpha = lambda a: min(a * 255 // 128, 255)
+
+def c16(v):
+    ch = lambda s: ((v >> s) & 31) << 3 | ((v >> s) & 31) >> 2
+    return [ch(0), ch(5), ch(10), 255 if v & 0x8000 else 0]
+
+def write(name, data, w, h, rgba):
+    open(out + name + '.tm2', 'wb').write(data)
+    open(out + name + '.png', 'wb').write(png(w, h, rgba))
+
+# 32 bit: horizontal red ramp, vertical green ramp, alpha from transparent over half to fully opaque
+w, h = 8, 4
+px = [(x * 36, y * 85, 0xC0, [0x00, 0x20, 0x40, 0x80][y]) for y in range(h) for x in range(w)]
+write('rgba32', tim2(picture(3, w, h, bytes(sum(map(list, px), [])))), w, h,
+      sum([[r, g, b, alpha(a)] for r, g, b, a in px], []))
+
+# 24 bit
+px = [((x * 31) & 255, (y * 60) & 255, (x * y * 9) & 255) for y in range(h) for x in range(w)]
+write('rgb24', tim2(picture(2, w, h, bytes(sum(map(list, px), [])))), w, h,
+      sum([[r, g, b, 255] for r, g, b in px], []))
+
+# 16 bit: every 5 bit value across the three channels, the top row without the STP bit
+vals = []
+for y in range(h):
+    for x in range(w):
+        i = y * w + x
+        vals.append(i | (31 - i) << 5 | ((i * 7) & 31) << 10 | (0 if y == 0 else 0x8000))
+write('rgba16', tim2(picture(1, w, h, b''.join(struct.pack('<H', v) for v in vals))), w, h,
+      sum([c16(v) for v in vals], []))
+
+# 4 bit with a 16 bit CLUT, low nibble first
+palette = [(i * 2) | (i * 2 + 1) << 5 | (31 - i * 2) << 10 | 0x8000 for i in range(16)]
+palette[0] = 0
+idx = [(x + y * 3) % 16 for y in range(h) for x in range(w)]
+data = bytes(idx[i] | idx[i + 1] << 4 for i in range(0, len(idx), 2))
+clut = b''.join(struct.pack('<H', c) for c in palette)
+write('index4', tim2(picture(4, w, h, data, clut, 16, 1)), w, h, sum([c16(palette[i]) for i in idx], []))
+
+# 8 bit with a 32 bit CLUT in the CSM1 arrangement
+w, h = 16, 16
+colors = [(i, (i * 3) & 255, 255 - i, 0x80 if i % 5 else 0x40) for i in range(256)]
+stored = [None] * 256
+for i, c in enumerate(colors):
+    j = i + 8 if i % 32 in range(8, 16) else i - 8 if i % 32 in range(16, 24) else i
+    stored[j] = c
+idx = [(y * 16 + x) ^ (y & 3) for y in range(h) for x in range(w)]
+write('index8', tim2(picture(5, w, h, bytes(idx), bytes(sum(map(list, stored), [])), 256, 3)), w, h,
+      sum([[r, g, b, alpha(a)] for r, g, b, a in (colors[i] for i in idx)], []))
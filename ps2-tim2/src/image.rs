@@ -0,0 +1,546 @@
+use crate::{PixelFormat, Tim2Error};
+use ps2_bytes::{read_u16_le, read_u32_le};
+
+/// Size of the picture header without the optional mipmap header and user data
+const PICTURE_HEADER_SIZE: usize = 0x30;
+
+/// Size of the mipmap header without the sizes of the levels
+const MIPMAP_HEADER_SIZE: usize = 0x10;
+
+/// Most mipmap levels a picture can have, one per bit of its 16-bit width and height
+const MAX_MIP_COUNT: u8 = 16;
+
+/// Flag in the CLUT type marking the CLUT as stored in the linear CSM2 layout
+const CLUT_CSM2: u8 = 0x80;
+
+/// Single mipmap level of a picture
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Tim2MipLevel<'a> {
+    width: u16,
+    height: u16,
+    pixel_data: &'a [u8],
+}
+
+impl<'a> Tim2MipLevel<'a> {
+    /// Width and height in pixels
+    #[must_use]
+    pub fn dimensions(&self) -> (u16, u16) {
+        (self.width, self.height)
+    }
+
+    /// Raw pixel data of the level
+    #[must_use]
+    pub fn pixel_data(&self) -> &'a [u8] {
+        self.pixel_data
+    }
+}
+
+/// Single picture of a TIM2 file
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Tim2Image {
+    width: u16,
+    height: u16,
+    pixel_format: PixelFormat,
+    pict_format: u8,
+    clut_type: u8,
+    clut_colors: u16,
+    /// `GS_TEX0`, `GS_TEX1`, `GS_REGS` and `GS_TEXCLUT`, kept as is for encoding
+    gs_registers: [u8; 0x18],
+    /// `GS_MIPTBP1` and `GS_MIPTBP2` of the mipmap header
+    mip_registers: [u8; 0x10],
+    mip_sizes: Vec<u32>,
+    user_data: Vec<u8>,
+    image_data: Vec<u8>,
+    clut: Option<Vec<u8>>,
+}
+
+impl Tim2Image {
+    /// Parse the picture at the start of `bytes`, returning it and its total size
+    pub(crate) fn parse(bytes: &[u8]) -> Result<(Self, usize), Tim2Error> {
+        let header = bytes
+            .get(..PICTURE_HEADER_SIZE)
+            .ok_or(Tim2Error::Truncated)?;
+
+        let total_size = read_u32_le(header, 0x00) as usize;
+        let clut_size = read_u32_le(header, 0x04) as usize;
+        let image_size = read_u32_le(header, 0x08) as usize;
+        let header_size = usize::from(read_u16_le(header, 0x0C));
+        let mip_count = header[0x11];
+        let clut_type = header[0x12];
+        let pixel_format =
+            PixelFormat::from_image_type(header[0x13]).ok_or(Tim2Error::UnsupportedFormat)?;
+        let width = read_u16_le(header, 0x14);
+        let height = read_u16_le(header, 0x16);
+
+        if mip_count > MAX_MIP_COUNT {
+            return Err(Tim2Error::InvalidHeader);
+        }
+        let has_mipmaps = mip_count > 1;
+        let mip_header_size = if has_mipmaps {
+            (MIPMAP_HEADER_SIZE + usize::from(mip_count) * 4).next_multiple_of(16)
+        } else {
+            0
+        };
+        if header_size < PICTURE_HEADER_SIZE + mip_header_size
+            || total_size < header_size + image_size + clut_size
+        {
+            return Err(Tim2Error::InvalidHeader);
+        }
+        let header = bytes.get(..header_size).ok_or(Tim2Error::Truncated)?;
+
+        let mut mip_registers = [0; 0x10];
+        let mip_sizes = if has_mipmaps {
+            let mip_header = &header[PICTURE_HEADER_SIZE..];
+            mip_registers.copy_from_slice(&mip_header[..MIPMAP_HEADER_SIZE]);
+            let sizes = (0..usize::from(mip_count))
+                .map(|level| read_u32_le(mip_header, MIPMAP_HEADER_SIZE + level * 4))
+                .collect::<Vec<_>>();
+            if sizes.iter().map(|&size| size as usize).sum::<usize>() > image_size {
+                return Err(Tim2Error::InvalidHeader);
+            }
+            for (level, &size) in sizes.iter().enumerate() {
+                let pixels =
+                    usize::from((width >> level).max(1)) * usize::from((height >> level).max(1));
+                if (size as usize) < (pixels * pixel_format.bits_per_pixel()).div_ceil(8) {
+                    return Err(Tim2Error::InvalidHeader);
+                }
+            }
+
+            sizes
+        } else {
+            Vec::new()
+        };
+
+        let picture = bytes.get(..total_size).ok_or(Tim2Error::Truncated)?;
+        let image_data = picture[header_size..header_size + image_size].to_vec();
+        let clut = (clut_size != 0).then(|| {
+            picture[header_size + image_size..header_size + image_size + clut_size].to_vec()
+        });
+
+        let image = Self {
+            width,
+            height,
+            pixel_format,
+            pict_format: header[0x10],
+            clut_type,
+            clut_colors: read_u16_le(header, 0x0E),
+            gs_registers: header[0x18..PICTURE_HEADER_SIZE].try_into().unwrap(),
+            mip_registers,
+            mip_sizes,
+            user_data: header[PICTURE_HEADER_SIZE + mip_header_size..].to_vec(),
+            image_data,
+            clut,
+        };
+
+        let pixels = usize::from(width) * usize::from(height);
+        let expected_len = (pixels * pixel_format.bits_per_pixel()).div_ceil(8);
+        let clut_format = PixelFormat::from_clut_type(clut_type);
+        if image.pixel_data().len() < expected_len {
+            return Err(Tim2Error::InvalidHeader);
+        }
+        if pixel_format.is_indexed() && (image.clut.is_none() || clut_format.is_none()) {
+            return Err(Tim2Error::UnsupportedFormat);
+        }
+
+        Ok((image, total_size))
+    }
+
+    /// Append the encoded picture to `out`
+    pub(crate) fn encode(&self, out: &mut Vec<u8>) -> Result<(), Tim2Error> {
+        let to_u32 = |len: usize| u32::try_from(len).map_err(|_| Tim2Error::InvalidHeader);
+        let mip_count = match self.mip_sizes.len() {
+            0 => 1,
+            count => u8::try_from(count)
+                .ok()
+                .filter(|&count| count <= MAX_MIP_COUNT)
+                .ok_or(Tim2Error::InvalidHeader)?,
+        };
+
+        let mut mip_header = Vec::new();
+        if mip_count > 1 {
+            mip_header.extend_from_slice(&self.mip_registers);
+            for size in &self.mip_sizes {
+                mip_header.extend_from_slice(&size.to_le_bytes());
+            }
+            mip_header.resize(mip_header.len().next_multiple_of(16), 0);
+        }
+
+        let clut = self.clut.as_deref().unwrap_or_default();
+        let header_size = PICTURE_HEADER_SIZE + mip_header.len() + self.user_data.len();
+        let total_size = header_size + self.image_data.len() + clut.len();
+        let image_type = match self.pixel_format {
+            PixelFormat::Rgba16 => 1,
+            PixelFormat::Rgb24 => 2,
+            PixelFormat::Rgba32 => 3,
+            PixelFormat::Index4 => 4,
+            PixelFormat::Index8 => 5,
+        };
+
+        out.extend_from_slice(&to_u32(total_size)?.to_le_bytes());
+        out.extend_from_slice(&to_u32(clut.len())?.to_le_bytes());
+        out.extend_from_slice(&to_u32(self.image_data.len())?.to_le_bytes());
+        out.extend_from_slice(
+            &u16::try_from(header_size)
+                .map_err(|_| Tim2Error::InvalidHeader)?
+                .to_le_bytes(),
+        );
+        out.extend_from_slice(&self.clut_colors.to_le_bytes());
+        out.extend_from_slice(&[self.pict_format, mip_count, self.clut_type, image_type]);
+        out.extend_from_slice(&self.width.to_le_bytes());
+        out.extend_from_slice(&self.height.to_le_bytes());
+        out.extend_from_slice(&self.gs_registers);
+        out.extend_from_slice(&mip_header);
+        out.extend_from_slice(&self.user_data);
+        out.extend_from_slice(&self.image_data);
+        out.extend_from_slice(clut);
+
+        Ok(())
+    }
+
+    /// Width in pixels
+    #[must_use]
+    pub fn width(&self) -> u16 {
+        self.width
+    }
+
+    /// Height in pixels
+    #[must_use]
+    pub fn height(&self) -> u16 {
+        self.height
+    }
+
+    /// Format of the pixels
+    #[must_use]
+    pub fn pixel_format(&self) -> PixelFormat {
+        self.pixel_format
+    }
+
+    /// Format of the colours in the CLUT, `None` if the image has no CLUT
+    #[must_use]
+    pub fn clut_format(&self) -> Option<PixelFormat> {
+        self.clut.as_ref()?;
+        PixelFormat::from_clut_type(self.clut_type)
+    }
+
+    /// Number of colours in the CLUT
+    #[must_use]
+    pub fn clut_colors(&self) -> u16 {
+        self.clut_colors
+    }
+
+    /// Raw contents of the colour lookup table, as stored in the file
+    #[must_use]
+    pub fn clut(&self) -> Option<&[u8]> {
+        self.clut.as_deref()
+    }
+
+    /// Raw pixel data of the image, excluding any further mipmap levels
+    #[must_use]
+    pub fn pixel_data(&self) -> &[u8] {
+        match self.mip_sizes.first() {
+            Some(&size) => &self.image_data[..size as usize],
+            None => &self.image_data,
+        }
+    }
+
+    /// Number of mipmap levels including the base image
+    #[must_use]
+    pub fn mip_count(&self) -> u8 {
+        u8::try_from(self.mip_sizes.len().max(1)).unwrap_or(u8::MAX)
+    }
+
+    /// Mipmap level `level`, level 0 is the base image
+    ///
+    /// TIM2 stores the levels from the largest to the smallest, each level has half the dimensions of the
+    /// previous one
+    ///
+    /// # Errors
+    ///
+    /// - The image has no such level
+    pub fn mip_level(&self, level: u8) -> Result<Tim2MipLevel<'_>, Tim2Error> {
+        if level >= self.mip_count() {
+            return Err(Tim2Error::InvalidMipLevel);
+        }
+
+        let pixel_data = if self.mip_sizes.is_empty() {
+            self.image_data.as_slice()
+        } else {
+            let level = usize::from(level);
+            let offset = self.mip_sizes[..level]
+                .iter()
+                .map(|&size| size as usize)
+                .sum::<usize>();
+            &self.image_data[offset..offset + self.mip_sizes[level] as usize]
+        };
+
+        Ok(Tim2MipLevel {
+            width: (self.width >> level).max(1),
+            height: (self.height >> level).max(1),
+            pixel_data,
+        })
+    }
+
+    /// Decode the image into a buffer of `width * height` RGBA8888 pixels, row by row from the top left
+    ///
+    /// The alpha channel is scaled from the PS2 range (`0x80` is opaque) to the full range. 16 bit
+    /// colours are opaque if their alpha bit is set and fully transparent otherwise
+    ///
+    /// # Errors
+    ///
+    /// - A pixel references a colour outside of the CLUT
+    pub fn to_rgba8888(&self) -> Result<Vec<u8>, Tim2Error> {
+        let pixels = usize::from(self.width) * usize::from(self.height);
+        let data = self.pixel_data();
+        let mut rgba = Vec::with_capacity(pixels * 4);
+
+        if self.pixel_format.is_indexed() {
+            let palette = self.palette();
+            for pixel in 0..pixels {
+                let index = match self.pixel_format {
+                    // The first pixel is stored in the low nibble
+                    PixelFormat::Index4 => data[pixel / 2] >> ((pixel % 2) * 4) & 0x0F,
+                    _ => data[pixel],
+                };
+                let color = palette
+                    .get(usize::from(index))
+                    .ok_or(Tim2Error::InvalidHeader)?;
+                rgba.extend_from_slice(color);
+            }
+        } else {
+            let bytes_per_pixel = self.pixel_format.bits_per_pixel() / 8;
+            for color in data.chunks_exact(bytes_per_pixel).take(pixels) {
+                rgba.extend_from_slice(&decode_color(self.pixel_format, color));
+            }
+        }
+
+        Ok(rgba)
+    }
+
+    /// Decode the CLUT into RGBA8888 colours, undoing the CSM1 arrangement of 256 colour CLUTs
+    fn palette(&self) -> Vec<[u8; 4]> {
+        let (Some(clut), Some(format)) = (self.clut.as_deref(), self.clut_format()) else {
+            return Vec::new();
+        };
+        let colors = clut
+            .chunks_exact(format.bits_per_pixel() / 8)
+            .map(|color| decode_color(format, color))
+            .collect::<Vec<_>>();
+
+        if self.pixel_format != PixelFormat::Index8 || self.clut_type & CLUT_CSM2 != 0 {
+            return colors;
+        }
+
+        // CSM1 stores the colours in blocks of 8x2, swapping the second and third group of 8 in every 32 colours
+        (0..colors.len())
+            .filter_map(|index| {
+                let swizzled = match index % 32 {
+                    8..=15 => index + 8,
+                    16..=23 => index - 8,
+                    _ => index,
+                };
+                colors.get(swizzled).copied()
+            })
+            .collect()
+    }
+}
+
+/// Decode a single 16, 24 or 32 bit colour into RGBA8888
+fn decode_color(format: PixelFormat, bytes: &[u8]) -> [u8; 4] {
+    let scale_alpha = |alpha: u8| u8::try_from((u16::from(alpha) * 255 / 0x80).min(255)).unwrap();
+    match format {
+        PixelFormat::Rgba16 => {
+            let color = read_u16_le(bytes, 0);
+            let channel = |shift: u16| {
+                let value = (color >> shift & 0x1F).to_le_bytes()[0];
+                value << 3 | value >> 2
+            };
+            let alpha = if color & 0x8000 == 0 { 0 } else { 255 };
+            [channel(0), channel(5), channel(10), alpha]
+        }
+        PixelFormat::Rgb24 => [bytes[0], bytes[1], bytes[2], 255],
+        _ => [bytes[0], bytes[1], bytes[2], scale_alpha(bytes[3])],
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        test_tim2::{decode_png, Tim2Builder},
+        PixelFormat, Tim2, Tim2Error,
+    };
+
+    #[test]
+    fn pixel_formats() {
+        for (format, bytes_per_row, clut_colors) in [
+            (PixelFormat::Rgba32, 32, None),
+            (PixelFormat::Rgb24, 24, None),
+            (PixelFormat::Rgba16, 16, None),
+            (PixelFormat::Index8, 8, Some(256)),
+            (PixelFormat::Index4, 4, Some(16)),
+        ] {
+            let bytes = Tim2Builder::new().image(format, 8, 4).build();
+            let tim2 = Tim2::parse(&bytes).unwrap();
+            let image = &tim2.images()[0];
+
+            assert_eq!(image.width(), 8);
+            assert_eq!(image.height(), 4);
+            assert_eq!(image.pixel_format(), format);
+            assert_eq!(image.pixel_data().len(), bytes_per_row * 4);
+            assert_eq!(image.pixel_data()[1], 1);
+
+            if let Some(colors) = clut_colors {
+                assert_eq!(image.clut_format(), Some(PixelFormat::Rgba32));
+                assert_eq!(image.clut_colors(), colors);
+                assert_eq!(image.clut().unwrap().len(), usize::from(colors) * 4);
+            } else {
+                assert_eq!(image.clut_format(), None);
+                assert_eq!(image.clut(), None);
+            }
+        }
+    }
+
+    #[test]
+    fn to_rgba8888() {
+        let decode = |format| {
+            let bytes = Tim2Builder::new().image(format, 4, 2).build();
+            Tim2::parse(&bytes).unwrap().images()[0]
+                .to_rgba8888()
+                .unwrap()
+        };
+
+        let rgba32 = decode(PixelFormat::Rgba32);
+        assert_eq!(rgba32.len(), 4 * 2 * 4);
+        assert_eq!(rgba32[..8], [0, 1, 2, 5, 4, 5, 6, 13]);
+
+        let rgb24 = decode(PixelFormat::Rgb24);
+        assert_eq!(rgb24[..8], [0, 1, 2, 255, 3, 4, 5, 255]);
+
+        // 0x0100 and 0x0302 split into 5 bit channels
+        let rgba16 = decode(PixelFormat::Rgba16);
+        assert_eq!(rgba16[..8], [0, 66, 0, 0, 16, 198, 0, 0]);
+
+        // The bytes 0x00 and 0x01 hold the indices 0, 0, 1 and 0
+        let index4 = decode(PixelFormat::Index4);
+        assert_eq!(index4.len(), 4 * 2 * 4);
+        assert_eq!(
+            index4[..16],
+            [0, 255, 0, 255, 0, 255, 0, 255, 1, 254, 2, 255, 0, 255, 0, 255]
+        );
+    }
+
+    // The fixtures and their PNGs both come from `fixtures/generate.py`, so this only checks the decoder
+    // against that script's reading of the format, not against images exported by PS2 tools
+    #[test]
+    fn reference_images() {
+        for (name, tim2, png) in [
+            (
+                "rgba32",
+                &include_bytes!("../fixtures/rgba32.tm2")[..],
+                &include_bytes!("../fixtures/rgba32.png")[..],
+            ),
+            (
+                "rgb24",
+                include_bytes!("../fixtures/rgb24.tm2"),
+                include_bytes!("../fixtures/rgb24.png"),
+            ),
+            (
+                "rgba16",
+                include_bytes!("../fixtures/rgba16.tm2"),
+                include_bytes!("../fixtures/rgba16.png"),
+            ),
+            (
+                "index4",
+                include_bytes!("../fixtures/index4.tm2"),
+                include_bytes!("../fixtures/index4.png"),
+            ),
+            (
+                "index8",
+                include_bytes!("../fixtures/index8.tm2"),
+                include_bytes!("../fixtures/index8.png"),
+            ),
+        ] {
+            let tim2 = Tim2::parse(tim2).unwrap();
+            let image = &tim2.images()[0];
+            let (width, height, pixels) = decode_png(png);
+            assert_eq!(
+                (u32::from(image.width()), u32::from(image.height())),
+                (width, height),
+                "{name}"
+            );
+            assert_eq!(image.to_rgba8888().unwrap(), pixels, "{name}");
+        }
+    }
+
+    #[test]
+    fn clut_swizzle() {
+        let bytes = Tim2Builder::new().image(PixelFormat::Index8, 16, 2).build();
+        let mut tim2 = Tim2::parse(&bytes).unwrap();
+        let rgba = tim2.images()[0].to_rgba8888().unwrap();
+
+        // Index 8 is stored at position 16 and vice versa, the CLUT stores colour `i` at position `i`
+        let color = |pixel: usize| &rgba[pixel * 4..pixel * 4 + 4];
+        assert_eq!(color(7), [7, 248, 14, 255]);
+        assert_eq!(color(8), [16, 239, 32, 255]);
+        assert_eq!(color(16), [8, 247, 16, 255]);
+        assert_eq!(color(24), [24, 231, 48, 255]);
+
+        // CSM2 CLUTs are linear
+        tim2.images[0].clut_type |= super::CLUT_CSM2;
+        let rgba = tim2.images()[0].to_rgba8888().unwrap();
+        assert_eq!(rgba[8 * 4..9 * 4], [8, 247, 16, 255]);
+
+        // Indices beyond the CLUT
+        tim2.images[0].clut.as_mut().unwrap().truncate(4 * 4);
+        assert_eq!(
+            tim2.images()[0].to_rgba8888(),
+            Err(Tim2Error::InvalidHeader)
+        );
+    }
+
+    #[test]
+    fn mipmaps() {
+        let bytes = Tim2Builder::new()
+            .mipmapped_image(PixelFormat::Rgba32, 32, 16, 4)
+            .build();
+        let tim2 = Tim2::parse(&bytes).unwrap();
+        let image = &tim2.images()[0];
+
+        assert_eq!(image.mip_count(), 4);
+        assert_eq!(image.mip_level(0).unwrap().pixel_data(), image.pixel_data());
+        let mut previous = image.mip_level(0).unwrap();
+        assert_eq!(previous.dimensions(), (32, 16));
+        for level in 1..4 {
+            let mip = image.mip_level(level).unwrap();
+            let (width, height) = mip.dimensions();
+            assert_eq!((width * 2, height * 2), previous.dimensions());
+            assert_eq!(
+                mip.pixel_data().len(),
+                usize::from(width) * usize::from(height) * 4
+            );
+            assert_eq!(mip.pixel_data()[1], 1);
+            previous = mip;
+        }
+        assert_eq!(image.mip_level(4), Err(Tim2Error::InvalidMipLevel));
+
+        // Levels down to 1x1 pixels, of which a 16-bit size has at most 16
+        let bytes = Tim2Builder::new()
+            .mipmapped_image(PixelFormat::Rgba32, 4, 4, 16)
+            .build();
+        let tim2 = Tim2::parse(&bytes).unwrap();
+        assert_eq!(tim2.images()[0].mip_level(15).unwrap().dimensions(), (1, 1));
+        let bytes = Tim2Builder::new()
+            .mipmapped_image(PixelFormat::Rgba32, 4, 4, 17)
+            .build();
+        assert_eq!(Tim2::parse(&bytes).unwrap_err(), Tim2Error::InvalidHeader);
+
+        // Images without a mipmap header only have the base level
+        let bytes = Tim2Builder::new().image(PixelFormat::Rgba32, 4, 4).build();
+        let tim2 = Tim2::parse(&bytes).unwrap();
+        assert_eq!(tim2.images()[0].mip_count(), 1);
+        assert_eq!(tim2.images()[0].mip_level(0).unwrap().dimensions(), (4, 4));
+        assert_eq!(
+            tim2.images()[0].mip_level(1),
+            Err(Tim2Error::InvalidMipLevel)
+        );
+    }
+}
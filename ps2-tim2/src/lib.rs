@@ -0,0 +1,305 @@
+#![doc = include_str!("../README.md")]
+#![forbid(missing_docs, unsafe_code)]
+#![warn(clippy::all, clippy::pedantic)]
+
+use ps2_bytes::read_u16_le;
+use std::{
+    error::Error as StdError,
+    fmt::{self, Display},
+};
+
+mod image;
+#[cfg(test)]
+mod test_tim2;
+
+pub use self::image::{Tim2Image, Tim2MipLevel};
+
+/// Magic at the start of the file
+const MAGIC: &[u8] = b"TIM2";
+
+/// Size of the file header
+const FILE_HEADER_SIZE: usize = 16;
+
+/// Offset of the first picture if the file uses 128 byte alignment
+const ALIGNED_HEADER_SIZE: usize = 128;
+
+/// Errors that might occur when parsing a TIM2 file
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Tim2Error {
+    /// The file ends in the middle of a header or picture
+    Truncated,
+
+    /// The file doesn't start with the TIM2 magic
+    InvalidMagic,
+
+    /// A picture header contains inconsistent sizes
+    InvalidHeader,
+
+    /// A picture uses an unknown pixel or CLUT format
+    UnsupportedFormat,
+
+    /// The requested mipmap level doesn't exist
+    InvalidMipLevel,
+}
+
+impl Display for Tim2Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+impl StdError for Tim2Error {}
+
+/// Format of the pixels of an image or the colours of a CLUT
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum PixelFormat {
+    /// 16 bit colour (5 bits per colour channel, 1 bit alpha)
+    Rgba16,
+
+    /// 24 bit colour without alpha
+    Rgb24,
+
+    /// 32 bit colour
+    Rgba32,
+
+    /// 4 bit index into a 16 colour CLUT
+    Index4,
+
+    /// 8 bit index into a 256 colour CLUT
+    Index8,
+}
+
+impl PixelFormat {
+    /// Decode the image type of a picture header
+    fn from_image_type(image_type: u8) -> Option<Self> {
+        match image_type {
+            1 => Some(Self::Rgba16),
+            2 => Some(Self::Rgb24),
+            3 => Some(Self::Rgba32),
+            4 => Some(Self::Index4),
+            5 => Some(Self::Index8),
+            _ => None,
+        }
+    }
+
+    /// Decode the colour type of a CLUT, ignoring the storage mode flags
+    fn from_clut_type(clut_type: u8) -> Option<Self> {
+        match clut_type & 0x3F {
+            1 => Some(Self::Rgba16),
+            2 => Some(Self::Rgb24),
+            3 => Some(Self::Rgba32),
+            _ => None,
+        }
+    }
+
+    /// Number of bits per pixel
+    #[must_use]
+    pub fn bits_per_pixel(self) -> usize {
+        match self {
+            Self::Rgba16 => 16,
+            Self::Rgb24 => 24,
+            Self::Rgba32 => 32,
+            Self::Index4 => 4,
+            Self::Index8 => 8,
+        }
+    }
+
+    /// Whether the pixels are indices into a CLUT
+    #[must_use]
+    pub fn is_indexed(self) -> bool {
+        matches!(self, Self::Index4 | Self::Index8)
+    }
+}
+
+/// Parsed TIM2 file
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Tim2 {
+    version: u8,
+    aligned: bool,
+    images: Vec<Tim2Image>,
+}
+
+impl Tim2 {
+    /// Parse a TIM2 file
+    ///
+    /// # Errors
+    ///
+    /// - The file is truncated
+    /// - The magic is invalid
+    /// - A picture header is invalid or uses an unsupported format
+    pub fn parse(bytes: &[u8]) -> Result<Self, Tim2Error> {
+        let header = bytes.get(..FILE_HEADER_SIZE).ok_or(Tim2Error::Truncated)?;
+        if !header.starts_with(MAGIC) {
+            return Err(Tim2Error::InvalidMagic);
+        }
+
+        let version = header[4];
+        // Format ID 1 aligns the pictures to 128 bytes, the file header is padded accordingly
+        let aligned = header[5] == 1;
+        let count = read_u16_le(header, 6);
+
+        let mut offset = if aligned {
+            ALIGNED_HEADER_SIZE
+        } else {
+            FILE_HEADER_SIZE
+        };
+        let mut images = Vec::with_capacity(usize::from(count));
+        for _ in 0..count {
+            let picture = bytes.get(offset..).ok_or(Tim2Error::Truncated)?;
+            let (image, len) = Tim2Image::parse(picture)?;
+            images.push(image);
+            offset += len;
+        }
+
+        Ok(Self {
+            version,
+            aligned,
+            images,
+        })
+    }
+
+    /// Serialise the file back into its binary form
+    ///
+    /// Unknown header fields and user data are written back as they were parsed
+    ///
+    /// # Errors
+    ///
+    /// - There are too many pictures or a picture is too large for the header fields
+    pub fn encode(&self) -> Result<Vec<u8>, Tim2Error> {
+        let count = u16::try_from(self.images.len()).map_err(|_| Tim2Error::InvalidHeader)?;
+
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&[self.version, u8::from(self.aligned)]);
+        bytes.extend_from_slice(&count.to_le_bytes());
+        bytes.resize(
+            if self.aligned {
+                ALIGNED_HEADER_SIZE
+            } else {
+                FILE_HEADER_SIZE
+            },
+            0,
+        );
+        for image in &self.images {
+            image.encode(&mut bytes)?;
+        }
+
+        Ok(bytes)
+    }
+
+    /// Version of the format
+    #[must_use]
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    /// Whether the pictures are aligned to 128 bytes instead of 16 bytes
+    #[must_use]
+    pub fn is_aligned(&self) -> bool {
+        self.aligned
+    }
+
+    /// Pictures contained in the file
+    #[must_use]
+    pub fn images(&self) -> &[Tim2Image] {
+        &self.images
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{test_tim2::Tim2Builder, PixelFormat, Tim2, Tim2Error};
+
+    #[test]
+    fn parse() {
+        let bytes = Tim2Builder::new()
+            .image(PixelFormat::Rgba32, 4, 2)
+            .image(PixelFormat::Index8, 16, 16)
+            .build();
+
+        let tim2 = Tim2::parse(&bytes).unwrap();
+        assert_eq!(tim2.version(), 4);
+        assert!(!tim2.is_aligned());
+        assert_eq!(tim2.images().len(), 2);
+        assert_eq!(tim2.images()[0].width(), 4);
+        assert_eq!(tim2.images()[1].pixel_format(), PixelFormat::Index8);
+    }
+
+    #[test]
+    fn aligned() {
+        let bytes = Tim2Builder::new()
+            .aligned()
+            .image(PixelFormat::Rgb24, 8, 8)
+            .build();
+
+        let tim2 = Tim2::parse(&bytes).unwrap();
+        assert!(tim2.is_aligned());
+        assert_eq!(tim2.images()[0].pixel_data().len(), 8 * 8 * 3);
+    }
+
+    #[test]
+    fn encode() {
+        for format in [
+            PixelFormat::Rgba16,
+            PixelFormat::Rgb24,
+            PixelFormat::Rgba32,
+            PixelFormat::Index4,
+            PixelFormat::Index8,
+        ] {
+            let bytes = Tim2Builder::new()
+                .image(format, 16, 8)
+                .mipmapped_image(format, 16, 16, 3)
+                .build();
+            let tim2 = Tim2::parse(&bytes).unwrap();
+
+            let encoded = tim2.encode().unwrap();
+            assert_eq!(encoded, bytes);
+            assert_eq!(Tim2::parse(&encoded).unwrap(), tim2);
+        }
+
+        let bytes = Tim2Builder::new()
+            .aligned()
+            .image(PixelFormat::Index8, 8, 8)
+            .build();
+        let tim2 = Tim2::parse(&bytes).unwrap();
+        assert_eq!(tim2.encode().unwrap(), bytes);
+
+        // Register values survive the round trip
+        let mut bytes = bytes;
+        bytes[128 + 0x18] = 0x12;
+        bytes[128 + 0x2F] = 0x34;
+        assert_eq!(Tim2::parse(&bytes).unwrap().encode().unwrap(), bytes);
+
+        // As does user data after the picture header
+        let mut bytes = Tim2Builder::new().image(PixelFormat::Rgba32, 4, 4).build();
+        bytes[16] += 16;
+        bytes[16 + 0x0C] += 16;
+        bytes.splice(16 + 0x30..16 + 0x30, [0xAB; 16]);
+        assert_eq!(Tim2::parse(&bytes).unwrap().encode().unwrap(), bytes);
+    }
+
+    #[test]
+    fn reject_invalid() {
+        let bytes = Tim2Builder::new().image(PixelFormat::Rgba16, 4, 4).build();
+
+        assert_eq!(Tim2::parse(&bytes[..8]), Err(Tim2Error::Truncated));
+        assert_eq!(
+            Tim2::parse(&bytes[..bytes.len() - 1]),
+            Err(Tim2Error::Truncated)
+        );
+
+        let mut corrupted = bytes.clone();
+        corrupted[0] = b'X';
+        assert_eq!(Tim2::parse(&corrupted), Err(Tim2Error::InvalidMagic));
+
+        // Unknown image type
+        let mut corrupted = bytes.clone();
+        corrupted[16 + 0x13] = 6;
+        assert_eq!(Tim2::parse(&corrupted), Err(Tim2Error::UnsupportedFormat));
+
+        // Image data smaller than the dimensions require
+        let mut corrupted = bytes;
+        corrupted[16 + 0x14] = 8;
+        assert_eq!(Tim2::parse(&corrupted), Err(Tim2Error::InvalidHeader));
+    }
+}
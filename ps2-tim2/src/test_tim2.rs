@@ -0,0 +1,199 @@
+//! `Tim2Builder` for TIM2 files of any pixel format and mipmap count, and a PNG reader for the `fixtures`
+//!
+//! Pixel data is filled with incrementing bytes, indexed images get a 32 bit CLUT where colour `i` is
+//! `(i, 255 - i, i * 2, 0x80)`
+
+use crate::PixelFormat;
+
+struct Image {
+    format: PixelFormat,
+    width: u16,
+    height: u16,
+    mip_count: u8,
+}
+
+pub struct Tim2Builder {
+    aligned: bool,
+    images: Vec<Image>,
+}
+
+impl Tim2Builder {
+    pub fn new() -> Self {
+        Self {
+            aligned: false,
+            images: Vec::new(),
+        }
+    }
+
+    pub fn aligned(mut self) -> Self {
+        self.aligned = true;
+        self
+    }
+
+    pub fn image(self, format: PixelFormat, width: u16, height: u16) -> Self {
+        self.mipmapped_image(format, width, height, 1)
+    }
+
+    /// Image with `mip_count` levels, each padded to 16 bytes
+    pub fn mipmapped_image(
+        mut self,
+        format: PixelFormat,
+        width: u16,
+        height: u16,
+        mip_count: u8,
+    ) -> Self {
+        self.images.push(Image {
+            format,
+            width,
+            height,
+            mip_count,
+        });
+        self
+    }
+
+    pub fn build(self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(b"TIM2");
+        bytes.push(4);
+        bytes.push(u8::from(self.aligned));
+        bytes.extend_from_slice(&u16::try_from(self.images.len()).unwrap().to_le_bytes());
+        bytes.resize(if self.aligned { 128 } else { 16 }, 0);
+
+        for image in &self.images {
+            let mut image_data = Vec::new();
+            let mut mip_sizes = Vec::new();
+            for level in 0..image.mip_count {
+                let shrink =
+                    |size: u16| usize::from(size.checked_shr(level.into()).unwrap_or(0).max(1));
+                let pixels = shrink(image.width) * shrink(image.height);
+                let mut data = (0..(pixels * image.format.bits_per_pixel()).div_ceil(8))
+                    .map(|i: usize| i.to_le_bytes()[0])
+                    .collect::<Vec<_>>();
+                if image.mip_count > 1 {
+                    data.resize(data.len().next_multiple_of(16), 0);
+                }
+                mip_sizes.push(u32::try_from(data.len()).unwrap());
+                image_data.extend_from_slice(&data);
+            }
+            let mut mip_header = Vec::new();
+            if image.mip_count > 1 {
+                mip_header.resize(0x10, 0);
+                for size in mip_sizes {
+                    mip_header.extend_from_slice(&size.to_le_bytes());
+                }
+                mip_header.resize(mip_header.len().next_multiple_of(16), 0);
+            }
+            let header_size = 48 + mip_header.len();
+            let (image_type, colors) = match image.format {
+                PixelFormat::Rgba16 => (1, 0),
+                PixelFormat::Rgb24 => (2, 0),
+                PixelFormat::Rgba32 => (3, 0),
+                PixelFormat::Index4 => (4, 16),
+                PixelFormat::Index8 => (5, 256),
+            };
+            let clut = (0..colors)
+                .flat_map(|i: u16| {
+                    let i = i.to_le_bytes()[0];
+                    [i, 255 - i, i.wrapping_mul(2), 0x80]
+                })
+                .collect::<Vec<_>>();
+
+            let u32 = |value: usize| u32::try_from(value).unwrap().to_le_bytes();
+            bytes.extend_from_slice(&u32(header_size + image_data.len() + clut.len()));
+            bytes.extend_from_slice(&u32(clut.len()));
+            bytes.extend_from_slice(&u32(image_data.len()));
+            bytes.extend_from_slice(&u16::try_from(header_size).unwrap().to_le_bytes());
+            bytes.extend_from_slice(&colors.to_le_bytes());
+            bytes.extend_from_slice(&[
+                0,
+                image.mip_count,
+                if colors == 0 { 0 } else { 3 },
+                image_type,
+            ]);
+            bytes.extend_from_slice(&image.width.to_le_bytes());
+            bytes.extend_from_slice(&image.height.to_le_bytes());
+            bytes.extend_from_slice(&[0; 24]);
+            bytes.extend_from_slice(&mip_header);
+            bytes.extend_from_slice(&image_data);
+            bytes.extend_from_slice(&clut);
+        }
+
+        bytes
+    }
+}
+
+/// Decode an 8 bit RGBA PNG into its width, height and pixels
+///
+/// Only what the reference images in `fixtures` use is supported
+pub fn decode_png(bytes: &[u8]) -> (u32, u32, Vec<u8>) {
+    use flate2::read::ZlibDecoder;
+    use std::io::Read;
+
+    assert_eq!(bytes[..8], *b"\x89PNG\r\n\x1a\n");
+    let be_u32 = |offset: usize| u32::from_be_bytes(bytes[offset..offset + 4].try_into().unwrap());
+    let (mut width, mut height) = (0, 0);
+    let mut compressed = Vec::new();
+    let mut offset = 8;
+    while offset < bytes.len() {
+        let len = be_u32(offset) as usize;
+        let data = &bytes[offset + 8..offset + 8 + len];
+        match &bytes[offset + 4..offset + 8] {
+            b"IHDR" => {
+                width = be_u32(offset + 8);
+                height = be_u32(offset + 12);
+                // 8 bit RGBA, not interlaced
+                assert_eq!(data[8..], [8, 6, 0, 0, 0]);
+            }
+            b"IDAT" => compressed.extend_from_slice(data),
+            _ => {}
+        }
+        offset += 12 + len;
+    }
+
+    let mut filtered = Vec::new();
+    ZlibDecoder::new(&compressed[..])
+        .read_to_end(&mut filtered)
+        .unwrap();
+    let stride = width as usize * 4;
+    let mut pixels = vec![0u8; stride * height as usize];
+    for (y, row) in filtered.chunks_exact(stride + 1).enumerate() {
+        for x in 0..stride {
+            let left = if x >= 4 {
+                pixels[y * stride + x - 4]
+            } else {
+                0
+            };
+            let up = if y > 0 {
+                pixels[(y - 1) * stride + x]
+            } else {
+                0
+            };
+            let up_left = if x >= 4 && y > 0 {
+                pixels[(y - 1) * stride + x - 4]
+            } else {
+                0
+            };
+            let predicted = match row[0] {
+                0 => 0,
+                1 => left,
+                2 => up,
+                3 => u8::midpoint(left, up),
+                4 => {
+                    let estimate = i16::from(left) + i16::from(up) - i16::from(up_left);
+                    let distance = |value: u8| (estimate - i16::from(value)).abs();
+                    if distance(left) <= distance(up) && distance(left) <= distance(up_left) {
+                        left
+                    } else if distance(up) <= distance(up_left) {
+                        up
+                    } else {
+                        up_left
+                    }
+                }
+                filter => panic!("unknown PNG filter {filter}"),
+            };
+            pixels[y * stride + x] = row[x + 1].wrapping_add(predicted);
+        }
+    }
+
+    (width, height, pixels)
+}
@@ -0,0 +1,129 @@
+//! SPU2 ADPCM as used by VAG files
+
+/// Size of a single ADPCM block
+pub const BLOCK_SIZE: usize = 16;
+
+/// Number of samples encoded in a block
+pub const SAMPLES_PER_BLOCK: usize = 28;
+
+/// Prediction filter coefficients, in 1/64
+const COEFFICIENTS: [(i32, i32); 5] = [(0, 0), (60, 0), (115, -52), (98, -55), (122, -60)];
+
+/// Block flag marking the start of the loop
+pub(crate) const FLAG_LOOP_START: u8 = 0x04;
+
+/// Block flag marking the end of the loop (or of the sound, if the repeat flag isn't set)
+pub(crate) const FLAG_LOOP_END: u8 = 0x01;
+
+/// Block flag telling the SPU2 to jump back to the loop start at the loop end
+pub(crate) const FLAG_LOOP_REPEAT: u8 = 0x02;
+
+/// Flags of the terminating block that isn't played anymore
+pub(crate) const FLAG_STOP: u8 = 0x07;
+
+/// Decoder for SPU2 ADPCM blocks, carrying the prediction history from one block to the next
+///
+/// Every block starts with the shift and filter byte and the flag byte, followed by 28 4 bit samples. Shifts
+/// above 12 behave like 9 on the hardware, unknown filters are clamped to the last one
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct AdpcmDecoder {
+    history: [i32; 2],
+}
+
+impl AdpcmDecoder {
+    /// Create a decoder at the start of a stream
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Decode a single block into its samples
+    pub fn decode_block(&mut self, block: &[u8; BLOCK_SIZE]) -> [i16; SAMPLES_PER_BLOCK] {
+        let (shift, coefficients) = block_parameters(block[0]);
+        let mut samples = [0; SAMPLES_PER_BLOCK];
+        let nibbles = block[2..].iter().flat_map(|&byte| [byte & 0x0F, byte >> 4]);
+        for (sample, nibble) in samples.iter_mut().zip(nibbles) {
+            // Place the nibble in the top bits to sign-extend it
+            let nibble = i8::from_le_bytes([nibble << 4]) >> 4;
+            *sample = self.sample(nibble, shift, coefficients);
+        }
+        samples
+    }
+
+    /// Reconstruct the next sample from its 4 bit residual
+    fn sample(&mut self, nibble: i8, shift: u8, (f0, f1): (i32, i32)) -> i16 {
+        let residual = (i32::from(nibble) << 12) >> shift;
+        let sample = (residual + self.predict(f0, f1)).clamp(i16::MIN.into(), i16::MAX.into());
+        self.history = [sample, self.history[0]];
+        i16::try_from(sample).unwrap_or_default()
+    }
+
+    /// Predict the next sample from the previous two
+    fn predict(self, f0: i32, f1: i32) -> i32 {
+        (self.history[0] * f0 + self.history[1] * f1 + 32) >> 6
+    }
+}
+
+/// ADPCM encoder searching the filter and shift with the least error for each block
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct Encoder {
+    decoder: AdpcmDecoder,
+}
+
+impl Encoder {
+    /// Encode 28 samples into a block with the given flags
+    pub(crate) fn encode_block(
+        &mut self,
+        samples: &[i16; SAMPLES_PER_BLOCK],
+        flags: u8,
+    ) -> [u8; BLOCK_SIZE] {
+        let mut best = (i64::MAX, 0, 0, [0; SAMPLES_PER_BLOCK], self.decoder);
+        for (filter, &coefficients) in COEFFICIENTS.iter().enumerate() {
+            for shift in 0..=12 {
+                let mut decoder = self.decoder;
+                let mut nibbles = [0; SAMPLES_PER_BLOCK];
+                let mut error = 0;
+                for (nibble, &sample) in nibbles.iter_mut().zip(samples) {
+                    let (f0, f1) = coefficients;
+                    let step = 1 << (12 - shift);
+                    let residual = i32::from(sample) - decoder.predict(f0, f1);
+                    // Round to the nearest step, the decoder reconstructs `nibble * step`
+                    let quantised = (residual + step / 2).div_euclid(step).clamp(-8, 7);
+                    *nibble = i8::try_from(quantised).unwrap_or_default();
+
+                    let reconstructed = decoder.sample(*nibble, shift, coefficients);
+                    error += (i64::from(sample) - i64::from(reconstructed)).pow(2);
+                }
+
+                if error < best.0 {
+                    best = (error, filter, shift, nibbles, decoder);
+                }
+            }
+        }
+
+        let (_, filter, shift, nibbles, decoder) = best;
+        self.decoder = decoder;
+
+        let mut block = [0; BLOCK_SIZE];
+        #[allow(clippy::cast_possible_truncation)] // There are only 5 filters
+        let filter = filter as u8;
+        block[0] = (filter << 4) | shift;
+        block[1] = flags;
+        for (byte, pair) in block[2..].iter_mut().zip(nibbles.chunks_exact(2)) {
+            let [low] = pair[0].to_le_bytes();
+            let [high] = pair[1].to_le_bytes();
+            *byte = (low & 0x0F) | (high << 4);
+        }
+        block
+    }
+}
+
+/// Shift and filter coefficients of a block
+///
+/// Shifts above 12 behave like 9 on the hardware, unknown filters are clamped to the last one
+fn block_parameters(shift_filter: u8) -> (u8, (i32, i32)) {
+    let shift = shift_filter & 0x0F;
+    let shift = if shift > 12 { 9 } else { shift };
+    let filter = usize::from(shift_filter >> 4).min(COEFFICIENTS.len() - 1);
+    (shift, COEFFICIENTS[filter])
+}
@@ -0,0 +1,397 @@
+#![doc = include_str!("../README.md")]
+#![forbid(missing_docs, unsafe_code)]
+#![warn(clippy::all, clippy::pedantic)]
+
+use self::adpcm::{Encoder, FLAG_LOOP_END, FLAG_LOOP_REPEAT, FLAG_LOOP_START, FLAG_STOP};
+use ps2_bytes::read_u32_be;
+use std::{
+    error::Error as StdError,
+    fmt::{self, Display},
+};
+
+mod adpcm;
+mod stereo;
+
+pub use self::{
+    adpcm::{AdpcmDecoder, BLOCK_SIZE, SAMPLES_PER_BLOCK},
+    stereo::{encode_stereo_vag, VagStereo},
+};
+
+/// Magic at the start of the file
+const MAGIC: &[u8] = b"VAGp";
+
+/// Version written by [`encode_vag`]
+const VERSION: u32 = 0x20;
+
+/// Size of the file header
+pub(crate) const HEADER_SIZE: usize = 48;
+
+/// Errors that might occur when parsing a VAG file
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum VagError {
+    /// The file ends in the middle of the header or the ADPCM data
+    Truncated,
+
+    /// The file doesn't start with the VAG magic
+    InvalidMagic,
+
+    /// The size of the ADPCM data isn't a multiple of the block size
+    InvalidHeader,
+
+    /// The audio is too long for the data size field of the header
+    TooLong,
+}
+
+impl Display for VagError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+impl StdError for VagError {}
+
+/// Parsed VAG file
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Vag {
+    version: u32,
+    sample_rate: u32,
+    channels: u8,
+    name: String,
+    data: Vec<u8>,
+}
+
+impl Vag {
+    /// Parse a VAG file
+    ///
+    /// # Errors
+    ///
+    /// - The file is truncated
+    /// - The magic is invalid
+    /// - The data size isn't a multiple of the block size
+    pub fn parse(bytes: &[u8]) -> Result<Self, VagError> {
+        let header = bytes.get(..HEADER_SIZE).ok_or(VagError::Truncated)?;
+        if !header.starts_with(MAGIC) {
+            return Err(VagError::InvalidMagic);
+        }
+
+        let (mut vag, data_size) = Self::parse_header(header)?;
+        vag.data = bytes
+            .get(HEADER_SIZE..HEADER_SIZE + data_size)
+            .ok_or(VagError::Truncated)?
+            .to_vec();
+        Ok(vag)
+    }
+
+    /// Parse the header fields shared by all variants, returning the file without data and the data size
+    pub(crate) fn parse_header(header: &[u8]) -> Result<(Self, usize), VagError> {
+        let version = read_u32_be(header, 0x04);
+        let data_size = read_u32_be(header, 0x0C) as usize;
+        let sample_rate = read_u32_be(header, 0x10);
+        // Mono files usually leave the channel count zeroed
+        let channels = header[0x1E].max(1);
+
+        let name = &header[0x20..HEADER_SIZE];
+        let name_len = name.iter().position(|&b| b == 0).unwrap_or(name.len());
+        let name = String::from_utf8_lossy(&name[..name_len]).into_owned();
+
+        if !data_size.is_multiple_of(BLOCK_SIZE) {
+            return Err(VagError::InvalidHeader);
+        }
+
+        let vag = Self {
+            version,
+            sample_rate,
+            channels,
+            name,
+            data: Vec::new(),
+        };
+        Ok((vag, data_size))
+    }
+
+    /// Version of the format
+    #[must_use]
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// Sample rate in Hz
+    #[must_use]
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Number of channels
+    #[must_use]
+    pub fn channels(&self) -> u8 {
+        self.channels
+    }
+
+    /// Name stored in the header
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Raw ADPCM data following the header
+    #[must_use]
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Blocks of the ADPCM data up to the end of the sound
+    fn blocks(&self) -> impl Iterator<Item = &[u8; BLOCK_SIZE]> {
+        let mut ended = false;
+        self.data.as_chunks().0.iter().take_while(move |block| {
+            let take = !ended && block[1] != FLAG_STOP;
+            ended |= block[1] & FLAG_LOOP_END != 0;
+            take
+        })
+    }
+
+    /// Index of the first sample of the loop, if the sound loops
+    #[must_use]
+    pub fn loop_start(&self) -> Option<usize> {
+        self.loop_end()?;
+        self.blocks()
+            .position(|block| block[1] & FLAG_LOOP_START != 0)
+            .map(|block| block * SAMPLES_PER_BLOCK)
+    }
+
+    /// Index one past the last sample of the loop, if the sound loops
+    #[must_use]
+    pub fn loop_end(&self) -> Option<usize> {
+        let (index, last) = self.blocks().enumerate().last()?;
+        let flags = FLAG_LOOP_END | FLAG_LOOP_REPEAT;
+        (last[1] & flags == flags).then_some((index + 1) * SAMPLES_PER_BLOCK)
+    }
+
+    /// Decode the ADPCM data into signed 16 bit PCM samples
+    ///
+    /// Decoding stops at the block marking the end of the sound, loops are not unrolled
+    #[must_use]
+    pub fn decode_pcm16(&self) -> Vec<i16> {
+        let mut decoder = AdpcmDecoder::new();
+        let mut samples = Vec::with_capacity(self.data.len() / BLOCK_SIZE * SAMPLES_PER_BLOCK);
+        for block in self.blocks() {
+            samples.extend(decoder.decode_block(block));
+        }
+        samples
+    }
+}
+
+/// Encode mono PCM samples into a VAG file
+///
+/// Each block uses the filter and shift that reconstruct its samples with the least squared error.
+/// The last block is marked as the end of the sound and followed by the usual terminating block
+///
+/// # Errors
+///
+/// - The encoded data is too large for the header
+pub fn encode_vag(pcm: &[i16], sample_rate: u32) -> Result<Vec<u8>, VagError> {
+    let data = encode_adpcm(pcm);
+    let mut bytes = write_header(MAGIC, data.len(), sample_rate, 0)?;
+    bytes.extend_from_slice(&data);
+    Ok(bytes)
+}
+
+/// Encode PCM samples into ADPCM blocks, including the terminating block
+pub(crate) fn encode_adpcm(pcm: &[i16]) -> Vec<u8> {
+    let block_count = pcm.len().div_ceil(SAMPLES_PER_BLOCK);
+    let mut data = Vec::with_capacity((block_count + 1) * BLOCK_SIZE);
+
+    let mut encoder = Encoder::default();
+    for (index, chunk) in pcm.chunks(SAMPLES_PER_BLOCK).enumerate() {
+        // The last block is padded with silence
+        let mut samples = [0; SAMPLES_PER_BLOCK];
+        samples[..chunk.len()].copy_from_slice(chunk);
+
+        let flags = if index + 1 == block_count {
+            FLAG_LOOP_END
+        } else {
+            0
+        };
+        data.extend_from_slice(&encoder.encode_block(&samples, flags));
+    }
+    data.extend_from_slice(&[0, FLAG_STOP]);
+    data.resize((block_count + 1) * BLOCK_SIZE, 0);
+
+    data
+}
+
+/// Write a file header for `data_size` bytes of ADPCM data (per channel)
+pub(crate) fn write_header(
+    magic: &[u8],
+    data_size: usize,
+    sample_rate: u32,
+    channels: u8,
+) -> Result<Vec<u8>, VagError> {
+    let data_size = u32::try_from(data_size).map_err(|_| VagError::TooLong)?;
+
+    let mut bytes = Vec::with_capacity(HEADER_SIZE);
+    bytes.extend_from_slice(magic);
+    bytes.extend_from_slice(&VERSION.to_be_bytes());
+    bytes.extend_from_slice(&[0; 4]);
+    bytes.extend_from_slice(&data_size.to_be_bytes());
+    bytes.extend_from_slice(&sample_rate.to_be_bytes());
+    bytes.resize(HEADER_SIZE, 0);
+    bytes[0x1E] = channels;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{encode_vag, Vag, VagError};
+
+    /// Samples of the test blocks, worked out by a separate script implementing the decoding formula
+    ///
+    /// No independent decoder like vgmstream was run on them, so they only guard against regressions.
+    /// [`decode_by_hand`] checks a few samples worked out by hand instead
+    const REFERENCE: [i16; 56] = [
+        -1280, -1200, -1125, -287, 1011, 2228, 553, 2310, 1910, -1, 1023, -65, -1853, -2249, -2620,
+        -2456, -1534, -670, -2676, -1229, -1920, -8, 505, -1063, 795, -279, -1286, -1718, -2170,
+        -2567, -2897, -3152, -3278, -3313, -3178, -2971, -2820, -2573, -2316, -2199, -1974, -1856,
+        -1811, -1810, -1781, -1746, -1610, -1458, -1408, -1297, -1203, -1028, -806, -741, -789,
+        -912,
+    ];
+
+    fn block(shift_filter: u8, flags: u8, seed: u8) -> Vec<u8> {
+        let mut block = vec![shift_filter, flags];
+        block.extend((0..14u8).map(|i| i.wrapping_mul(37).wrapping_add(seed)));
+        block
+    }
+
+    fn vag_file(blocks: &[Vec<u8>]) -> Vec<u8> {
+        let data = blocks.concat();
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(b"VAGp");
+        bytes.extend_from_slice(&0x20u32.to_be_bytes());
+        bytes.extend_from_slice(&[0; 4]);
+        bytes.extend_from_slice(&u32::try_from(data.len()).unwrap().to_be_bytes());
+        bytes.extend_from_slice(&44_100u32.to_be_bytes());
+        bytes.resize(0x20, 0);
+        bytes.extend_from_slice(b"test\0\0\0\0\0\0\0\0\0\0\0\0");
+        bytes.extend_from_slice(&data);
+        bytes
+    }
+
+    #[test]
+    fn parse() {
+        let bytes = vag_file(&[block(0x14, 0x06, 11), block(0x28, 0x03, 200)]);
+        let vag = Vag::parse(&bytes).unwrap();
+
+        assert_eq!(vag.version(), 0x20);
+        assert_eq!(vag.sample_rate(), 44_100);
+        assert_eq!(vag.channels(), 1);
+        assert_eq!(vag.name(), "test");
+        assert_eq!(vag.data(), &bytes[48..]);
+    }
+
+    #[test]
+    fn decode_by_hand() {
+        // Filter 0 with shift 0 places the nibbles in the top bits, filter 1 then predicts 60/64 of the
+        // previous sample, rounded as in `(old * 60 + 32) >> 6` from the nocash PSX specifications
+        let mut first = vec![0x00, 0x00, 0xF7];
+        first.resize(15, 0);
+        first.push(0x70);
+        let bytes = vag_file(&[
+            first,
+            vec![0x1C, 0x00, 0x01, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+        ]);
+        let samples = Vag::parse(&bytes).unwrap().decode_pcm16();
+
+        assert_eq!(samples[..3], [28_672, -4_096, 0]);
+        assert_eq!(samples[27], 28_672);
+        assert_eq!(samples[28..31], [26_881, 25_201, 23_626]);
+    }
+
+    #[test]
+    fn decode_pcm16() {
+        let bytes = vag_file(&[
+            block(0x14, 0x06, 11),
+            block(0x28, 0x03, 200),
+            block(0x00, 0x07, 0),
+        ]);
+        let vag = Vag::parse(&bytes).unwrap();
+
+        assert_eq!(vag.decode_pcm16(), REFERENCE);
+    }
+
+    #[test]
+    fn loop_points() {
+        let looping = vag_file(&[
+            block(0x00, 0x00, 0),
+            block(0x14, 0x06, 11),
+            block(0x28, 0x03, 200),
+            block(0x00, 0x07, 0),
+        ]);
+        let vag = Vag::parse(&looping).unwrap();
+        assert_eq!(vag.loop_start(), Some(28));
+        assert_eq!(vag.loop_end(), Some(84));
+
+        let one_shot = vag_file(&[block(0x14, 0x00, 11), block(0x28, 0x01, 200)]);
+        let vag = Vag::parse(&one_shot).unwrap();
+        assert_eq!(vag.loop_start(), None);
+        assert_eq!(vag.loop_end(), None);
+        assert_eq!(vag.decode_pcm16().len(), 56);
+    }
+
+    #[test]
+    fn invalid() {
+        let mut bytes = vag_file(&[block(0x14, 0x06, 11)]);
+        assert_eq!(Vag::parse(&bytes[..40]), Err(VagError::Truncated));
+        assert_eq!(Vag::parse(&bytes[..60]), Err(VagError::Truncated));
+
+        bytes[0x0F] = 15;
+        assert_eq!(Vag::parse(&bytes), Err(VagError::InvalidHeader));
+
+        bytes[0] = b'X';
+        assert_eq!(Vag::parse(&bytes), Err(VagError::InvalidMagic));
+    }
+
+    #[test]
+    fn encode_vag_round_trip() {
+        // Two tones at different frequencies and amplitudes, plus a short burst of silence
+        let pcm: Vec<i16> = (0..4410)
+            .map(|i| {
+                let t = f64::from(i) / 44_100.0;
+                let signal = 12_000.0 * (t * 440.0 * std::f64::consts::TAU).sin()
+                    + 4_000.0 * (t * 3_000.0 * std::f64::consts::TAU).sin();
+                #[allow(clippy::cast_possible_truncation)] // The amplitude stays within range
+                let sample = signal as i16;
+                if (2000..2100).contains(&i) {
+                    0
+                } else {
+                    sample
+                }
+            })
+            .collect();
+
+        let bytes = encode_vag(&pcm, 44_100).unwrap();
+        assert_eq!(&bytes[..4], b"VAGp");
+        assert_eq!(bytes[4..8], 0x20u32.to_be_bytes());
+        let vag = Vag::parse(&bytes).unwrap();
+        assert_eq!(vag.sample_rate(), 44_100);
+        assert_eq!(vag.data().len(), bytes.len() - 48);
+        assert_eq!(vag.loop_end(), None);
+
+        let decoded = vag.decode_pcm16();
+        assert_eq!(decoded.len(), pcm.len().div_ceil(28) * 28);
+
+        let (signal, noise) =
+            pcm.iter()
+                .zip(&decoded)
+                .fold((0.0, 0.0), |(signal, noise), (&a, &b)| {
+                    let error = f64::from(a) - f64::from(b);
+                    (signal + f64::from(a).powi(2), noise + error.powi(2))
+                });
+        let snr = 10.0 * (signal / noise).log10();
+        assert!(snr >= 30.0, "SNR of {snr:.1} dB");
+    }
+
+    #[test]
+    fn encode_vag_empty() {
+        let bytes = encode_vag(&[], 22_050).unwrap();
+        let vag = Vag::parse(&bytes).unwrap();
+        assert!(vag.decode_pcm16().is_empty());
+    }
+}
@@ -0,0 +1,208 @@
+use crate::{encode_adpcm, write_header, Vag, VagError, BLOCK_SIZE, HEADER_SIZE};
+use ps2_bytes::read_u32_be;
+
+/// Magics of interleaved VAG files
+const STEREO_MAGICS: [&[u8]; 2] = [b"VAGi", b"VAGs"];
+
+/// Offset of the data, the header is padded up to it
+const DATA_OFFSET: usize = 0x800;
+
+/// Interleave written by [`encode_stereo_vag`]
+const INTERLEAVE: u32 = 0x800;
+
+/// Parsed VAG file containing two interleaved channels
+///
+/// The header stores the interleave at 0x08 and the size of a single channel as its data size. The data starts
+/// at 0x800 and alternates between blocks of the left and the right channel, each as long as the interleave.
+/// `VAGs` files are read with the same layout
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct VagStereo {
+    left: Vag,
+    right: Vag,
+}
+
+impl VagStereo {
+    /// Parse an interleaved VAG file
+    ///
+    /// # Errors
+    ///
+    /// - The file is truncated
+    /// - The magic is invalid
+    /// - The data size or the interleave isn't a multiple of the block size
+    pub fn parse(bytes: &[u8]) -> Result<Self, VagError> {
+        let header = bytes.get(..HEADER_SIZE).ok_or(VagError::Truncated)?;
+        if !STEREO_MAGICS.iter().any(|magic| header.starts_with(magic)) {
+            return Err(VagError::InvalidMagic);
+        }
+
+        let (mut channel, data_size) = Vag::parse_header(header)?;
+        let interleave = read_u32_be(header, 0x08) as usize;
+        if interleave == 0 || !interleave.is_multiple_of(BLOCK_SIZE) {
+            return Err(VagError::InvalidHeader);
+        }
+        channel.channels = 1;
+        let mut left = channel.clone();
+        let mut right = channel;
+
+        let data = bytes.get(DATA_OFFSET..).ok_or(VagError::Truncated)?;
+        for (index, start) in (0..data_size).step_by(interleave).enumerate() {
+            let len = interleave.min(data_size - start);
+            for (channel, vag) in [&mut left, &mut right].into_iter().enumerate() {
+                let offset = (index * 2 + channel) * interleave;
+                let chunk = data.get(offset..offset + len).ok_or(VagError::Truncated)?;
+                vag.data.extend_from_slice(chunk);
+            }
+        }
+
+        Ok(Self { left, right })
+    }
+
+    /// Left channel
+    #[must_use]
+    pub fn left(&self) -> &Vag {
+        &self.left
+    }
+
+    /// Right channel
+    #[must_use]
+    pub fn right(&self) -> &Vag {
+        &self.right
+    }
+
+    /// Sample rate in Hz
+    #[must_use]
+    pub fn sample_rate(&self) -> u32 {
+        self.left.sample_rate()
+    }
+
+    /// Decode both channels into signed 16 bit PCM samples, see [`Vag::decode_pcm16`]
+    #[must_use]
+    pub fn decode_pcm16(&self) -> (Vec<i16>, Vec<i16>) {
+        (self.left.decode_pcm16(), self.right.decode_pcm16())
+    }
+}
+
+/// Encode two channels of PCM samples into an interleaved VAG file
+///
+/// The channels are encoded independently and may differ in length
+///
+/// # Errors
+///
+/// - The encoded data is too large for the header
+pub fn encode_stereo_vag(
+    left: &[i16],
+    right: &[i16],
+    sample_rate: u32,
+) -> Result<Vec<u8>, VagError> {
+    let mut left = encode_adpcm(left);
+    let mut right = encode_adpcm(right);
+    let data_size = left.len().max(right.len());
+
+    // Anything after the terminating block is never played, so the shorter channel is padded with zeros
+    let padded_size = data_size.next_multiple_of(INTERLEAVE as usize);
+    left.resize(padded_size, 0);
+    right.resize(padded_size, 0);
+
+    let mut bytes = write_header(STEREO_MAGICS[0], data_size, sample_rate, 2)?;
+    bytes[0x08..0x0C].copy_from_slice(&INTERLEAVE.to_be_bytes());
+    bytes.resize(DATA_OFFSET, 0);
+    for (left, right) in left
+        .chunks(INTERLEAVE as usize)
+        .zip(right.chunks(INTERLEAVE as usize))
+    {
+        bytes.extend_from_slice(left);
+        bytes.extend_from_slice(right);
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{encode_stereo_vag, encode_vag, Vag, VagError, VagStereo};
+
+    fn tone(len: i32, period: i32) -> Vec<i16> {
+        (0..len)
+            .map(|i| if (i / period) % 2 == 0 { 8_000 } else { -8_000 })
+            .collect()
+    }
+
+    #[test]
+    fn round_trip() {
+        let left = tone(5_000, 50);
+        let right = tone(3_000, 7);
+        let bytes = encode_stereo_vag(&left, &right, 48_000).unwrap();
+        assert_eq!(&bytes[..4], b"VAGi");
+
+        let stereo = VagStereo::parse(&bytes).unwrap();
+        assert_eq!(stereo.sample_rate(), 48_000);
+        assert_eq!(stereo.left().channels(), 1);
+
+        // Each channel decodes exactly like the same samples encoded as mono
+        let (decoded_left, decoded_right) = stereo.decode_pcm16();
+        let mono_left = Vag::parse(&encode_vag(&left, 48_000).unwrap()).unwrap();
+        let mono_right = Vag::parse(&encode_vag(&right, 48_000).unwrap()).unwrap();
+        assert_eq!(decoded_left, mono_left.decode_pcm16());
+        assert_eq!(decoded_right, mono_right.decode_pcm16());
+        assert_eq!(decoded_left.len(), 5_012);
+        assert_eq!(decoded_right.len(), 3_024);
+    }
+
+    #[test]
+    fn interleave() {
+        let bytes = encode_stereo_vag(&tone(5_000, 50), &[], 44_100).unwrap();
+        let mono = encode_vag(&tone(5_000, 50), 44_100).unwrap();
+        let data_size = mono.len() - 48;
+        assert_eq!(bytes[0x08..0x0C], 0x800_u32.to_be_bytes());
+        assert_eq!(
+            bytes[0x0C..0x10],
+            u32::try_from(data_size).unwrap().to_be_bytes()
+        );
+        assert_eq!(bytes[0x1E], 2);
+
+        // Left, right, left, right from 0x800 on, with the first left block matching the mono data
+        assert_eq!(bytes.len(), 0x800 + 4 * 0x800);
+        assert_eq!(bytes[0x800..0x1000], mono[48..48 + 0x800]);
+        assert_eq!(bytes[0x1000..0x1002], [0, 0x07]);
+        assert_eq!(
+            bytes[0x1800..0x1800 + 16],
+            mono[48 + 0x800..48 + 0x800 + 16]
+        );
+    }
+
+    #[test]
+    fn header_interleave() {
+        // Two blocks per channel, interleaved every block
+        let mut bytes = b"VAGi".to_vec();
+        bytes.extend([
+            0, 0, 0, 0x20, 0, 0, 0, 0x10, 0, 0, 0, 0x20, 0, 0, 0xAC, 0x44,
+        ]);
+        bytes.resize(0x800, 0);
+        for block in [0x11, 0x21, 0x12, 0x22] {
+            bytes.extend([block; 16]);
+        }
+
+        let stereo = VagStereo::parse(&bytes).unwrap();
+        assert_eq!(stereo.sample_rate(), 44_100);
+        assert_eq!(stereo.left().data(), [[0x11; 16], [0x12; 16]].concat());
+        assert_eq!(stereo.right().data(), [[0x21; 16], [0x22; 16]].concat());
+
+        bytes[0x0B] = 0x18;
+        assert_eq!(VagStereo::parse(&bytes), Err(VagError::InvalidHeader));
+        bytes[0x0B] = 0;
+        assert_eq!(VagStereo::parse(&bytes), Err(VagError::InvalidHeader));
+    }
+
+    #[test]
+    fn invalid() {
+        let bytes = encode_stereo_vag(&tone(5_000, 50), &tone(5_000, 60), 44_100).unwrap();
+        assert_eq!(
+            VagStereo::parse(&bytes[..bytes.len() - 0x800]),
+            Err(VagError::Truncated)
+        );
+        assert_eq!(
+            VagStereo::parse(&encode_vag(&[], 44_100).unwrap()),
+            Err(VagError::InvalidMagic)
+        );
+        assert_eq!(Vag::parse(&bytes), Err(VagError::InvalidMagic));
+    }
+}
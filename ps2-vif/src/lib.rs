@@ -0,0 +1,513 @@
+#![doc = include_str!("../README.md")]
+#![forbid(missing_docs, unsafe_code)]
+#![warn(clippy::all, clippy::pedantic)]
+
+use ps2_bytes::read_u32_le;
+use std::{
+    error::Error as StdError,
+    fmt::{self, Display},
+};
+
+mod unpack;
+
+/// Errors that might occur when parsing a VIF stream
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum VifError {
+    /// The stream isn't made up of whole 32 bit words
+    Unaligned,
+
+    /// The stream ends in the middle of the data of a command
+    Truncated,
+
+    /// A VIF code has an unknown command number
+    UnknownCommand {
+        /// Command number of the VIF code
+        cmd: u8,
+
+        /// Byte offset of the VIF code
+        offset: usize,
+    },
+}
+
+impl Display for VifError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+impl StdError for VifError {}
+
+/// Decoded VIF code together with the data following it
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum VifCommand {
+    /// No operation
+    Nop,
+
+    /// Set the cycle register used by UNPACK
+    Stcycl {
+        /// Cycle length
+        cl: u8,
+
+        /// Write cycle length
+        wl: u8,
+    },
+
+    /// Set the offset of the second VU memory buffer
+    Offset(u16),
+
+    /// Set the base address of the VU memory double buffer
+    Base(u16),
+
+    /// Set the ITOP register
+    Itop(u16),
+
+    /// Set the addition mode used by UNPACK
+    Stmod(u8),
+
+    /// Mask (`true`) or unmask the transfer over PATH3
+    Mskpath3(bool),
+
+    /// Set the MARK register
+    Mark(u16),
+
+    /// Wait for the microprogram to end
+    Flushe,
+
+    /// Wait for the microprogram to end and the GIF transfer over PATH1 and PATH2
+    Flush,
+
+    /// Wait for the microprogram to end and all GIF transfers
+    Flusha,
+
+    /// Start the microprogram at the given address
+    Mscal(u16),
+
+    /// Start the microprogram at the given address once the GIF transfer ended
+    Mscalf(u16),
+
+    /// Continue the microprogram from where it stopped
+    Mscnt,
+
+    /// Set the write mask used by UNPACK
+    Stmask(u32),
+
+    /// Set the filling data used by UNPACK
+    Strow([u32; 4]),
+
+    /// Set the column data used by UNPACK
+    Stcol([u32; 4]),
+
+    /// Load a microprogram into the micro memory
+    Mpg {
+        /// Load address in units of 64 bit instructions
+        addr: u16,
+
+        /// Instructions of the microprogram
+        data: Vec<u64>,
+    },
+
+    /// Transfer data to the GIF over PATH2
+    Direct(Vec<u8>),
+
+    /// Transfer data to the GIF over PATH2 without interrupting a PATH3 IMAGE transfer
+    DirectHl(Vec<u8>),
+
+    /// Decompress data into the VU memory
+    Unpack {
+        /// Number of components per vector minus one
+        vn: u8,
+
+        /// Size of the components, 0 (32 bit), 1 (16 bit), 2 (8 bit) or 3 (V4-5, 16 bit per vector)
+        vl: u8,
+
+        /// Destination address in quadwords
+        addr: u16,
+
+        /// Addition mode set by STMOD when the command was executed
+        mode: u8,
+
+        /// Whether 8 and 16 bit components are zero-extended instead of sign-extended
+        usn: bool,
+
+        /// Whether the write mask set by STMASK is applied
+        masked: bool,
+
+        /// Whether `addr` is relative to the TOPS register
+        use_tops: bool,
+
+        /// Number of vectors written to the VU memory
+        num: u16,
+
+        /// Packed input data, without the padding to 32 bits
+        data: Vec<u8>,
+    },
+}
+
+/// Parser for VIF1 command streams
+#[derive(Clone, Copy, Debug)]
+pub struct VifStream;
+
+impl VifStream {
+    /// Parse a stream of little-endian VIF codes and their data
+    ///
+    /// The amount of data read by UNPACK depends on the cycle set by STCYCL, which is assumed to be `CL = WL = 1`
+    /// until the stream sets it
+    ///
+    /// # Errors
+    ///
+    /// - The stream isn't made up of whole 32 bit words
+    /// - The stream ends in the middle of the data of a command
+    /// - A VIF code has an unknown command number
+    pub fn parse(data: &[u8]) -> Result<Vec<VifCommand>, VifError> {
+        if !data.len().is_multiple_of(4) {
+            return Err(VifError::Unaligned);
+        }
+
+        let mut reader = Reader { data, offset: 0 };
+        let mut state = State {
+            cl: 1,
+            wl: 1,
+            mode: 0,
+        };
+        let mut commands = Vec::new();
+        while reader.offset < data.len() {
+            commands.push(reader.command(&mut state)?);
+        }
+
+        Ok(commands)
+    }
+}
+
+/// Registers set by earlier commands that affect how later ones are decoded
+struct State {
+    cl: u8,
+    wl: u8,
+    mode: u8,
+}
+
+/// Cursor over the words of a VIF stream
+struct Reader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    /// Take the next `len` bytes
+    fn take(&mut self, len: usize) -> Result<&'a [u8], VifError> {
+        let bytes = self
+            .data
+            .get(self.offset..self.offset + len)
+            .ok_or(VifError::Truncated)?;
+        self.offset += len;
+        Ok(bytes)
+    }
+
+    /// Take the next 32 bit word
+    fn word(&mut self) -> Result<u32, VifError> {
+        Ok(read_u32_le(self.take(4)?, 0))
+    }
+
+    /// Take the next four 32 bit words
+    fn words(&mut self) -> Result<[u32; 4], VifError> {
+        let bytes = self.take(16)?;
+        Ok([0, 4, 8, 12].map(|offset| read_u32_le(bytes, offset)))
+    }
+
+    /// Decode the next VIF code and its data
+    fn command(&mut self, state: &mut State) -> Result<VifCommand, VifError> {
+        let code_offset = self.offset;
+        let [imm_low, imm_high, num, cmd] = self.word()?.to_le_bytes();
+        let imm = u16::from_le_bytes([imm_low, imm_high]);
+        // The top bit requests an interrupt and doesn't affect the command
+        let cmd = cmd & 0x7F;
+        // NUM counts up to 256, which is encoded as 0
+        let count = match num {
+            0 => 256,
+            num => u16::from(num),
+        };
+
+        let command = match cmd {
+            0x00 => VifCommand::Nop,
+            0x01 => {
+                (state.cl, state.wl) = (imm_low, imm_high);
+                VifCommand::Stcycl {
+                    cl: imm_low,
+                    wl: imm_high,
+                }
+            }
+            0x02 => VifCommand::Offset(imm & 0x3FF),
+            0x03 => VifCommand::Base(imm & 0x3FF),
+            0x04 => VifCommand::Itop(imm & 0x3FF),
+            0x05 => {
+                state.mode = imm_low & 0b11;
+                VifCommand::Stmod(state.mode)
+            }
+            0x06 => VifCommand::Mskpath3(imm & 0x8000 != 0),
+            0x07 => VifCommand::Mark(imm),
+            0x10 => VifCommand::Flushe,
+            0x11 => VifCommand::Flush,
+            0x13 => VifCommand::Flusha,
+            0x14 => VifCommand::Mscal(imm),
+            0x15 => VifCommand::Mscalf(imm),
+            0x17 => VifCommand::Mscnt,
+            0x20 => VifCommand::Stmask(self.word()?),
+            0x30 => VifCommand::Strow(self.words()?),
+            0x31 => VifCommand::Stcol(self.words()?),
+            0x4A => VifCommand::Mpg {
+                addr: imm,
+                data: self
+                    .take(usize::from(count) * 8)?
+                    .chunks_exact(8)
+                    .map(|instruction| {
+                        u64::from(read_u32_le(instruction, 0))
+                            | u64::from(read_u32_le(instruction, 4)) << 32
+                    })
+                    .collect(),
+            },
+            0x50 | 0x51 => {
+                // IMMEDIATE counts up to 65536 quadwords, which is encoded as 0
+                let qwords = match imm {
+                    0 => 0x1_0000,
+                    qwords => usize::from(qwords),
+                };
+                let bytes = self.take(qwords * 16)?.to_vec();
+                if cmd == 0x50 {
+                    VifCommand::Direct(bytes)
+                } else {
+                    VifCommand::DirectHl(bytes)
+                }
+            }
+            0x60..=0x7F => {
+                let (vn, vl) = (cmd >> 2 & 0b11, cmd & 0b11);
+                let len = unpack::input_len(vn, vl, count, state.cl, state.wl);
+                // The data is padded to whole words
+                let data = self.take(len.next_multiple_of(4))?[..len].to_vec();
+                VifCommand::Unpack {
+                    vn,
+                    vl,
+                    addr: imm & 0x3FF,
+                    mode: state.mode,
+                    usn: imm & 0x4000 != 0,
+                    masked: cmd & 0x10 != 0,
+                    use_tops: imm & 0x8000 != 0,
+                    num: count,
+                    data,
+                }
+            }
+            cmd => {
+                return Err(VifError::UnknownCommand {
+                    cmd,
+                    offset: code_offset,
+                })
+            }
+        };
+
+        Ok(command)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{VifCommand, VifError, VifStream};
+
+    fn code(cmd: u8, num: u8, imm: u16) -> u32 {
+        u32::from(cmd) << 24 | u32::from(num) << 16 | u32::from(imm)
+    }
+
+    fn to_bytes(words: &[u32]) -> Vec<u8> {
+        words.iter().flat_map(|word| word.to_le_bytes()).collect()
+    }
+
+    #[test]
+    fn unpack_triangle() {
+        let triangle = [
+            [0.0_f32, 1.0, 0.0, 1.0],
+            [-1.0, -1.0, 0.0, 1.0],
+            [1.0, -1.0, 0.0, 1.0],
+        ];
+        let mut words = vec![code(0x01, 0, 0x0101), code(0x6C, 3, 0x4010)];
+        words.extend(
+            triangle
+                .iter()
+                .flatten()
+                .map(|component| component.to_bits()),
+        );
+        // Interrupt bit set
+        words.push(code(0x94, 0, 0x0020));
+
+        let commands = VifStream::parse(&to_bytes(&words)).unwrap();
+        assert_eq!(commands.len(), 3);
+        assert_eq!(commands[0], VifCommand::Stcycl { cl: 1, wl: 1 });
+        assert_eq!(commands[2], VifCommand::Mscal(0x20));
+
+        let VifCommand::Unpack {
+            vn,
+            vl,
+            addr,
+            usn,
+            masked,
+            num,
+            ref data,
+            ..
+        } = commands[1]
+        else {
+            panic!("expected an UNPACK, got {:?}", commands[1]);
+        };
+        assert_eq!(
+            (vn, vl, addr, usn, masked, num),
+            (3, 0, 0x10, true, false, 3)
+        );
+        assert_eq!(data.len(), 48);
+
+        let vertices = commands[1]
+            .unpacked_vectors()
+            .unwrap()
+            .into_iter()
+            .map(|vector| vector.map(f32::from_bits))
+            .collect::<Vec<_>>();
+        assert_eq!(vertices, triangle);
+        assert_eq!(commands[0].unpacked_vectors(), None);
+    }
+
+    #[test]
+    fn unpack_formats() {
+        // S-8 sign-extended, V2-16 zero-extended, V4-5
+        let words = [
+            code(0x62, 2, 0),
+            0x0000_80FF,
+            code(0x65, 1, 0x4000),
+            0xFFFF_8000,
+            code(0x6F, 2, 0),
+            0x8000_7FFF,
+        ];
+        let commands = VifStream::parse(&to_bytes(&words)).unwrap();
+
+        assert_eq!(
+            commands[0].unpacked_vectors().unwrap(),
+            [[u32::MAX; 4], [0xFFFF_FF80; 4]]
+        );
+        assert_eq!(
+            commands[1].unpacked_vectors().unwrap(),
+            [[0x8000, 0xFFFF, 0, 0]]
+        );
+        assert_eq!(
+            commands[2].unpacked_vectors().unwrap(),
+            [[0xF8, 0xF8, 0xF8, 0], [0, 0, 0, 0x80]]
+        );
+    }
+
+    #[test]
+    fn commands() {
+        let words = [
+            code(0x00, 0, 0),
+            code(0x02, 0, 0x1FF),
+            code(0x03, 0, 0x200),
+            code(0x04, 0, 0x3FF),
+            code(0x05, 0, 1),
+            code(0x06, 0, 0x8000),
+            code(0x07, 0, 0xBEEF),
+            code(0x10, 0, 0),
+            code(0x11, 0, 0),
+            code(0x13, 0, 0),
+            code(0x15, 0, 0x10),
+            code(0x17, 0, 0),
+            code(0x20, 0, 0),
+            0x5555_AAAA,
+            code(0x30, 0, 0),
+            1,
+            2,
+            3,
+            4,
+            code(0x31, 0, 0),
+            5,
+            6,
+            7,
+            8,
+            code(0x4A, 2, 0x100),
+            0x1111_1111,
+            0x2222_2222,
+            0x3333_3333,
+            0x4444_4444,
+            code(0x50, 0, 1),
+            9,
+            10,
+            11,
+            12,
+        ];
+        let commands = VifStream::parse(&to_bytes(&words)).unwrap();
+
+        assert_eq!(
+            commands,
+            [
+                VifCommand::Nop,
+                VifCommand::Offset(0x1FF),
+                VifCommand::Base(0x200),
+                VifCommand::Itop(0x3FF),
+                VifCommand::Stmod(1),
+                VifCommand::Mskpath3(true),
+                VifCommand::Mark(0xBEEF),
+                VifCommand::Flushe,
+                VifCommand::Flush,
+                VifCommand::Flusha,
+                VifCommand::Mscalf(0x10),
+                VifCommand::Mscnt,
+                VifCommand::Stmask(0x5555_AAAA),
+                VifCommand::Strow([1, 2, 3, 4]),
+                VifCommand::Stcol([5, 6, 7, 8]),
+                VifCommand::Mpg {
+                    addr: 0x100,
+                    data: vec![0x2222_2222_1111_1111, 0x4444_4444_3333_3333],
+                },
+                VifCommand::Direct(to_bytes(&[9, 10, 11, 12])),
+            ]
+        );
+    }
+
+    #[test]
+    fn unpack_mode_and_cycle() {
+        // STMOD is recorded on the UNPACK and STCYCL changes how much data is read
+        let words = [
+            code(0x05, 0, 2),
+            code(0x01, 0, 0x0402),
+            code(0x6C, 4, 0),
+            1,
+            2,
+            3,
+            4,
+            5,
+            6,
+            7,
+            8,
+            code(0x00, 0, 0),
+        ];
+        let commands = VifStream::parse(&to_bytes(&words)).unwrap();
+
+        assert_eq!(commands.len(), 4);
+        let VifCommand::Unpack { mode, ref data, .. } = commands[2] else {
+            panic!("expected an UNPACK, got {:?}", commands[2]);
+        };
+        assert_eq!(mode, 2);
+        assert_eq!(data.len(), 32);
+    }
+
+    #[test]
+    fn reject_invalid() {
+        assert_eq!(VifStream::parse(&[0; 6]), Err(VifError::Unaligned));
+        assert_eq!(
+            VifStream::parse(&to_bytes(&[code(0x00, 0, 0), code(0x42, 0, 0)])),
+            Err(VifError::UnknownCommand {
+                cmd: 0x42,
+                offset: 4
+            })
+        );
+        assert_eq!(
+            VifStream::parse(&to_bytes(&[code(0x6C, 1, 0), 1, 2])),
+            Err(VifError::Truncated)
+        );
+        assert_eq!(
+            VifStream::parse(&to_bytes(&[code(0x20, 0, 0)])),
+            Err(VifError::Truncated)
+        );
+        assert_eq!(VifStream::parse(&[]), Ok(Vec::new()));
+    }
+}
@@ -0,0 +1,113 @@
+use crate::VifCommand;
+
+/// Number of bytes of input data an UNPACK of `num` vectors reads, without the padding to 32 bits
+///
+/// If WL is larger than CL only CL out of every WL vectors are read from the data, the others are filled in
+pub(crate) fn input_len(vn: u8, vl: u8, num: u16, cl: u8, wl: u8) -> usize {
+    let num = usize::from(num);
+    let (cl, wl) = (usize::from(cl), usize::from(wl));
+    let vectors = if wl <= cl || wl == 0 {
+        num
+    } else {
+        cl * (num / wl) + (num % wl).min(cl)
+    };
+
+    vectors * bits_per_vector(vn, vl) / 8
+}
+
+/// Size of a single input vector in bits
+fn bits_per_vector(vn: u8, vl: u8) -> usize {
+    match vl {
+        3 => 16,
+        vl => (usize::from(vn) + 1) * (32 >> vl),
+    }
+}
+
+impl VifCommand {
+    /// Decode the input vectors of an UNPACK
+    ///
+    /// 8 and 16 bit components are sign- or zero-extended according to USN, V4-5 colours are expanded to 8 bits
+    /// per channel. Single components are copied into all four fields of the vector, missing components are 0.
+    /// The write cycle, STMOD addition and masking aren't applied. Returns `None` for any other command
+    #[must_use]
+    pub fn unpacked_vectors(&self) -> Option<Vec<[u32; 4]>> {
+        let Self::Unpack {
+            vn, vl, usn, data, ..
+        } = self
+        else {
+            return None;
+        };
+        let (vn, vl, usn) = (*vn, *vl, *usn);
+
+        let bits = bits_per_vector(vn, vl);
+        let vectors = data.len() * 8 / bits;
+        let element = |bit_offset: usize| -> u32 {
+            let byte = bit_offset / 8;
+            match vl {
+                0 => {
+                    u32::from_le_bytes([data[byte], data[byte + 1], data[byte + 2], data[byte + 3]])
+                }
+                1 => {
+                    let value = u16::from_le_bytes([data[byte], data[byte + 1]]);
+                    if usn {
+                        u32::from(value)
+                    } else {
+                        i32::from(value.cast_signed()).cast_unsigned()
+                    }
+                }
+                _ => {
+                    let value = data[byte];
+                    if usn {
+                        u32::from(value)
+                    } else {
+                        i32::from(value.cast_signed()).cast_unsigned()
+                    }
+                }
+            }
+        };
+
+        let unpacked = (0..vectors)
+            .map(|index| {
+                let offset = index * bits;
+                if vl == 3 {
+                    let color =
+                        u32::from(u16::from_le_bytes([data[offset / 8], data[offset / 8 + 1]]));
+                    let channel = |shift: u32| (color >> shift & 0x1F) << 3;
+                    return [channel(0), channel(5), channel(10), (color >> 15) << 7];
+                }
+
+                let component_bits = 32 >> vl;
+                let mut vector = [0; 4];
+                for (component, value) in vector.iter_mut().enumerate().take(usize::from(vn) + 1) {
+                    *value = element(offset + component * component_bits);
+                }
+                if vn == 0 {
+                    vector = [vector[0]; 4];
+                }
+
+                vector
+            })
+            .collect();
+
+        Some(unpacked)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::input_len;
+
+    #[test]
+    fn input_lengths() {
+        // V4-32
+        assert_eq!(input_len(3, 0, 3, 1, 1), 48);
+        // V3-16
+        assert_eq!(input_len(2, 1, 3, 1, 1), 18);
+        // S-8
+        assert_eq!(input_len(0, 2, 5, 1, 1), 5);
+        // V4-5
+        assert_eq!(input_len(3, 3, 3, 1, 1), 6);
+        // Filling write, only 2 out of every 4 vectors are read
+        assert_eq!(input_len(3, 0, 10, 2, 4), 6 * 16);
+    }
+}
@@ -0,0 +1,111 @@
+use crate::Error;
+use alloc::borrow::Cow;
+use core::{
+    fmt::{self, Display},
+    str::FromStr,
+};
+
+/// Device the boot executable is loaded from, see [`SystemCnf::boot_device`](crate::SystemCnf::boot_device)
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum BootDevice<'a> {
+    /// Disc drive (`cdrom0`), used by retail discs
+    Cdrom,
+
+    /// USB mass storage (`mass`)
+    Mass,
+
+    /// Internal HDD (`hdd0`)
+    Hdd,
+
+    /// Host file system of a development kit or emulator (`host`)
+    Host,
+
+    /// Any other device, preserved exactly as it appeared in the file
+    Other(Cow<'a, str>),
+}
+
+impl BootDevice<'_> {
+    /// Retrieve the device name, without the trailing `:`
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Cdrom => "cdrom0",
+            Self::Mass => "mass",
+            Self::Hdd => "hdd0",
+            Self::Host => "host",
+            Self::Other(other) => other,
+        }
+    }
+
+    /// Convert into an owned `BootDevice` that no longer borrows from the input
+    #[must_use]
+    pub fn into_owned(self) -> BootDevice<'static> {
+        match self {
+            Self::Cdrom => BootDevice::Cdrom,
+            Self::Mass => BootDevice::Mass,
+            Self::Hdd => BootDevice::Hdd,
+            Self::Host => BootDevice::Host,
+            Self::Other(other) => BootDevice::Other(Cow::Owned(other.into_owned())),
+        }
+    }
+}
+
+impl Display for BootDevice<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl<'a> TryFrom<&'a str> for BootDevice<'a> {
+    type Error = Error;
+
+    /// Parse a device name (without the trailing `:`), borrowing unknown values from the input
+    ///
+    /// Only empty values are rejected, unknown devices (including other unit numbers such as `mass0`)
+    /// are preserved in [`BootDevice::Other`]
+    fn try_from(value: &'a str) -> Result<Self, Self::Error> {
+        match value.trim() {
+            "" => Err(Error::EmptyValue),
+            "cdrom0" => Ok(Self::Cdrom),
+            "mass" => Ok(Self::Mass),
+            "hdd0" => Ok(Self::Hdd),
+            "host" => Ok(Self::Host),
+            other => Ok(Self::Other(other.into())),
+        }
+    }
+}
+
+impl FromStr for BootDevice<'_> {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        BootDevice::try_from(s).map(BootDevice::into_owned)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{BootDevice, Error};
+    use std::borrow::Cow;
+
+    #[test]
+    fn known() {
+        assert_eq!("cdrom0".parse(), Ok(BootDevice::Cdrom));
+        assert_eq!("mass".parse(), Ok(BootDevice::Mass));
+        assert_eq!("hdd0".parse(), Ok(BootDevice::Hdd));
+        assert_eq!("host".parse(), Ok(BootDevice::Host));
+        assert_eq!(BootDevice::Cdrom.to_string(), "cdrom0");
+    }
+
+    #[test]
+    fn other() {
+        let device = BootDevice::try_from("mc0").unwrap();
+        assert!(matches!(device, BootDevice::Other(Cow::Borrowed("mc0"))));
+        assert_eq!(device.as_str(), "mc0");
+    }
+
+    #[test]
+    fn empty() {
+        assert_eq!(" ".parse::<BootDevice<'_>>(), Err(Error::EmptyValue));
+    }
+}
@@ -0,0 +1,161 @@
+use crate::{Error, HddUnitPower, SystemCnf, VideoMode};
+use alloc::{borrow::Cow, vec::Vec};
+
+/// Builder for constructing a [`SystemCnf`] without parsing raw text
+#[derive(Debug, Default)]
+pub struct SystemCnfBuilder<'a> {
+    elf_path: Option<Cow<'a, str>>,
+    ps1_boot: Option<Cow<'a, str>>,
+    version: Option<Cow<'a, str>>,
+    video_mode: Option<VideoMode<'a>>,
+    hdd_unit_power: Option<HddUnitPower<'a>>,
+    extras: Vec<(Cow<'a, str>, Cow<'a, str>)>,
+}
+
+impl<'a> SystemCnfBuilder<'a> {
+    /// Create a new, empty builder
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the path to the initial ELF file
+    #[must_use]
+    pub fn elf_path(mut self, elf_path: impl Into<Cow<'a, str>>) -> Self {
+        self.elf_path = Some(elf_path.into());
+        self
+    }
+
+    /// Set the path to the PS1 executable
+    #[must_use]
+    pub fn ps1_boot(mut self, ps1_boot: impl Into<Cow<'a, str>>) -> Self {
+        self.ps1_boot = Some(ps1_boot.into());
+        self
+    }
+
+    /// Set the version of the game
+    #[must_use]
+    pub fn version(mut self, version: impl Into<Cow<'a, str>>) -> Self {
+        self.version = Some(version.into());
+        self
+    }
+
+    /// Set the video mode
+    #[must_use]
+    pub fn video_mode(mut self, video_mode: VideoMode<'a>) -> Self {
+        self.video_mode = Some(video_mode);
+        self
+    }
+
+    /// Set the `HDDUNITPOWER` value
+    #[must_use]
+    pub fn hdd_unit_power(mut self, hdd_unit_power: HddUnitPower<'a>) -> Self {
+        self.hdd_unit_power = Some(hdd_unit_power);
+        self
+    }
+
+    /// Append an additional key-value pair
+    #[must_use]
+    pub fn extra(mut self, key: impl Into<Cow<'a, str>>, value: impl Into<Cow<'a, str>>) -> Self {
+        self.extras.push((key.into(), value.into()));
+        self
+    }
+
+    /// Build the `SystemCnf`
+    ///
+    /// # Errors
+    ///
+    /// - Required fields are missing (at least one of the ELF path and the PS1 executable path has to be set)
+    /// - The ELF path, PS1 executable path or the version are empty
+    pub fn build(self) -> Result<SystemCnf<'a>, Error> {
+        if self.elf_path.is_none() && self.ps1_boot.is_none() {
+            return Err(Error::MissingField);
+        }
+        let version = self.version.ok_or(Error::MissingField)?;
+        let video_mode = self.video_mode.ok_or(Error::MissingField)?;
+
+        let is_empty = |value: &Option<Cow<'_, str>>| {
+            value.as_ref().is_some_and(|value| value.trim().is_empty())
+        };
+        if is_empty(&self.elf_path) || is_empty(&self.ps1_boot) || version.trim().is_empty() {
+            return Err(Error::EmptyValue);
+        }
+
+        Ok(SystemCnf {
+            elf_path: self.elf_path,
+            ps1_boot: self.ps1_boot,
+            version,
+            video_mode,
+            hdd_unit_power: self.hdd_unit_power,
+            extras: self.extras,
+            order: Vec::new(),
+            quoted: Vec::new(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Error, HddUnitPower, SystemCnf, SystemCnfBuilder, VideoMode};
+
+    #[test]
+    fn build() {
+        let built = SystemCnfBuilder::new()
+            .elf_path("cdrom0:\\SLUS_213.48")
+            .version(String::from("1.00"))
+            .video_mode(VideoMode::Ntsc)
+            .hdd_unit_power(HddUnitPower::NicHdd)
+            .build()
+            .unwrap();
+
+        let encoded = built.to_string();
+        assert_eq!(SystemCnf::parse(&encoded).unwrap(), built);
+    }
+
+    #[test]
+    fn display() {
+        let built = SystemCnf::builder()
+            .elf_path("cdrom0:\\SLES_123.45")
+            .version("1.01")
+            .video_mode(VideoMode::Pal)
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            built.to_string(),
+            "BOOT2 = cdrom0:\\SLES_123.45;1\r\nVER = 1.01\r\nVMODE = PAL\r\n"
+        );
+    }
+
+    #[test]
+    fn ps1_boot() {
+        let built = SystemCnf::builder()
+            .ps1_boot("cdrom:\\SLUS_000.67")
+            .version("1.00")
+            .video_mode(VideoMode::Ntsc)
+            .build()
+            .unwrap();
+
+        assert!(built.is_ps1_disc());
+        assert_eq!(SystemCnf::parse(&built.to_string()).unwrap(), built);
+    }
+
+    #[test]
+    fn missing_fields() {
+        let result = SystemCnfBuilder::new()
+            .elf_path("cdrom0:\\SLUS_213.48")
+            .video_mode(VideoMode::Ntsc)
+            .build();
+        assert_eq!(result, Err(Error::MissingField));
+    }
+
+    #[test]
+    fn empty_fields() {
+        let result = SystemCnfBuilder::new()
+            .elf_path("")
+            .version("1.00")
+            .video_mode(VideoMode::Pal)
+            .build();
+        assert_eq!(result, Err(Error::EmptyValue));
+    }
+}
@@ -0,0 +1,23 @@
+use alloc::borrow::Cow;
+use core::fmt::{self, Display};
+
+/// Single field that differs between two configs, see [`SystemCnf::diff`](crate::SystemCnf::diff)
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct FieldDiff<'a> {
+    /// Key of the field, recognised keys use their canonical uppercase form
+    pub key: Cow<'a, str>,
+
+    /// Value in the old config, `None` if the field wasn't set
+    pub old: Option<Cow<'a, str>>,
+
+    /// Value in the new config, `None` if the field was removed
+    pub new: Option<Cow<'a, str>>,
+}
+
+impl Display for FieldDiff<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let old = self.old.as_deref().unwrap_or("(unset)");
+        let new = self.new.as_deref().unwrap_or("(unset)");
+        write!(f, "{}: {old} -> {new}", self.key)
+    }
+}
@@ -0,0 +1,150 @@
+use alloc::{format, string::String};
+use core::fmt::{self, Display};
+
+/// Region of a game, derived from the prefix of its serial
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Region {
+    /// North America (`SLUS`, `SCUS`, ...)
+    NorthAmerica,
+
+    /// Europe (`SLES`, `SCES`, ...)
+    Europe,
+
+    /// Japan (`SLPS`, `SCPS`, `SLPM`, ...)
+    Japan,
+
+    /// Any prefix not covered by the other regions
+    Other,
+}
+
+impl Region {
+    /// Derive the region from a serial prefix such as `SLUS`
+    ///
+    /// The region is encoded in the third letter of the prefix:
+    ///
+    /// - `U` (`SLUS`, `SCUS`): [`Region::NorthAmerica`]
+    /// - `E` (`SLES`, `SCES`): [`Region::Europe`]
+    /// - `P` (`SLPS`, `SCPS`, `SLPM`): [`Region::Japan`]
+    /// - Anything else (e.g. `SLKA`): [`Region::Other`]
+    #[must_use]
+    pub fn from_prefix(prefix: &str) -> Self {
+        match prefix.as_bytes().get(2) {
+            Some(b'U') => Self::NorthAmerica,
+            Some(b'E') => Self::Europe,
+            Some(b'P') => Self::Japan,
+            _ => Self::Other,
+        }
+    }
+}
+
+/// Structured serial of a game, e.g. `SLUS-21348`
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct GameId<'a> {
+    /// Four letter prefix of the serial (`SLUS`, `SCES`, etc.)
+    pub prefix: &'a str,
+
+    /// Numeric part of the serial
+    pub code: u32,
+}
+
+impl<'a> GameId<'a> {
+    /// Extract the serial from the on-disc ELF filename (e.g. `cdrom0:\SLUS_213.48`)
+    ///
+    /// Returns `None` if the filename doesn't follow the standard `XXXX_NNN.NN` pattern
+    #[must_use]
+    pub fn from_elf_path(elf_path: &'a str) -> Option<Self> {
+        let file_name = elf_path.rsplit(['\\', '/', ':']).next()?;
+        let (prefix, number) = file_name.split_once('_')?;
+        let (high, low) = number.split_once('.')?;
+
+        let is_prefix = prefix.len() == 4 && prefix.bytes().all(|b| b.is_ascii_uppercase());
+        let is_number =
+            |digits: &str, len| digits.len() == len && digits.bytes().all(|b| b.is_ascii_digit());
+        if !is_prefix || !is_number(high, 3) || !is_number(low, 2) {
+            return None;
+        }
+
+        let code = high.parse::<u32>().ok()? * 100 + low.parse::<u32>().ok()?;
+        Some(Self { prefix, code })
+    }
+
+    /// On-disc filename of the ELF (e.g. `SLUS_213.48`), the inverse of [`GameId::from_elf_path`]
+    #[must_use]
+    pub fn to_filename(&self) -> String {
+        format!(
+            "{}_{:03}.{:02}",
+            self.prefix,
+            self.code / 100,
+            self.code % 100
+        )
+    }
+
+    /// Path of the ELF as it appears in `BOOT2` (e.g. `cdrom0:\\SLUS_213.48`), without the `;1` suffix
+    #[must_use]
+    pub fn to_boot_path(&self) -> String {
+        format!("cdrom0:\\{}", self.to_filename())
+    }
+
+    /// Region derived from the prefix
+    #[must_use]
+    pub fn region(&self) -> Region {
+        Region::from_prefix(self.prefix)
+    }
+}
+
+impl Display for GameId<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{:05}", self.prefix, self.code)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{GameId, Region};
+
+    #[test]
+    fn from_elf_path() {
+        let id = GameId::from_elf_path("cdrom0:\\SLUS_213.48").unwrap();
+        assert_eq!(id.prefix, "SLUS");
+        assert_eq!(id.code, 21348);
+        assert_eq!(id.region(), Region::NorthAmerica);
+        assert_eq!(id.to_string(), "SLUS-21348");
+
+        let id = GameId::from_elf_path("cdrom0:/SCES_500.01").unwrap();
+        assert_eq!(id.to_string(), "SCES-50001");
+        assert_eq!(id.region(), Region::Europe);
+
+        let id = GameId::from_elf_path("cdrom0:\\SLPM_000.12").unwrap();
+        assert_eq!(id.to_string(), "SLPM-00012");
+        assert_eq!(id.region(), Region::Japan);
+    }
+
+    #[test]
+    fn to_boot_path() {
+        let id = GameId {
+            prefix: "SLUS",
+            code: 21348,
+        };
+        assert_eq!(id.to_filename(), "SLUS_213.48");
+        assert_eq!(id.to_boot_path(), "cdrom0:\\SLUS_213.48");
+
+        for path in [
+            "cdrom0:\\SLUS_213.48",
+            "cdrom0:\\SCES_500.01",
+            "cdrom0:\\SLPM_000.12",
+        ] {
+            let id = GameId::from_elf_path(path).unwrap();
+            assert_eq!(id.to_boot_path(), path);
+            assert_eq!(GameId::from_elf_path(&id.to_boot_path()), Some(id));
+        }
+    }
+
+    #[test]
+    fn non_standard_paths() {
+        assert_eq!(GameId::from_elf_path("cdrom0:\\MAIN.ELF"), None);
+        assert_eq!(GameId::from_elf_path("cdrom0:\\SLUS_21348"), None);
+        assert_eq!(GameId::from_elf_path("cdrom0:\\SLU_213.48"), None);
+        assert_eq!(GameId::from_elf_path("cdrom0:\\SLUS_2a3.48"), None);
+        assert_eq!(GameId::from_elf_path("mass:/APPS/SLUS_213.480"), None);
+    }
+}
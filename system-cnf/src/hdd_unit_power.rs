@@ -0,0 +1,137 @@
+use crate::Error;
+use alloc::borrow::Cow;
+use core::{
+    fmt::{self, Display},
+    str::FromStr,
+};
+
+/// Value of `HDDUNITPOWER`, telling the console which parts of the expansion bay device to power
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum HddUnitPower<'a> {
+    /// Network adapter and HDD (`NICHDD`)
+    NicHdd,
+
+    /// HDD only (`HDD`)
+    Hdd,
+
+    /// Any other value, preserved exactly as it appeared in the file
+    Other(Cow<'a, str>),
+}
+
+impl HddUnitPower<'_> {
+    /// Retrieve the value in its string representation
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::NicHdd => "NICHDD",
+            Self::Hdd => "HDD",
+            Self::Other(other) => other,
+        }
+    }
+
+    /// Convert into an owned `HddUnitPower` that no longer borrows from the input
+    #[must_use]
+    pub fn into_owned(self) -> HddUnitPower<'static> {
+        match self {
+            Self::NicHdd => HddUnitPower::NicHdd,
+            Self::Hdd => HddUnitPower::Hdd,
+            Self::Other(other) => HddUnitPower::Other(Cow::Owned(other.into_owned())),
+        }
+    }
+}
+
+impl Display for HddUnitPower<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl<'a> TryFrom<&'a str> for HddUnitPower<'a> {
+    type Error = Error;
+
+    /// Parse an `HDDUNITPOWER` value, borrowing unknown values from the input
+    ///
+    /// Only empty values are rejected, unknown values are preserved in [`HddUnitPower::Other`]
+    fn try_from(value: &'a str) -> Result<Self, Self::Error> {
+        match value.trim() {
+            "" => Err(Error::EmptyValue),
+            "NICHDD" => Ok(Self::NicHdd),
+            "HDD" => Ok(Self::Hdd),
+            other => Ok(Self::Other(other.into())),
+        }
+    }
+}
+
+impl FromStr for HddUnitPower<'_> {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        HddUnitPower::try_from(s).map(HddUnitPower::into_owned)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for HddUnitPower<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de: 'a, 'a> serde::Deserialize<'de> for HddUnitPower<'a> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Visitor;
+
+        impl<'de> serde::de::Visitor<'de> for Visitor {
+            type Value = HddUnitPower<'de>;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("an HDDUNITPOWER string")
+            }
+
+            fn visit_borrowed_str<E: serde::de::Error>(
+                self,
+                v: &'de str,
+            ) -> Result<Self::Value, E> {
+                HddUnitPower::try_from(v).map_err(E::custom)
+            }
+
+            fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<Self::Value, E> {
+                v.parse().map_err(E::custom)
+            }
+        }
+
+        deserializer.deserialize_str(Visitor)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Error, HddUnitPower};
+    use std::borrow::Cow;
+
+    #[test]
+    fn known() {
+        assert_eq!("NICHDD".parse(), Ok(HddUnitPower::NicHdd));
+        assert_eq!("HDD".parse(), Ok(HddUnitPower::Hdd));
+        assert_eq!(HddUnitPower::NicHdd.to_string(), "NICHDD");
+        assert_eq!(HddUnitPower::Hdd.as_str(), "HDD");
+    }
+
+    #[test]
+    fn other() {
+        let power = HddUnitPower::try_from(" NIC ").unwrap();
+        assert!(matches!(power, HddUnitPower::Other(Cow::Borrowed("NIC"))));
+        assert_eq!(power.to_string(), "NIC");
+
+        // Matching is case-sensitive so unusual spellings survive the round trip
+        let power: HddUnitPower<'_> = "NicHdd".parse().unwrap();
+        assert_eq!(power, HddUnitPower::Other("NicHdd".into()));
+        assert_eq!(power.as_str(), "NicHdd");
+    }
+
+    #[test]
+    fn empty() {
+        assert_eq!("".parse::<HddUnitPower<'_>>(), Err(Error::EmptyValue));
+    }
+}
@@ -1,146 +1,782 @@
 #![doc = include_str!("../README.md")]
 #![forbid(missing_docs, unsafe_code)]
 #![warn(clippy::all, clippy::pedantic)]
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
 
-use std::{
-    borrow::Cow,
-    error::Error as StdError,
-    fmt::{self, Display},
-    str::FromStr,
+extern crate alloc;
+
+use self::parser::Parser;
+use alloc::{borrow::Cow, collections::BTreeMap, string::String, vec::Vec};
+use core::fmt::{self, Display};
+#[cfg(feature = "std")]
+use std::{error::Error as StdError, fs, io, path::Path};
+
+mod boot_device;
+mod builder;
+mod diff;
+mod game_id;
+mod hdd_unit_power;
+mod options;
+mod parser;
+mod validation;
+mod version;
+mod video_mode;
+mod warning;
+
+pub use self::{
+    boot_device::BootDevice,
+    builder::SystemCnfBuilder,
+    diff::FieldDiff,
+    game_id::{GameId, Region},
+    hdd_unit_power::HddUnitPower,
+    options::{DuplicateKeyPolicy, ParseOptions},
+    validation::ValidationError,
+    version::GameVersion,
+    video_mode::VideoMode,
+    warning::Warning,
 };
 
+/// Keys recognised by the parser, in canonical order
+///
+/// Any other key is an unrecognised one, stored in [`SystemCnf::extras`]
+pub const KNOWN_KEYS: &[&str] = &["BOOT2", "BOOT", "VER", "VMODE", "HDDUNITPOWER"];
+
 /// Errors that might occurr when
-#[derive(Debug, Eq, Ord, PartialEq, PartialOrd)]
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
 pub enum Error {
     /// Malformed `SYSTEM.CNF`
-    MalformedFile,
+    MalformedFile {
+        /// 1-based line number the problem occurred on
+        line: usize,
+    },
 
     /// Required field is missing
     MissingField,
 
+    /// Required field is empty
+    EmptyField {
+        /// 1-based line number the problem occurred on
+        line: usize,
+    },
+
+    /// Value parsed on its own (e.g. through `FromStr`) or passed to the [`SystemCnfBuilder`] is empty
+    EmptyValue,
+
     /// Video mode is unknown
-    UnknownVideoMode,
+    UnknownVideoMode {
+        /// 1-based line number the problem occurred on
+        line: usize,
+    },
+
+    /// Version doesn't follow the `M.mm` format
+    InvalidVersion,
+
+    /// Key isn't recognised and unknown keys aren't allowed (see [`ParseOptions::allow_unknown_keys`])
+    UnknownKey {
+        /// 1-based line number the problem occurred on
+        line: usize,
+    },
+
+    /// Key appears more than once and duplicates aren't allowed (see [`ParseOptions::duplicate_keys`])
+    DuplicateKey {
+        /// 1-based line number of the repeated key
+        line: usize,
+    },
+
+    /// The file isn't valid UTF-8
+    InvalidUtf8 {
+        /// Number of bytes that were valid UTF-8
+        valid_up_to: usize,
+    },
+
+    /// The input looks like binary data rather than text, it contains a NUL byte or too many control characters
+    NotText,
+
+    /// Reading the file failed
+    #[cfg(feature = "std")]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    Io(io::ErrorKind),
 }
 
 impl Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{:?}", self)
+        match self {
+            Self::MalformedFile { line } => write!(f, "malformed line {line}"),
+            Self::MissingField => f.write_str("required field is missing"),
+            Self::EmptyField { line } => write!(f, "required field is empty on line {line}"),
+            Self::EmptyValue => f.write_str("value is empty"),
+            Self::UnknownVideoMode { line } => write!(f, "unknown video mode on line {line}"),
+            Self::InvalidVersion => f.write_str("version doesn't follow the M.mm format"),
+            Self::UnknownKey { line } => write!(f, "unknown key on line {line}"),
+            Self::DuplicateKey { line } => write!(f, "duplicate key on line {line}"),
+            Self::InvalidUtf8 { valid_up_to } => {
+                write!(f, "invalid UTF-8 after {valid_up_to} bytes")
+            }
+            Self::NotText => f.write_str("input is binary data rather than text"),
+            #[cfg(feature = "std")]
+            Self::Io(kind) => write!(f, "failed to read file: {kind}"),
+        }
     }
 }
 
+#[cfg(feature = "std")]
 impl StdError for Error {}
 
-/// Video mode of the ROM
-#[derive(Debug, Eq, Ord, PartialEq, PartialOrd)]
-pub enum VideoMode {
-    /// NTSC
-    Ntsc,
+/// Line ending used when serialising a `SYSTEM.CNF` file
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum LineEnding {
+    /// `\r\n`, expected by the PS2
+    #[default]
+    Crlf,
 
-    /// PAL
-    Pal,
+    /// `\n`
+    Lf,
 }
 
-impl VideoMode {
-    /// Retrieve the value of the video mode in its string representation
-    fn as_str(&self) -> &str {
+impl LineEnding {
+    /// Retrieve the line ending as a string
+    #[must_use]
+    pub fn as_str(&self) -> &'static str {
         match self {
-            Self::Ntsc => "NTSC",
-            Self::Pal => "PAL",
+            Self::Crlf => "\r\n",
+            Self::Lf => "\n",
         }
     }
 }
 
-impl FromStr for VideoMode {
-    type Err = Error;
+/// Field of a `SYSTEM.CNF` file, used to record the order fields appeared in
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub enum Field {
+    /// `BOOT2` ([`SystemCnf::elf_path`])
+    ElfPath,
+
+    /// `BOOT` ([`SystemCnf::ps1_boot`])
+    Ps1Boot,
+
+    /// `VER` ([`SystemCnf::version`])
+    Version,
+
+    /// `VMODE` ([`SystemCnf::video_mode`])
+    VideoMode,
+
+    /// `HDDUNITPOWER` ([`SystemCnf::hdd_unit_power`])
+    HddUnitPower,
+
+    /// Unrecognised field at the given index of [`SystemCnf::extras`]
+    Extra(usize),
+}
+
+/// Key the boot executable is taken from
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub enum BootKind {
+    /// PS1 executable (`BOOT`)
+    Ps1,
+
+    /// PS2 executable (`BOOT2`)
+    Ps2,
+}
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s.trim() {
-            "NTSC" => Ok(Self::Ntsc),
-            "PAL" => Ok(Self::Pal),
-            _ => Err(Error::UnknownVideoMode),
+impl BootKind {
+    /// Retrieve the key of the boot kind
+    #[must_use]
+    pub fn key(&self) -> &'static str {
+        match self {
+            Self::Ps1 => "BOOT",
+            Self::Ps2 => "BOOT2",
         }
     }
 }
 
 /// Parsed form of a `SYSTEM.CNF` file
-#[derive(Debug, Eq, Ord, PartialEq, PartialOrd)]
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
 pub struct SystemCnf<'a> {
-    /// Path to the initial ELF file
-    pub elf_path: Cow<'a, str>,
+    /// Path to the initial ELF file (`BOOT2`)
+    #[cfg_attr(feature = "serde", serde(borrow))]
+    pub elf_path: Option<Cow<'a, str>>,
+
+    /// Path to the PS1 executable (`BOOT`)
+    #[cfg_attr(feature = "serde", serde(borrow))]
+    pub ps1_boot: Option<Cow<'a, str>>,
 
     /// Version of the game
+    #[cfg_attr(feature = "serde", serde(borrow))]
     pub version: Cow<'a, str>,
 
     /// Video mode
-    pub video_mode: VideoMode,
+    #[cfg_attr(feature = "serde", serde(borrow))]
+    pub video_mode: VideoMode<'a>,
+
+    /// Expansion bay devices the game needs powered (`HDDUNITPOWER`)
+    #[cfg_attr(feature = "serde", serde(borrow))]
+    pub hdd_unit_power: Option<HddUnitPower<'a>>,
+
+    /// Unrecognised key-value pairs, in the order they appeared in the file
+    pub extras: Vec<(Cow<'a, str>, Cow<'a, str>)>,
 
-    /// ???
-    pub hdd_unit_power: Option<Cow<'a, str>>,
+    /// Order the fields appeared in the file, `Display` emits them in the same order. Empty if the file used the
+    /// canonical order
+    ///
+    /// Fields that are set but not listed are emitted afterwards in the canonical order (`BOOT2`, `BOOT`, `VER`,
+    /// `VMODE`, `HDDUNITPOWER`, followed by the extras). Leave it empty to always use the canonical order
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub order: Vec<Field>,
+
+    /// Fields whose value was wrapped in double quotes in the file, `Display` wraps them in quotes again
+    ///
+    /// Only filled in if [`ParseOptions::strip_quotes`] is enabled
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub quoted: Vec<Field>,
 }
 
 impl<'a> SystemCnf<'a> {
+    /// Read and parse a `SYSTEM.CNF` file from the given path
+    ///
+    /// # Errors
+    ///
+    /// - Reading the file failed
+    /// - Parsing the file failed (see [`SystemCnf::from_bytes`])
+    #[cfg(feature = "std")]
+    pub fn from_path(path: impl AsRef<Path>) -> Result<SystemCnf<'static>, Error> {
+        let raw_cnf = fs::read(path).map_err(|err| Error::Io(err.kind()))?;
+        SystemCnf::from_bytes(&raw_cnf).map(SystemCnf::into_owned)
+    }
+
+    /// Read and parse a `SYSTEM.CNF` file from a reader, e.g. an entry of a compressed archive
+    ///
+    /// The file is read and parsed line by line, accepting the same input as [`SystemCnf::from_bytes`]. With the
+    /// `encoding` feature, lines that aren't valid UTF-8 are decoded as Shift-JIS. All values are owned
+    ///
+    /// # Errors
+    ///
+    /// - Reading failed
+    /// - Parsing the file failed (see [`SystemCnf::from_bytes`])
+    #[cfg(feature = "std")]
+    pub fn from_reader<R: io::BufRead>(reader: R) -> Result<SystemCnf<'static>, Error> {
+        Parser::new(ParseOptions::default(), None).parse_reader(reader)
+    }
+
+    /// Parse a `SYSTEM.CNF` file from raw bytes
+    ///
+    /// A leading UTF-8 byte order mark is stripped. With the `encoding` feature, files that aren't valid UTF-8
+    /// are decoded as Shift-JIS instead, producing an owned `SystemCnf`
+    ///
+    /// # Errors
+    ///
+    /// - The bytes are binary data rather than text
+    /// - The bytes aren't valid UTF-8 (or Shift-JIS, with the `encoding` feature)
+    /// - Parsing the file failed (see [`SystemCnf::parse`])
+    pub fn from_bytes(bytes: &'a [u8]) -> Result<Self, Error> {
+        let bytes = bytes.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(bytes);
+        if parser::looks_binary(bytes) {
+            return Err(Error::NotText);
+        }
+
+        // Only checked once, decoding Shift-JIS doesn't turn text into binary data or the other way around
+        let parser = Parser::new(ParseOptions::default(), None);
+        match core::str::from_utf8(bytes) {
+            Ok(raw_cnf) => parser.parse_text(raw_cnf),
+            #[cfg(feature = "encoding")]
+            Err(err) => {
+                let raw_cnf = encoding_rs::SHIFT_JIS
+                    .decode_without_bom_handling_and_without_replacement(bytes)
+                    .ok_or(Error::InvalidUtf8 {
+                        valid_up_to: err.valid_up_to(),
+                    })?;
+                parser.parse_text(&raw_cnf).map(SystemCnf::into_owned)
+            }
+            #[cfg(not(feature = "encoding"))]
+            Err(err) => Err(Error::InvalidUtf8 {
+                valid_up_to: err.valid_up_to(),
+            }),
+        }
+    }
+
+    /// Create a `SystemCnf` booting the given ELF, leaving the optional fields unset
+    ///
+    /// Unlike [`SystemCnf::builder`], the values aren't checked for being empty
+    #[must_use]
+    pub fn new(
+        elf_path: impl Into<Cow<'a, str>>,
+        version: impl Into<Cow<'a, str>>,
+        video_mode: VideoMode<'a>,
+    ) -> Self {
+        Self {
+            elf_path: Some(elf_path.into()),
+            ps1_boot: None,
+            version: version.into(),
+            video_mode,
+            hdd_unit_power: None,
+            extras: Vec::new(),
+            order: Vec::new(),
+            quoted: Vec::new(),
+        }
+    }
+
+    /// Create a builder for constructing a `SystemCnf` programmatically
+    #[must_use]
+    pub fn builder() -> SystemCnfBuilder<'a> {
+        SystemCnfBuilder::new()
+    }
+
     /// Parse a `SYSTEM.CNF` file
     ///
+    /// `\r\n`, `\n` and bare `\r` line endings are accepted. Lines without a `=` (such as blank lines) are skipped
+    ///
+    /// Lines starting with `#` or `;` are treated as comments and skipped. Trailing comments are *not* stripped
+    /// since `;` is also part of the `BOOT2` path syntax (`cdrom0:\SLUS_213.48;1`)
+    ///
+    /// A leading UTF-8 byte order mark is stripped. Other byte order marks aren't handled
+    ///
+    /// An empty `HDDUNITPOWER` is treated as not set. Empty unrecognised fields are preserved as-is
+    ///
+    /// All values borrow from `raw_cnf`. Parsing performs no heap allocations unless the file contains
+    /// unrecognised keys (which are stored in [`SystemCnf::extras`]) or its fields aren't in the canonical order
+    /// (which is stored in [`SystemCnf::order`])
+    ///
     /// # Errors
     ///
-    /// - The video mode is invalid
-    /// - Required fields are missing
+    /// - The file is binary data rather than text, as it contains a NUL byte or mostly control characters
+    /// - The video mode is empty
+    /// - Required fields are missing (at least one of `BOOT2` and `BOOT` has to be present)
+    /// - `BOOT2`, `BOOT` or `VER` is empty
     /// - The file is somehow malformed
     pub fn parse(raw_cnf: &'a str) -> Result<Self, Error> {
-        // Not really a fan of this parsing approach but I can't think of anything better ATM
-        let mut elf_path = None;
-        let mut version = None;
-        let mut video_mode = None;
-        let mut hdd_unit_power = None;
-
-        for line in raw_cnf.lines() {
-            let mut kv_iter = line.split('=');
-            let key = kv_iter.next().ok_or(Error::MalformedFile)?;
-            let value = kv_iter.next().ok_or(Error::MalformedFile)?;
-
-            match key.trim() {
-                "BOOT2" => {
-                    let mut path = value.trim();
-                    path = path.strip_suffix(";1").unwrap_or(path);
-                    elf_path = Some(path.into());
+        Self::parse_with(raw_cnf, ParseOptions::default())
+    }
+
+    /// Parse a `SYSTEM.CNF` file with the given options
+    ///
+    /// See [`SystemCnf::parse`] for the accepted syntax
+    ///
+    /// # Errors
+    ///
+    /// - See [`SystemCnf::parse`]
+    /// - The file contains something the options don't allow
+    pub fn parse_with(raw_cnf: &'a str, options: ParseOptions) -> Result<Self, Error> {
+        Parser::new(options, None).parse(raw_cnf)
+    }
+
+    /// Parse a `SYSTEM.CNF` file like [`SystemCnf::parse`], additionally reporting the issues that were tolerated
+    ///
+    /// Warnings are reported in line order. They're also returned if parsing fails, covering the lines up to the error
+    pub fn parse_with_warnings(raw_cnf: &'a str) -> (Result<Self, Error>, Vec<Warning>) {
+        let mut warnings = Vec::new();
+        let result = Parser::new(ParseOptions::default(), Some(&mut warnings)).parse(raw_cnf);
+        (result, warnings)
+    }
+
+    /// Parse a `SYSTEM.CNF` file with the default options, reporting every error instead of only the first one
+    ///
+    /// Each missing required field is reported as its own [`Error::MissingField`]
+    ///
+    /// # Errors
+    ///
+    /// - See [`SystemCnf::parse`]
+    pub fn parse_collect_errors(raw_cnf: &'a str) -> Result<Self, Vec<Error>> {
+        Parser::new(ParseOptions::default(), None).parse_collect_errors(raw_cnf)
+    }
+
+    /// Extract the game serial from the ELF path
+    ///
+    /// Returns `None` if the ELF filename doesn't follow the standard serial pattern
+    #[must_use]
+    pub fn game_id(&self) -> Option<GameId<'_>> {
+        GameId::from_elf_path(self.elf_path.as_deref()?)
+    }
+
+    /// Region of the game, derived from the prefix of its serial
+    ///
+    /// See [`Region::from_prefix`] for the mapping. Returns `None` if there is no standard serial, see
+    /// [`SystemCnf::game_id`]
+    #[must_use]
+    pub fn region(&self) -> Option<Region> {
+        self.game_id().map(|game_id| game_id.region())
+    }
+
+    /// Filename of the ELF without the device (e.g. `cdrom0:`, `mass:`, `host:`) and directories
+    ///
+    /// Both `\\` and `/` are accepted as path separators. Returns `None` if `BOOT2` isn't set
+    #[must_use]
+    pub fn elf_filename(&self) -> Option<&str> {
+        self.elf_path.as_deref()?.rsplit(['\\', '/', ':']).next()
+    }
+
+    /// Path of the ELF without the device prefix (e.g. `\\DATA\\SLUS_213.48` for `cdrom0:\\DATA\\SLUS_213.48`)
+    ///
+    /// Everything after the first `:` is returned, or the whole path if there is no device.
+    /// Unlike [`SystemCnf::elf_filename`] the directories are kept. Returns `None` if `BOOT2` isn't set
+    #[must_use]
+    pub fn elf_path_without_device(&self) -> Option<&str> {
+        let elf_path = self.elf_path.as_deref()?;
+        Some(elf_path.split_once(':').map_or(elf_path, |(_, path)| path))
+    }
+
+    /// Major and minor components of the version, `None` if the version isn't two numbers separated by a `.`
+    ///
+    /// Unlike [`SystemCnf::game_version`] the minor component may have any number of digits
+    #[must_use]
+    pub fn version_parts(&self) -> Option<(u32, u32)> {
+        let (major, minor) = self.version.trim().split_once('.')?;
+        let parse = |digits: &str| {
+            if digits.bytes().all(|b| b.is_ascii_digit()) {
+                digits.parse().ok()
+            } else {
+                None
+            }
+        };
+
+        Some((parse(major)?, parse(minor)?))
+    }
+
+    /// Parse the version into its structured form
+    ///
+    /// # Errors
+    ///
+    /// - The version doesn't follow the `M.mm` format
+    pub fn game_version(&self) -> Result<GameVersion, Error> {
+        self.version.parse()
+    }
+
+    /// Check that the config looks like the one of a retail disc
+    ///
+    /// The boot path has to point to an executable named after the serial in the root of the disc
+    /// (`cdrom0:\SLUS_213.48`, or `cdrom:\SLUS_000.67` for PS1 executables) and the version has to follow the `M.mm`
+    /// format. This is stricter than [`SystemCnf::parse`], which accepts any values
+    ///
+    /// # Errors
+    ///
+    /// - See [`ValidationError`]
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        let (path, device) = match self.boot_kind() {
+            Some(BootKind::Ps2) => (self.elf_path.as_deref(), "cdrom0:\\"),
+            Some(BootKind::Ps1) => (self.ps1_boot.as_deref(), "cdrom:\\"),
+            None => (None, ""),
+        };
+        let path = path.ok_or(ValidationError::MissingBootPath)?;
+
+        let file_name = path
+            .strip_prefix(device)
+            .filter(|file_name| !file_name.contains(['\\', '/']))
+            .ok_or(ValidationError::InvalidDevice)?;
+        if GameId::from_elf_path(file_name).is_none() {
+            return Err(ValidationError::InvalidSerial);
+        }
+
+        self.game_version()
+            .map_err(|_| ValidationError::InvalidVersion)?;
+
+        Ok(())
+    }
+
+    /// Whether this is the config of a PS1 disc (`BOOT` is set, `BOOT2` is absent)
+    #[must_use]
+    pub fn is_ps1_disc(&self) -> bool {
+        self.ps1_boot.is_some() && self.elf_path.is_none()
+    }
+
+    /// Which key the console boots from, `BOOT2` takes precedence over `BOOT`
+    #[must_use]
+    pub fn boot_kind(&self) -> Option<BootKind> {
+        if self.elf_path.is_some() {
+            Some(BootKind::Ps2)
+        } else if self.ps1_boot.is_some() {
+            Some(BootKind::Ps1)
+        } else {
+            None
+        }
+    }
+
+    /// Device `BOOT2` loads the ELF from, parsed from the prefix before the first `:`
+    ///
+    /// Returns `None` if `BOOT2` isn't set or doesn't contain a device prefix
+    #[must_use]
+    pub fn boot_device(&self) -> Option<BootDevice<'_>> {
+        let (device, _) = self.elf_path.as_deref()?.split_once(':')?;
+        BootDevice::try_from(device).ok()
+    }
+
+    /// Path to the initial ELF file (`BOOT2`), borrowed without cloning
+    #[must_use]
+    pub fn elf_path_str(&self) -> Option<&str> {
+        self.elf_path.as_deref()
+    }
+
+    /// Version of the game, borrowed without cloning
+    #[must_use]
+    pub fn version_str(&self) -> &str {
+        &self.version
+    }
+
+    /// `HDDUNITPOWER` value in its string representation, borrowed without cloning
+    #[must_use]
+    pub fn hdd_unit_power_str(&self) -> Option<&str> {
+        self.hdd_unit_power.as_ref().map(HddUnitPower::as_str)
+    }
+
+    /// Path to the executable the console boots, taken from `BOOT2` or, if it is absent, `BOOT`
+    #[must_use]
+    pub fn boot_path(&self) -> Option<&str> {
+        self.elf_path.as_deref().or(self.ps1_boot.as_deref())
+    }
+
+    /// Iterate over all recognised fields that are set as key-value pairs
+    ///
+    /// The keys are yielded in their canonical uppercase form and order, regardless of [`SystemCnf::order`].
+    /// Unrecognised fields (see [`SystemCnf::extras`]) aren't included
+    pub fn entries(&self) -> impl Iterator<Item = (&'static str, Cow<'_, str>)> {
+        [
+            ("BOOT2", self.elf_path.as_deref().map(Cow::Borrowed)),
+            ("BOOT", self.ps1_boot.as_deref().map(Cow::Borrowed)),
+            ("VER", Some(Cow::Borrowed(&*self.version))),
+            ("VMODE", Some(Cow::Borrowed(self.video_mode.as_str()))),
+            (
+                "HDDUNITPOWER",
+                self.hdd_unit_power
+                    .as_ref()
+                    .map(|hdd_unit_power| Cow::Borrowed(hdd_unit_power.as_str())),
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(key, value)| Some((key, value?)))
+    }
+
+    /// Collect all fields that are set, including unrecognised ones, into a map ordered by key
+    ///
+    /// Recognised keys use their canonical uppercase form. If an unrecognised key appears multiple times, the last
+    /// value is used
+    #[must_use]
+    pub fn to_map(&self) -> BTreeMap<Cow<'_, str>, Cow<'_, str>> {
+        self.entries()
+            .map(|(key, value)| (Cow::Borrowed(key), value))
+            .chain(
+                self.extras
+                    .iter()
+                    .map(|(key, value)| (Cow::Borrowed(&**key), Cow::Borrowed(&**value))),
+            )
+            .collect()
+    }
+
+    /// List the fields that differ between this config and `other`, ordered by key
+    ///
+    /// Unrecognised fields are compared as well, fields that are only set in one of the configs have `None` as
+    /// the value of the other one
+    #[must_use]
+    pub fn diff<'b>(&'b self, other: &'b SystemCnf<'_>) -> Vec<FieldDiff<'b>> {
+        let mut old = self.to_map();
+        let mut diffs = Vec::new();
+        for (key, new) in other.to_map() {
+            let old = old.remove(&key);
+            if old.as_ref() != Some(&new) {
+                diffs.push(FieldDiff {
+                    key,
+                    old,
+                    new: Some(new),
+                });
+            }
+        }
+        diffs.extend(old.into_iter().map(|(key, old)| FieldDiff {
+            key,
+            old: Some(old),
+            new: None,
+        }));
+        diffs.sort();
+
+        diffs
+    }
+
+    /// Serialise the `SystemCnf` using the given line ending
+    ///
+    /// The `Display` implementation always uses `\r\n` for on-disc compatibility
+    #[must_use]
+    pub fn to_string_with_line_ending(&self, line_ending: LineEnding) -> String {
+        let mut out = String::new();
+        // Writing into a `String` can't fail
+        let _ = self.encode(&mut out, line_ending);
+        out
+    }
+
+    /// Write the serialised form (with `\r\n` line endings) directly into an `io::Write` sink
+    ///
+    /// The output is identical to the `Display` implementation but avoids the intermediate `String`
+    ///
+    /// # Errors
+    ///
+    /// - Writing into the sink failed
+    #[cfg(feature = "std")]
+    pub fn write_to<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        write!(writer, "{self}")
+    }
+
+    /// Order the fields that are set are serialised in, see [`SystemCnf::order`]
+    fn field_order(&self) -> Vec<Field> {
+        let canonical = [
+            Field::ElfPath,
+            Field::Ps1Boot,
+            Field::Version,
+            Field::VideoMode,
+            Field::HddUnitPower,
+        ]
+        .into_iter()
+        .chain((0..self.extras.len()).map(Field::Extra));
+
+        let mut order = Vec::new();
+        for field in self.order.iter().copied().chain(canonical) {
+            let is_set = match field {
+                Field::ElfPath => self.elf_path.is_some(),
+                Field::Ps1Boot => self.ps1_boot.is_some(),
+                Field::Version | Field::VideoMode => true,
+                Field::HddUnitPower => self.hdd_unit_power.is_some(),
+                Field::Extra(index) => index < self.extras.len(),
+            };
+            if is_set && !order.contains(&field) {
+                order.push(field);
+            }
+        }
+
+        order
+    }
+
+    /// Write the serialised form into a `fmt::Write` sink
+    fn encode(&self, w: &mut impl fmt::Write, line_ending: LineEnding) -> fmt::Result {
+        let le = line_ending.as_str();
+
+        for field in self.field_order() {
+            let q = if self.quoted.contains(&field) {
+                "\""
+            } else {
+                ""
+            };
+            match field {
+                Field::ElfPath => write!(
+                    w,
+                    "BOOT2 = {q}{};1{q}{le}",
+                    self.elf_path.as_deref().unwrap_or_default()
+                )?,
+                Field::Ps1Boot => write!(
+                    w,
+                    "BOOT = {q}{};1{q}{le}",
+                    self.ps1_boot.as_deref().unwrap_or_default()
+                )?,
+                Field::Version => write!(w, "VER = {q}{}{q}{le}", self.version)?,
+                Field::VideoMode => {
+                    write!(w, "VMODE = {q}{}{q}{le}", self.video_mode.as_str())?;
+                }
+                Field::HddUnitPower => write!(
+                    w,
+                    "HDDUNITPOWER = {q}{}{q}{le}",
+                    self.hdd_unit_power
+                        .as_ref()
+                        .map(HddUnitPower::as_str)
+                        .unwrap_or_default()
+                )?,
+                Field::Extra(index) => {
+                    let (key, value) = &self.extras[index];
+                    write!(w, "{key} = {q}{value}{q}{le}")?;
                 }
-                "VER" => version = Some(value.trim().into()),
-                "VMODE" => video_mode = Some(value.parse()?),
-                "HDDUNITPOWER" => hdd_unit_power = Some(value.trim().into()),
-                _ => (),
             }
         }
 
-        Ok(Self {
-            elf_path: elf_path.ok_or(Error::MissingField)?,
-            version: version.ok_or(Error::MissingField)?,
-            video_mode: video_mode.ok_or(Error::MissingField)?,
-            hdd_unit_power,
-        })
+        Ok(())
+    }
+
+    /// Overlay the fields of `other` on top of this config
+    ///
+    /// `VER` and `VMODE` are always taken from `other`. Optional fields are only overridden if they are set in
+    /// `other`, `None` leaves the value in `self` untouched. Extras replace the value of an extra with the same
+    /// (case-insensitively matched) key or are appended otherwise
+    pub fn merge(&mut self, other: &SystemCnf<'a>) {
+        let overlay = |field: &mut Option<Cow<'a, str>>, value: &Option<Cow<'a, str>>| {
+            if value.is_some() {
+                field.clone_from(value);
+            }
+        };
+        overlay(&mut self.elf_path, &other.elf_path);
+        overlay(&mut self.ps1_boot, &other.ps1_boot);
+        if other.hdd_unit_power.is_some() {
+            self.hdd_unit_power.clone_from(&other.hdd_unit_power);
+        }
+        self.version.clone_from(&other.version);
+        self.video_mode.clone_from(&other.video_mode);
+
+        for (key, value) in &other.extras {
+            match self
+                .extras
+                .iter_mut()
+                .find(|(existing, _)| existing.eq_ignore_ascii_case(key))
+            {
+                Some((_, existing)) => existing.clone_from(value),
+                None => self.extras.push((key.clone(), value.clone())),
+            }
+        }
+    }
+
+    /// Consuming variant of [`SystemCnf::merge`]
+    #[must_use]
+    pub fn merged(mut self, other: &SystemCnf<'a>) -> Self {
+        self.merge(other);
+        self
+    }
+
+    /// Convert into an owned `SystemCnf` that no longer borrows from the input
+    #[must_use]
+    pub fn into_owned(self) -> SystemCnf<'static> {
+        SystemCnf {
+            elf_path: self
+                .elf_path
+                .map(|elf_path| Cow::Owned(elf_path.into_owned())),
+            ps1_boot: self
+                .ps1_boot
+                .map(|ps1_boot| Cow::Owned(ps1_boot.into_owned())),
+            version: Cow::Owned(self.version.into_owned()),
+            video_mode: self.video_mode.into_owned(),
+            hdd_unit_power: self.hdd_unit_power.map(HddUnitPower::into_owned),
+            extras: self
+                .extras
+                .into_iter()
+                .map(|(key, value)| (Cow::Owned(key.into_owned()), Cow::Owned(value.into_owned())))
+                .collect(),
+            order: self.order,
+            quoted: self.quoted,
+        }
+    }
+}
+
+impl<'a> TryFrom<&'a str> for SystemCnf<'a> {
+    type Error = Error;
+
+    /// See [`SystemCnf::parse`]
+    fn try_from(raw_cnf: &'a str) -> Result<Self, Self::Error> {
+        Self::parse(raw_cnf)
+    }
+}
+
+impl<'a> TryFrom<&'a [u8]> for SystemCnf<'a> {
+    type Error = Error;
+
+    /// See [`SystemCnf::from_bytes`]
+    fn try_from(bytes: &'a [u8]) -> Result<Self, Self::Error> {
+        Self::from_bytes(bytes)
     }
 }
 
 impl Display for SystemCnf<'_> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(
-            f,
-            "BOOT2 = {};1\r\nVER = {}\r\nVMODE = {}\r\n",
-            self.elf_path,
-            self.version,
-            self.video_mode.as_str()
-        )?;
-        if let Some(ref hdd_unit_power) = self.hdd_unit_power {
-            write!(f, "HDDUNITPOWER = {}\r\n", hdd_unit_power)?;
-        }
-
-        Ok(())
+        self.encode(f, LineEnding::Crlf)
     }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::{SystemCnf, VideoMode};
-    use std::str;
+    use crate::{
+        BootDevice, BootKind, DuplicateKeyPolicy, Error, Field, FieldDiff, GameVersion,
+        HddUnitPower, LineEnding, ParseOptions, Region, SystemCnf, ValidationError, VideoMode,
+        Warning, KNOWN_KEYS,
+    };
+    use std::{borrow::Cow, collections::HashSet, str};
 
     static SYSTEM_CNF: &[u8] = &[
         0x42, 0x4F, 0x4F, 0x54, 0x32, 0x20, 0x3D, 0x20, 0x63, 0x64, 0x72, 0x6F, 0x6D, 0x30, 0x3A,
@@ -159,14 +795,1168 @@ mod test {
         assert_eq!(SYSTEM_CNF, encoded.as_bytes());
     }
 
+    #[test]
+    fn new() {
+        let cnf = SystemCnf::new("cdrom0:\\SLES_123.45", "1.01", VideoMode::Pal);
+        assert_eq!(
+            cnf.to_string(),
+            "BOOT2 = cdrom0:\\SLES_123.45;1\r\nVER = 1.01\r\nVMODE = PAL\r\n"
+        );
+
+        let built = SystemCnf::builder()
+            .elf_path("cdrom0:\\SLES_123.45")
+            .version("1.01")
+            .video_mode(VideoMode::Pal)
+            .build()
+            .unwrap();
+        assert_eq!(cnf, built);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn write_to() {
+        let txt = str::from_utf8(SYSTEM_CNF).unwrap();
+        let parsed = SystemCnf::parse(txt).unwrap();
+
+        let mut buf = Vec::new();
+        parsed.write_to(&mut buf).unwrap();
+        assert_eq!(buf, SYSTEM_CNF);
+    }
+
+    #[test]
+    fn encode_lf() {
+        let txt = str::from_utf8(SYSTEM_CNF).unwrap();
+        let parsed = SystemCnf::parse(txt).unwrap();
+        let encoded = parsed.to_string_with_line_ending(LineEnding::Lf);
+
+        assert!(!encoded.contains('\r'));
+        assert_eq!(encoded, txt.replace("\r\n", "\n"));
+        assert_eq!(parsed.to_string_with_line_ending(LineEnding::Crlf), txt);
+    }
+
     #[test]
     fn decode() {
         let txt = str::from_utf8(SYSTEM_CNF).unwrap();
         let parsed = SystemCnf::parse(txt).unwrap();
 
-        assert_eq!(parsed.elf_path, "cdrom0:\\SLUS_213.48");
+        assert_eq!(parsed.elf_path.as_deref(), Some("cdrom0:\\SLUS_213.48"));
         assert_eq!(parsed.version, "1.00");
         assert_eq!(parsed.video_mode, VideoMode::Ntsc);
         assert_eq!(parsed.hdd_unit_power, None);
+        assert!(parsed.extras.is_empty());
+        assert!(!parsed.is_ps1_disc());
+    }
+
+    #[test]
+    fn ps1_boot() {
+        let txt = "BOOT = cdrom:\\SLUS_000.67;1\r\nVER = 1.00\r\nVMODE = NTSC\r\n";
+        let parsed = SystemCnf::parse(txt).unwrap();
+
+        assert_eq!(parsed.elf_path, None);
+        assert_eq!(parsed.ps1_boot.as_deref(), Some("cdrom:\\SLUS_000.67"));
+        assert!(parsed.is_ps1_disc());
+        assert_eq!(parsed.boot_kind(), Some(BootKind::Ps1));
+        assert_eq!(parsed.boot_path(), Some("cdrom:\\SLUS_000.67"));
+        assert_eq!(parsed.to_string(), txt);
+    }
+
+    #[test]
+    fn ps1_config() {
+        let txt = "BOOT = cdrom:\\SCUS_944.26;1\r\nTCB = 4\r\nEVENT = 10\r\nSTACK = 801FFF00\r\nVER = 1.00\r\nVMODE = NTSC\r\n";
+        let parsed = SystemCnf::parse(txt).unwrap();
+
+        assert_eq!(parsed.boot_kind(), Some(BootKind::Ps1));
+        assert_eq!(parsed.boot_path(), Some("cdrom:\\SCUS_944.26"));
+        assert_eq!(
+            parsed.extras,
+            [
+                ("TCB".into(), "4".into()),
+                ("EVENT".into(), "10".into()),
+                ("STACK".into(), "801FFF00".into()),
+            ]
+        );
+        assert_eq!(parsed.to_string(), txt);
+    }
+
+    #[test]
+    fn boot_device() {
+        let device = |txt: &str| {
+            SystemCnf::parse(txt)
+                .unwrap()
+                .boot_device()
+                .map(BootDevice::into_owned)
+        };
+
+        assert_eq!(
+            device("BOOT2 = cdrom0:\\SLUS_213.48;1\nVER = 1.00\nVMODE = NTSC\n"),
+            Some(BootDevice::Cdrom)
+        );
+        assert_eq!(
+            device("BOOT2 = mass:/APPS/OPNPS2LD.ELF\nVER = 1.00\nVMODE = NTSC\n"),
+            Some(BootDevice::Mass)
+        );
+        assert_eq!(
+            device("BOOT2 = pfs0:/BOOT.ELF\nVER = 1.00\nVMODE = NTSC\n"),
+            Some(BootDevice::Other("pfs0".into()))
+        );
+        assert_eq!(device("BOOT2 = BOOT.ELF\nVER = 1.00\nVMODE = NTSC\n"), None);
+        assert_eq!(
+            device("BOOT = cdrom:\\SLUS_000.67;1\nVER = 1.00\nVMODE = NTSC\n"),
+            None
+        );
+    }
+
+    #[test]
+    fn hybrid_boot() {
+        let txt = "BOOT2 = cdrom0:\\SLUS_213.48;1\r\nBOOT = cdrom:\\SLUS_000.67;1\r\nVER = 1.00\r\nVMODE = NTSC\r\n";
+        let parsed = SystemCnf::parse(txt).unwrap();
+
+        assert!(!parsed.is_ps1_disc());
+        assert_eq!(parsed.boot_kind(), Some(BootKind::Ps2));
+        assert_eq!(parsed.boot_path(), Some("cdrom0:\\SLUS_213.48"));
+        assert_eq!(parsed.to_string(), txt);
+    }
+
+    #[test]
+    fn missing_boot() {
+        let txt = "VER = 1.00\r\nVMODE = NTSC\r\n";
+        assert_eq!(SystemCnf::parse(txt), Err(Error::MissingField));
+    }
+
+    #[test]
+    fn line_endings() {
+        let crlf = str::from_utf8(SYSTEM_CNF).unwrap();
+        let lf = crlf.replace("\r\n", "\n");
+        let mixed = "BOOT2 = cdrom0:\\SLUS_213.48;1\nVER = 1.00\r\nVMODE = NTSC\n";
+
+        let expected = SystemCnf::parse(crlf).unwrap();
+        assert_eq!(SystemCnf::parse(&lf).unwrap(), expected);
+        assert_eq!(SystemCnf::parse(mixed).unwrap(), expected);
+    }
+
+    #[test]
+    fn bare_cr_line_endings() {
+        let crlf = str::from_utf8(SYSTEM_CNF).unwrap();
+        let cr = crlf.replace("\r\n", "\r");
+        assert_eq!(
+            SystemCnf::parse(&cr).unwrap(),
+            SystemCnf::parse(crlf).unwrap()
+        );
+
+        // Line numbers count bare `\r` terminators as well
+        let txt = "BOOT2 = cdrom0:\\SLUS_213.48;1\rVER = 1.00\r\r = NTSC\r";
+        assert_eq!(SystemCnf::parse(txt), Err(Error::MalformedFile { line: 4 }));
+    }
+
+    #[test]
+    fn byte_order_mark() {
+        let with_bom = [&[0xEF, 0xBB, 0xBF], SYSTEM_CNF].concat();
+        let parsed = SystemCnf::parse(str::from_utf8(&with_bom).unwrap()).unwrap();
+
+        let expected = SystemCnf::parse(str::from_utf8(SYSTEM_CNF).unwrap()).unwrap();
+        assert_eq!(parsed, expected);
+    }
+
+    #[test]
+    fn from_bytes() {
+        let expected = SystemCnf::parse(str::from_utf8(SYSTEM_CNF).unwrap()).unwrap();
+        assert_eq!(SystemCnf::from_bytes(SYSTEM_CNF).unwrap(), expected);
+
+        let with_bom = [&[0xEF, 0xBB, 0xBF], SYSTEM_CNF].concat();
+        assert_eq!(SystemCnf::from_bytes(&with_bom).unwrap(), expected);
+    }
+
+    #[test]
+    fn try_from() {
+        let txt = str::from_utf8(SYSTEM_CNF).unwrap();
+        let expected = SystemCnf::parse(txt).unwrap();
+        assert_eq!(SystemCnf::try_from(txt), Ok(expected.clone()));
+        assert_eq!(SystemCnf::try_from(SYSTEM_CNF), Ok(expected.clone()));
+
+        let parsed = [txt, "VER = 1.00\r\n"]
+            .into_iter()
+            .map(SystemCnf::try_from)
+            .collect::<Result<Vec<_>, _>>();
+        assert_eq!(parsed, Err(Error::MissingField));
+        let parsed: Vec<SystemCnf<'_>> = [SYSTEM_CNF, SYSTEM_CNF]
+            .into_iter()
+            .map(TryInto::try_into)
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(parsed, [expected.clone(), expected]);
+    }
+
+    #[test]
+    fn from_bytes_invalid_utf8() {
+        let invalid = [b"BOOT2 = cdrom0:\\", &[0xFF][..], b";1\r\n"].concat();
+        assert_eq!(
+            SystemCnf::from_bytes(&invalid),
+            Err(Error::InvalidUtf8 { valid_up_to: 16 })
+        );
+    }
+
+    #[test]
+    fn not_text() {
+        let with_nul = b"BOOT2 = cdrom0:\\SLUS_213.48;1\r\nVER = 1.00\0\r\nVMODE = NTSC\r\n";
+        assert_eq!(SystemCnf::from_bytes(with_nul), Err(Error::NotText));
+        let txt = str::from_utf8(with_nul).unwrap();
+        assert_eq!(SystemCnf::parse(txt), Err(Error::NotText));
+        assert_eq!(
+            SystemCnf::parse_collect_errors(txt),
+            Err(vec![Error::NotText])
+        );
+
+        // Start of an ELF header, which is rejected before it's found not to be UTF-8
+        let elf = [
+            0x7F, b'E', b'L', b'F', 0x01, 0x01, 0x01, 0x00, 0x00, 0x00, 0xFF, 0xFE,
+        ];
+        assert_eq!(SystemCnf::from_bytes(&elf), Err(Error::NotText));
+
+        // Control characters without any NUL byte
+        let noisy = "\x01\x02\x03\x04BOOT2 = x\x05\x06\r\n";
+        assert_eq!(SystemCnf::parse(noisy), Err(Error::NotText));
+
+        // Tabs and line endings aren't counted
+        let tabs = "\tBOOT2\t=\tx\r\n\tVER\t=\t1.00\r\n\tVMODE\t=\tNTSC\r\n\r\n\r\n";
+        assert!(SystemCnf::parse(tabs).is_ok());
+    }
+
+    #[test]
+    fn blank_lines() {
+        let txt =
+            "\r\nBOOT2 = cdrom0:\\SLUS_213.48;1\r\n   \r\n\nVER = 1.00  \r\n\tVMODE = NTSC\r\n\r\n";
+        let parsed = SystemCnf::parse(txt).unwrap();
+
+        assert_eq!(parsed.to_string(), str::from_utf8(SYSTEM_CNF).unwrap());
+    }
+
+    #[test]
+    fn comments() {
+        let txt = "# Generated by a build script\r\n; KEY = not a value\r\n\r\nBOOT2 = cdrom0:\\SLUS_213.48;1\r\n\r\n  # VER = 2.00\r\nVER = 1.00\r\n\r\nVMODE = NTSC\r\n";
+        let parsed = SystemCnf::parse(txt).unwrap();
+
+        assert!(parsed.extras.is_empty());
+        assert_eq!(parsed.to_string(), str::from_utf8(SYSTEM_CNF).unwrap());
+    }
+
+    #[test]
+    fn trailing_comments_preserved() {
+        let txt = "BOOT2 = cdrom0:\\SLUS_213.48;1\r\nVER = 1.00 # comment\r\nVMODE = NTSC\r\n";
+        let parsed = SystemCnf::parse(txt).unwrap();
+
+        assert_eq!(parsed.version, "1.00 # comment");
+    }
+
+    #[test]
+    fn empty_key() {
+        let txt = "BOOT2 = cdrom0:\\SLUS_213.48;1\r\n = 1.00\r\nVMODE = NTSC\r\n";
+        assert_eq!(SystemCnf::parse(txt), Err(Error::MalformedFile { line: 2 }));
+    }
+
+    #[test]
+    fn lowercase_keys() {
+        let txt = "boot2 = cdrom0:\\SLUS_213.48;1\r\nver = 1.00\r\nvmode = NTSC\r\n";
+        let parsed = SystemCnf::parse(txt).unwrap();
+
+        assert_eq!(parsed.elf_path.as_deref(), Some("cdrom0:\\SLUS_213.48"));
+        assert_eq!(parsed.to_string(), str::from_utf8(SYSTEM_CNF).unwrap());
+    }
+
+    #[test]
+    fn mixed_case_keys() {
+        let txt = "Boot2 = cdrom0:\\SLUS_213.48;1\r\nVer = 1.00\r\nVmode = PAL\r\nHddUnitPower = NICHDD\r\n";
+        let parsed = SystemCnf::parse(txt).unwrap();
+
+        assert_eq!(parsed.elf_path.as_deref(), Some("cdrom0:\\SLUS_213.48"));
+        assert_eq!(parsed.version, "1.00");
+        assert_eq!(parsed.video_mode, VideoMode::Pal);
+        assert_eq!(parsed.hdd_unit_power, Some(HddUnitPower::NicHdd));
+        assert!(parsed.extras.is_empty());
+        assert_eq!(
+            parsed.to_string(),
+            "BOOT2 = cdrom0:\\SLUS_213.48;1\r\nVER = 1.00\r\nVMODE = PAL\r\nHDDUNITPOWER = NICHDD\r\n"
+        );
+    }
+
+    #[test]
+    fn extended_video_modes() {
+        let txt = "BOOT2 = cdrom0:\\SLES_123.45;1\r\nVER = 1.00\r\nVMODE = PAL60\r\n";
+        let parsed = SystemCnf::parse(txt).unwrap();
+        assert_eq!(parsed.video_mode, VideoMode::Pal60);
+        assert_eq!(parsed.to_string(), txt);
+
+        let txt = "BOOT2 = cdrom0:\\SLES_123.45;1\r\nVER = 1.00\r\nVMODE = MULTI\r\n";
+        let parsed = SystemCnf::parse(txt).unwrap();
+        assert_eq!(parsed.video_mode, VideoMode::Other("MULTI".into()));
+        assert_eq!(parsed.to_string(), txt);
+    }
+
+    #[test]
+    fn empty_video_mode() {
+        let txt = "BOOT2 = cdrom0:\\SLES_123.45;1\r\nVER = 1.00\r\nVMODE = \r\n";
+        assert_eq!(
+            SystemCnf::parse(txt),
+            Err(Error::UnknownVideoMode { line: 3 })
+        );
+    }
+
+    #[test]
+    fn error_line_numbers() {
+        let txt = "# Comment\r\n\r\nBOOT2 = cdrom0:\\SLES_123.45;1\r\nVER = 1.00\r\nVMODE =\r\n";
+        let err = SystemCnf::parse(txt).unwrap_err();
+
+        assert_eq!(err, Error::UnknownVideoMode { line: 5 });
+        assert_eq!(err.to_string(), "unknown video mode on line 5");
+    }
+
+    #[test]
+    fn parse_options() {
+        let txt = "BOOT2 = cdrom0:\\SLUS_213.48;1\r\n\r\nVER = 1.00\r\nFOO = BAR\r\n";
+
+        assert_eq!(SystemCnf::parse(txt), Err(Error::MissingField));
+        assert_eq!(
+            SystemCnf::parse_with(txt, ParseOptions::strict()),
+            Err(Error::MalformedFile { line: 2 })
+        );
+
+        let lenient = SystemCnf::parse_with(txt, ParseOptions::lenient()).unwrap();
+        assert_eq!(lenient.video_mode, VideoMode::Ntsc);
+        assert_eq!(lenient.extras, [("FOO".into(), "BAR".into())]);
+    }
+
+    #[test]
+    fn parse_options_individual() {
+        let txt = "BOOT2 = cdrom0:\\SLUS_213.48;1\r\nVER = 1.00\r\nVMODE = NTSC\r\nFOO = BAR\r\n";
+        let strict = ParseOptions::strict();
+
+        assert_eq!(
+            SystemCnf::parse_with(txt, strict),
+            Err(Error::UnknownKey { line: 4 })
+        );
+        assert!(SystemCnf::parse_with(
+            txt,
+            ParseOptions {
+                allow_unknown_keys: true,
+                ..strict
+            }
+        )
+        .is_ok());
+
+        let strict_txt = str::from_utf8(SYSTEM_CNF).unwrap();
+        assert_eq!(
+            SystemCnf::parse_with(strict_txt, strict),
+            SystemCnf::parse(strict_txt)
+        );
+        assert_eq!(ParseOptions::default(), {
+            ParseOptions {
+                allow_missing_video_mode: false,
+                ..ParseOptions::lenient()
+            }
+        });
+    }
+
+    #[test]
+    fn duplicate_keys() {
+        let txt = "BOOT2 = cdrom0:\\SLUS_213.48;1\r\nVER = 1.00\r\nVMODE = NTSC\r\nFOO = A\r\nvmode = PAL\r\nfoo = B\r\n";
+        let with_policy = |duplicate_keys| {
+            SystemCnf::parse_with(
+                txt,
+                ParseOptions {
+                    duplicate_keys,
+                    ..ParseOptions::default()
+                },
+            )
+        };
+
+        let first = with_policy(DuplicateKeyPolicy::FirstWins).unwrap();
+        assert_eq!(first.video_mode, VideoMode::Ntsc);
+        assert_eq!(first.extras, [("FOO".into(), "A".into())]);
+
+        let last = with_policy(DuplicateKeyPolicy::LastWins).unwrap();
+        assert_eq!(last.video_mode, VideoMode::Pal);
+        assert_eq!(last.extras, [("FOO".into(), "B".into())]);
+        assert_eq!(SystemCnf::parse(txt).unwrap(), last);
+
+        assert_eq!(
+            with_policy(DuplicateKeyPolicy::Error),
+            Err(Error::DuplicateKey { line: 5 })
+        );
+        assert_eq!(
+            SystemCnf::parse_with(txt, ParseOptions::strict()),
+            Err(Error::UnknownKey { line: 4 })
+        );
+    }
+
+    #[test]
+    fn known_keys() {
+        let options = ParseOptions {
+            allow_unknown_keys: false,
+            ..ParseOptions::lenient()
+        };
+        for key in KNOWN_KEYS {
+            let txt = format!("{key} = X\r\n");
+            let result = SystemCnf::parse_with(&txt, options);
+            assert_ne!(result, Err(Error::UnknownKey { line: 1 }), "{key}");
+        }
+
+        let txt =
+            "BOOT2 = cdrom0:\\SLUS_213.48;1\r\nBOOT = cdrom:\\SLUS_000.67;1\r\nVER = 1.00\r\n\
+                   VMODE = NTSC\r\nHDDUNITPOWER = NICHDD\r\n";
+        let cnf = SystemCnf::parse(txt).unwrap();
+        assert!(cnf.extras.is_empty());
+        assert!(cnf
+            .entries()
+            .map(|(key, _)| key)
+            .eq(KNOWN_KEYS.iter().copied()));
+    }
+
+    #[test]
+    fn tab_separated() {
+        let txt = "BOOT2\t=\tcdrom0:\\SLUS_213.48;1\r\n\tVER\t=\t1.00\t\r\nVMODE\t=\tNTSC\r\nFOO\t=\tBAR\r\n";
+        let cnf = SystemCnf::parse(txt).unwrap();
+
+        assert_eq!(cnf.elf_path.as_deref(), Some("cdrom0:\\SLUS_213.48"));
+        assert_eq!(cnf.version, "1.00");
+        assert_eq!(cnf.video_mode, VideoMode::Ntsc);
+        assert_eq!(cnf.extras, [("FOO".into(), "BAR".into())]);
+    }
+
+    #[test]
+    fn alternate_separator() {
+        let txt = "BOOT2 : cdrom0:\\SLUS_213.48;1\r\nVER = 1.00\r\nVMODE:PAL\r\n";
+        let options = ParseOptions {
+            alternate_separator: Some(':'),
+            ..ParseOptions::default()
+        };
+        let cnf = SystemCnf::parse_with(txt, options).unwrap();
+
+        assert_eq!(cnf.elf_path.as_deref(), Some("cdrom0:\\SLUS_213.48"));
+        assert_eq!(cnf.version, "1.00");
+        assert_eq!(cnf.video_mode, VideoMode::Pal);
+
+        // Without the option the lines are skipped like any other line without a `=`
+        assert_eq!(SystemCnf::parse(txt), Err(Error::MissingField));
+    }
+
+    #[test]
+    fn quoted_values() {
+        let options = ParseOptions {
+            strip_quotes: true,
+            ..ParseOptions::default()
+        };
+
+        let quoted = "BOOT2 = cdrom0:\\SLUS_213.48;1\r\nVER = \"1.00\"\r\nVMODE = NTSC\r\n";
+        let cnf = SystemCnf::parse_with(quoted, options).unwrap();
+        assert_eq!(cnf.version, "1.00");
+        assert_eq!(cnf.game_version(), Ok(GameVersion { major: 1, minor: 0 }));
+        assert_eq!(cnf.quoted, [Field::Version]);
+        assert_eq!(cnf.to_string(), quoted);
+
+        let unquoted = "BOOT2 = cdrom0:\\SLUS_213.48;1\r\nVER = 1.00\r\nVMODE = NTSC\r\n";
+        let cnf = SystemCnf::parse_with(unquoted, options).unwrap();
+        assert_eq!(cnf.version, "1.00");
+        assert!(cnf.quoted.is_empty());
+        assert_eq!(cnf.to_string(), unquoted);
+
+        let all_quoted = "BOOT2 = \"cdrom0:\\SLUS_213.48;1\"\r\nVER = \"1.00\"\r\n\
+                          VMODE = \"PAL\"\r\nFOO = \"BAR\"\r\n";
+        let cnf = SystemCnf::parse_with(all_quoted, options).unwrap();
+        assert_eq!(cnf.elf_path.as_deref(), Some("cdrom0:\\SLUS_213.48"));
+        assert_eq!(cnf.video_mode, VideoMode::Pal);
+        assert_eq!(cnf.extras, [("FOO".into(), "BAR".into())]);
+        assert_eq!(cnf.to_string(), all_quoted);
+
+        // Without the option the quotes are part of the value
+        let cnf = SystemCnf::parse(quoted).unwrap();
+        assert_eq!(cnf.version, "\"1.00\"");
+        assert!(cnf.quoted.is_empty());
+        assert_eq!(cnf.to_string(), quoted);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn from_reader() {
+        let cnf = SystemCnf::from_reader(std::io::Cursor::new(SYSTEM_CNF)).unwrap();
+        assert_eq!(cnf, SystemCnf::from_bytes(SYSTEM_CNF).unwrap());
+        assert!(matches!(cnf.elf_path, Some(Cow::Owned(_))));
+
+        assert_eq!(
+            SystemCnf::from_reader(&b"BOOT2 = cdrom0:\\X;1\r\nVER = \xFF\r\n"[..]),
+            Err(Error::InvalidUtf8 { valid_up_to: 27 })
+        );
+
+        // Byte order mark, bare `\r` line endings and a reader returning tiny buffers
+        let mixed = b"\xEF\xBB\xBFBOOT2 = cdrom0:\\SLUS_213.48;1\rVER = 1.00\n\nVMODE = NTSC\r\n";
+        let reader = std::io::BufReader::with_capacity(3, &mixed[..]);
+        assert_eq!(
+            SystemCnf::from_reader(reader).unwrap(),
+            SystemCnf::from_bytes(mixed).unwrap()
+        );
+
+        let with_nul = b"BOOT2 = cdrom0:\\SLUS_213.48;1\r\nVER = 1.00\0\r\nVMODE = NTSC\r\n";
+        assert_eq!(SystemCnf::from_reader(&with_nul[..]), Err(Error::NotText));
+        let noisy = b"\x01\x02\x03\x04BOOT2 = x\x05\x06\r\n";
+        assert_eq!(SystemCnf::from_reader(&noisy[..]), Err(Error::NotText));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn from_reader_stops_at_error() {
+        use std::io::{self, BufReader, Read};
+
+        struct FailingReader;
+
+        impl Read for FailingReader {
+            fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+                Err(io::ErrorKind::BrokenPipe.into())
+            }
+        }
+
+        // The lines after the error are never read
+        let reader = BufReader::new((&b"BOOT2 = x\r\n = 1.00\r\n"[..]).chain(FailingReader));
+        assert_eq!(
+            SystemCnf::from_reader(reader),
+            Err(Error::MalformedFile { line: 2 })
+        );
+
+        let reader = BufReader::new((&b"BOOT2 = x\r\n"[..]).chain(FailingReader));
+        assert_eq!(
+            SystemCnf::from_reader(reader),
+            Err(Error::Io(io::ErrorKind::BrokenPipe))
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "encoding")]
+    fn shift_jis() {
+        // `TITLE = ゲーム` in Shift-JIS
+        let mut bytes =
+            b"BOOT2 = cdrom0:\\SLUS_213.48;1\r\nVER = 1.00\r\nVMODE = NTSC\r\nTITLE = ".to_vec();
+        bytes.extend_from_slice(&[0x83, 0x51, 0x81, 0x5B, 0x83, 0x80]);
+        bytes.extend_from_slice(b"\r\n");
+
+        let cnf = SystemCnf::from_bytes(&bytes).unwrap();
+        assert_eq!(cnf.extras, [("TITLE".into(), "ゲーム".into())]);
+        assert!(matches!(cnf.version, Cow::Owned(_)));
+        assert_eq!(SystemCnf::from_reader(&bytes[..]).unwrap(), cnf);
+
+        // Neither UTF-8 nor Shift-JIS
+        assert_eq!(
+            SystemCnf::from_bytes(b"VER = \xFF\r\n"),
+            Err(Error::InvalidUtf8 { valid_up_to: 6 })
+        );
+    }
+
+    #[test]
+    fn str_accessors() {
+        let txt = "BOOT2 = cdrom0:\\SLUS_213.48;1\r\nVER = 1.00\r\nVMODE = NTSC\r\nHDDUNITPOWER = NICHDD\r\n";
+        let cnf = SystemCnf::parse(txt).unwrap();
+
+        assert_eq!(cnf.elf_path_str(), Some("cdrom0:\\SLUS_213.48"));
+        assert_eq!(cnf.version_str(), "1.00");
+        assert_eq!(cnf.hdd_unit_power_str(), Some("NICHDD"));
+        assert!(matches!(cnf.elf_path, Some(Cow::Borrowed(_))));
+        assert!(matches!(cnf.version, Cow::Borrowed(_)));
+
+        let cnf =
+            SystemCnf::parse("BOOT = cdrom:\\SLUS_000.67;1\r\nVER = 1.00\r\nVMODE = NTSC\r\n")
+                .unwrap();
+        assert_eq!(cnf.elf_path_str(), None);
+        assert_eq!(cnf.hdd_unit_power_str(), None);
+    }
+
+    #[test]
+    fn parse_collect_errors() {
+        let txt = "BOOT2 = cdrom0:\\SLUS_213.48;1\r\nFOO = BAR\r\n";
+        assert_eq!(
+            SystemCnf::parse_collect_errors(txt),
+            Err(vec![Error::MissingField, Error::MissingField])
+        );
+
+        let txt = "BOOT2 = cdrom0:\\SLUS_213.48;1\r\nVER =\r\n= 1\r\nVMODE = NTSC\r\n";
+        assert_eq!(
+            SystemCnf::parse_collect_errors(txt),
+            Err(vec![
+                Error::EmptyField { line: 2 },
+                Error::MalformedFile { line: 3 }
+            ])
+        );
+
+        let txt = str::from_utf8(SYSTEM_CNF).unwrap();
+        assert_eq!(
+            SystemCnf::parse_collect_errors(txt).ok(),
+            SystemCnf::parse(txt).ok()
+        );
+    }
+
+    #[test]
+    fn parse_with_warnings() {
+        let txt = "BOOT2 = cdrom0:\\SLUS_213.48;1\r\nVMODE = NTSC\r\nVer = 1.00\r\nVMODE = PAL\r\nFOO =\r\n";
+        let (cnf, warnings) = SystemCnf::parse_with_warnings(txt);
+
+        assert_eq!(cnf.unwrap().video_mode, VideoMode::Pal);
+        assert_eq!(
+            warnings,
+            [
+                Warning::NonCanonicalKeyCase { line: 3 },
+                Warning::DuplicateKey { line: 4 },
+                Warning::UnknownKey { line: 5 },
+                Warning::EmptyValue { line: 5 },
+            ]
+        );
+
+        let (cnf, warnings) = SystemCnf::parse_with_warnings("vmode = NTSC\nvmode = PAL\n");
+        assert_eq!(cnf, Err(Error::MissingField));
+        assert_eq!(
+            warnings,
+            [
+                Warning::NonCanonicalKeyCase { line: 1 },
+                Warning::DuplicateKey { line: 2 },
+                Warning::NonCanonicalKeyCase { line: 2 },
+            ]
+        );
+
+        let canonical = "BOOT2 = cdrom0:\\SLUS_213.48;1\r\nVER = 1.00\r\nVMODE = NTSC\r\n";
+        let (cnf, warnings) = SystemCnf::parse_with_warnings(canonical);
+        assert!(cnf.is_ok());
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn validate() {
+        let txt = str::from_utf8(SYSTEM_CNF).unwrap();
+        assert_eq!(SystemCnf::parse(txt).unwrap().validate(), Ok(()));
+
+        let txt = "BOOT = cdrom:\\SLUS_000.67;1\r\nVER = 1.00\r\nVMODE = NTSC\r\n";
+        assert_eq!(SystemCnf::parse(txt).unwrap().validate(), Ok(()));
+    }
+
+    #[test]
+    fn validate_invalid() {
+        let validate = |elf_path: &str, version: &str| {
+            SystemCnf::builder()
+                .elf_path(elf_path.to_string())
+                .version(version.to_string())
+                .video_mode(VideoMode::Ntsc)
+                .build()
+                .unwrap()
+                .validate()
+        };
+
+        assert_eq!(
+            validate("cdrom0:\\FOO", "1.00"),
+            Err(ValidationError::InvalidSerial)
+        );
+        assert_eq!(
+            validate("cdrom0:\\SLUS_21348", "1.00"),
+            Err(ValidationError::InvalidSerial)
+        );
+        assert_eq!(
+            validate("mass:/SLUS_213.48", "1.00"),
+            Err(ValidationError::InvalidDevice)
+        );
+        assert_eq!(
+            validate("cdrom0:\\DATA\\SLUS_213.48", "1.00"),
+            Err(ValidationError::InvalidDevice)
+        );
+        assert_eq!(
+            validate("cdrom0:\\SLUS_213.48", "1.0"),
+            Err(ValidationError::InvalidVersion)
+        );
+
+        let mut missing = SystemCnf::parse(str::from_utf8(SYSTEM_CNF).unwrap()).unwrap();
+        missing.elf_path = None;
+        assert_eq!(missing.validate(), Err(ValidationError::MissingBootPath));
+    }
+
+    #[test]
+    fn to_map() {
+        let txt = str::from_utf8(SYSTEM_CNF).unwrap();
+        let parsed = SystemCnf::parse(txt).unwrap();
+        let map = parsed.to_map();
+        assert_eq!(map.len(), 3);
+        assert_eq!(map["BOOT2"], "cdrom0:\\SLUS_213.48");
+        assert_eq!(map["VER"], "1.00");
+        assert_eq!(map["VMODE"], "NTSC");
+
+        let txt = "BOOT2 = cdrom0:\\SLUS_213.48;1\r\nVER = 1.00\r\nVMODE = PAL\r\nFOO = BAR\r\nABC = 1\r\n";
+        let parsed = SystemCnf::parse(txt).unwrap();
+        assert_eq!(
+            parsed.to_map().into_iter().collect::<Vec<_>>(),
+            [
+                ("ABC".into(), "1".into()),
+                ("BOOT2".into(), "cdrom0:\\SLUS_213.48".into()),
+                ("FOO".into(), "BAR".into()),
+                ("VER".into(), "1.00".into()),
+                ("VMODE".into(), "PAL".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn entries() {
+        let txt = str::from_utf8(SYSTEM_CNF).unwrap();
+        let parsed = SystemCnf::parse(txt).unwrap();
+        assert_eq!(
+            parsed.entries().collect::<Vec<_>>(),
+            [
+                ("BOOT2", "cdrom0:\\SLUS_213.48".into()),
+                ("VER", "1.00".into()),
+                ("VMODE", "NTSC".into())
+            ]
+        );
+
+        let txt = "BOOT2 = cdrom0:\\SLUS_213.48;1\r\nBOOT = cdrom:\\SLUS_000.67;1\r\nVER = 1.00\r\nVMODE = PAL\r\nHDDUNITPOWER = NICHDD\r\nFOO = BAR\r\n";
+        let parsed = SystemCnf::parse(txt).unwrap();
+        assert_eq!(
+            parsed.entries().collect::<Vec<_>>(),
+            [
+                ("BOOT2", "cdrom0:\\SLUS_213.48".into()),
+                ("BOOT", "cdrom:\\SLUS_000.67".into()),
+                ("VER", "1.00".into()),
+                ("VMODE", "PAL".into()),
+                ("HDDUNITPOWER", "NICHDD".into())
+            ]
+        );
+    }
+
+    #[test]
+    fn game_version() {
+        let txt = str::from_utf8(SYSTEM_CNF).unwrap();
+        let parsed = SystemCnf::parse(txt).unwrap();
+
+        assert_eq!(
+            parsed.game_version(),
+            Ok(GameVersion { major: 1, minor: 0 })
+        );
+    }
+
+    #[test]
+    fn version_parts() {
+        let parts = |version: &str| {
+            let txt = format!("BOOT2 = cdrom0:\\SLUS_213.48;1\nVER = {version}\nVMODE = NTSC\n");
+            SystemCnf::parse(&txt).unwrap().version_parts()
+        };
+
+        assert_eq!(parts("1.00"), Some((1, 0)));
+        assert_eq!(parts("1.01"), Some((1, 1)));
+        assert!(parts("1.01") > parts("1.00"));
+        for invalid in ["abc", "1", "1.a0", "a.00", "1.", "+1.00"] {
+            assert_eq!(parts(invalid), None);
+        }
+    }
+
+    #[test]
+    fn elf_filename() {
+        let filename = |path: &str| {
+            let txt = format!("BOOT2 = {path}\nVER = 1.00\nVMODE = NTSC\n");
+            SystemCnf::parse(&txt)
+                .unwrap()
+                .elf_filename()
+                .map(str::to_owned)
+        };
+
+        assert_eq!(filename("cdrom0:\\SLUS_213.48;1").unwrap(), "SLUS_213.48");
+        assert_eq!(filename("cdrom0:/SLUS_213.48").unwrap(), "SLUS_213.48");
+        assert_eq!(filename("mass:/FOO/BAR.ELF").unwrap(), "BAR.ELF");
+        assert_eq!(filename("host:BAR.ELF").unwrap(), "BAR.ELF");
+
+        let ps1 =
+            SystemCnf::parse("BOOT = cdrom:\\SLUS_005.94;1\nVER = 1.00\nVMODE = NTSC\n").unwrap();
+        assert_eq!(ps1.elf_filename(), None);
+    }
+
+    #[test]
+    fn elf_path_without_device() {
+        let path = |path: &str| {
+            let txt = format!("BOOT2 = {path}\nVER = 1.00\nVMODE = NTSC\n");
+            SystemCnf::parse(&txt)
+                .unwrap()
+                .elf_path_without_device()
+                .map(str::to_owned)
+        };
+
+        assert_eq!(path("cdrom0:\\A\\B.ELF;1").unwrap(), "\\A\\B.ELF");
+        assert_eq!(path("mass:/APPS/BOOT.ELF").unwrap(), "/APPS/BOOT.ELF");
+        assert_eq!(path("\\A\\B.ELF").unwrap(), "\\A\\B.ELF");
+
+        let ps1 =
+            SystemCnf::parse("BOOT = cdrom:\\SLUS_005.94;1\nVER = 1.00\nVMODE = NTSC\n").unwrap();
+        assert_eq!(ps1.elf_path_without_device(), None);
+    }
+
+    #[test]
+    fn game_id() {
+        let txt = str::from_utf8(SYSTEM_CNF).unwrap();
+        let parsed = SystemCnf::parse(txt).unwrap();
+        let game_id = parsed.game_id().unwrap();
+
+        assert_eq!(game_id.to_string(), "SLUS-21348");
+        assert_eq!(game_id.region(), Region::NorthAmerica);
+    }
+
+    #[test]
+    fn region() {
+        let region = |elf_path: &str| {
+            let txt = format!("BOOT2 = {elf_path};1\nVER = 1.00\nVMODE = NTSC\n");
+            SystemCnf::parse(&txt).unwrap().region()
+        };
+
+        assert_eq!(region("cdrom0:\\SCUS_971.13"), Some(Region::NorthAmerica));
+        assert_eq!(region("cdrom0:\\SLES_525.41"), Some(Region::Europe));
+        assert_eq!(region("cdrom0:\\SLPS_250.01"), Some(Region::Japan));
+        assert_eq!(region("cdrom0:\\SLKA_251.01"), Some(Region::Other));
+        assert_eq!(region("cdrom0:\\MAIN.ELF"), None);
+    }
+
+    #[test]
+    fn unknown_keys_round_trip() {
+        let txt = "BOOT2 = cdrom0:\\SLUS_213.48;1\r\nVER = 1.00\r\nVMODE = NTSC\r\nFOO = BAR\r\n";
+        let parsed = SystemCnf::parse(txt).unwrap();
+
+        assert_eq!(parsed.extras, [("FOO".into(), "BAR".into())]);
+        assert_eq!(parsed.to_string(), txt);
+    }
+
+    #[test]
+    fn dedupe() {
+        let txt = str::from_utf8(SYSTEM_CNF).unwrap();
+        let parsed = SystemCnf::parse(txt).unwrap();
+
+        let mut set = HashSet::new();
+        set.insert(parsed.clone());
+        set.insert(SystemCnf::parse(txt).unwrap());
+        assert_eq!(set.len(), 1);
+
+        let errors = HashSet::from([
+            Error::MissingField,
+            Error::MissingField,
+            Error::EmptyField { line: 2 },
+        ]);
+        assert_eq!(errors.len(), 2);
+    }
+
+    #[test]
+    fn empty_values() {
+        let parsed = SystemCnf::parse(
+            "BOOT2 = cdrom0:\\SLUS_213.48;1\nVER = 1.00\nVMODE = NTSC\nHDDUNITPOWER = \nFOO =\n",
+        )
+        .unwrap();
+        assert_eq!(parsed.hdd_unit_power, None);
+        assert_eq!(parsed.extras, [("FOO".into(), "".into())]);
+        assert!(!parsed.to_string().contains("HDDUNITPOWER"));
+
+        for (txt, line) in [
+            ("BOOT2 = cdrom0:\\SLUS_213.48;1\nVER = \nVMODE = NTSC\n", 2),
+            ("BOOT2 =  \nVER = 1.00\nVMODE = NTSC\n", 1),
+            ("VER = 1.00\nVMODE = NTSC\nBOOT =\n", 3),
+        ] {
+            assert_eq!(SystemCnf::parse(txt), Err(Error::EmptyField { line }));
+        }
+    }
+
+    #[test]
+    fn value_with_equals_sign() {
+        let txt = "BOOT2 = cdrom0:\\SLUS_123.45;1\nVER = 1.00\nVMODE = NTSC\nFOO = a=b\nBAR==\n";
+        let parsed = SystemCnf::parse(txt).unwrap();
+
+        assert_eq!(
+            parsed.extras,
+            [("FOO".into(), "a=b".into()), ("BAR".into(), "=".into())]
+        );
+    }
+
+    #[test]
+    fn hdd_unit_power() {
+        let txt =
+            "BOOT2 = pfs:/EXECUTE.KELF;1\r\nVER = 1.00\r\nVMODE = NTSC\r\nHDDUNITPOWER = NIC\r\n";
+        let parsed = SystemCnf::parse(txt).unwrap();
+        assert_eq!(
+            parsed.hdd_unit_power,
+            Some(HddUnitPower::Other("NIC".into()))
+        );
+        assert_eq!(parsed.to_string(), txt);
+
+        let txt = txt.replace("NIC\r", "NICHDD\r");
+        let parsed = SystemCnf::parse(&txt).unwrap();
+        assert_eq!(parsed.hdd_unit_power, Some(HddUnitPower::NicHdd));
+    }
+
+    #[test]
+    fn diff() {
+        let old = SystemCnf::parse(
+            "BOOT2 = cdrom0:\\SLUS_213.48;1\r\nVER = 1.00\r\nVMODE = NTSC\r\nFOO = 1\r\n",
+        )
+        .unwrap();
+        let new = SystemCnf::parse(
+            "BOOT2 = cdrom0:\\SLUS_213.48;1\r\nVER = 1.01\r\nVMODE = PAL\r\nFOO = 1\r\n",
+        )
+        .unwrap();
+
+        let diffs = old.diff(&new);
+        assert_eq!(
+            diffs,
+            [
+                FieldDiff {
+                    key: "VER".into(),
+                    old: Some("1.00".into()),
+                    new: Some("1.01".into()),
+                },
+                FieldDiff {
+                    key: "VMODE".into(),
+                    old: Some("NTSC".into()),
+                    new: Some("PAL".into()),
+                },
+            ]
+        );
+        assert_eq!(diffs[1].to_string(), "VMODE: NTSC -> PAL");
+        assert!(old.diff(&old).is_empty());
+
+        // Fields set in only one of the configs
+        let mut removed = new.clone();
+        removed.extras.clear();
+        removed.hdd_unit_power = Some(HddUnitPower::NicHdd);
+        let diffs = new.diff(&removed);
+        assert_eq!(diffs.len(), 2);
+        assert_eq!(diffs[0].to_string(), "FOO: 1 -> (unset)");
+        assert_eq!(diffs[1].to_string(), "HDDUNITPOWER: (unset) -> NICHDD");
+    }
+
+    #[test]
+    fn merge() {
+        let base = SystemCnf::parse(
+            "BOOT2 = cdrom0:\\SLUS_213.48;1\r\nVER = 1.00\r\nVMODE = NTSC\r\nFOO = 1\r\n",
+        )
+        .unwrap();
+        // Only VMODE differs, the optional fields not set in the overlay are left untouched
+        let overlay = SystemCnf {
+            elf_path: None,
+            video_mode: VideoMode::Pal,
+            extras: Vec::new(),
+            ..base.clone()
+        };
+        let mut merged = base.clone();
+        merged.merge(&overlay);
+        assert_eq!(
+            merged,
+            SystemCnf {
+                video_mode: VideoMode::Pal,
+                ..base.clone()
+            }
+        );
+
+        let overlay = SystemCnf::parse(
+            "BOOT2 = cdrom0:\\SLUS_213.49;1\r\nVER = 1.01\r\nVMODE = NTSC\r\nfoo = 2\r\nBAR = 3\r\n",
+        )
+        .unwrap();
+        let merged = base.merged(&overlay);
+        assert_eq!(merged.elf_path.as_deref(), Some("cdrom0:\\SLUS_213.49"));
+        assert_eq!(merged.version, "1.01");
+        assert_eq!(
+            merged.to_string(),
+            "BOOT2 = cdrom0:\\SLUS_213.49;1\r\nVER = 1.01\r\nVMODE = NTSC\r\nFOO = 2\r\nBAR = 3\r\n"
+        );
+    }
+
+    #[test]
+    fn preserve_order() {
+        let txt = "VER = 1.02\r\nFOO = BAR\r\nBOOT2 = cdrom0:\\SLPM_654.32;1\r\nHDDUNITPOWER = NICHDD\r\nVMODE = NTSC\r\nBAZ = 1\r\n";
+        let parsed = SystemCnf::parse(txt).unwrap();
+        assert_eq!(
+            parsed.order,
+            [
+                Field::Version,
+                Field::Extra(0),
+                Field::ElfPath,
+                Field::HddUnitPower,
+                Field::VideoMode,
+                Field::Extra(1),
+            ]
+        );
+        assert_eq!(parsed.to_string().as_bytes(), txt.as_bytes());
+        assert_eq!(parsed.clone().into_owned().to_string(), txt);
+
+        // The canonical order isn't recorded
+        let canonical = SystemCnf::parse(
+            "BOOT2 = cdrom0:\\SLPM_654.32;1\r\nVER = 1.02\r\nVMODE = NTSC\r\nFOO = BAR\r\n",
+        )
+        .unwrap();
+        assert!(canonical.order.is_empty());
+
+        // Fields missing from the order are appended in the canonical order
+        let mut modified = parsed;
+        modified.order.truncate(2);
+        modified.ps1_boot = Some("cdrom:\\SLPM_000.01".into());
+        assert_eq!(
+            modified.to_string(),
+            "VER = 1.02\r\nFOO = BAR\r\nBOOT2 = cdrom0:\\SLPM_654.32;1\r\nBOOT = cdrom:\\SLPM_000.01;1\r\nVMODE = NTSC\r\nHDDUNITPOWER = NICHDD\r\nBAZ = 1\r\n"
+        );
+    }
+
+    #[test]
+    fn extras_byte_identical() {
+        let txt = "BOOT2 = cdrom0:\\SLPM_654.32;1\r\nVER = 1.02\r\nVMODE = NTSC\r\nHDDUNITPOWER = NICHDD\r\nPARAM2 = 0x1F\r\nVENDOR = ACME\r\n";
+        let parsed = SystemCnf::parse(txt).unwrap();
+
+        assert_eq!(
+            parsed.extras,
+            [
+                ("PARAM2".into(), "0x1F".into()),
+                ("VENDOR".into(), "ACME".into())
+            ]
+        );
+        assert_eq!(parsed.to_string().as_bytes(), txt.as_bytes());
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn from_path() {
+        let path = std::env::temp_dir().join(format!("system-cnf-test-{}.cnf", std::process::id()));
+        std::fs::write(&path, SYSTEM_CNF).unwrap();
+        let parsed = SystemCnf::from_path(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        let expected = SystemCnf::parse(str::from_utf8(SYSTEM_CNF).unwrap()).unwrap();
+        assert_eq!(parsed.unwrap(), expected);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn from_path_missing() {
+        let path = std::env::temp_dir().join("system-cnf-test-does-not-exist.cnf");
+        assert_eq!(
+            SystemCnf::from_path(path),
+            Err(Error::Io(std::io::ErrorKind::NotFound))
+        );
+    }
+
+    #[test]
+    fn into_owned() {
+        let mut configs: Vec<SystemCnf<'static>> = Vec::new();
+
+        {
+            let txt = String::from_utf8(SYSTEM_CNF.to_vec()).unwrap();
+            let parsed = SystemCnf::parse(&txt).unwrap();
+            configs.push(parsed.into_owned());
+        }
+
+        let owned = &configs[0];
+        assert_eq!(owned.elf_path.as_deref(), Some("cdrom0:\\SLUS_213.48"));
+        assert_eq!(owned.version, "1.00");
+        assert_eq!(owned.video_mode, VideoMode::Ntsc);
+        assert_eq!(owned.hdd_unit_power, None);
+    }
+
+    #[test]
+    fn into_owned_outlives_source() {
+        let txt = String::from(
+            "BOOT2 = cdrom0:\\SLUS_213.48;1\r\nBOOT = cdrom:\\SLUS_000.67;1\r\nVER = 1.00\r\nVMODE = PAL\r\nHDDUNITPOWER = NICHDD\r\nFOO = BAR\r\n",
+        );
+        let owned = SystemCnf::parse(&txt).unwrap().into_owned();
+        drop(txt);
+
+        let owned = std::thread::spawn(move || owned).join().unwrap();
+        assert_eq!(owned.elf_path.as_deref(), Some("cdrom0:\\SLUS_213.48"));
+        assert_eq!(owned.ps1_boot.as_deref(), Some("cdrom:\\SLUS_000.67"));
+        assert_eq!(owned.version, "1.00");
+        assert_eq!(owned.video_mode, VideoMode::Pal);
+        assert_eq!(owned.hdd_unit_power, Some(HddUnitPower::NicHdd));
+        assert_eq!(owned.extras, [("FOO".into(), "BAR".into())]);
+    }
+
+    #[test]
+    fn hash() {
+        use std::collections::HashSet;
+
+        let txt = str::from_utf8(SYSTEM_CNF).unwrap();
+        let parsed = SystemCnf::parse(txt).unwrap();
+        let cloned = parsed.clone();
+
+        let mut set = HashSet::new();
+        set.insert(parsed);
+        assert!(set.contains(&cloned));
+
+        let encoded = cloned.to_string();
+        assert!(set.contains(&SystemCnf::parse(&encoded).unwrap()));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        let txt = str::from_utf8(SYSTEM_CNF).unwrap();
+        let parsed = SystemCnf::parse(txt).unwrap();
+
+        let json = serde_json::to_string(&parsed).unwrap();
+        assert!(json.contains(r#""video_mode":"NTSC""#));
+        assert!(json.contains(r#""hdd_unit_power":null"#));
+
+        let deserialized: SystemCnf<'_> = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized, parsed);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_borrowed() {
+        use std::borrow::Cow;
+
+        let json = r#"{"elf_path":"cdrom0:\\SLUS_213.48","version":"1.00","video_mode":"PAL","ps1_boot":null,"hdd_unit_power":null,"extras":[]}"#;
+        let deserialized: SystemCnf<'_> = serde_json::from_str(json).unwrap();
+
+        // Strings without escape sequences are borrowed straight from the input
+        assert!(matches!(deserialized.version, Cow::Borrowed("1.00")));
+        assert!(matches!(deserialized.elf_path, Some(Cow::Owned(_))));
+        assert_eq!(
+            deserialized.elf_path.as_deref(),
+            Some("cdrom0:\\SLUS_213.48")
+        );
+        assert_eq!(deserialized.video_mode, VideoMode::Pal);
+    }
+
+    #[test]
+    fn pathological_input() {
+        let long_value = format!(
+            "BOOT2 = cdrom0:\\{};1\nVER = 1.00\nVMODE = NTSC\n",
+            "A".repeat(1 << 20)
+        );
+        for txt in [
+            "",
+            "   \n\t\n",
+            "\r",
+            "\r\r\r\n\r",
+            "=",
+            "====",
+            " = = = ",
+            "BOOT2 ==== ;1",
+            "BOOT2 = ;1\nVER = 1.00\nVMODE = NTSC",
+            "BOOT2 = x\rVER = 1.00\rVMODE = NTSC\r",
+            "VMODE = \u{0}",
+            "\u{feff}\u{feff}BOOT2 = x",
+            long_value.as_str(),
+        ] {
+            if let Ok(parsed) = SystemCnf::parse(txt) {
+                assert_eq!(SystemCnf::parse(&parsed.to_string()).unwrap(), parsed);
+            }
+        }
+        assert!(SystemCnf::parse(&long_value).is_ok());
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn never_panics(bytes in proptest::collection::vec(proptest::prelude::any::<u8>(), 0..256)) {
+            let _ = SystemCnf::from_bytes(&bytes);
+        }
+
+        #[test]
+        fn round_trip(
+            lines in "((BOOT2|BOOT|VER|VMODE|HDDUNITPOWER|FOO|vmode|#|;| )*[ \t]*=?[ \t]*(NTSC|PAL|[A-Za-z0-9;:\\\\. =\r]{0,12})\r?\n){0,8}",
+        ) {
+            // The lines on their own are rarely a valid file, so also try them after a valid one
+            for txt in [lines.clone(), format!("BOOT2 = cdrom0:\\SLUS_213.48;1\nVER = 1.00\nVMODE = NTSC\n{lines}")] {
+                if let Ok(parsed) = SystemCnf::parse(&txt) {
+                    let encoded = parsed.to_string();
+                    proptest::prop_assert_eq!(SystemCnf::parse(&encoded).unwrap(), parsed);
+                }
+            }
+        }
     }
 }
@@ -0,0 +1,87 @@
+/// How [`SystemCnf::parse_with`](crate::SystemCnf::parse_with) handles a key appearing more than once
+///
+/// Keys are compared case-insensitively. Repeated keys keep the position of their first occurrence when encoding
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum DuplicateKeyPolicy {
+    /// Keep the value of the first occurrence, later lines with the same key are ignored entirely
+    FirstWins,
+
+    /// Keep the value of the last occurrence
+    #[default]
+    LastWins,
+
+    /// Return [`Error::DuplicateKey`](crate::Error::DuplicateKey)
+    Error,
+}
+
+/// Options controlling how strictly [`SystemCnf::parse_with`](crate::SystemCnf::parse_with) parses a file
+///
+/// The default matches [`SystemCnf::parse`](crate::SystemCnf::parse): unknown keys and blank lines are accepted,
+/// a missing video mode is rejected and the last occurrence of a repeated key wins
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct ParseOptions {
+    /// Fall back to [`VideoMode::Ntsc`](crate::VideoMode::Ntsc) if `VMODE` is missing instead of returning an error
+    pub allow_missing_video_mode: bool,
+
+    /// Preserve unrecognised keys in [`SystemCnf::extras`](crate::SystemCnf::extras) instead of returning an error
+    pub allow_unknown_keys: bool,
+
+    /// Skip blank lines and other lines without a `=` instead of returning an error
+    pub allow_blank_lines: bool,
+
+    /// Which value to keep if a key appears more than once
+    pub duplicate_keys: DuplicateKeyPolicy,
+
+    /// Separator accepted in place of `=` on lines that don't contain a `=`, such as the `:` used by some homebrew
+    ///
+    /// Only the first occurrence separates the key from the value, so `BOOT2 : cdrom0:\SLUS_213.48;1` still works
+    pub alternate_separator: Option<char>,
+
+    /// Strip a single pair of double quotes surrounding a value, e.g. `VER = "1.00"`
+    ///
+    /// Stripped fields are recorded in [`SystemCnf::quoted`](crate::SystemCnf::quoted) so `Display` can re-add the
+    /// quotes
+    pub strip_quotes: bool,
+}
+
+impl ParseOptions {
+    /// Options rejecting anything that doesn't strictly conform to the format
+    #[must_use]
+    pub fn strict() -> Self {
+        Self {
+            allow_missing_video_mode: false,
+            allow_unknown_keys: false,
+            allow_blank_lines: false,
+            duplicate_keys: DuplicateKeyPolicy::Error,
+            alternate_separator: None,
+            strip_quotes: false,
+        }
+    }
+
+    /// Options accepting as much as possible
+    #[must_use]
+    pub fn lenient() -> Self {
+        Self {
+            allow_missing_video_mode: true,
+            allow_unknown_keys: true,
+            allow_blank_lines: true,
+            duplicate_keys: DuplicateKeyPolicy::LastWins,
+            alternate_separator: None,
+            strip_quotes: false,
+        }
+    }
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            allow_missing_video_mode: false,
+            allow_unknown_keys: true,
+            allow_blank_lines: true,
+            duplicate_keys: DuplicateKeyPolicy::LastWins,
+            alternate_separator: None,
+            strip_quotes: false,
+        }
+    }
+}
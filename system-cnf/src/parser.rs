@@ -0,0 +1,514 @@
+use crate::{
+    DuplicateKeyPolicy, Error, Field, HddUnitPower, ParseOptions, SystemCnf, VideoMode, Warning,
+    KNOWN_KEYS,
+};
+use alloc::{borrow::Cow, vec::Vec};
+#[cfg(feature = "std")]
+use std::io::BufRead;
+
+/// Fields the [`KNOWN_KEYS`] are stored in, at the same indices
+const KNOWN_FIELDS: [Field; 5] = [
+    Field::ElfPath,
+    Field::Ps1Boot,
+    Field::Version,
+    Field::VideoMode,
+    Field::HddUnitPower,
+];
+
+const _: () = assert!(KNOWN_KEYS.len() == KNOWN_FIELDS.len());
+
+/// State of a `SYSTEM.CNF` being parsed line by line
+///
+/// Nothing is allocated as long as the file only contains recognised keys in canonical order and no warnings
+/// are collected
+pub(crate) struct Parser<'a, 'w> {
+    options: ParseOptions,
+    warnings: Option<&'w mut Vec<Warning>>,
+    elf_path: Option<Cow<'a, str>>,
+    ps1_boot: Option<Cow<'a, str>>,
+    version: Option<Cow<'a, str>>,
+    video_mode: Option<VideoMode<'a>>,
+    hdd_unit_power: Option<HddUnitPower<'a>>,
+    extras: Vec<(Cow<'a, str>, Cow<'a, str>)>,
+    /// Order of the fields, only recorded once they deviate from the canonical order
+    order: Vec<Field>,
+    /// Last field seen while the fields are still in canonical order
+    last_field: Option<Field>,
+    /// Which of the [`KNOWN_FIELDS`] have been seen
+    seen_known: [bool; KNOWN_FIELDS.len()],
+    /// Fields whose value had its quotes stripped
+    quoted: Vec<Field>,
+}
+
+impl<'a, 'w> Parser<'a, 'w> {
+    /// Create a new parser, collecting warnings into `warnings` if given
+    pub(crate) fn new(options: ParseOptions, warnings: Option<&'w mut Vec<Warning>>) -> Self {
+        Self {
+            options,
+            warnings,
+            elf_path: None,
+            ps1_boot: None,
+            version: None,
+            video_mode: None,
+            hdd_unit_power: None,
+            extras: Vec::new(),
+            order: Vec::new(),
+            last_field: None,
+            seen_known: [false; KNOWN_FIELDS.len()],
+            quoted: Vec::new(),
+        }
+    }
+
+    /// Parse the whole file, see [`SystemCnf::parse`] for the accepted syntax
+    pub(crate) fn parse(self, raw_cnf: &'a str) -> Result<SystemCnf<'a>, Error> {
+        if looks_binary(raw_cnf.as_bytes()) {
+            return Err(Error::NotText);
+        }
+
+        self.parse_text(raw_cnf)
+    }
+
+    /// Like [`Parser::parse`], for input that has already been checked to not be binary data
+    pub(crate) fn parse_text(mut self, raw_cnf: &'a str) -> Result<SystemCnf<'a>, Error> {
+        // Not really a fan of this parsing approach but I can't think of anything better ATM
+        let raw_cnf = raw_cnf.strip_prefix('\u{feff}').unwrap_or(raw_cnf);
+        for (line_number, line) in (1..).zip(lines(raw_cnf)) {
+            self.line(line_number, line)?;
+        }
+
+        self.finish()
+    }
+
+    /// Like [`Parser::parse`], but keep going after an error and return all of them
+    pub(crate) fn parse_collect_errors(
+        mut self,
+        raw_cnf: &'a str,
+    ) -> Result<SystemCnf<'a>, Vec<Error>> {
+        if looks_binary(raw_cnf.as_bytes()) {
+            return Err(alloc::vec![Error::NotText]);
+        }
+
+        let raw_cnf = raw_cnf.strip_prefix('\u{feff}').unwrap_or(raw_cnf);
+        let mut errors = Vec::new();
+        for (line_number, line) in (1..).zip(lines(raw_cnf)) {
+            if let Err(error) = self.line(line_number, line) {
+                errors.push(error);
+            }
+        }
+
+        errors.extend(self.missing_fields());
+        if errors.is_empty() {
+            self.finish().map_err(|error| alloc::vec![error])
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Convert into a parser whose values no longer borrow from the input
+    #[cfg(feature = "std")]
+    fn into_owned(self) -> Parser<'static, 'w> {
+        let owned = |value: Cow<'_, str>| Cow::Owned(value.into_owned());
+        Parser {
+            options: self.options,
+            warnings: self.warnings,
+            elf_path: self.elf_path.map(owned),
+            ps1_boot: self.ps1_boot.map(owned),
+            version: self.version.map(owned),
+            video_mode: self.video_mode.map(VideoMode::into_owned),
+            hdd_unit_power: self.hdd_unit_power.map(HddUnitPower::into_owned),
+            extras: self
+                .extras
+                .into_iter()
+                .map(|(key, value)| (owned(key), owned(value)))
+                .collect(),
+            order: self.order,
+            last_field: self.last_field,
+            seen_known: self.seen_known,
+            quoted: self.quoted,
+        }
+    }
+
+    fn line(&mut self, line_number: usize, line: &'a str) -> Result<(), Error> {
+        if line.trim_start().starts_with(['#', ';']) {
+            return Ok(());
+        }
+
+        // Only the first `=` separates the key from the value, any further ones are part of the value
+        let pair = line.split_once('=').or_else(|| {
+            self.options
+                .alternate_separator
+                .and_then(|separator| line.split_once(separator))
+        });
+        let Some((key, value)) = pair else {
+            if !self.options.allow_blank_lines {
+                return Err(Error::MalformedFile { line: line_number });
+            }
+
+            // Blank lines and other lines without a key-value pair carry no information
+            return Ok(());
+        };
+        let key = key.trim();
+        if key.is_empty() {
+            return Err(Error::MalformedFile { line: line_number });
+        }
+
+        // Keys are matched case-insensitively, `Display` always emits the canonical uppercase form
+        let known = KNOWN_KEYS
+            .iter()
+            .position(|known| key.eq_ignore_ascii_case(known));
+        let is_duplicate = match known {
+            Some(index) => core::mem::replace(&mut self.seen_known[index], true),
+            None => self
+                .extras
+                .iter()
+                .any(|(existing, _)| existing.eq_ignore_ascii_case(key)),
+        };
+        if is_duplicate {
+            self.warn(Warning::DuplicateKey { line: line_number });
+            match self.options.duplicate_keys {
+                DuplicateKeyPolicy::FirstWins => return Ok(()),
+                DuplicateKeyPolicy::LastWins => {}
+                DuplicateKeyPolicy::Error => {
+                    return Err(Error::DuplicateKey { line: line_number });
+                }
+            }
+        }
+
+        let (value, quoted) = match strip_quotes(value) {
+            Some(unquoted) if self.options.strip_quotes => (unquoted, true),
+            _ => (value, false),
+        };
+
+        let known = known.map(|index| KNOWN_FIELDS[index]);
+        let is_required = matches!(
+            known,
+            Some(Field::ElfPath | Field::Ps1Boot | Field::Version)
+        );
+        if is_required && value.trim().is_empty() {
+            return Err(Error::EmptyField { line: line_number });
+        }
+
+        let field = self.set(line_number, known, key, value)?;
+        self.quoted.retain(|&existing| existing != field);
+        if quoted {
+            self.quoted.push(field);
+        }
+
+        if let Field::Extra(..) = field {
+            self.warn(Warning::UnknownKey { line: line_number });
+        } else if key.bytes().any(|b| b.is_ascii_lowercase()) {
+            self.warn(Warning::NonCanonicalKeyCase { line: line_number });
+        }
+        if value.trim().is_empty() {
+            self.warn(Warning::EmptyValue { line: line_number });
+        }
+
+        // Repeated keys keep the position of their first occurrence
+        if !is_duplicate {
+            self.record_order(field);
+        }
+
+        Ok(())
+    }
+
+    fn warn(&mut self, warning: Warning) {
+        if let Some(warnings) = self.warnings.as_deref_mut() {
+            warnings.push(warning);
+        }
+    }
+
+    /// Record the position of a newly seen field
+    fn record_order(&mut self, field: Field) {
+        if !self.order.is_empty() {
+            self.order.push(field);
+            return;
+        }
+
+        match self.last_field {
+            Some(last) if last > field => {
+                // All fields seen so far were in ascending order, so they can be reconstructed
+                self.order = KNOWN_FIELDS
+                    .into_iter()
+                    .zip(self.seen_known)
+                    .filter(|&(known, seen)| seen && known != field)
+                    .map(|(known, _)| known)
+                    .chain((0..self.extras.len()).map(Field::Extra))
+                    .collect();
+                self.order.push(field);
+            }
+            // Still in canonical order, which doesn't need to be recorded
+            _ => self.last_field = Some(field),
+        }
+    }
+
+    /// Store the value of a key, returning the field it was stored in
+    ///
+    /// `known` is the field of the key if it is one of the [`KNOWN_KEYS`]
+    fn set(
+        &mut self,
+        line_number: usize,
+        known: Option<Field>,
+        key: &'a str,
+        value: &'a str,
+    ) -> Result<Field, Error> {
+        let field = match known {
+            Some(Field::ElfPath) => {
+                self.elf_path = Some(parse_boot_path(value));
+                Field::ElfPath
+            }
+            Some(Field::Ps1Boot) => {
+                self.ps1_boot = Some(parse_boot_path(value));
+                Field::Ps1Boot
+            }
+            Some(Field::Version) => {
+                self.version = Some(value.trim().into());
+                Field::Version
+            }
+            Some(Field::VideoMode) => {
+                let mode = VideoMode::try_from(value)
+                    .map_err(|_| Error::UnknownVideoMode { line: line_number })?;
+                self.video_mode = Some(mode);
+                Field::VideoMode
+            }
+            Some(Field::HddUnitPower) => {
+                // Empty values are the only ones rejected and count as not set
+                self.hdd_unit_power = HddUnitPower::try_from(value).ok();
+                Field::HddUnitPower
+            }
+            _ if !self.options.allow_unknown_keys => {
+                return Err(Error::UnknownKey { line: line_number });
+            }
+            Some(Field::Extra(..)) | None => {
+                // Repeated unknown keys replace the earlier value, if they get this far
+                let existing = self
+                    .extras
+                    .iter()
+                    .position(|(existing, _)| existing.eq_ignore_ascii_case(key));
+                if let Some(index) = existing {
+                    self.extras[index].1 = value.trim().into();
+                    Field::Extra(index)
+                } else {
+                    self.extras.push((key.into(), value.trim().into()));
+                    Field::Extra(self.extras.len() - 1)
+                }
+            }
+        };
+
+        Ok(field)
+    }
+
+    /// One [`Error::MissingField`] for every required field that is missing
+    ///
+    /// Fields whose key appeared but failed to parse already caused an error and aren't reported again
+    fn missing_fields(&self) -> impl Iterator<Item = Error> {
+        let seen = |fields: &[Field]| {
+            KNOWN_FIELDS
+                .into_iter()
+                .zip(self.seen_known)
+                .any(|(known, seen)| seen && fields.contains(&known))
+        };
+        let missing_boot = self.elf_path.is_none()
+            && self.ps1_boot.is_none()
+            && !seen(&[Field::ElfPath, Field::Ps1Boot]);
+        let missing_version = self.version.is_none() && !seen(&[Field::Version]);
+        let missing_video_mode = self.video_mode.is_none()
+            && !self.options.allow_missing_video_mode
+            && !seen(&[Field::VideoMode]);
+
+        [missing_boot, missing_version, missing_video_mode]
+            .into_iter()
+            .filter(|missing| *missing)
+            .map(|_| Error::MissingField)
+    }
+
+    fn finish(self) -> Result<SystemCnf<'a>, Error> {
+        if self.elf_path.is_none() && self.ps1_boot.is_none() {
+            return Err(Error::MissingField);
+        }
+
+        let mut cnf = SystemCnf {
+            elf_path: self.elf_path,
+            ps1_boot: self.ps1_boot,
+            version: self.version.ok_or(Error::MissingField)?,
+            video_mode: self
+                .video_mode
+                .or(self
+                    .options
+                    .allow_missing_video_mode
+                    .then_some(VideoMode::Ntsc))
+                .ok_or(Error::MissingField)?,
+            hdd_unit_power: self.hdd_unit_power,
+            extras: self.extras,
+            order: Vec::new(),
+            quoted: self.quoted,
+        };
+
+        // Only record the order if it differs from the canonical one, keeping such files equal to built ones
+        let mut order = self.order;
+        if !order.is_empty() {
+            let canonical = cnf.field_order();
+            order.retain(|field| canonical.contains(field));
+            if order != canonical {
+                cnf.order = order;
+            }
+        }
+
+        Ok(cnf)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'w> Parser<'static, 'w> {
+    /// Parse a file line by line from a reader, see [`SystemCnf::from_reader`]
+    pub(crate) fn parse_reader<R: BufRead>(
+        mut self,
+        mut reader: R,
+    ) -> Result<SystemCnf<'static>, Error> {
+        let mut text = TextCheck::default();
+        let mut chunk = Vec::new();
+        // Offset of the chunk after the byte order mark, for reporting invalid UTF-8
+        let mut offset = 0;
+        let mut line_number = 1;
+        loop {
+            chunk.clear();
+            let read = reader
+                .read_until(b'\n', &mut chunk)
+                .map_err(|err| Error::Io(err.kind()))?;
+            if read == 0 {
+                break;
+            }
+
+            // Only the first chunk can start with the byte order mark, no bytes have been consumed before it
+            let bytes = match chunk.strip_prefix(b"\xEF\xBB\xBF") {
+                Some(bytes) if offset == 0 => bytes,
+                _ => &chunk[..],
+            };
+            text.feed(bytes);
+            if text.has_nul() {
+                return Err(Error::NotText);
+            }
+
+            let decoded = decode(bytes, offset)?;
+            offset += bytes.len();
+            // A chunk ends at the first `\n`, but may contain bare `\r` line endings
+            for line in lines(&decoded) {
+                let result;
+                (self, result) = self.owned_line(line_number, line);
+                if let Err(error) = result {
+                    return Err(if text.looks_binary() {
+                        Error::NotText
+                    } else {
+                        error
+                    });
+                }
+                line_number += 1;
+            }
+        }
+
+        if text.looks_binary() {
+            return Err(Error::NotText);
+        }
+
+        self.finish()
+    }
+
+    /// Parse a line that doesn't live as long as the parser, keeping owned copies of its values
+    fn owned_line(self, line_number: usize, line: &str) -> (Self, Result<(), Error>) {
+        let mut parser: Parser<'_, 'w> = self;
+        let result = parser.line(line_number, line);
+        (parser.into_owned(), result)
+    }
+}
+
+/// Value inside a single pair of double quotes surrounding the trimmed value, if there are any
+fn strip_quotes(value: &str) -> Option<&str> {
+    value
+        .trim()
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+}
+
+/// Whether the input is binary data rather than text
+///
+/// That is the case if it contains a NUL byte or more than one in ten bytes are ASCII control characters other
+/// than tabs, line endings and form feeds. Bytes outside of ASCII aren't counted, they're part of non-ASCII text
+pub(crate) fn looks_binary(bytes: &[u8]) -> bool {
+    let mut text = TextCheck::default();
+    text.feed(bytes);
+    text.looks_binary()
+}
+
+/// Running counts behind [`looks_binary`], for input that arrives in pieces
+#[derive(Default)]
+struct TextCheck {
+    len: usize,
+    control: usize,
+    nul: bool,
+}
+
+impl TextCheck {
+    fn feed(&mut self, bytes: &[u8]) {
+        self.len += bytes.len();
+        for &byte in bytes {
+            match byte {
+                0 => self.nul = true,
+                b'\t' | b'\n' | b'\r' | 0x0C => {}
+                0x01..=0x1F | 0x7F => self.control += 1,
+                _ => {}
+            }
+        }
+    }
+
+    #[cfg(feature = "std")]
+    fn has_nul(&self) -> bool {
+        self.nul
+    }
+
+    fn looks_binary(&self) -> bool {
+        self.nul || self.control * 10 > self.len
+    }
+}
+
+/// Decode a chunk of the file starting at `offset`, see [`SystemCnf::from_bytes`] for the accepted encodings
+#[cfg(feature = "std")]
+fn decode(bytes: &[u8], offset: usize) -> Result<Cow<'_, str>, Error> {
+    match core::str::from_utf8(bytes) {
+        Ok(text) => Ok(Cow::Borrowed(text)),
+        #[cfg(feature = "encoding")]
+        Err(err) => encoding_rs::SHIFT_JIS
+            .decode_without_bom_handling_and_without_replacement(bytes)
+            .ok_or(Error::InvalidUtf8 {
+                valid_up_to: offset + err.valid_up_to(),
+            }),
+        #[cfg(not(feature = "encoding"))]
+        Err(err) => Err(Error::InvalidUtf8 {
+            valid_up_to: offset + err.valid_up_to(),
+        }),
+    }
+}
+
+/// Trim a `BOOT`/`BOOT2` path and strip the `;1` file version suffix
+fn parse_boot_path(value: &str) -> Cow<'_, str> {
+    let path = value.trim();
+    path.strip_suffix(";1").unwrap_or(path).into()
+}
+
+/// Split into lines terminated by `\r\n`, `\n` or a bare `\r`
+///
+/// Like [`str::lines`], a trailing line terminator doesn't produce an empty last line
+fn lines(raw_cnf: &str) -> impl Iterator<Item = &str> {
+    let mut rest = raw_cnf;
+    core::iter::from_fn(move || {
+        if rest.is_empty() {
+            return None;
+        }
+
+        let end = rest.find(['\r', '\n']).unwrap_or(rest.len());
+        let (line, terminator) = rest.split_at(end);
+        rest = terminator
+            .strip_prefix("\r\n")
+            .or_else(|| terminator.strip_prefix(['\r', '\n']))
+            .unwrap_or(terminator);
+        Some(line)
+    })
+}
@@ -0,0 +1,34 @@
+use core::fmt::{self, Display};
+#[cfg(feature = "std")]
+use std::error::Error as StdError;
+
+/// Reasons a `SYSTEM.CNF` doesn't look like the one of a retail disc
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub enum ValidationError {
+    /// Neither `BOOT2` nor `BOOT` is set
+    MissingBootPath,
+
+    /// The boot path doesn't point to the root of the disc (`cdrom0:\` for PS2, `cdrom:\` for PS1 executables)
+    InvalidDevice,
+
+    /// The boot filename doesn't follow the `XXXX_NNN.NN` serial pattern
+    InvalidSerial,
+
+    /// The version doesn't follow the `M.mm` format
+    InvalidVersion,
+}
+
+impl Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::MissingBootPath => "neither BOOT2 nor BOOT is set",
+            Self::InvalidDevice => "boot path doesn't start with cdrom0:\\ (or cdrom:\\ for BOOT)",
+            Self::InvalidSerial => "boot filename doesn't follow the XXXX_NNN.NN pattern",
+            Self::InvalidVersion => "version doesn't follow the M.mm format",
+        })
+    }
+}
+
+#[cfg(feature = "std")]
+impl StdError for ValidationError {}
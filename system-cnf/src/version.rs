@@ -0,0 +1,77 @@
+use crate::Error;
+use core::{
+    fmt::{self, Display},
+    str::FromStr,
+};
+
+/// Structured version of a game in the `M.mm` format (e.g. `1.00`)
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct GameVersion {
+    /// Major version
+    pub major: u16,
+
+    /// Minor version
+    pub minor: u16,
+}
+
+impl FromStr for GameVersion {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (major, minor) = s.trim().split_once('.').ok_or(Error::InvalidVersion)?;
+
+        let is_digits =
+            |digits: &str| !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit());
+        if !is_digits(major) || !is_digits(minor) || minor.len() != 2 {
+            return Err(Error::InvalidVersion);
+        }
+
+        Ok(Self {
+            major: major.parse().map_err(|_| Error::InvalidVersion)?,
+            minor: minor.parse().map_err(|_| Error::InvalidVersion)?,
+        })
+    }
+}
+
+impl Display for GameVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{:02}", self.major, self.minor)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Error, GameVersion};
+
+    #[test]
+    fn parse() {
+        let version: GameVersion = "1.00".parse().unwrap();
+        assert_eq!(version, GameVersion { major: 1, minor: 0 });
+        assert_eq!(version.to_string(), "1.00");
+
+        let version: GameVersion = "2.10".parse().unwrap();
+        assert_eq!(
+            version,
+            GameVersion {
+                major: 2,
+                minor: 10
+            }
+        );
+        assert_eq!(version.to_string(), "2.10");
+    }
+
+    #[test]
+    fn ordering() {
+        let older: GameVersion = "1.00".parse().unwrap();
+        let newer: GameVersion = "1.10".parse().unwrap();
+        assert!(newer > older);
+        assert!("2.00".parse::<GameVersion>().unwrap() > newer);
+    }
+
+    #[test]
+    fn invalid() {
+        for invalid in ["abc", "", "1", "1.", ".00", "1.0", "1.000", "1.a0", "-1.00"] {
+            assert_eq!(invalid.parse::<GameVersion>(), Err(Error::InvalidVersion));
+        }
+    }
+}
@@ -0,0 +1,194 @@
+use crate::Error;
+use alloc::borrow::Cow;
+use core::{
+    fmt::{self, Display},
+    str::FromStr,
+};
+
+/// Video mode of the ROM
+///
+/// Defaults to [`VideoMode::Ntsc`], the mode most retail discs use
+#[derive(Clone, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum VideoMode<'a> {
+    /// NTSC
+    #[default]
+    Ntsc,
+
+    /// PAL
+    Pal,
+
+    /// PAL at 60Hz
+    Pal60,
+
+    /// Any other video mode, preserved exactly as it appeared in the file
+    Other(Cow<'a, str>),
+}
+
+impl VideoMode<'_> {
+    /// Retrieve the value of the video mode in its string representation
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Ntsc => "NTSC",
+            Self::Pal => "PAL",
+            Self::Pal60 => "PAL60",
+            Self::Other(other) => other,
+        }
+    }
+
+    /// Convert into an owned `VideoMode` that no longer borrows from the input
+    #[must_use]
+    pub fn into_owned(self) -> VideoMode<'static> {
+        match self {
+            Self::Ntsc => VideoMode::Ntsc,
+            Self::Pal => VideoMode::Pal,
+            Self::Pal60 => VideoMode::Pal60,
+            Self::Other(other) => VideoMode::Other(Cow::Owned(other.into_owned())),
+        }
+    }
+}
+
+impl Display for VideoMode<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Compares against the string representation, e.g. `VideoMode::Ntsc == "NTSC"`
+///
+/// The comparison is case-sensitive and doesn't trim, unlike parsing
+impl PartialEq<str> for VideoMode<'_> {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for VideoMode<'_> {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl<'a> TryFrom<&'a str> for VideoMode<'a> {
+    type Error = Error;
+
+    /// Parse a video mode, borrowing unknown values from the input
+    ///
+    /// Only empty values are rejected, unknown values are preserved in [`VideoMode::Other`]
+    fn try_from(value: &'a str) -> Result<Self, Self::Error> {
+        match value.trim() {
+            "" => Err(Error::EmptyValue),
+            "NTSC" => Ok(Self::Ntsc),
+            "PAL" => Ok(Self::Pal),
+            "PAL60" => Ok(Self::Pal60),
+            other => Ok(Self::Other(other.into())),
+        }
+    }
+}
+
+impl FromStr for VideoMode<'_> {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        VideoMode::try_from(s).map(VideoMode::into_owned)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for VideoMode<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de: 'a, 'a> serde::Deserialize<'de> for VideoMode<'a> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Visitor;
+
+        impl<'de> serde::de::Visitor<'de> for Visitor {
+            type Value = VideoMode<'de>;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a video mode string")
+            }
+
+            fn visit_borrowed_str<E: serde::de::Error>(
+                self,
+                v: &'de str,
+            ) -> Result<Self::Value, E> {
+                VideoMode::try_from(v).map_err(E::custom)
+            }
+
+            fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<Self::Value, E> {
+                v.parse().map_err(E::custom)
+            }
+        }
+
+        deserializer.deserialize_str(Visitor)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Error, VideoMode};
+    use std::borrow::Cow;
+
+    #[test]
+    fn round_trip() {
+        for mode in [
+            VideoMode::Ntsc,
+            VideoMode::Pal,
+            VideoMode::Pal60,
+            VideoMode::Other("MULTI".into()),
+        ] {
+            assert_eq!(mode.as_str().parse::<VideoMode<'_>>(), Ok(mode.clone()));
+            assert_eq!(mode.to_string(), mode.as_str());
+        }
+    }
+
+    #[test]
+    fn eq_str() {
+        assert_eq!(VideoMode::Ntsc, "NTSC");
+        assert!(VideoMode::Pal60 == *"PAL60");
+        assert!(VideoMode::Other("MULTI".into()) == "MULTI");
+        assert!(VideoMode::Pal != "PAL60");
+        assert!(VideoMode::Ntsc != "ntsc");
+    }
+
+    #[test]
+    fn default() {
+        assert_eq!(VideoMode::default(), VideoMode::Ntsc);
+    }
+
+    #[test]
+    fn pal60() {
+        assert_eq!("PAL60".parse(), Ok(VideoMode::Pal60));
+        assert_eq!(VideoMode::Pal60.to_string(), "PAL60");
+    }
+
+    #[test]
+    fn other() {
+        let mode = VideoMode::try_from(" MULTI ").unwrap();
+        assert!(matches!(mode, VideoMode::Other(Cow::Borrowed("MULTI"))));
+        assert_eq!(mode.to_string(), "MULTI");
+    }
+
+    #[test]
+    fn empty() {
+        assert_eq!("".parse::<VideoMode<'_>>(), Err(Error::EmptyValue));
+        assert_eq!(VideoMode::try_from("  "), Err(Error::EmptyValue));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde() {
+        assert_eq!(
+            serde_json::to_string(&VideoMode::Pal60).unwrap(),
+            r#""PAL60""#
+        );
+
+        let mode: VideoMode<'_> = serde_json::from_str(r#""MULTI""#).unwrap();
+        assert!(matches!(mode, VideoMode::Other(Cow::Borrowed("MULTI"))));
+    }
+}
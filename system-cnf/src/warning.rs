@@ -0,0 +1,41 @@
+use core::fmt::{self, Display};
+
+/// Non-fatal issues tolerated while parsing, see [`SystemCnf::parse_with_warnings`](crate::SystemCnf::parse_with_warnings)
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub enum Warning {
+    /// Key isn't recognised and was preserved in [`SystemCnf::extras`](crate::SystemCnf::extras)
+    UnknownKey {
+        /// 1-based line number the problem occurred on
+        line: usize,
+    },
+
+    /// Recognised key isn't written in its canonical uppercase form
+    NonCanonicalKeyCase {
+        /// 1-based line number the problem occurred on
+        line: usize,
+    },
+
+    /// Key already appeared on an earlier line
+    DuplicateKey {
+        /// 1-based line number of the repeated key
+        line: usize,
+    },
+
+    /// Optional field has an empty value
+    EmptyValue {
+        /// 1-based line number the problem occurred on
+        line: usize,
+    },
+}
+
+impl Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownKey { line } => write!(f, "unknown key on line {line}"),
+            Self::NonCanonicalKeyCase { line } => write!(f, "non-uppercase key on line {line}"),
+            Self::DuplicateKey { line } => write!(f, "duplicate key on line {line}"),
+            Self::EmptyValue { line } => write!(f, "empty value on line {line}"),
+        }
+    }
+}
@@ -0,0 +1,58 @@
+//! Lives in its own test binary since it replaces the global allocator
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+};
+use system_cnf::{ParseOptions, SystemCnf};
+
+/// Allocator counting the allocations of the current thread
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|allocations| allocations.set(allocations.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn allocations<T>(f: impl FnOnce() -> T) -> (T, usize) {
+    let before = ALLOCATIONS.with(Cell::get);
+    let result = f();
+    (result, ALLOCATIONS.with(Cell::get) - before)
+}
+
+#[test]
+fn parse_without_allocations() {
+    let txt =
+        "BOOT2 = cdrom0:\\SLUS_213.48;1\r\nVER = 1.00\r\nVMODE = NTSC\r\nHDDUNITPOWER = NICHDD\r\n";
+
+    let (cnf, count) = allocations(|| SystemCnf::parse(txt).unwrap());
+    assert_eq!(count, 0);
+    assert_eq!(cnf.elf_path_str(), Some("cdrom0:\\SLUS_213.48"));
+
+    let (_, count) = allocations(|| SystemCnf::parse_with(txt, ParseOptions::strict()).unwrap());
+    assert_eq!(count, 0);
+
+    // Lowercase keys, comments and blank lines don't allocate either
+    let txt = "# Comment\r\n\r\nboot2 = cdrom0:\\SLUS_213.48;1\r\nver = 1.00\r\nvmode = PAL\r\n";
+    let (_, count) = allocations(|| SystemCnf::parse(txt).unwrap());
+    assert_eq!(count, 0);
+
+    // Unrecognised keys and non-canonical orders have to be stored
+    let (_, count) = allocations(|| {
+        SystemCnf::parse("VER = 1.00\r\nBOOT2 = cdrom0:\\X;1\r\nVMODE = NTSC\r\n").unwrap()
+    });
+    assert_ne!(count, 0);
+}